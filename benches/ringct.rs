@@ -0,0 +1,153 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Benchmarks sign() and verify() along each dimension of a transaction,
+//! varying one of inputs, outputs and ring size while the others stay at
+//! their defaults.
+
+use bls_ringct::{
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::{ff::Field, Curve, Group},
+    pedersen_gens,
+    rand::rngs::OsRng,
+    ringct::{Amount, DEFAULT_DECOYS_PER_INPUT},
+    DecoyInput, MlsagMaterial, Output, RevealedCommitment, RingCtMaterial, TrueInput,
+};
+use criterion::{criterion_group, criterion_main, BenchmarkGroup, BenchmarkId, Criterion};
+
+const DEFAULT_INPUTS: usize = 2;
+const DEFAULT_OUTPUTS: usize = 2;
+const DEFAULT_RING_SIZE: usize = DEFAULT_DECOYS_PER_INPUT + 1;
+
+#[derive(Clone, Copy)]
+struct Dimensions {
+    inputs: usize,
+    outputs: usize,
+    ring_size: usize,
+}
+
+impl Default for Dimensions {
+    fn default() -> Self {
+        Self {
+            inputs: DEFAULT_INPUTS,
+            outputs: DEFAULT_OUTPUTS,
+            ring_size: DEFAULT_RING_SIZE,
+        }
+    }
+}
+
+fn material(dims: Dimensions) -> (RingCtMaterial, Vec<Vec<G1Affine>>) {
+    let pc_gens = pedersen_gens();
+
+    let inputs = Vec::from_iter((0..dims.inputs).map(|_| {
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(dims.outputs as u64, OsRng),
+        );
+        let decoy_inputs = Vec::from_iter((1..dims.ring_size).map(|_| DecoyInput {
+            public_key: G1Projective::random(OsRng).to_affine(),
+            commitment: G1Projective::random(OsRng).to_affine(),
+        }));
+        MlsagMaterial::new(true_input, decoy_inputs, &mut OsRng).unwrap()
+    }));
+
+    let outputs = Vec::from_iter(
+        (0..dims.outputs).map(|_| Output::new(G1Projective::random(OsRng), dims.inputs as u64)),
+    );
+
+    let public_commitments = Vec::from_iter(inputs.iter().map(|input| input.commitments(&pc_gens)));
+
+    let material = RingCtMaterial {
+        inputs,
+        outputs,
+        fee: Amount::ZERO,
+        extra: vec![],
+        network_id: None,
+        not_valid_after: None,
+    };
+    (material, public_commitments)
+}
+
+fn bench_dimension<M: criterion::measurement::Measurement>(
+    group: &mut BenchmarkGroup<M>,
+    name: &str,
+    values: &[usize],
+    dims_for: impl Fn(usize) -> Dimensions,
+    verify: bool,
+) {
+    for &value in values {
+        let (material, public_commitments) = material(dims_for(value));
+        if verify {
+            let (tx, _) = material
+                .sign(&mut OsRng)
+                .expect("Failed to sign transaction");
+            group.bench_with_input(BenchmarkId::new(name, value), &value, |b, _| {
+                b.iter(|| tx.verify(&public_commitments).expect("Failed to verify"))
+            });
+        } else {
+            group.bench_with_input(BenchmarkId::new(name, value), &value, |b, _| {
+                b.iter(|| {
+                    material
+                        .sign(&mut OsRng)
+                        .expect("Failed to sign transaction")
+                })
+            });
+        }
+    }
+}
+
+fn bench_all_dimensions<M: criterion::measurement::Measurement>(
+    group: &mut BenchmarkGroup<M>,
+    verify: bool,
+) {
+    bench_dimension(
+        group,
+        "inputs",
+        &[1, 2, 4, 8, 16],
+        |inputs| Dimensions {
+            inputs,
+            ..Default::default()
+        },
+        verify,
+    );
+    bench_dimension(
+        group,
+        "outputs",
+        &[2, 4, 8, 16],
+        |outputs| Dimensions {
+            outputs,
+            ..Default::default()
+        },
+        verify,
+    );
+    bench_dimension(
+        group,
+        "ring_size",
+        &[2, 4, 8, 11, 16],
+        |ring_size| Dimensions {
+            ring_size,
+            ..Default::default()
+        },
+        verify,
+    );
+}
+
+fn bench_sign(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ringct_sign");
+    group.sample_size(10);
+    bench_all_dimensions(&mut group, false);
+    group.finish();
+}
+
+fn bench_verify(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ringct_verify");
+    group.sample_size(10);
+    bench_all_dimensions(&mut group, true);
+    group.finish();
+}
+
+criterion_group!(benches, bench_sign, bench_verify);
+criterion_main!(benches);
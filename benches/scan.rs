@@ -0,0 +1,69 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Compares scanning foreign outputs with view tags against deriving the
+//! full one-time key for every output.
+
+use bls_ringct::{
+    blstrs::{G1Projective, Scalar},
+    group::{ff::Field, Curve, Group},
+    rand::rngs::OsRng,
+    ringct::{Amount, OutputProof},
+    DecoyInput, MlsagMaterial, Output, RevealedCommitment, RingCtMaterial, StealthKeys, TrueInput,
+};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+const NUM_OUTPUTS: usize = 16;
+
+fn foreign_outputs() -> Vec<OutputProof> {
+    let true_input = TrueInput::new(
+        Scalar::random(OsRng),
+        RevealedCommitment::from_value(NUM_OUTPUTS as u64, OsRng),
+    );
+    let decoy_inputs = vec![DecoyInput {
+        public_key: G1Projective::random(OsRng).to_affine(),
+        commitment: G1Projective::random(OsRng).to_affine(),
+    }];
+
+    let outputs = Vec::from_iter((0..NUM_OUTPUTS).map(|_| {
+        Output::to_stealth_address(&StealthKeys::random(&mut OsRng).address(), 1, &mut OsRng)
+    }));
+
+    let (tx, _revealed_output_commitments) = RingCtMaterial {
+        inputs: vec![MlsagMaterial::new(true_input, decoy_inputs, &mut OsRng).unwrap()],
+        outputs,
+        fee: Amount::ZERO,
+        extra: vec![],
+        network_id: None,
+        not_valid_after: None,
+    }
+    .sign(&mut OsRng)
+    .expect("Failed to sign transaction");
+
+    tx.outputs
+}
+
+fn bench_scan(c: &mut Criterion) {
+    let outputs = foreign_outputs();
+    let keys = StealthKeys::random(&mut OsRng);
+
+    let mut group = c.benchmark_group("scan");
+
+    group.bench_function("view_tags", |b| b.iter(|| keys.scan_outputs(&outputs)));
+    group.bench_function("full_derivation", |b| {
+        b.iter(|| {
+            outputs
+                .iter()
+                .filter_map(|output| keys.one_time_secret_key(output))
+                .count()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_scan);
+criterion_main!(benches);
@@ -0,0 +1,94 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Compare `cargo bench` against `cargo bench --features parallel` to
+//! measure the speedup of parallel verification.
+
+use std::collections::BTreeMap;
+
+use bls_ringct::{
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::{ff::Field, Curve, Group},
+    pedersen_gens,
+    rand::rngs::OsRng,
+    ringct::{Amount, RingCtTransaction},
+    DecoyInput, MlsagMaterial, Output, RevealedCommitment, RingCtMaterial, TrueInput,
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+const RING_SIZE: usize = 11;
+
+fn signed_tx(num_inputs: usize, num_outputs: usize) -> (RingCtTransaction, Vec<Vec<G1Affine>>) {
+    let pc_gens = pedersen_gens();
+    let mut ledger: BTreeMap<[u8; 48], G1Affine> = Default::default();
+
+    let inputs = Vec::from_iter((0..num_inputs).map(|_| {
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(num_outputs as u64, OsRng),
+        );
+        ledger.insert(
+            true_input.public_key().to_compressed(),
+            true_input.revealed_commitment.commit(&pc_gens).to_affine(),
+        );
+
+        let decoy_inputs = Vec::from_iter((1..RING_SIZE).map(|_| {
+            let decoy = DecoyInput {
+                public_key: G1Projective::random(OsRng).to_affine(),
+                commitment: G1Projective::random(OsRng).to_affine(),
+            };
+            ledger.insert(decoy.public_key.to_compressed(), decoy.commitment);
+            decoy
+        }));
+
+        MlsagMaterial::new(true_input, decoy_inputs, &mut OsRng).unwrap()
+    }));
+
+    let outputs = Vec::from_iter(
+        (0..num_outputs).map(|_| Output::new(G1Projective::random(OsRng), num_inputs as u64)),
+    );
+
+    let (tx, _revealed_output_commitments) = RingCtMaterial {
+        inputs,
+        outputs,
+        fee: Amount::ZERO,
+        extra: vec![],
+        network_id: None,
+        not_valid_after: None,
+    }
+    .sign(&mut OsRng)
+    .expect("Failed to sign transaction");
+
+    let public_commitments = Vec::from_iter(tx.mlsags.iter().map(|mlsag| {
+        Vec::from_iter(
+            mlsag
+                .public_keys()
+                .iter()
+                .map(|pk| ledger[&pk.to_compressed()]),
+        )
+    }));
+
+    (tx, public_commitments)
+}
+
+fn bench_verify(c: &mut Criterion) {
+    let mut group = c.benchmark_group("verify");
+    group.sample_size(10);
+
+    for num_inputs in [1, 4, 8, 16] {
+        let (tx, public_commitments) = signed_tx(num_inputs, 2);
+        group.bench_with_input(
+            BenchmarkId::new("inputs", num_inputs),
+            &num_inputs,
+            |b, _| b.iter(|| tx.verify(&public_commitments).expect("Failed to verify")),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_verify);
+criterion_main!(benches);
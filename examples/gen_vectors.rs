@@ -0,0 +1,35 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Writes the known-answer test vectors to `tests/vectors` (or the directory
+//! given as the first argument):
+//!
+//!     cargo run --example gen_vectors
+
+#[path = "../tests/common/vectors.rs"]
+mod vectors;
+
+use std::{fs, path::PathBuf};
+
+use vectors::{Vector, SEEDS, VERSIONS};
+
+fn main() -> std::io::Result<()> {
+    let dir = std::env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/vectors"));
+    fs::create_dir_all(&dir)?;
+
+    for version in VERSIONS {
+        for seed in SEEDS {
+            let vector = Vector::generate(seed, version);
+            let path = dir.join(vector.file_name());
+            fs::write(&path, vector.to_text())?;
+            println!("wrote {}", path.display());
+        }
+    }
+    Ok(())
+}
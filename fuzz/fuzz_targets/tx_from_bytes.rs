@@ -0,0 +1,19 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+#![no_main]
+
+use bls_ringct::{ringct::RingCtTransaction, ClsagSignature, MlsagSignature};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(tx) = RingCtTransaction::from_bytes(data) {
+        let bytes = tx.to_bytes();
+        assert_eq!(RingCtTransaction::from_bytes(&bytes), Ok(tx));
+    }
+    let _ = MlsagSignature::from_bytes(data);
+    let _ = ClsagSignature::from_bytes(data);
+});
@@ -0,0 +1,45 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+#![no_main]
+
+use bls_ringct::{
+    blstrs::{G1Affine, G1Projective},
+    group::Curve,
+    ringct::RingCtTransaction,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let tx = match RingCtTransaction::from_bytes(data) {
+        Ok(tx) => tx,
+        Err(_) => return,
+    };
+
+    // The commitments a ledger would return if the hidden commitments are honest,
+    // which gets the fuzzer past the cheap ring checks.
+    let rings = tx
+        .mlsags
+        .iter()
+        .map(|m| (&m.ring, m.pseudo_commitment))
+        .chain(tx.clsags.iter().map(|c| (&c.ring, c.pseudo_commitment)))
+        .chain(tx.matrix_mlsag.iter().flat_map(|m| {
+            m.rings.iter().zip(m.pseudo_commitments.iter().copied())
+        }));
+    let public_commitments: Vec<Vec<G1Affine>> = rings
+        .map(|(ring, pseudo_commitment)| {
+            ring.iter()
+                .map(|(_, hidden_commitment)| {
+                    (G1Projective::from(hidden_commitment) + pseudo_commitment).to_affine()
+                })
+                .collect()
+        })
+        .collect();
+
+    let result = tx.verify(&public_commitments);
+    let report = tx.verify_detailed(&public_commitments);
+    assert_eq!(result.err().as_ref(), report.errors().first().copied());
+});
@@ -0,0 +1,209 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::{ff::Field, Curve, Group},
+    rand::RngCore,
+};
+use tiny_keccak::{Hasher, Sha3};
+
+use crate::{
+    codec::{self, Reader},
+    ringct::Amount,
+    Error, Result, RevealedCommitment,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const KEY_DOMAIN: &[u8] = b"blst-ringct-amount-secrets-key";
+const KEYSTREAM_DOMAIN: &[u8] = b"blst-ringct-amount-secrets-keystream";
+const TAG_DOMAIN: &[u8] = b"blst-ringct-amount-secrets-tag";
+const PLAINTEXT_LEN: usize = 40; // u64 value + 32 byte blinding factor
+                                 // ephemeral public key, ciphertext length and ciphertext, tag
+pub(crate) const ENCRYPTED_AMOUNT_LEN: usize = 48 + 4 + PLAINTEXT_LEN + 32;
+
+/// The value and blinding factor of an output's commitment, which the
+/// recipient needs in order to later spend the output.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AmountSecrets(RevealedCommitment);
+
+impl AmountSecrets {
+    pub fn value(&self) -> Amount {
+        Amount::new(self.0.value)
+    }
+
+    pub fn blinding_factor(&self) -> Scalar {
+        self.0.blinding
+    }
+
+    pub fn revealed_commitment(&self) -> RevealedCommitment {
+        self.0
+    }
+
+    /// Encrypts the secrets to the owner of `recipient`, using a key derived
+    /// from an ephemeral Diffie-Hellman exchange over G1.
+    pub fn encrypt_to(&self, recipient: &G1Affine, mut rng: impl RngCore) -> EncryptedAmount {
+        let ephemeral_secret = Scalar::random(&mut rng);
+        let ephemeral_public_key = (G1Projective::generator() * ephemeral_secret).to_affine();
+        let shared_secret = (recipient * ephemeral_secret).to_affine();
+        let key = derive_key(&shared_secret, &ephemeral_public_key);
+
+        let mut ciphertext = self.0.to_bytes();
+        apply_keystream(&key, &mut ciphertext);
+        let tag = tag(&key, &ciphertext);
+
+        EncryptedAmount {
+            ephemeral_public_key,
+            ciphertext,
+            tag,
+        }
+    }
+}
+
+impl From<RevealedCommitment> for AmountSecrets {
+    fn from(revealed_commitment: RevealedCommitment) -> Self {
+        Self(revealed_commitment)
+    }
+}
+
+impl From<AmountSecrets> for RevealedCommitment {
+    fn from(amount_secrets: AmountSecrets) -> Self {
+        amount_secrets.0
+    }
+}
+
+/// AmountSecrets encrypted to an output's owner. Outputs paid to a stealth
+/// address with independent range proofs carry none, their amount is read by
+/// rewinding the range proof instead, see `OutputProof::rewind_amount()`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedAmount {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    #[cfg_attr(
+        feature = "json-schema",
+        schemars(with = "crate::json_schema::HexPoint")
+    )]
+    pub ephemeral_public_key: G1Affine,
+    pub ciphertext: Vec<u8>,
+    pub tag: [u8; 32],
+}
+
+impl EncryptedAmount {
+    /// Recovers the secrets with the recipient's secret key, failing if they
+    /// were encrypted to someone else or have been tampered with.
+    pub fn try_decrypt<S: Into<Scalar>>(&self, recipient_secret_key: S) -> Result<AmountSecrets> {
+        let shared_secret = (self.ephemeral_public_key * recipient_secret_key.into()).to_affine();
+        let key = derive_key(&shared_secret, &self.ephemeral_public_key);
+
+        if self.ciphertext.len() != PLAINTEXT_LEN || tag(&key, &self.ciphertext) != self.tag {
+            return Err(Error::AmountDecryptionFailed);
+        }
+
+        let mut plaintext = self.ciphertext.clone();
+        apply_keystream(&key, &mut plaintext);
+
+        let mut reader = Reader::new(&plaintext);
+        let value = reader.read_u64()?;
+        let blinding = reader
+            .read_scalar()
+            .map_err(|_| Error::AmountDecryptionFailed)?;
+
+        Ok(AmountSecrets(RevealedCommitment { value, blinding }))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Default::default();
+        v.extend(self.ephemeral_public_key.to_compressed());
+        codec::write_len(&mut v, self.ciphertext.len());
+        v.extend(&self.ciphertext);
+        v.extend(self.tag);
+        v
+    }
+
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let encrypted_amount = Self::decode(&mut reader)?;
+        reader.finish()?;
+        Ok(encrypted_amount)
+    }
+
+    pub(crate) fn decode(reader: &mut Reader) -> Result<Self> {
+        let ephemeral_public_key = reader.read_point()?;
+        let ciphertext_len = reader.read_len()?;
+        let ciphertext = reader.take(ciphertext_len)?.to_vec();
+        let tag = reader.read_array()?;
+
+        Ok(Self {
+            ephemeral_public_key,
+            ciphertext,
+            tag,
+        })
+    }
+}
+
+fn sha3_256(material: &[&[u8]]) -> [u8; 32] {
+    let mut sha3 = Sha3::v256();
+    for chunk in material {
+        sha3.update(chunk);
+    }
+    let mut hash = [0u8; 32];
+    sha3.finalize(&mut hash);
+    hash
+}
+
+fn derive_key(shared_secret: &G1Affine, ephemeral_public_key: &G1Affine) -> [u8; 32] {
+    sha3_256(&[
+        KEY_DOMAIN,
+        &shared_secret.to_compressed(),
+        &ephemeral_public_key.to_compressed(),
+    ])
+}
+
+fn apply_keystream(key: &[u8; 32], data: &mut [u8]) {
+    for (counter, chunk) in data.chunks_mut(32).enumerate() {
+        let block = sha3_256(&[KEYSTREAM_DOMAIN, key, &(counter as u32).to_le_bytes()]);
+        for (b, k) in chunk.iter_mut().zip(block) {
+            *b ^= k;
+        }
+    }
+}
+
+fn tag(key: &[u8; 32], ciphertext: &[u8]) -> [u8; 32] {
+    sha3_256(&[TAG_DOMAIN, key, ciphertext])
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::rand::rngs::OsRng;
+
+    use super::*;
+
+    #[test]
+    fn test_amount_secrets_encryption_round_trip() {
+        let secret_key = Scalar::random(OsRng);
+        let public_key = crate::public_key(secret_key).to_affine();
+        let amount_secrets = AmountSecrets::from(RevealedCommitment::from_value(42, OsRng));
+
+        let encrypted = amount_secrets.encrypt_to(&public_key, OsRng);
+        assert_eq!(encrypted.try_decrypt(secret_key), Ok(amount_secrets));
+        assert_eq!(
+            encrypted.try_decrypt(Scalar::random(OsRng)),
+            Err(Error::AmountDecryptionFailed)
+        );
+
+        let mut tampered = encrypted;
+        tampered.ciphertext[0] ^= 1;
+        assert_eq!(
+            tampered.try_decrypt(secret_key),
+            Err(Error::AmountDecryptionFailed)
+        );
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Verification against ring member commitments fetched asynchronously, eg.
+//! from a remote ledger or a DHT.
+
+use async_trait::async_trait;
+use bls_bulletproofs::blstrs::G1Affine;
+use futures::future::try_join_all;
+
+use crate::{codec, ringct::RingCtTransaction, Error, Result};
+
+/// Resolves the commitment of a ring member from its public key asynchronously
+#[async_trait]
+pub trait AsyncCommitmentLookup: Sync {
+    async fn commitment(&self, public_key: &G1Affine) -> Option<G1Affine>;
+}
+
+impl RingCtTransaction {
+    /// Verifies the transaction, fetching the commitment of each ring member
+    /// through `lookup`. The lookups of all ring members run concurrently.
+    pub async fn verify_with_async_lookup(
+        &self,
+        lookup: &impl AsyncCommitmentLookup,
+    ) -> Result<()> {
+        let public_keys_per_ring = self.public_keys_per_ring();
+        let public_commitments_per_ring =
+            try_join_all(public_keys_per_ring.iter().enumerate().map(
+                |(input_index, public_keys)| {
+                    try_join_all(public_keys.iter().map(move |pk| async move {
+                        lookup
+                            .commitment(pk)
+                            .await
+                            .ok_or_else(|| Error::CommitmentNotFound {
+                                input_index,
+                                public_key: codec::to_hex(&pk.to_compressed()),
+                            })
+                    }))
+                },
+            ))
+            .await?;
+
+        self.verify(&public_commitments_per_ring)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use bls_bulletproofs::{
+        blstrs::{G1Projective, Scalar},
+        group::{ff::Field, Curve, Group},
+        rand::rngs::OsRng,
+    };
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::{
+        mock::random_decoys, ringct::Amount, DecoyInput, MlsagMaterial, Output, RevealedCommitment,
+        RingCtMaterial, TrueInput,
+    };
+
+    #[derive(Default)]
+    struct RemoteLedger {
+        commitments: BTreeMap<[u8; 48], G1Affine>,
+    }
+
+    #[async_trait]
+    impl AsyncCommitmentLookup for RemoteLedger {
+        async fn commitment(&self, public_key: &G1Affine) -> Option<G1Affine> {
+            self.commitments.get(&public_key.to_compressed()).copied()
+        }
+    }
+
+    #[test]
+    fn test_verify_with_async_lookup() {
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(3, OsRng),
+        );
+        let decoy_inputs: Vec<DecoyInput> = random_decoys(2);
+
+        let mut ledger = RemoteLedger::default();
+        ledger.commitments.insert(
+            true_input.public_key().to_compressed(),
+            true_input
+                .revealed_commitment
+                .commit(RingCtMaterial::pc_gens())
+                .to_affine(),
+        );
+        ledger.commitments.insert(
+            decoy_inputs[0].public_key.to_compressed(),
+            decoy_inputs[0].commitment,
+        );
+
+        let material = RingCtMaterial {
+            inputs: vec![MlsagMaterial::new(true_input, decoy_inputs.clone(), &mut OsRng).unwrap()],
+            outputs: vec![Output::new(G1Projective::random(OsRng), 3)],
+            fee: Amount::ZERO,
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+        let (tx, _revealed_output_commitments) = material
+            .sign(&mut OsRng)
+            .expect("Failed to sign transaction");
+
+        assert_eq!(
+            block_on(tx.verify_with_async_lookup(&ledger)),
+            Err(Error::CommitmentNotFound {
+                input_index: 0,
+                public_key: codec::to_hex(&decoy_inputs[1].public_key.to_compressed()),
+            })
+        );
+
+        ledger.commitments.insert(
+            decoy_inputs[1].public_key.to_compressed(),
+            decoy_inputs[1].commitment,
+        );
+        assert_eq!(block_on(tx.verify_with_async_lookup(&ledger)), Ok(()));
+    }
+}
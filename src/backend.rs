@@ -0,0 +1,49 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use bls_bulletproofs::blstrs::{G1Projective, Scalar};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// The multi-scalar multiplications done while verifying ring signatures
+/// and batched range proofs.
+///
+/// Verification logic stays in this crate, a backend only computes
+/// `sum(scalars[i] * points[i])`, so deployments can plug in an accelerated
+/// implementation (a GPU, a different pippenger) without forking it.
+pub trait VerificationBackend: Send + Sync {
+    /// Computes `sum(scalars[i] * points[i])`, `points` and `scalars` have
+    /// the same length.
+    fn multi_exp(&self, points: &[G1Projective], scalars: &[Scalar]) -> G1Projective;
+
+    /// Computes several independent multi-exponentiations, in order.
+    ///
+    /// Verifying many rings hands each step of all of them over at once,
+    /// backends with a high per-call cost should override this.
+    fn batch_multi_exp(&self, jobs: &[(&[G1Projective], &[Scalar])]) -> Vec<G1Projective> {
+        jobs.iter()
+            .map(|(points, scalars)| self.multi_exp(points, scalars))
+            .collect()
+    }
+}
+
+/// The default backend, blst's multi-exponentiation on the CPU
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CpuBackend;
+
+impl VerificationBackend for CpuBackend {
+    fn multi_exp(&self, points: &[G1Projective], scalars: &[Scalar]) -> G1Projective {
+        G1Projective::multi_exp(points, scalars)
+    }
+
+    #[cfg(feature = "parallel")]
+    fn batch_multi_exp(&self, jobs: &[(&[G1Projective], &[Scalar])]) -> Vec<G1Projective> {
+        jobs.par_iter()
+            .map(|(points, scalars)| G1Projective::multi_exp(points, scalars))
+            .collect()
+    }
+}
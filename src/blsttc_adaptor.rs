@@ -0,0 +1,150 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Conversions from blsttc keys, for using them as the keys of inputs and
+//! outputs.
+//!
+//! blsttc keys live on the same G1 curve but may be built on another version
+//! of blstrs, so they are converted through their encodings. Only a key share
+//! whose secret is known in full can own a `TrueInput`; the shares of a
+//! threshold key sign together with `threshold::SigningSession` instead.
+
+use bls_bulletproofs::blstrs::{G1Affine, Scalar};
+
+use crate::{
+    ringct::Amount, Error, Output, PublicKey, Result, RevealedCommitment, SecretKey, TrueInput,
+};
+
+/// The secret key scalar of a blsttc secret key or key share
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlsttcSecretKey(pub Scalar);
+
+/// The G1 point of a blsttc public key or public key share
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlsttcPublicKey(pub G1Affine);
+
+impl TryFrom<&blsttc::SecretKey> for BlsttcSecretKey {
+    type Error = Error;
+
+    fn try_from(secret_key: &blsttc::SecretKey) -> Result<Self> {
+        scalar_from_be_bytes(secret_key.to_bytes())
+    }
+}
+
+impl TryFrom<&blsttc::SecretKeyShare> for BlsttcSecretKey {
+    type Error = Error;
+
+    fn try_from(secret_key_share: &blsttc::SecretKeyShare) -> Result<Self> {
+        scalar_from_be_bytes(secret_key_share.to_bytes())
+    }
+}
+
+impl From<BlsttcSecretKey> for Scalar {
+    fn from(secret_key: BlsttcSecretKey) -> Self {
+        secret_key.0
+    }
+}
+
+impl TryFrom<&blsttc::PublicKey> for BlsttcPublicKey {
+    type Error = Error;
+
+    fn try_from(public_key: &blsttc::PublicKey) -> Result<Self> {
+        point_from_bytes(public_key.to_bytes())
+    }
+}
+
+impl TryFrom<&blsttc::PublicKeyShare> for BlsttcPublicKey {
+    type Error = Error;
+
+    fn try_from(public_key_share: &blsttc::PublicKeyShare) -> Result<Self> {
+        point_from_bytes(public_key_share.to_bytes())
+    }
+}
+
+impl From<BlsttcPublicKey> for G1Affine {
+    fn from(public_key: BlsttcPublicKey) -> Self {
+        public_key.0
+    }
+}
+
+impl From<BlsttcSecretKey> for SecretKey {
+    fn from(secret_key: BlsttcSecretKey) -> Self {
+        Self(secret_key.0)
+    }
+}
+
+impl From<BlsttcPublicKey> for PublicKey {
+    fn from(public_key: BlsttcPublicKey) -> Self {
+        Self(public_key.0)
+    }
+}
+
+impl TrueInput {
+    /// A true input owned by a blsttc secret key share
+    pub fn from_blsttc_key_share(
+        secret_key_share: &blsttc::SecretKeyShare,
+        revealed_commitment: RevealedCommitment,
+    ) -> Result<Self> {
+        Ok(Self::new(
+            BlsttcSecretKey::try_from(secret_key_share)?,
+            revealed_commitment,
+        ))
+    }
+}
+
+impl Output {
+    /// An output paid to a blsttc public key
+    pub fn to_blsttc_public_key(
+        public_key: &blsttc::PublicKey,
+        amount: impl Into<Amount>,
+    ) -> Result<Self> {
+        Ok(Self::new(BlsttcPublicKey::try_from(public_key)?, amount))
+    }
+}
+
+// blsttc encodes secret keys big endian
+fn scalar_from_be_bytes(bytes: [u8; 32]) -> Result<BlsttcSecretKey> {
+    Option::from(Scalar::from_bytes_be(&bytes))
+        .map(BlsttcSecretKey)
+        .ok_or(Error::InvalidScalarEncoding)
+}
+
+fn point_from_bytes(bytes: [u8; 48]) -> Result<BlsttcPublicKey> {
+    Option::from(G1Affine::from_compressed(&bytes))
+        .map(BlsttcPublicKey)
+        .ok_or(Error::InvalidPointEncoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{group::Curve, rand::rngs::OsRng};
+
+    use super::*;
+
+    #[test]
+    fn test_blsttc_keys_convert_to_matching_keys() {
+        let secret_key = blsttc::SecretKey::random();
+        let secret_key_share = blsttc::SecretKeyShare::from_bytes(secret_key.to_bytes()).unwrap();
+        let public_key_share = secret_key_share.public_key_share();
+
+        let true_input = TrueInput::from_blsttc_key_share(
+            &secret_key_share,
+            RevealedCommitment::from_value(3, OsRng),
+        )
+        .unwrap();
+        assert_eq!(
+            BlsttcPublicKey::try_from(&public_key_share),
+            Ok(BlsttcPublicKey(true_input.public_key().to_affine()))
+        );
+
+        let public_key = secret_key.public_key();
+        let output = Output::to_blsttc_public_key(&public_key, 3).unwrap();
+        assert_eq!(
+            output.public_key(),
+            BlsttcPublicKey::try_from(&public_key).unwrap().0
+        );
+    }
+}
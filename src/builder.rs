@@ -0,0 +1,287 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use bls_bulletproofs::rand::{rngs::OsRng, CryptoRng, RngCore};
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+
+use crate::{
+    ringct::{Amount, RingCtConfig, RingCtPolicy, RingCtTransaction},
+    DecoyInput, Error, MlsagMaterial, Output, Result, RevealedCommitment, RingCtMaterial,
+    TrueInput,
+};
+
+/// Assembles the inputs and outputs of a transaction, checking that they
+/// balance and that every input has decoys before anything is signed.
+#[derive(Debug, Clone, Default)]
+pub struct RingCtTransactionBuilder {
+    inputs: Vec<(TrueInput, Vec<DecoyInput>)>,
+    pending_decoys: Vec<DecoyInput>,
+    outputs: Vec<Output>,
+    fee: Amount,
+    extra: Vec<u8>,
+    network_id: Option<[u8; 32]>,
+    not_valid_after: Option<u64>,
+    config: Option<RingCtConfig>,
+    policy: RingCtPolicy,
+    rng_seed: Option<[u8; 32]>,
+}
+
+impl RingCtTransactionBuilder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds an input to spend
+    pub fn add_true_input(mut self, true_input: TrueInput) -> Self {
+        let decoys = std::mem::take(&mut self.pending_decoys);
+        self.inputs.push((true_input, decoys));
+        self
+    }
+
+    /// Adds decoys to the ring of the most recently added true input,
+    /// or of the next one if no true input has been added yet.
+    pub fn add_decoys(mut self, decoys: impl IntoIterator<Item = DecoyInput>) -> Self {
+        match self.inputs.last_mut() {
+            Some((_, ring)) => ring.extend(decoys),
+            None => self.pending_decoys.extend(decoys),
+        }
+        self
+    }
+
+    pub fn add_output(mut self, output: Output) -> Self {
+        self.outputs.push(output);
+        self
+    }
+
+    pub fn set_fee(mut self, fee: impl Into<Amount>) -> Self {
+        self.fee = fee.into();
+        self
+    }
+
+    /// Sets the application data signed with the transaction
+    pub fn set_extra(mut self, extra: impl Into<Vec<u8>>) -> Self {
+        self.extra = extra.into();
+        self
+    }
+
+    /// Binds the transaction to a network, eg. by its genesis hash or chain id
+    pub fn set_network_id(mut self, network_id: [u8; 32]) -> Self {
+        self.network_id = Some(network_id);
+        self
+    }
+
+    /// Lets the transaction expire if it isn't included by `height`
+    pub fn set_not_valid_after(mut self, height: u64) -> Self {
+        self.not_valid_after = Some(height);
+        self
+    }
+
+    /// Requires every input to have exactly the number of decoys set by `config`
+    pub fn set_config(mut self, config: RingCtConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Limits the number of inputs, outputs and decoys, the default
+    /// `RingCtPolicy` is used otherwise, as verifiers do
+    pub fn set_policy(mut self, policy: RingCtPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Makes the ring ordering, blinding factors and proofs reproducible
+    pub fn set_rng_seed(mut self, seed: [u8; 32]) -> Self {
+        self.rng_seed = Some(seed);
+        self
+    }
+
+    /// Validates the inputs and outputs and produces the material to sign
+    pub fn build(self) -> Result<RingCtMaterial> {
+        let mut rng = self.rng();
+        self.build_with_rng(&mut rng)
+    }
+
+    /// Validates, builds and signs the transaction
+    pub fn sign(self) -> Result<(RingCtTransaction, Vec<RevealedCommitment>)> {
+        let mut rng = self.rng();
+        let material = self.build_with_rng(&mut rng)?;
+        material.sign(&mut rng)
+    }
+
+    fn rng(&self) -> ChaCha20Rng {
+        let seed = self.rng_seed.unwrap_or_else(|| {
+            let mut seed = [0u8; 32];
+            OsRng.fill_bytes(&mut seed);
+            seed
+        });
+        ChaCha20Rng::from_seed(seed)
+    }
+
+    fn build_with_rng(self, mut rng: impl RngCore + CryptoRng) -> Result<RingCtMaterial> {
+        if let Some(input_index) = self.inputs.iter().position(|(_, d)| d.is_empty()) {
+            return Err(Error::MissingDecoys { input_index });
+        }
+        if let Some(config) = &self.config {
+            for (input_index, (_, decoys)) in self.inputs.iter().enumerate() {
+                config.check_ring_size(input_index, decoys.len() + 1)?;
+            }
+        }
+        self.policy.check(
+            self.outputs.len(),
+            self.inputs.iter().map(|(_, decoys)| decoys.len() + 1),
+        )?;
+        self.policy
+            .check_output_amounts(self.outputs.iter().map(Output::amount))?;
+
+        let inputs = self
+            .inputs
+            .into_iter()
+            .map(|(true_input, decoys)| MlsagMaterial::new(true_input, decoys, &mut rng))
+            .collect::<Result<_>>()?;
+
+        let material = RingCtMaterial {
+            inputs,
+            outputs: self.outputs,
+            fee: self.fee,
+            extra: self.extra,
+            network_id: self.network_id,
+            not_valid_after: self.not_valid_after,
+        };
+        material.validate()?;
+        Ok(material)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use bls_bulletproofs::{
+        blstrs::{G1Affine, G1Projective, Scalar},
+        group::{ff::Field, Curve, Group},
+    };
+
+    use super::*;
+
+    fn random_decoys(n: usize, ledger: &mut BTreeMap<[u8; 48], G1Affine>) -> Vec<DecoyInput> {
+        Vec::from_iter((0..n).map(|_| {
+            let decoy = DecoyInput {
+                public_key: G1Projective::random(OsRng).to_affine(),
+                commitment: G1Projective::random(OsRng).to_affine(),
+            };
+            ledger.insert(decoy.public_key.to_compressed(), decoy.commitment);
+            decoy
+        }))
+    }
+
+    #[test]
+    fn test_builder_signs_verifiable_tx() {
+        let mut ledger = BTreeMap::new();
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(7, OsRng),
+        );
+        ledger.insert(
+            true_input.public_key().to_compressed(),
+            true_input
+                .revealed_commitment
+                .commit(&crate::pedersen_gens())
+                .to_affine(),
+        );
+
+        let (tx, _revealed_output_commitments) = RingCtTransactionBuilder::new()
+            .add_true_input(true_input)
+            .add_decoys(random_decoys(3, &mut ledger))
+            .add_output(Output::new(G1Projective::random(OsRng), 4))
+            .add_output(Output::new(G1Projective::random(OsRng), 3))
+            .sign()
+            .expect("Failed to sign transaction");
+
+        let public_commitments = Vec::from_iter(tx.mlsags.iter().map(|mlsag| {
+            Vec::from_iter(
+                mlsag
+                    .public_keys()
+                    .iter()
+                    .map(|pk| ledger[&pk.to_compressed()]),
+            )
+        }));
+        assert!(tx.verify(&public_commitments).is_ok());
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_material() {
+        let mut ledger = BTreeMap::new();
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(7, OsRng),
+        );
+        let output = Output::new(G1Projective::random(OsRng), 6);
+
+        let builder = RingCtTransactionBuilder::new().add_true_input(true_input);
+        assert_eq!(
+            builder.clone().add_output(output.clone()).build().err(),
+            Some(Error::MissingDecoys { input_index: 0 })
+        );
+
+        let builder = builder.add_decoys(random_decoys(2, &mut ledger));
+        assert_eq!(builder.clone().build().err(), Some(Error::NoOutputs));
+        assert_eq!(
+            builder
+                .clone()
+                .set_policy(RingCtPolicy {
+                    max_decoys: 1,
+                    ..Default::default()
+                })
+                .build()
+                .err(),
+            Some(Error::TooManyDecoys {
+                input_index: 0,
+                max: 1,
+                actual: 2
+            })
+        );
+        assert_eq!(
+            builder
+                .clone()
+                .add_output(Output::new(G1Projective::random(OsRng), 0))
+                .set_policy(RingCtPolicy {
+                    min_output_amount: Amount::new(1),
+                    ..Default::default()
+                })
+                .build()
+                .err(),
+            Some(Error::DustOutput {
+                output_index: 0,
+                amount: 0,
+                min: 1
+            })
+        );
+        assert_eq!(
+            builder.add_output(output).build().err(),
+            Some(Error::InvalidBalance {
+                input_amount: 7,
+                output_amount: 6
+            })
+        );
+    }
+
+    #[test]
+    fn test_builder_rng_seed_is_deterministic() {
+        let mut ledger = BTreeMap::new();
+        let builder = RingCtTransactionBuilder::new()
+            .add_decoys(random_decoys(2, &mut ledger))
+            .add_true_input(TrueInput::new(
+                Scalar::random(OsRng),
+                RevealedCommitment::from_value(5, OsRng),
+            ))
+            .add_output(Output::new(G1Projective::random(OsRng), 5))
+            .set_rng_seed([42; 32]);
+
+        let (tx_a, _) = builder.clone().sign().expect("Failed to sign transaction");
+        let (tx_b, _) = builder.sign().expect("Failed to sign transaction");
+        assert_eq!(tx_a, tx_b);
+    }
+}
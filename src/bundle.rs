@@ -0,0 +1,219 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Bundles of transactions which a ledger must apply all together or not at
+//! all, eg. the legs of a multi-party settlement signed by different parties.
+//!
+//! The bundle hash commits to the key images of every member, which are
+//! known before signing, and each member signs it as a prefix of its extra
+//! data. A ledger must reject a transaction whose `bundle_hash()` is set
+//! unless it comes in a `TransactionBundle` with all the other members.
+
+use bls_bulletproofs::blstrs::G1Affine;
+use tiny_keccak::{Hasher, Sha3};
+
+use crate::{
+    codec::{self, Reader},
+    ringct::RingCtTransaction,
+    Error, KeyImage, Result, RingCtMaterial,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const BUNDLE_DOMAIN: &[u8] = b"blst-ringct-bundle";
+// the prefix of the extra data of bundled transactions, before the bundle hash
+const BUNDLE_EXTRA_TAG: &[u8] = b"blst-ringct-bundle:";
+
+impl RingCtMaterial {
+    /// Binds the transaction to the bundle with hash `bundle_hash`, by
+    /// prefixing its extra data with it
+    pub fn bind_to_bundle(&mut self, bundle_hash: [u8; 32]) {
+        self.extra = [BUNDLE_EXTRA_TAG, &bundle_hash, &self.extra].concat();
+    }
+}
+
+impl RingCtTransaction {
+    /// The hash of the bundle the transaction is bound to, if any
+    pub fn bundle_hash(&self) -> Option<[u8; 32]> {
+        let hash = self.extra.strip_prefix(BUNDLE_EXTRA_TAG)?.get(..32)?;
+        hash.try_into().ok()
+    }
+}
+
+/// Transactions bound to each other, see `TransactionBundle::bind()`
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionBundle {
+    transactions: Vec<RingCtTransaction>,
+}
+
+impl TransactionBundle {
+    /// The hash of a bundle of transactions spending the key images of
+    /// `members`, one list per transaction in bundle order
+    pub fn hash_of(members: &[Vec<KeyImage>]) -> [u8; 32] {
+        let mut sha3 = Sha3::v256();
+        sha3.update(BUNDLE_DOMAIN);
+        sha3.update(&(members.len() as u32).to_le_bytes());
+        for key_images in members {
+            sha3.update(&(key_images.len() as u32).to_le_bytes());
+            for key_image in key_images {
+                sha3.update(&key_image.to_bytes());
+            }
+        }
+        let mut hash = [0; 32];
+        sha3.finalize(&mut hash);
+        hash
+    }
+
+    /// Binds the materials of the bundle's transactions to each other before
+    /// they're signed, returning the bundle hash
+    pub fn bind(materials: &mut [RingCtMaterial]) -> [u8; 32] {
+        let members: Vec<Vec<KeyImage>> = materials.iter().map(|m| m.key_images()).collect();
+        let bundle_hash = Self::hash_of(&members);
+        for material in materials.iter_mut() {
+            material.bind_to_bundle(bundle_hash);
+        }
+        bundle_hash
+    }
+
+    /// Checks that every transaction is bound to the bundle of all of them
+    pub fn new(transactions: Vec<RingCtTransaction>) -> Result<Self> {
+        let members: Vec<Vec<KeyImage>> = transactions.iter().map(|tx| tx.key_images()).collect();
+        let bundle_hash = Self::hash_of(&members);
+        if let Some(tx_index) = transactions
+            .iter()
+            .position(|tx| tx.bundle_hash() != Some(bundle_hash))
+        {
+            return Err(Error::TransactionNotInBundle { tx_index });
+        }
+        Ok(Self { transactions })
+    }
+
+    /// The hash every transaction of the bundle is bound to
+    pub fn hash(&self) -> [u8; 32] {
+        let members: Vec<Vec<KeyImage>> =
+            self.transactions.iter().map(|tx| tx.key_images()).collect();
+        Self::hash_of(&members)
+    }
+
+    pub fn transactions(&self) -> &[RingCtTransaction] {
+        &self.transactions
+    }
+
+    pub fn into_transactions(self) -> Vec<RingCtTransaction> {
+        self.transactions
+    }
+
+    /// Verifies every transaction, given the ring commitments of each
+    pub fn verify(&self, public_commitments_per_tx: &[Vec<Vec<G1Affine>>]) -> Result<()> {
+        if public_commitments_per_tx.len() != self.transactions.len() {
+            return Err(Error::ExpectedPublicCommitmentsForEachTransaction);
+        }
+        for (tx, public_commitments_per_ring) in
+            self.transactions.iter().zip(public_commitments_per_tx)
+        {
+            tx.verify(public_commitments_per_ring)?;
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Default::default();
+        codec::write_len(&mut v, self.transactions.len());
+        for tx in self.transactions.iter() {
+            let bytes = tx.to_bytes();
+            codec::write_len(&mut v, bytes.len());
+            v.extend(bytes);
+        }
+        v
+    }
+
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let transactions = reader.read_vec(|r| {
+            let len = r.read_len()?;
+            RingCtTransaction::from_bytes(&r.take(len)?)
+        })?;
+        reader.finish()?;
+        Self::new(transactions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{
+        blstrs::{G1Projective, Scalar},
+        group::{ff::Field, Group},
+        rand::rngs::OsRng,
+    };
+
+    use super::*;
+    use crate::{
+        mock::random_decoys, ringct::Amount, MlsagMaterial, Output, RevealedCommitment, TrueInput,
+    };
+
+    fn material(value: u64) -> RingCtMaterial {
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(value, OsRng),
+        );
+        let decoy_inputs = random_decoys(2);
+        RingCtMaterial {
+            inputs: vec![MlsagMaterial::new(true_input, decoy_inputs, &mut OsRng).unwrap()],
+            outputs: vec![Output::new(G1Projective::random(OsRng), value)],
+            fee: Amount::ZERO,
+            extra: b"note".to_vec(),
+            network_id: None,
+            not_valid_after: None,
+        }
+    }
+
+    #[test]
+    fn test_bundle_binds_its_transactions() {
+        let mut materials = vec![material(3), material(5)];
+        let bundle_hash = TransactionBundle::bind(&mut materials);
+        let signed: Vec<(RingCtTransaction, Vec<Vec<G1Affine>>)> = materials
+            .iter()
+            .map(|m| {
+                let (tx, _revealed_output_commitments) =
+                    m.sign(&mut OsRng).expect("Failed to sign transaction");
+                let public_commitments = m
+                    .inputs
+                    .iter()
+                    .map(|i| i.commitments(RingCtMaterial::pc_gens()))
+                    .collect();
+                (tx, public_commitments)
+            })
+            .collect();
+        let (txs, public_commitments): (Vec<_>, Vec<_>) = signed.into_iter().unzip();
+        assert_eq!(txs[0].bundle_hash(), Some(bundle_hash));
+        assert!(txs[0].extra.ends_with(b"note"));
+
+        let bundle = TransactionBundle::new(txs.clone()).expect("Failed to bundle");
+        assert_eq!(bundle.hash(), bundle_hash);
+        assert_eq!(bundle.verify(&public_commitments), Ok(()));
+        assert_eq!(
+            TransactionBundle::from_bytes(&bundle.to_bytes()),
+            Ok(bundle)
+        );
+
+        // a member alone, or with a transaction from elsewhere, isn't a bundle
+        assert_eq!(
+            TransactionBundle::new(txs[..1].to_vec()),
+            Err(Error::TransactionNotInBundle { tx_index: 0 })
+        );
+        let (stray, _revealed_output_commitments) = material(5)
+            .sign(&mut OsRng)
+            .expect("Failed to sign transaction");
+        assert_eq!(stray.bundle_hash(), None);
+        assert_eq!(
+            TransactionBundle::new(vec![txs[0].clone(), stray]),
+            Err(Error::TransactionNotInBundle { tx_index: 0 })
+        );
+    }
+}
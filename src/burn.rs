@@ -0,0 +1,232 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Burn outputs, which provably destroy their amount.
+//!
+//! A burn output is paid to a nothing-up-my-sleeve public key hashed to the
+//! curve, whose secret key nobody knows, so it can never be spent. Rings may
+//! not contain it, so it can't be used as a decoy either. The sender proves
+//! the total amount burned by a transaction with a `BurnProof`: a proof of
+//! knowledge of the blinding factor `b` with `sum(C_burn) - vB = bB'`.
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::{ff::Field, Curve, Group},
+    rand::{CryptoRng, RngCore},
+};
+use std::sync::OnceLock;
+
+use crate::{
+    codec::Reader,
+    mlsag::hash_to_scalar,
+    ringct::{Amount, OutputProof, RingCtTransaction},
+    Error, Output, Result, RevealedCommitment, RingCtMaterial,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const BURN_KEY_DOMAIN: &[u8] = b"blst-ringct-burn-public-key";
+const BURN_PROOF_DOMAIN: &[u8] = b"blst-ringct-burn-proof";
+
+/// The public key of burn outputs, which has no known secret key
+pub fn burn_public_key() -> G1Affine {
+    static BURN_PUBLIC_KEY: OnceLock<G1Affine> = OnceLock::new();
+    *BURN_PUBLIC_KEY
+        .get_or_init(|| G1Projective::hash_to_curve(b"burn", BURN_KEY_DOMAIN, &[]).to_affine())
+}
+
+impl Output {
+    /// An output destroying `amount`
+    pub fn burn(amount: impl Into<Amount>) -> Self {
+        Self::new(burn_public_key(), amount)
+    }
+}
+
+impl OutputProof {
+    pub fn is_burn(&self) -> bool {
+        *self.public_key() == burn_public_key()
+    }
+}
+
+/// Proves the total amount destroyed by the burn outputs of a transaction
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BurnProof {
+    pub amount: Amount,
+    pub challenge: Scalar,
+    pub response: Scalar,
+}
+
+impl BurnProof {
+    /// Proves the amount burned by `tx`, given the revealed commitments of its
+    /// outputs returned when it was signed
+    pub fn create(
+        tx: &RingCtTransaction,
+        revealed_output_commitments: &[RevealedCommitment],
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Self> {
+        let burned: Vec<&RevealedCommitment> = tx
+            .outputs
+            .iter()
+            .enumerate()
+            .filter(|(_, output)| output.is_burn())
+            .map(|(i, _)| {
+                revealed_output_commitments
+                    .get(i)
+                    .ok_or(Error::OutputIndexOutOfRange(i))
+            })
+            .collect::<Result<_>>()?;
+        let amount = Amount::checked_sum(burned.iter().map(|r| Amount::new(r.value)))?;
+        let blinding = burned
+            .iter()
+            .fold(Scalar::zero(), |sum, r| sum + r.blinding);
+
+        let nonce = Scalar::random(&mut *rng);
+        let mut proof = Self {
+            amount,
+            challenge: Scalar::zero(),
+            response: Scalar::zero(),
+        };
+        proof.challenge = proof.challenge(tx, &(RingCtMaterial::pc_gens().B_blinding * nonce));
+        proof.response = nonce - proof.challenge * blinding;
+
+        // the revealed commitments may not be those of the transaction
+        tx.verify_burn_proof(&proof)?;
+        Ok(proof)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Default::default();
+        v.extend(self.amount.to_le_bytes());
+        v.extend(self.challenge.to_bytes_le());
+        v.extend(self.response.to_bytes_le());
+        v
+    }
+
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let proof = Self {
+            amount: Amount::new(reader.read_u64()?),
+            challenge: reader.read_scalar()?,
+            response: reader.read_scalar()?,
+        };
+        reader.finish()?;
+        Ok(proof)
+    }
+
+    fn challenge(&self, tx: &RingCtTransaction, nonce_commitment: &G1Projective) -> Scalar {
+        let id = tx.id();
+        let amount = self.amount.to_le_bytes();
+        let nonce_commitment = nonce_commitment.to_compressed();
+        hash_to_scalar(&[BURN_PROOF_DOMAIN, &id, &amount, &nonce_commitment])
+    }
+}
+
+impl RingCtTransaction {
+    /// Checks that `proof` shows the burn outputs destroy `proof.amount` in
+    /// total. The ring signatures sign the outputs, so the amount can't change
+    /// once the transaction is signed.
+    pub fn verify_burn_proof(&self, proof: &BurnProof) -> Result<()> {
+        let pc_gens = RingCtMaterial::pc_gens();
+        let burned = self
+            .outputs
+            .iter()
+            .filter(|output| output.is_burn())
+            .fold(G1Projective::identity(), |sum, output| {
+                sum + G1Projective::from(output.commitment())
+            });
+
+        // sum(C_burn) - vB = bB' for some b
+        let blinding_commitment = burned - pc_gens.B * Scalar::from(proof.amount.value());
+        let nonce_commitment =
+            pc_gens.B_blinding * proof.response + blinding_commitment * proof.challenge;
+        if proof.challenge(self, &nonce_commitment) != proof.challenge {
+            return Err(Error::InvalidBurnProof);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{mock::random_decoys, MlsagMaterial, TrueInput};
+
+    fn material(true_input: TrueInput, outputs: Vec<Output>) -> RingCtMaterial {
+        let decoys = random_decoys(3);
+        RingCtMaterial {
+            inputs: vec![MlsagMaterial::new(true_input, decoys, &mut OsRng).unwrap()],
+            outputs,
+            fee: Amount::ZERO,
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        }
+    }
+
+    #[test]
+    fn test_burn_proof() {
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(10, OsRng),
+        );
+        let material = material(
+            true_input,
+            vec![
+                Output::burn(4),
+                Output::new(G1Projective::random(OsRng), 3),
+                Output::burn(3),
+            ],
+        );
+        let (tx, revealed_output_commitments) = material
+            .sign(&mut OsRng)
+            .expect("Failed to sign transaction");
+        assert_eq!(
+            tx.outputs
+                .iter()
+                .map(OutputProof::is_burn)
+                .collect::<Vec<_>>(),
+            vec![true, false, true]
+        );
+
+        let proof = BurnProof::create(&tx, &revealed_output_commitments, &mut OsRng)
+            .expect("Failed to create burn proof");
+        assert_eq!(proof.amount, 7);
+        assert_eq!(tx.verify_burn_proof(&proof), Ok(()));
+
+        let decoded = BurnProof::from_bytes(&proof.to_bytes()).unwrap();
+        assert_eq!(decoded, proof);
+
+        let mut overstated = proof;
+        overstated.amount = Amount::new(8);
+        assert_eq!(
+            tx.verify_burn_proof(&overstated),
+            Err(Error::InvalidBurnProof)
+        );
+    }
+
+    #[test]
+    fn test_burn_outputs_cannot_be_spent_or_used_as_decoys() {
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(2, OsRng),
+        );
+        let mut material = material(
+            true_input,
+            vec![Output::new(G1Projective::random(OsRng), 2)],
+        );
+        material.inputs[0].decoy_inputs[0].public_key = burn_public_key();
+
+        assert_eq!(
+            material.sign(&mut OsRng).err(),
+            Some(Error::BurnOutputInRing { input_index: 0 })
+        );
+    }
+}
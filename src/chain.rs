@@ -0,0 +1,143 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Chaining, for payment pipelines which spend the outputs of a transaction
+//! as soon as it's signed rather than once it's confirmed.
+//!
+//! The chained transaction only verifies against a ledger which holds the
+//! outputs it spends, so it has to be submitted after its parent. Its rings
+//! should still be filled with decoys from the ledger.
+
+use bls_bulletproofs::rand::{CryptoRng, RngCore};
+
+use crate::{
+    ringct::RingCtTransaction, DecoyInput, Error, MlsagMaterial, Result, RevealedCommitment,
+    SecretKey, TrueInput,
+};
+
+impl RingCtTransaction {
+    /// The true input spending output `output_index` of this transaction,
+    /// given the revealed output commitments `sign()` returned with it and
+    /// the output's secret key. Fails if they don't open the output's
+    /// commitment or own its public key.
+    pub fn chained_true_input(
+        &self,
+        revealed_output_commitments: &[RevealedCommitment],
+        output_index: usize,
+        secret_key: impl Into<SecretKey>,
+    ) -> Result<TrueInput> {
+        if revealed_output_commitments.len() != self.outputs.len() {
+            return Err(Error::AmountSecretsDoNotMatchCommitment);
+        }
+        let output = self
+            .outputs
+            .get(output_index)
+            .ok_or(Error::OutputIndexOutOfRange(output_index))?;
+        TrueInput::from_output(
+            output,
+            secret_key.into(),
+            revealed_output_commitments[output_index],
+        )
+    }
+
+    /// The inputs of a transaction spending outputs of this one, for
+    /// `RingCtMaterial::inputs`. Each spend is the index of an output, its
+    /// secret key and the decoys to hide it among.
+    pub fn chained_inputs<S: Into<SecretKey>>(
+        &self,
+        revealed_output_commitments: &[RevealedCommitment],
+        spends: impl IntoIterator<Item = (usize, S, Vec<DecoyInput>)>,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Vec<MlsagMaterial>> {
+        spends
+            .into_iter()
+            .map(|(output_index, secret_key, decoy_inputs)| {
+                let true_input =
+                    self.chained_true_input(revealed_output_commitments, output_index, secret_key)?;
+                MlsagMaterial::new(true_input, decoy_inputs, &mut *rng)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{
+        blstrs::{G1Projective, Scalar},
+        group::{ff::Field, Group},
+        rand::rngs::OsRng,
+    };
+
+    use super::*;
+    use crate::{mock::random_decoys, ringct::Amount, Output, RingCtMaterial};
+
+    #[test]
+    fn test_chained_transaction_spends_unconfirmed_outputs() {
+        let (change_key, payee_key) = (Scalar::random(OsRng), Scalar::random(OsRng));
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(10, OsRng),
+        );
+        let parent_material = RingCtMaterial {
+            inputs: vec![MlsagMaterial::new(true_input, random_decoys(2), &mut OsRng).unwrap()],
+            outputs: vec![
+                Output::new(crate::public_key(payee_key), 3),
+                Output::new(crate::public_key(change_key), 7),
+            ],
+            fee: Amount::ZERO,
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+        let (parent, revealed_output_commitments) = parent_material
+            .sign(&mut OsRng)
+            .expect("Failed to sign transaction");
+
+        let inputs = parent
+            .chained_inputs(
+                &revealed_output_commitments,
+                [(1, change_key, random_decoys(2))],
+                &mut OsRng,
+            )
+            .expect("Failed to chain inputs");
+        let child_material = RingCtMaterial {
+            inputs,
+            outputs: vec![Output::new(G1Projective::random(OsRng), 7)],
+            fee: Amount::ZERO,
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+        let (child, _revealed_output_commitments) = child_material
+            .sign(&mut OsRng)
+            .expect("Failed to sign chained transaction");
+        let public_commitments = child_material
+            .inputs
+            .iter()
+            .map(|m| m.commitments(RingCtMaterial::pc_gens()))
+            .collect::<Vec<_>>();
+        assert_eq!(child.verify(&public_commitments), Ok(()));
+
+        assert_eq!(
+            parent
+                .chained_true_input(&revealed_output_commitments, 0, change_key)
+                .map(|_| ()),
+            Err(Error::SecretKeyDoesNotOwnOutput)
+        );
+        assert_eq!(
+            parent
+                .chained_true_input(&revealed_output_commitments[..1], 0, payee_key)
+                .map(|_| ()),
+            Err(Error::AmountSecretsDoNotMatchCommitment)
+        );
+        assert_eq!(
+            parent
+                .chained_true_input(&revealed_output_commitments, 2, payee_key)
+                .map(|_| ()),
+            Err(Error::OutputIndexOutOfRange(2))
+        );
+    }
+}
@@ -0,0 +1,308 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! CLSAG: Concise Linkable Spontaneous Anonymous Group signatures.
+//!
+//! Rather than signing with the spend key and the commitment blinding delta
+//! as two separate ring dimensions (as MLSAG does), CLSAG aggregates them into
+//! a single key, so each ring member needs one response scalar instead of two.
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::{ff::Field, prime::PrimeCurveAffine, Curve, Group, GroupEncoding},
+    rand::{CryptoRng, RngCore},
+    PedersenGens,
+};
+use subtle::{Choice, ConditionallySelectable};
+
+use crate::{
+    codec::{self, Reader},
+    mlsag::{append_ring, ct_get, ct_set, ct_wrap, hash_to_scalar, nonce_transcript},
+    ringct::validate_point,
+    Error, KeyImage, MlsagMaterial, Result, RevealedCommitment,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const AGG_0_DOMAIN: &[u8] = b"blst-ringct-clsag-agg-0";
+const AGG_1_DOMAIN: &[u8] = b"blst-ringct-clsag-agg-1";
+const ROUND_DOMAIN: &[u8] = b"blst-ringct-clsag-round";
+
+impl MlsagMaterial {
+    /// Signs this input with a CLSAG instead of an MLSAG, with nonces drawn
+    /// as `sign()` draws them
+    pub fn sign_clsag(
+        &self,
+        msg: &[u8],
+        revealed_pseudo_commitment: &RevealedCommitment,
+        pc_gens: &PedersenGens,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> ClsagSignature {
+        #[allow(non_snake_case)]
+        let G1 = G1Projective::generator();
+
+        let public_keys = self.public_keys();
+        let commitments = self.commitments(pc_gens);
+        let pi = self.pi();
+
+        let pseudo_commitment = revealed_pseudo_commitment.commit(pc_gens);
+
+        let ring: Vec<(G1Affine, G1Affine)> = public_keys
+            .into_iter()
+            .zip(commitments)
+            .map(|(pk, commitment)| (pk, (commitment - pseudo_commitment).to_affine()))
+            .collect();
+        let pseudo_commitment = pseudo_commitment.to_affine();
+
+        let mut transcript = nonce_transcript(b"clsag", msg);
+        append_ring(&mut transcript, &ring, &pseudo_commitment);
+        let mut nonce_rng = self.nonce_rng(&transcript, rng);
+        let alpha = Scalar::random(&mut nonce_rng);
+        let mut s: Vec<Scalar> = (0..ring.len())
+            .map(|_| Scalar::random(&mut nonce_rng))
+            .collect();
+
+        let secret_keys = (
+            self.true_input.secret_key,
+            self.true_input.revealed_commitment.blinding - revealed_pseudo_commitment.blinding,
+        );
+
+        let hp = crate::hash_to_curve(self.true_input.public_key());
+        let key_image = (hp * secret_keys.0).to_affine();
+        let commitment_key_image = (hp * secret_keys.1).to_affine();
+
+        let ring_bytes = ring_bytes(&ring, &pseudo_commitment);
+        let (mu_p, mu_c) = aggregation_coefficients(&ring_bytes, &key_image, &commitment_key_image);
+        let w_tilde = key_image * mu_p + commitment_key_image * mu_c;
+
+        let ring_len = ring.len();
+        let (public_keys, hidden_commitments): (Vec<G1Affine>, Vec<G1Affine>) =
+            ring.iter().copied().unzip();
+        let mut c: Vec<Scalar> = (0..ring_len).map(|_| Scalar::zero()).collect();
+
+        // As with MLSAG signing, every step does the same work and reads and
+        // writes the ring by scanning all of it, so nothing depends on `pi`.
+        for offset in 0..ring_len {
+            let n = ct_wrap(pi + offset, ring_len);
+            let is_true_input = Choice::from((offset == 0) as u8);
+            let public_key = ct_get(&public_keys, n, G1Affine::identity());
+            let hidden_commitment = ct_get(&hidden_commitments, n, G1Affine::identity());
+            let c_n = Scalar::conditional_select(
+                &ct_get(&c, n, Scalar::zero()),
+                &Scalar::zero(),
+                is_true_input,
+            );
+            let s_n =
+                Scalar::conditional_select(&ct_get(&s, n, Scalar::zero()), &alpha, is_true_input);
+
+            let w = public_key * mu_p + hidden_commitment * mu_c;
+            let c_next = round_hash(
+                &ring_bytes,
+                msg,
+                G1 * s_n + w * c_n,
+                crate::hash_to_curve(public_key.into()) * s_n + w_tilde * c_n,
+            );
+            ct_set(&mut c, ct_wrap(n + 1, ring_len), &c_next);
+        }
+
+        let c_pi = ct_get(&c, pi, Scalar::zero());
+        ct_set(
+            &mut s,
+            pi,
+            &(alpha - c_pi * (mu_p * secret_keys.0 + mu_c * secret_keys.1)),
+        );
+
+        ClsagSignature {
+            c0: c[0],
+            s,
+            key_image: key_image.into(),
+            commitment_key_image,
+            ring,
+            pseudo_commitment,
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct ClsagSignature {
+    #[cfg_attr(
+        feature = "json-schema",
+        schemars(with = "crate::json_schema::ScalarLimbs")
+    )]
+    pub c0: Scalar,
+    #[cfg_attr(
+        feature = "json-schema",
+        schemars(with = "Vec<crate::json_schema::ScalarLimbs>")
+    )]
+    pub s: Vec<Scalar>,
+    pub key_image: KeyImage,
+    /// D = z * Hp(P), where z is the blinding delta between commitment and pseudo commitment
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    #[cfg_attr(
+        feature = "json-schema",
+        schemars(with = "crate::json_schema::HexPoint")
+    )]
+    pub commitment_key_image: G1Affine,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    #[cfg_attr(
+        feature = "json-schema",
+        schemars(with = "Vec<(crate::json_schema::HexPoint, crate::json_schema::HexPoint)>")
+    )]
+    pub ring: Vec<(G1Affine, G1Affine)>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    #[cfg_attr(
+        feature = "json-schema",
+        schemars(with = "crate::json_schema::HexPoint")
+    )]
+    pub pseudo_commitment: G1Affine,
+}
+
+impl ClsagSignature {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Default::default();
+        v.extend(self.c0.to_bytes_le());
+        codec::write_len(&mut v, self.s.len());
+        for s in self.s.iter() {
+            v.extend(s.to_bytes_le());
+        }
+        v.extend(self.key_image.to_bytes());
+        v.extend(self.commitment_key_image.to_bytes().as_ref());
+        codec::write_len(&mut v, self.ring.len());
+        for (x, y) in self.ring.iter() {
+            v.extend(x.to_bytes().as_ref());
+            v.extend(y.to_bytes().as_ref());
+        }
+        v.extend(self.pseudo_commitment.to_bytes().as_ref());
+        v
+    }
+
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let sig = Self::decode(&mut reader)?;
+        reader.finish()?;
+        Ok(sig)
+    }
+
+    pub(crate) fn decode(reader: &mut Reader) -> Result<Self> {
+        let max_ring_size = reader.limits().max_ring_size;
+        let c0 = reader.read_scalar()?;
+        let s = reader.read_vec_max(max_ring_size, "ring members", |r| r.read_scalar())?;
+        let key_image = KeyImage::from(reader.read_point()?);
+        let commitment_key_image = reader.read_point()?;
+        let ring = reader.read_vec_max(max_ring_size, "ring members", |r| {
+            Ok((r.read_point()?, r.read_point()?))
+        })?;
+        let pseudo_commitment = reader.read_point()?;
+
+        Ok(Self {
+            c0,
+            s,
+            key_image,
+            commitment_key_image,
+            ring,
+            pseudo_commitment,
+        })
+    }
+
+    pub fn pseudo_commitment(&self) -> G1Affine {
+        self.pseudo_commitment
+    }
+
+    pub fn key_image(&self) -> KeyImage {
+        self.key_image
+    }
+
+    pub fn public_keys(&self) -> Vec<G1Affine> {
+        self.ring.iter().map(|(pk, _)| *pk).collect()
+    }
+
+    pub fn verify(&self, msg: &[u8], public_commitments: &[G1Affine]) -> Result<()> {
+        // an empty ring would skip the challenge loop and trivially close
+        if self.ring.is_empty() {
+            return Err(Error::EmptyRing);
+        }
+        if self.ring.len() != public_commitments.len() || self.s.len() != self.ring.len() {
+            return Err(Error::ExpectedAPublicCommitmentsForEachRingEntry);
+        }
+        // Check that hidden commitments in the ring where computed with: C - C'
+        for ((_, hidden_commitment), public_commitment) in self.ring.iter().zip(public_commitments)
+        {
+            if G1Projective::from(hidden_commitment)
+                != public_commitment - G1Projective::from(self.pseudo_commitment)
+            {
+                return Err(Error::InvalidHiddenCommitmentInRing);
+            }
+        }
+
+        #[allow(non_snake_case)]
+        let G1 = G1Projective::generator();
+
+        let key_image = self.key_image.to_affine();
+        validate_point(&key_image, || "key image".to_string())?;
+        validate_point(&self.commitment_key_image, || {
+            "commitment key image".to_string()
+        })?;
+
+        let ring_bytes = ring_bytes(&self.ring, &self.pseudo_commitment);
+        let (mu_p, mu_c) =
+            aggregation_coefficients(&ring_bytes, &key_image, &self.commitment_key_image);
+        let w_tilde = key_image * mu_p + self.commitment_key_image * mu_c;
+
+        let mut c = self.c0;
+        for (n, keys) in self.ring.iter().enumerate() {
+            let w = keys.0 * mu_p + keys.1 * mu_c;
+            c = round_hash(
+                &ring_bytes,
+                msg,
+                G1 * self.s[n] + w * c,
+                crate::hash_to_curve(keys.0.into()) * self.s[n] + w_tilde * c,
+            );
+        }
+
+        if self.c0 != c {
+            Err(Error::InvalidRingSignature)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn ring_bytes(ring: &[(G1Affine, G1Affine)], pseudo_commitment: &G1Affine) -> Vec<u8> {
+    let mut v: Vec<u8> = Default::default();
+    for (pk, hidden_commitment) in ring.iter() {
+        v.extend(pk.to_compressed());
+        v.extend(hidden_commitment.to_compressed());
+    }
+    v.extend(pseudo_commitment.to_compressed());
+    v
+}
+
+fn aggregation_coefficients(
+    ring_bytes: &[u8],
+    key_image: &G1Affine,
+    commitment_key_image: &G1Affine,
+) -> (Scalar, Scalar) {
+    let key_image = key_image.to_compressed();
+    let commitment_key_image = commitment_key_image.to_compressed();
+    (
+        hash_to_scalar(&[AGG_0_DOMAIN, ring_bytes, &key_image, &commitment_key_image]),
+        hash_to_scalar(&[AGG_1_DOMAIN, ring_bytes, &key_image, &commitment_key_image]),
+    )
+}
+
+fn round_hash(ring_bytes: &[u8], msg: &[u8], l: G1Projective, r: G1Projective) -> Scalar {
+    hash_to_scalar(&[
+        ROUND_DOMAIN,
+        ring_bytes,
+        msg,
+        &l.to_compressed(),
+        &r.to_compressed(),
+    ])
+}
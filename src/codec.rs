@@ -0,0 +1,270 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Helpers for the hand-written `to_bytes()` and `from_bytes()` methods.
+//! The encoding is documented in `docs/wire-format.md`.
+
+use bls_bulletproofs::blstrs::{G1Affine, Scalar};
+use std::{
+    borrow::Cow,
+    io::{self, Read, Write},
+};
+
+use tiny_keccak::{Hasher, Sha3};
+
+use crate::{ringct::DecodeLimits, Error, Result};
+
+/// Lowercase hex encoding, used for displaying points
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Parses lowercase or uppercase hex, `None` if it isn't valid hex
+#[cfg(feature = "serde")]
+pub(crate) fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Appends a u32 little-endian length prefix
+pub(crate) fn write_len(v: &mut Vec<u8>, len: usize) {
+    v.extend((len as u32).to_le_bytes());
+}
+
+/// Writes a u32 little-endian length prefix to a stream
+pub(crate) fn write_len_to(writer: &mut impl Write, len: usize) -> Result<()> {
+    write_all(writer, &(len as u32).to_le_bytes())
+}
+
+/// Writes `bytes` to a stream
+pub(crate) fn write_all(writer: &mut impl Write, bytes: &[u8]) -> Result<()> {
+    writer.write_all(bytes).map_err(io_error)
+}
+
+fn io_error(err: io::Error) -> Error {
+    match err.kind() {
+        io::ErrorKind::UnexpectedEof => Error::UnexpectedEndOfBytes,
+        _ => Error::Io(err.to_string()),
+    }
+}
+
+/// A sink which absorbs bytes as they're produced, either hashing them or
+/// collecting them, so that a message can be hashed without being copied
+/// into a buffer first
+pub(crate) trait MessageDigest {
+    fn absorb(&mut self, bytes: &[u8]);
+}
+
+impl MessageDigest for Vec<u8> {
+    fn absorb(&mut self, bytes: &[u8]) {
+        self.extend_from_slice(bytes);
+    }
+}
+
+impl MessageDigest for Sha3 {
+    fn absorb(&mut self, bytes: &[u8]) {
+        self.update(bytes);
+    }
+}
+
+/// A writer feeding a `MessageDigest`, for hashing the encodings of the
+/// `write_to()` methods
+pub(crate) struct DigestWriter<'a, D>(pub(crate) &'a mut D);
+
+impl<D: MessageDigest> Write for DigestWriter<'_, D> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.absorb(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A writer which only counts the bytes written to it
+#[derive(Default)]
+pub(crate) struct ByteCounter(pub(crate) usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+enum Source<'a> {
+    Bytes(&'a [u8]),
+    // Reading more than `remaining` bytes fails, so a peer can't make us
+    // allocate without bound.
+    Stream {
+        reader: &'a mut dyn Read,
+        remaining: usize,
+        max_len: usize,
+    },
+}
+
+/// Reads the encodings produced by the various `to_bytes()` methods, from a
+/// byte slice or a stream
+pub(crate) struct Reader<'a> {
+    source: Source<'a>,
+    limits: DecodeLimits,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            source: Source::Bytes(bytes),
+            limits: DecodeLimits::default(),
+        }
+    }
+
+    /// Reads from a stream, consuming at most `max_len` bytes
+    pub(crate) fn from_stream(reader: &'a mut dyn Read, max_len: usize) -> Self {
+        Self {
+            source: Source::Stream {
+                reader,
+                remaining: max_len,
+                max_len,
+            },
+            limits: DecodeLimits::default(),
+        }
+    }
+
+    /// Caps the lengths of the vectors read with `read_vec_max()`
+    pub(crate) fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub(crate) fn limits(&self) -> DecodeLimits {
+        self.limits
+    }
+
+    pub(crate) fn take(&mut self, n: usize) -> Result<Cow<'a, [u8]>> {
+        match &mut self.source {
+            Source::Bytes(bytes) => {
+                if bytes.len() < n {
+                    return Err(Error::UnexpectedEndOfBytes);
+                }
+                let (head, tail) = bytes.split_at(n);
+                *bytes = tail;
+                Ok(Cow::Borrowed(head))
+            }
+            Source::Stream {
+                reader,
+                remaining,
+                max_len,
+            } => {
+                if *remaining < n {
+                    return Err(Error::EncodingTooLong(*max_len));
+                }
+                *remaining -= n;
+                let mut buf = vec![0u8; n];
+                reader.read_exact(&mut buf).map_err(io_error)?;
+                Ok(Cow::Owned(buf))
+            }
+        }
+    }
+
+    pub(crate) fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(&self.take(N)?);
+        Ok(array)
+    }
+
+    pub(crate) fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn read_u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.read_array()?))
+    }
+
+    pub(crate) fn read_u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    pub(crate) fn read_u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.read_array()?))
+    }
+
+    pub(crate) fn read_len(&mut self) -> Result<usize> {
+        Ok(self.read_u32()? as usize)
+    }
+
+    /// Reads a length-prefixed vector of items
+    pub(crate) fn read_vec<T>(
+        &mut self,
+        read_item: impl FnMut(&mut Self) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        let len = self.read_len()?;
+        self.read_items(len, read_item)
+    }
+
+    /// Reads a length-prefixed vector of at most `max` items, failing on the
+    /// length prefix of a longer one before reading any of it
+    pub(crate) fn read_vec_max<T>(
+        &mut self,
+        max: usize,
+        field: &str,
+        read_item: impl FnMut(&mut Self) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        let len = self.read_len()?;
+        if len > max {
+            return Err(Error::DecodeLimitExceeded {
+                field: field.to_string(),
+                max,
+                len,
+            });
+        }
+        self.read_items(len, read_item)
+    }
+
+    // Every item is at least a byte, so a length beyond the bytes left is
+    // rejected before looping over it
+    fn read_items<T>(
+        &mut self,
+        len: usize,
+        mut read_item: impl FnMut(&mut Self) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        match &self.source {
+            Source::Bytes(bytes) if len > bytes.len() => return Err(Error::UnexpectedEndOfBytes),
+            Source::Stream {
+                remaining, max_len, ..
+            } if len > *remaining => return Err(Error::EncodingTooLong(*max_len)),
+            _ => {}
+        }
+        (0..len).map(|_| read_item(self)).collect()
+    }
+
+    pub(crate) fn read_scalar(&mut self) -> Result<Scalar> {
+        Option::from(Scalar::from_bytes_le(&self.read_array()?)).ok_or(Error::InvalidScalarEncoding)
+    }
+
+    pub(crate) fn read_point(&mut self) -> Result<G1Affine> {
+        Option::from(G1Affine::from_compressed(&self.read_array()?))
+            .ok_or(Error::InvalidPointEncoding)
+    }
+
+    /// Ensures every byte was consumed. A stream may carry further messages
+    /// after this one, so it is left as is.
+    pub(crate) fn finish(self) -> Result<()> {
+        match self.source {
+            Source::Bytes(bytes) if !bytes.is_empty() => Err(Error::TrailingBytes),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,301 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Proofs about Pedersen commitments which don't reveal their blinding
+//! factors: that two commitments commit to the same hidden value, or that a
+//! commitment commits to a public value, eg. zero.
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::ff::Field,
+    merlin::Transcript,
+    rand::{CryptoRng, RngCore},
+    PedersenGens,
+};
+
+use crate::{codec::Reader, mlsag::hash_to_scalar, Error, Result, RevealedCommitment};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const EQUAL_VALUE_DOMAIN: &[u8] = b"blst-ringct-equal-value-proof";
+const OPENING_DOMAIN: &[u8] = b"blst-ringct-opening-proof";
+
+/// Proves that two commitments commit to the same value, with a Schnorr
+/// proof of knowledge of `b` such that `C_a - C_b = bB'`
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EqualValueProof {
+    pub challenge: Scalar,
+    pub response: Scalar,
+}
+
+impl EqualValueProof {
+    /// Proves that the commitments opened by `a` and `b` commit to the same value
+    pub fn create(
+        a: &RevealedCommitment,
+        b: &RevealedCommitment,
+        pc_gens: &PedersenGens,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Self> {
+        if a.value != b.value {
+            return Err(Error::InvalidEqualValueProof);
+        }
+        let blinding = a.blinding - b.blinding;
+        let nonce = Scalar::random(&mut *rng);
+        let challenge = challenge(
+            &a.commit(pc_gens),
+            &b.commit(pc_gens),
+            &(pc_gens.B_blinding * nonce),
+        );
+        Ok(Self {
+            challenge,
+            response: nonce - challenge * blinding,
+        })
+    }
+
+    /// Checks that `a` and `b` commit to the same value
+    pub fn verify(&self, a: &G1Affine, b: &G1Affine, pc_gens: &PedersenGens) -> Result<()> {
+        let (a, b) = (G1Projective::from(a), G1Projective::from(b));
+        let nonce_commitment = pc_gens.B_blinding * self.response + (a - b) * self.challenge;
+        if challenge(&a, &b, &nonce_commitment) != self.challenge {
+            return Err(Error::InvalidEqualValueProof);
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Default::default();
+        v.extend(self.challenge.to_bytes_le());
+        v.extend(self.response.to_bytes_le());
+        v
+    }
+
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let proof = Self {
+            challenge: reader.read_scalar()?,
+            response: reader.read_scalar()?,
+        };
+        reader.finish()?;
+        Ok(proof)
+    }
+}
+
+/// Proves that a commitment commits to a public value `v`, with a Schnorr
+/// proof of knowledge of `b` such that `C - vB = bB'`. The proof is made over
+/// a transcript, so that the caller can bind it to its context, eg. the
+/// transaction of a fee or burn output.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpeningProof {
+    pub challenge: Scalar,
+    pub response: Scalar,
+}
+
+impl OpeningProof {
+    /// Proves that the commitment opened by `revealed` commits to its value
+    pub fn create(
+        revealed: &RevealedCommitment,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Self {
+        let nonce = Scalar::random(&mut *rng);
+        let challenge = opening_challenge(
+            transcript,
+            &revealed.commit(pc_gens),
+            revealed.value,
+            &(pc_gens.B_blinding * nonce),
+        );
+        Self {
+            challenge,
+            response: nonce - challenge * revealed.blinding,
+        }
+    }
+
+    /// Proves that `blinding * B'` commits to zero
+    pub fn create_zero(
+        blinding: Scalar,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Self {
+        Self::create(
+            &RevealedCommitment { value: 0, blinding },
+            pc_gens,
+            transcript,
+            rng,
+        )
+    }
+
+    /// Checks that `commitment` commits to `value`, given a transcript in
+    /// the state the proof was made over
+    pub fn verify(
+        &self,
+        commitment: &G1Affine,
+        value: u64,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+    ) -> Result<()> {
+        let commitment = G1Projective::from(commitment);
+        let nonce_commitment = pc_gens.B_blinding * self.response
+            + (commitment - pc_gens.B * Scalar::from(value)) * self.challenge;
+        if opening_challenge(transcript, &commitment, value, &nonce_commitment) != self.challenge {
+            return Err(Error::InvalidOpeningProof);
+        }
+        Ok(())
+    }
+
+    /// Checks that `commitment` commits to zero
+    pub fn verify_zero(
+        &self,
+        commitment: &G1Affine,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+    ) -> Result<()> {
+        self.verify(commitment, 0, pc_gens, transcript)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Default::default();
+        v.extend(self.challenge.to_bytes_le());
+        v.extend(self.response.to_bytes_le());
+        v
+    }
+
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let proof = Self {
+            challenge: reader.read_scalar()?,
+            response: reader.read_scalar()?,
+        };
+        reader.finish()?;
+        Ok(proof)
+    }
+}
+
+fn opening_challenge(
+    transcript: &mut Transcript,
+    commitment: &G1Projective,
+    value: u64,
+    nonce_commitment: &G1Projective,
+) -> Scalar {
+    transcript.append_message(b"dom-sep", OPENING_DOMAIN);
+    transcript.append_message(b"commitment", &commitment.to_compressed());
+    transcript.append_u64(b"value", value);
+    transcript.append_message(b"nonce_commitment", &nonce_commitment.to_compressed());
+    let mut challenge = [0; 32];
+    transcript.challenge_bytes(b"challenge", &mut challenge);
+    hash_to_scalar(&[OPENING_DOMAIN, &challenge])
+}
+
+fn challenge(a: &G1Projective, b: &G1Projective, nonce_commitment: &G1Projective) -> Scalar {
+    hash_to_scalar(&[
+        EQUAL_VALUE_DOMAIN,
+        &a.to_compressed(),
+        &b.to_compressed(),
+        &nonce_commitment.to_compressed(),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{group::Curve, rand::rngs::OsRng};
+
+    use super::*;
+
+    #[test]
+    fn test_revealed_commitment_arithmetic() {
+        let pc_gens = crate::pedersen_gens();
+        let a = RevealedCommitment::from_value(5, OsRng);
+        let b = RevealedCommitment::from_value(3, OsRng);
+
+        let sum = a + b;
+        assert_eq!(sum.value, 8);
+        assert_eq!(
+            sum.verify_opens(
+                &(a.commit(&pc_gens) + b.commit(&pc_gens)).to_affine(),
+                &pc_gens
+            ),
+            Ok(())
+        );
+        assert_eq!((a - b).value, 2);
+        assert_eq!([a, b].iter().sum::<RevealedCommitment>(), sum);
+        assert_eq!(b.checked_sub(a), None);
+        assert_eq!(
+            a.verify_opens(&b.commit(&pc_gens).to_affine(), &pc_gens),
+            Err(Error::AmountSecretsDoNotMatchCommitment)
+        );
+    }
+
+    #[test]
+    fn test_opening_proof() {
+        let pc_gens = crate::pedersen_gens();
+        let fee = RevealedCommitment::from_value(7, OsRng);
+        let c_fee = fee.commit(&pc_gens).to_affine();
+
+        let proof = OpeningProof::create(&fee, &pc_gens, &mut Transcript::new(b"tx 1"), &mut OsRng);
+        assert_eq!(
+            proof.verify(&c_fee, 7, &pc_gens, &mut Transcript::new(b"tx 1")),
+            Ok(())
+        );
+        assert_eq!(OpeningProof::from_bytes(&proof.to_bytes()), Ok(proof));
+        assert_eq!(
+            proof.verify(&c_fee, 8, &pc_gens, &mut Transcript::new(b"tx 1")),
+            Err(Error::InvalidOpeningProof)
+        );
+        // the proof is bound to its transcript
+        assert_eq!(
+            proof.verify(&c_fee, 7, &pc_gens, &mut Transcript::new(b"tx 2")),
+            Err(Error::InvalidOpeningProof)
+        );
+
+        let blinding = Scalar::random(OsRng);
+        let zero = (pc_gens.B_blinding * blinding).to_affine();
+        let proof = OpeningProof::create_zero(
+            blinding,
+            &pc_gens,
+            &mut Transcript::new(b"burn"),
+            &mut OsRng,
+        );
+        assert_eq!(
+            proof.verify_zero(&zero, &pc_gens, &mut Transcript::new(b"burn")),
+            Ok(())
+        );
+        assert_eq!(
+            proof.verify_zero(&c_fee, &pc_gens, &mut Transcript::new(b"burn")),
+            Err(Error::InvalidOpeningProof)
+        );
+    }
+
+    #[test]
+    fn test_equal_value_proof() {
+        let pc_gens = crate::pedersen_gens();
+        let a = RevealedCommitment::from_value(5, OsRng);
+        let b = RevealedCommitment::from_value(5, OsRng);
+        let (c_a, c_b) = (
+            a.commit(&pc_gens).to_affine(),
+            b.commit(&pc_gens).to_affine(),
+        );
+
+        let proof = EqualValueProof::create(&a, &b, &pc_gens, &mut OsRng).unwrap();
+        assert_eq!(proof.verify(&c_a, &c_b, &pc_gens), Ok(()));
+        assert_eq!(EqualValueProof::from_bytes(&proof.to_bytes()), Ok(proof));
+
+        let c = RevealedCommitment::from_value(6, OsRng);
+        assert_eq!(
+            proof.verify(&c_a, &c.commit(&pc_gens).to_affine(), &pc_gens),
+            Err(Error::InvalidEqualValueProof)
+        );
+        assert_eq!(
+            EqualValueProof::create(&a, &c, &pc_gens, &mut OsRng),
+            Err(Error::InvalidEqualValueProof)
+        );
+    }
+}
@@ -0,0 +1,169 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Conflict detection for mempools: transactions conflict when they spend
+//! a key image in common, and at most one of them can ever be applied.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{ringct::RingCtTransaction, KeyImage};
+
+impl RingCtTransaction {
+    /// The key images the transaction spends, as a set. `key_images()` lists
+    /// them in input order instead.
+    pub fn key_image_set(&self) -> BTreeSet<KeyImage> {
+        BTreeSet::from_iter(self.key_images())
+    }
+
+    /// Whether the transaction spends a key image `other` also spends
+    pub fn conflicts_with(&self, other: &RingCtTransaction) -> bool {
+        let key_images = self.key_image_set();
+        other.key_images().iter().any(|k| key_images.contains(k))
+    }
+}
+
+/// Tracks which pending transactions, by hash, spend which key images, so a
+/// mempool can find the transactions a new one conflicts with and reject it
+/// or choose between them.
+#[derive(Debug, Clone, Default)]
+pub struct ConflictGraph {
+    spenders: BTreeMap<KeyImage, BTreeSet<[u8; 32]>>,
+    key_images: BTreeMap<[u8; 32], BTreeSet<KeyImage>>,
+}
+
+impl ConflictGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The hashes of the transactions in the graph which conflict with `tx`
+    pub fn conflicts(&self, tx: &RingCtTransaction) -> BTreeSet<[u8; 32]> {
+        let hash = tx.hash();
+        tx.key_image_set()
+            .iter()
+            .filter_map(|k| self.spenders.get(k))
+            .flatten()
+            .filter(|h| **h != hash)
+            .copied()
+            .collect()
+    }
+
+    /// Adds `tx`, returning the hashes of the transactions it conflicts with.
+    /// It's added even if there are some, so that the caller can pick which
+    /// to keep and `remove()` the others.
+    pub fn insert(&mut self, tx: &RingCtTransaction) -> BTreeSet<[u8; 32]> {
+        let conflicts = self.conflicts(tx);
+        let hash = tx.hash();
+        let key_images = tx.key_image_set();
+        for key_image in key_images.iter() {
+            self.spenders.entry(*key_image).or_default().insert(hash);
+        }
+        self.key_images.insert(hash, key_images);
+        conflicts
+    }
+
+    /// Removes the transaction with hash `tx_hash`, eg. once it's applied or
+    /// evicted, returning whether it was in the graph
+    pub fn remove(&mut self, tx_hash: &[u8; 32]) -> bool {
+        let key_images = match self.key_images.remove(tx_hash) {
+            Some(key_images) => key_images,
+            None => return false,
+        };
+        for key_image in key_images {
+            if let Some(spenders) = self.spenders.get_mut(&key_image) {
+                spenders.remove(tx_hash);
+                if spenders.is_empty() {
+                    self.spenders.remove(&key_image);
+                }
+            }
+        }
+        true
+    }
+
+    /// The hashes of the transactions spending `key_image`
+    pub fn spenders(&self, key_image: &KeyImage) -> BTreeSet<[u8; 32]> {
+        self.spenders.get(key_image).cloned().unwrap_or_default()
+    }
+
+    pub fn contains(&self, tx_hash: &[u8; 32]) -> bool {
+        self.key_images.contains_key(tx_hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.key_images.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.key_images.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{
+        blstrs::{G1Projective, Scalar},
+        group::{ff::Field, Group},
+        rand::rngs::OsRng,
+    };
+
+    use super::*;
+    use crate::{
+        mock::random_decoys, ringct::Amount, MlsagMaterial, Output, RevealedCommitment,
+        RingCtMaterial, TrueInput,
+    };
+
+    fn spend(true_input: &TrueInput) -> RingCtTransaction {
+        let decoy_inputs = random_decoys(2);
+        let material = RingCtMaterial {
+            inputs: vec![MlsagMaterial::new(true_input.clone(), decoy_inputs, &mut OsRng).unwrap()],
+            outputs: vec![Output::new(G1Projective::random(OsRng), 2)],
+            fee: Amount::ZERO,
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+        let (tx, _revealed_output_commitments) = material
+            .sign(&mut OsRng)
+            .expect("Failed to sign transaction");
+        tx
+    }
+
+    #[test]
+    fn test_conflict_graph() {
+        let input = |value| {
+            TrueInput::new(
+                Scalar::random(OsRng),
+                RevealedCommitment::from_value(value, OsRng),
+            )
+        };
+        let (a, b) = (input(2), input(2));
+        let tx_a = spend(&a);
+        let double_spend_a = spend(&a);
+        let tx_b = spend(&b);
+
+        assert!(tx_a.conflicts_with(&double_spend_a));
+        assert!(!tx_a.conflicts_with(&tx_b));
+        assert_eq!(tx_a.key_image_set().len(), 1);
+
+        let mut graph = ConflictGraph::new();
+        assert!(graph.insert(&tx_a).is_empty());
+        assert!(graph.insert(&tx_b).is_empty());
+        assert_eq!(graph.insert(&double_spend_a), BTreeSet::from([tx_a.hash()]));
+        assert_eq!(graph.len(), 3);
+        assert_eq!(
+            graph.spenders(&a.key_image().into()),
+            BTreeSet::from([tx_a.hash(), double_spend_a.hash()])
+        );
+
+        assert!(graph.remove(&tx_a.hash()));
+        assert!(!graph.remove(&tx_a.hash()));
+        assert!(graph.conflicts(&double_spend_a).is_empty());
+        assert_eq!(
+            graph.conflicts(&tx_a),
+            BTreeSet::from([double_spend_a.hash()])
+        );
+    }
+}
@@ -0,0 +1,300 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use std::collections::BTreeSet;
+
+use bls_bulletproofs::{
+    blstrs::G1Affine,
+    rand::{Rng, RngCore},
+};
+
+use crate::{DecoyInput, Error, Result};
+
+/// Samples drawn per requested decoy before giving up, only reached when
+/// nearly every output in the store is excluded or already chosen.
+const MAX_SAMPLES_PER_DECOY: usize = 1000;
+
+/// The outputs decoys can be drawn from, eg. a ledger's spendable outputs
+pub trait DecoyStore {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The output at `index`, in order of creation so that 0 is the oldest
+    fn get(&self, index: usize) -> Option<DecoyInput>;
+}
+
+impl DecoyStore for [DecoyInput] {
+    fn len(&self) -> usize {
+        <[DecoyInput]>::len(self)
+    }
+
+    fn get(&self, index: usize) -> Option<DecoyInput> {
+        <[DecoyInput]>::get(self, index).copied()
+    }
+}
+
+impl DecoyStore for Vec<DecoyInput> {
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    fn get(&self, index: usize) -> Option<DecoyInput> {
+        self.as_slice().get(index).copied()
+    }
+}
+
+/// A strategy for choosing which outputs of a DecoyStore to use as decoys
+pub trait DecoySelector {
+    /// Samples the index of a candidate decoy in a store of `len` outputs,
+    /// or None to discard the sample.
+    fn sample_index(&self, len: usize, rng: &mut dyn RngCore) -> Option<usize>;
+
+    /// Selects `num_decoys` distinct outputs, none of which have a public key
+    /// in `exclude`, eg. the true inputs of the transaction.
+    fn select_decoys<S: DecoyStore + ?Sized>(
+        &self,
+        store: &S,
+        num_decoys: usize,
+        exclude: &[G1Affine],
+        mut rng: impl RngCore,
+    ) -> Result<Vec<DecoyInput>> {
+        let not_enough = Error::NotEnoughDecoys {
+            requested: num_decoys,
+            available: store.len(),
+        };
+        if store.len() < num_decoys {
+            return Err(not_enough);
+        }
+
+        let mut seen: BTreeSet<[u8; 48]> = exclude.iter().map(G1Affine::to_compressed).collect();
+        let mut decoys = Vec::with_capacity(num_decoys);
+
+        for _ in 0..num_decoys.saturating_mul(MAX_SAMPLES_PER_DECOY) {
+            if decoys.len() == num_decoys {
+                break;
+            }
+            let decoy = match self
+                .sample_index(store.len(), &mut rng)
+                .and_then(|index| store.get(index))
+            {
+                Some(decoy) => decoy,
+                None => continue,
+            };
+            if seen.insert(decoy.public_key.to_compressed()) {
+                decoys.push(decoy);
+            }
+        }
+
+        if decoys.len() < num_decoys {
+            return Err(not_enough);
+        }
+        Ok(decoys)
+    }
+}
+
+/// Selects every output with equal probability
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UniformSelector;
+
+impl DecoySelector for UniformSelector {
+    fn sample_index(&self, len: usize, rng: &mut dyn RngCore) -> Option<usize> {
+        if len == 0 {
+            return None;
+        }
+        Some(rng.gen_range(0..len))
+    }
+}
+
+/// Selects outputs by age, following the gamma distribution Monero fits to the
+/// ages of real spends. Uniform selection picks mostly old outputs, while most
+/// real spends are recent, which makes the true input stand out.
+///
+/// The log of an output's age in seconds is drawn from Gamma(shape, scale) and
+/// converted to a number of outputs with `seconds_per_output`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GammaSelector {
+    shape: f64,
+    scale: f64,
+    seconds_per_output: f64,
+}
+
+impl GammaSelector {
+    /// Monero's fitted shape
+    pub const DEFAULT_SHAPE: f64 = 19.28;
+    /// Monero's fitted scale, the inverse of its rate of 1.61
+    pub const DEFAULT_SCALE: f64 = 1.0 / 1.61;
+
+    /// A selector with Monero's shape and scale, where `seconds_per_output`
+    /// is the average time between outputs being added to the store
+    pub fn new(seconds_per_output: f64) -> Result<Self> {
+        Self::with_params(Self::DEFAULT_SHAPE, Self::DEFAULT_SCALE, seconds_per_output)
+    }
+
+    /// A selector with the given distribution, rejecting parameters that
+    /// aren't finite and positive, with which sampling wouldn't terminate or
+    /// would yield meaningless ages
+    pub fn with_params(shape: f64, scale: f64, seconds_per_output: f64) -> Result<Self> {
+        for (name, value) in [
+            ("shape", shape),
+            ("scale", scale),
+            ("seconds_per_output", seconds_per_output),
+        ] {
+            if !value.is_finite() || value <= 0.0 {
+                return Err(Error::InvalidSelectorParameter(format!(
+                    "{} must be finite and positive, not {}",
+                    name, value
+                )));
+            }
+        }
+        Ok(Self {
+            shape,
+            scale,
+            seconds_per_output,
+        })
+    }
+
+    pub fn shape(&self) -> f64 {
+        self.shape
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// The average time between outputs being added to the store
+    pub fn seconds_per_output(&self) -> f64 {
+        self.seconds_per_output
+    }
+}
+
+impl DecoySelector for GammaSelector {
+    fn sample_index(&self, len: usize, rng: &mut dyn RngCore) -> Option<usize> {
+        let age_secs = sample_gamma(self.shape, self.scale, rng).exp();
+        let age = (age_secs / self.seconds_per_output) as usize;
+        // outputs older than the oldest in the store are discarded
+        len.checked_sub(1)?.checked_sub(age)
+    }
+}
+
+/// Samples Gamma(shape, scale) with the method of Marsaglia and Tsang
+fn sample_gamma(shape: f64, scale: f64, rng: &mut dyn RngCore) -> f64 {
+    if shape < 1.0 {
+        // boost the shape above 1, see Marsaglia and Tsang section 6
+        let u: f64 = 1.0 - rng.gen::<f64>();
+        return sample_gamma(shape + 1.0, scale, rng) * u.powf(1.0 / shape);
+    }
+
+    let d = shape - 1.0 / 3.0;
+    let c = 1.0 / (9.0 * d).sqrt();
+    loop {
+        let x = sample_standard_normal(rng);
+        let v = (1.0 + c * x).powi(3);
+        if v <= 0.0 {
+            continue;
+        }
+        let u: f64 = 1.0 - rng.gen::<f64>();
+        if u.ln() < 0.5 * x * x + d - d * v + d * v.ln() {
+            return d * v * scale;
+        }
+    }
+}
+
+/// Samples N(0, 1) with the Box-Muller transform
+fn sample_standard_normal(rng: &mut dyn RngCore) -> f64 {
+    // 1 - [0, 1) lies in (0, 1], keeping ln() finite
+    let u1: f64 = 1.0 - rng.gen::<f64>();
+    let u2: f64 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::rand::rngs::OsRng;
+    use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+
+    use super::*;
+    use crate::mock::random_decoys;
+
+    #[test]
+    fn test_uniform_selection_is_distinct_and_excludes_true_inputs() {
+        let store = random_decoys(12);
+        let exclude = [store[0].public_key, store[1].public_key];
+
+        // every remaining output must be chosen
+        let decoys = UniformSelector
+            .select_decoys(&store, 10, &exclude, OsRng)
+            .unwrap();
+        let chosen: BTreeSet<_> = decoys
+            .iter()
+            .map(|d| d.public_key.to_compressed())
+            .collect();
+        assert_eq!(chosen.len(), 10);
+        assert!(exclude
+            .iter()
+            .all(|pk| !chosen.contains(&pk.to_compressed())));
+
+        assert_eq!(
+            UniformSelector
+                .select_decoys(&store, 11, &exclude, OsRng)
+                .err(),
+            Some(Error::NotEnoughDecoys {
+                requested: 11,
+                available: 12
+            })
+        );
+    }
+
+    #[test]
+    fn test_gamma_selection_favours_recent_outputs() {
+        let store = random_decoys(2000);
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        // with the default shape and scale the median age is about 1.5 days,
+        // or roughly 130 outputs at this rate
+        let selector = GammaSelector::new(1000.0).unwrap();
+
+        let mut indexes: Vec<usize> = (0..200)
+            .filter_map(|_| selector.sample_index(store.len(), &mut rng))
+            .collect();
+        indexes.sort_unstable();
+        assert!(indexes[indexes.len() / 2] > store.len() / 2);
+
+        let uniform_mean = (0..200)
+            .filter_map(|_| UniformSelector.sample_index(store.len(), &mut rng))
+            .sum::<usize>()
+            / 200;
+        assert!(uniform_mean < indexes[indexes.len() / 2]);
+
+        let decoys = selector.select_decoys(&store, 10, &[], &mut rng).unwrap();
+        assert_eq!(decoys.len(), 10);
+    }
+
+    #[test]
+    fn test_gamma_selector_rejects_invalid_params() {
+        let default = GammaSelector::new(1000.0).unwrap();
+        assert_eq!(default.shape(), GammaSelector::DEFAULT_SHAPE);
+        assert_eq!(default.scale(), GammaSelector::DEFAULT_SCALE);
+        assert_eq!(default.seconds_per_output(), 1000.0);
+
+        for (shape, scale, seconds_per_output) in [
+            (f64::NAN, 1.0, 1.0),
+            (0.0, 1.0, 1.0),
+            (-1.0, 1.0, 1.0),
+            (1.0, f64::INFINITY, 1.0),
+            (1.0, 0.0, 1.0),
+            (1.0, 1.0, f64::NAN),
+            (1.0, 1.0, -5.0),
+        ] {
+            assert!(matches!(
+                GammaSelector::with_params(shape, scale, seconds_per_output),
+                Err(Error::InvalidSelectorParameter(_))
+            ));
+        }
+        assert!(GammaSelector::with_params(0.5, 2.0, 60.0).is_ok());
+    }
+}
@@ -0,0 +1,169 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Plans the outputs of a payment: the payment itself and the change left
+//! from the inputs, optionally split into denominations.
+//!
+//! Amounts are hidden by their commitments, but recipients learn them, and
+//! a distinctive amount such as 1234567 links the outputs of a payment to
+//! their later spends. Splitting amounts into denominations of the form
+//! `d * 10^k`, eg. 1234567 into 1000000, 200000, 30000, 4000, 500, 60 and 7,
+//! makes outputs look alike across payments.
+
+use crate::{ringct::Amount, AmountSecrets, Error, Output, Result};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The amounts of the outputs of a payment
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputPlan {
+    /// The amounts paid to the recipient
+    pub payment: Vec<Amount>,
+    /// The amounts returned to the payer
+    pub change: Vec<Amount>,
+    pub fee: Amount,
+}
+
+impl OutputPlan {
+    /// Plans a payment of `payment` and `fee` from the inputs with amounts
+    /// `inputs`, returning the rest as change, with both split into
+    /// denominations if `split` is set.
+    pub fn new(
+        inputs: &[AmountSecrets],
+        payment: impl Into<Amount>,
+        fee: impl Into<Amount>,
+        split: bool,
+    ) -> Result<Self> {
+        let payment = payment.into();
+        let fee = fee.into();
+        let available = Amount::checked_sum(inputs.iter().map(AmountSecrets::value))?;
+        let required = payment.checked_add(fee).ok_or(Error::AmountOverflow)?;
+        let change = available
+            .checked_sub(required)
+            .ok_or(Error::InsufficientFunds {
+                available: available.value(),
+                required: required.value(),
+            })?;
+
+        let denominate = |amount: Amount| match (split, amount) {
+            (_, Amount::ZERO) => vec![],
+            (true, amount) => denominations(amount),
+            (false, amount) => vec![amount],
+        };
+        Ok(Self {
+            payment: denominate(payment),
+            change: denominate(change),
+            fee,
+        })
+    }
+
+    /// The number of outputs the plan makes
+    pub fn num_outputs(&self) -> usize {
+        self.payment.len() + self.change.len()
+    }
+
+    /// Makes the outputs, eg. with `|amount| Output::to_stealth_address(&address, amount, rng)`
+    /// to pay every output to a fresh one-time key. Change comes last.
+    pub fn into_outputs(
+        self,
+        payment_output: impl FnMut(Amount) -> Output,
+        change_output: impl FnMut(Amount) -> Output,
+    ) -> Vec<Output> {
+        let mut outputs: Vec<Output> = self.payment.into_iter().map(payment_output).collect();
+        outputs.extend(self.change.into_iter().map(change_output));
+        outputs
+    }
+}
+
+/// Splits `amount` into one denomination `d * 10^k` per non-zero decimal
+/// digit `d`, largest first
+pub fn denominations(amount: Amount) -> Vec<Amount> {
+    let mut denominations = vec![];
+    let mut remaining = amount.value();
+    let mut power = 1u64;
+    while remaining > 0 {
+        let digit = remaining % 10;
+        if digit > 0 {
+            denominations.push(Amount::new(digit * power));
+        }
+        remaining /= 10;
+        power = power.saturating_mul(10);
+    }
+    denominations.reverse();
+    denominations
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{blstrs::G1Projective, group::Group, rand::rngs::OsRng};
+
+    use super::*;
+    use crate::RevealedCommitment;
+
+    fn inputs(values: &[u64]) -> Vec<AmountSecrets> {
+        values
+            .iter()
+            .map(|v| AmountSecrets::from(RevealedCommitment::from_value(*v, OsRng)))
+            .collect()
+    }
+
+    #[test]
+    fn test_denominations() {
+        assert_eq!(
+            denominations(Amount::new(1_020_304)),
+            vec![
+                Amount::new(1_000_000),
+                Amount::new(20_000),
+                Amount::new(300),
+                Amount::new(4)
+            ]
+        );
+        assert_eq!(denominations(Amount::ZERO), Vec::<Amount>::new());
+        assert_eq!(
+            Amount::checked_sum(denominations(Amount::MAX)),
+            Ok(Amount::MAX)
+        );
+    }
+
+    #[test]
+    fn test_output_plan() {
+        let plan = OutputPlan::new(&inputs(&[700, 600]), 1205, 10, true).unwrap();
+        assert_eq!(
+            plan.payment,
+            vec![Amount::new(1000), Amount::new(200), Amount::new(5)]
+        );
+        assert_eq!(plan.change, vec![Amount::new(80), Amount::new(5)]);
+
+        let outputs = plan.into_outputs(
+            |amount| Output::new(G1Projective::random(OsRng), amount),
+            |amount| Output::new(G1Projective::random(OsRng), amount),
+        );
+        assert_eq!(outputs.len(), 5);
+        assert_eq!(outputs[4].amount(), Amount::new(5));
+
+        let exact = OutputPlan::new(&inputs(&[100]), 90, 10, false).unwrap();
+        assert_eq!(exact.payment, vec![Amount::new(90)]);
+        assert_eq!(exact.change, Vec::<Amount>::new());
+
+        assert_eq!(
+            OutputPlan::new(&inputs(&[100]), 95, 10, false),
+            Err(Error::InsufficientFunds {
+                available: 100,
+                required: 105
+            })
+        );
+        assert_eq!(
+            OutputPlan::new(&inputs(&[u64::MAX, 1]), 1, 0, false),
+            Err(Error::AmountOverflow)
+        );
+        assert_eq!(
+            OutputPlan::new(&inputs(&[u64::MAX]), u64::MAX, 1, false),
+            Err(Error::AmountOverflow)
+        );
+    }
+}
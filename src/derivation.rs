@@ -0,0 +1,177 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Deterministic derivation of a wallet's owner keys and blinding factors
+//! from a single seed, eg. the seed of a BIP-39 mnemonic, so that a wallet
+//! can recover them after data loss.
+//!
+//! The derivation scheme, with `H` SHA3-256 and integers little endian:
+//!
+//! - the master chain key is `c_0 = H("blst-ringct-derivation-master" || seed)`
+//! - each index `i` of a path derives `c_n+1 = H("blst-ringct-derivation-child" || c_n || i)`
+//! - the owner key at a path is `hash_to_scalar("blst-ringct-derivation-owner-key" || c_n)`
+//! - the blinding factor of an output is
+//!   `hash_to_scalar("blst-ringct-derivation-blinding" || c_0 || len(context) as u32 || context || output_index)`
+//!
+//! where `hash_to_scalar` rehashes until the hash is a canonical Scalar. The
+//! transaction context must be unique to each transaction of the wallet, eg.
+//! a transaction counter, or outputs of different transactions would share
+//! blinding factors.
+
+use tiny_keccak::{Hasher, Sha3};
+
+use crate::{mlsag::hash_to_scalar, BlindingFactor, RingCtMaterial, SecretKey};
+
+const MASTER_DOMAIN: &[u8] = b"blst-ringct-derivation-master";
+const CHILD_DOMAIN: &[u8] = b"blst-ringct-derivation-child";
+const OWNER_KEY_DOMAIN: &[u8] = b"blst-ringct-derivation-owner-key";
+const BLINDING_DOMAIN: &[u8] = b"blst-ringct-derivation-blinding";
+
+/// Derives the owner secret key at `path` from a wallet seed
+pub fn derive_owner_key(seed: &[u8], path: &[u32]) -> SecretKey {
+    let chain_key = path.iter().fold(master_chain_key(seed), |chain_key, i| {
+        hash(&[CHILD_DOMAIN, &chain_key, &i.to_le_bytes()])
+    });
+    SecretKey(hash_to_scalar(&[OWNER_KEY_DOMAIN, &chain_key]))
+}
+
+/// Derives the blinding factor of the output at `output_index` of the
+/// transaction identified by `tx_context` from a wallet seed
+pub fn derive_blinding(seed: &[u8], tx_context: &[u8], output_index: u32) -> BlindingFactor {
+    BlindingFactor(hash_to_scalar(&[
+        BLINDING_DOMAIN,
+        &master_chain_key(seed),
+        &(tx_context.len() as u32).to_le_bytes(),
+        tx_context,
+        &output_index.to_le_bytes(),
+    ]))
+}
+
+impl RingCtMaterial {
+    /// The material with every output's blinding factor derived from a wallet
+    /// seed, so that the revealed output commitments of the signed
+    /// transaction can be recovered with `derive_blinding()`
+    pub fn with_derived_blindings(mut self, seed: &[u8], tx_context: &[u8]) -> Self {
+        for (output_index, output) in self.outputs.iter_mut().enumerate() {
+            output.blinding = Some(derive_blinding(seed, tx_context, output_index as u32).0);
+        }
+        self
+    }
+}
+
+fn master_chain_key(seed: &[u8]) -> [u8; 32] {
+    hash(&[MASTER_DOMAIN, seed])
+}
+
+fn hash(parts: &[&[u8]]) -> [u8; 32] {
+    let mut sha3 = Sha3::v256();
+    for part in parts {
+        sha3.update(part);
+    }
+    let mut hash = [0; 32];
+    sha3.finalize(&mut hash);
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{
+        blstrs::G1Projective,
+        group::{Curve, Group},
+        rand::rngs::OsRng,
+    };
+
+    use super::*;
+    use crate::{
+        mock::MockLedger, ringct::Amount, Error, MlsagMaterial, Output, RevealedCommitment,
+        TrueInput,
+    };
+
+    #[test]
+    fn test_derivation_is_deterministic() {
+        let seed = b"correct horse battery staple";
+        assert_eq!(
+            derive_owner_key(seed, &[0, 1]),
+            derive_owner_key(seed, &[0, 1])
+        );
+        assert_ne!(
+            derive_owner_key(seed, &[0, 1]),
+            derive_owner_key(seed, &[1, 0])
+        );
+        assert_ne!(
+            derive_owner_key(seed, &[0]),
+            derive_owner_key(b"another seed", &[0])
+        );
+        assert_ne!(
+            derive_blinding(seed, b"tx 0", 0),
+            derive_blinding(seed, b"tx 1", 0)
+        );
+    }
+
+    fn material(seed: &[u8], ledger: &mut MockLedger) -> RingCtMaterial {
+        ledger.fill(4, 0);
+
+        let true_input = TrueInput::new(
+            derive_owner_key(seed, &[0]),
+            RevealedCommitment::from_value(5, OsRng),
+        );
+        ledger.log(
+            true_input.public_key(),
+            true_input
+                .revealed_commitment
+                .commit(RingCtMaterial::pc_gens()),
+        );
+        let decoy_inputs = ledger.fetch_decoys(2, &[true_input.public_key()]);
+
+        RingCtMaterial {
+            inputs: vec![MlsagMaterial::new(true_input, decoy_inputs, &mut OsRng).unwrap()],
+            outputs: vec![
+                Output::new(G1Projective::random(OsRng), 3),
+                Output::new(derive_owner_key(seed, &[1]).public_key(), 2),
+            ],
+            fee: Amount::ZERO,
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        }
+    }
+
+    #[test]
+    fn test_output_blindings_are_recoverable() {
+        let seed = b"correct horse battery staple";
+        let mut ledger = MockLedger::new();
+        let material = material(seed, &mut ledger).with_derived_blindings(seed, b"tx 0");
+        let (tx, revealed_output_commitments) = material
+            .sign(&mut OsRng)
+            .expect("Failed to sign transaction");
+        assert_eq!(ledger.apply(&tx), Ok(()));
+
+        for (i, (revealed, output)) in revealed_output_commitments
+            .iter()
+            .zip(tx.outputs.iter())
+            .enumerate()
+        {
+            let recovered =
+                RevealedCommitment::new(revealed.value(), derive_blinding(seed, b"tx 0", i as u32));
+            assert_eq!(
+                recovered.commit(RingCtMaterial::pc_gens()).to_affine(),
+                output.commitment().0
+            );
+        }
+    }
+
+    #[test]
+    fn test_some_output_blindings_are_rejected() {
+        let seed = b"correct horse battery staple";
+        let mut material = material(seed, &mut MockLedger::new());
+        material.outputs[1].blinding = Some(derive_blinding(seed, b"tx 0", 1).0);
+
+        // the last output's blinding would otherwise balance the others
+        let partial = Err(Error::MissingOutputBlinding { output_index: 0 });
+        assert_eq!(material.validate(), partial);
+        assert_eq!(material.sign(&mut OsRng).map(|_| ()), partial);
+    }
+}
@@ -0,0 +1,334 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Disclosure of a transaction's outputs to an auditor.
+//!
+//! For each output paid to the wallet, the view key holder reveals the shared
+//! secret `D = aR` with a proof that it was computed with the address's view
+//! key, and the opening of the output's commitment. The auditor checks both
+//! against the transaction, without being able to spend the outputs.
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::{ff::Field, Curve, Group},
+    rand::{CryptoRng, RngCore},
+};
+
+use crate::{
+    codec::{self, Reader},
+    mlsag::hash_to_scalar,
+    ringct::{Amount, OutputProof, RingCtTransaction},
+    stealth::shared_secret_scalar,
+    AmountSecrets, Error, Result, RevealedCommitment, RingCtMaterial, StealthAddress, ViewKey,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const DISCLOSURE_DOMAIN: &[u8] = b"blst-ringct-transaction-disclosure";
+
+/// An output of a disclosed transaction paid to the disclosing wallet
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisclosedOutput {
+    pub output_index: u32,
+    /// `D = aR` for the view secret key `a` and the output's ephemeral key `R`
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub shared_secret: G1Affine,
+    pub amount_secrets: AmountSecrets,
+    pub challenge: Scalar,
+    pub response: Scalar,
+}
+
+/// The outputs of a transaction paid to a wallet, with their amounts
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionDisclosure {
+    pub tx_id: [u8; 32],
+    pub address: StealthAddress,
+    pub outputs: Vec<DisclosedOutput>,
+}
+
+impl ViewKey {
+    /// Discloses the outputs of `tx` paid to this key's address and their amounts
+    pub fn disclose_transaction(
+        &self,
+        tx: &RingCtTransaction,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<TransactionDisclosure> {
+        let address = self.address();
+        let tx_id = tx.id();
+
+        let outputs = self
+            .scan(tx)
+            .into_iter()
+            .map(|viewed| -> Result<DisclosedOutput> {
+                let output = &tx.outputs[viewed.output_index];
+                let ephemeral_public_key = output
+                    .ephemeral_public_key()
+                    .ok_or(Error::NotAStealthOutput)?;
+                let shared_secret = self
+                    .shared_secret(output)
+                    .ok_or(Error::InvalidTransactionDisclosure)?;
+
+                let nonce = Scalar::random(&mut *rng);
+                let mut disclosed = DisclosedOutput {
+                    output_index: viewed.output_index as u32,
+                    shared_secret,
+                    amount_secrets: viewed.amount_secrets,
+                    challenge: Scalar::zero(),
+                    response: Scalar::zero(),
+                };
+                disclosed.challenge = disclosed.challenge(
+                    &tx_id,
+                    &address,
+                    output,
+                    &ephemeral_public_key,
+                    &[
+                        G1Projective::generator() * nonce,
+                        ephemeral_public_key * nonce,
+                    ],
+                );
+                disclosed.response = nonce - disclosed.challenge * self.view_secret_key();
+                Ok(disclosed)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(TransactionDisclosure {
+            tx_id,
+            address,
+            outputs,
+        })
+    }
+}
+
+impl TransactionDisclosure {
+    /// Checks that each disclosed output of `tx` was paid to the disclosed
+    /// address and holds the disclosed amount
+    pub fn verify(&self, tx: &RingCtTransaction) -> Result<()> {
+        if tx.id() != self.tx_id {
+            return Err(Error::InvalidTransactionDisclosure);
+        }
+        let mut last_index = None;
+        for disclosed in self.outputs.iter() {
+            // in output order, so that an output can't be counted twice
+            if last_index >= Some(disclosed.output_index) {
+                return Err(Error::InvalidTransactionDisclosure);
+            }
+            last_index = Some(disclosed.output_index);
+            disclosed.verify(tx, &self.tx_id, &self.address)?;
+        }
+        Ok(())
+    }
+
+    /// The total amount paid to the address by the disclosed outputs
+    pub fn total_amount(&self) -> Result<Amount> {
+        Amount::checked_sum(self.outputs.iter().map(|o| o.amount_secrets.value()))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Default::default();
+        v.extend(self.tx_id);
+        v.extend(self.address.view_public_key.to_compressed());
+        v.extend(self.address.spend_public_key.to_compressed());
+        codec::write_len(&mut v, self.outputs.len());
+        for o in self.outputs.iter() {
+            v.extend(o.output_index.to_le_bytes());
+            v.extend(o.shared_secret.to_compressed());
+            v.extend(o.amount_secrets.revealed_commitment().to_bytes());
+            v.extend(o.challenge.to_bytes_le());
+            v.extend(o.response.to_bytes_le());
+        }
+        v
+    }
+
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let tx_id = reader.read_array()?;
+        let address = StealthAddress {
+            view_public_key: reader.read_point()?,
+            spend_public_key: reader.read_point()?,
+        };
+        let outputs = reader.read_vec(|r| {
+            Ok(DisclosedOutput {
+                output_index: r.read_u32()?,
+                shared_secret: r.read_point()?,
+                amount_secrets: AmountSecrets::from(RevealedCommitment {
+                    value: r.read_u64()?,
+                    blinding: r.read_scalar()?,
+                }),
+                challenge: r.read_scalar()?,
+                response: r.read_scalar()?,
+            })
+        })?;
+        reader.finish()?;
+
+        Ok(Self {
+            tx_id,
+            address,
+            outputs,
+        })
+    }
+}
+
+impl DisclosedOutput {
+    fn verify(
+        &self,
+        tx: &RingCtTransaction,
+        tx_id: &[u8; 32],
+        address: &StealthAddress,
+    ) -> Result<()> {
+        let output = tx
+            .outputs
+            .get(self.output_index as usize)
+            .ok_or(Error::OutputIndexOutOfRange(self.output_index as usize))?;
+        let ephemeral_public_key = output
+            .ephemeral_public_key()
+            .ok_or(Error::NotAStealthOutput)?;
+
+        // the output's one-time key was derived from the address
+        let one_time_key = G1Projective::generator() * shared_secret_scalar(&self.shared_secret)
+            + address.spend_public_key;
+        if one_time_key.to_affine() != *output.public_key() {
+            return Err(Error::InvalidTransactionDisclosure);
+        }
+
+        // the amount secrets open the output's commitment
+        if self
+            .amount_secrets
+            .revealed_commitment()
+            .commit(RingCtMaterial::pc_gens())
+            .to_affine()
+            != G1Affine::from(output.commitment())
+        {
+            return Err(Error::InvalidTransactionDisclosure);
+        }
+
+        // V = aG and D = aR for the same a
+        let nonce_commitments = [
+            G1Projective::generator() * self.response + address.view_public_key * self.challenge,
+            ephemeral_public_key * self.response + self.shared_secret * self.challenge,
+        ];
+        if self.challenge(
+            tx_id,
+            address,
+            output,
+            &ephemeral_public_key,
+            &nonce_commitments,
+        ) != self.challenge
+        {
+            return Err(Error::InvalidTransactionDisclosure);
+        }
+        Ok(())
+    }
+
+    fn challenge(
+        &self,
+        tx_id: &[u8; 32],
+        address: &StealthAddress,
+        output: &OutputProof,
+        ephemeral_public_key: &G1Affine,
+        nonce_commitments: &[G1Projective; 2],
+    ) -> Scalar {
+        let points: Vec<[u8; 48]> = [
+            address.view_public_key,
+            address.spend_public_key,
+            *output.public_key(),
+            *ephemeral_public_key,
+            self.shared_secret,
+        ]
+        .iter()
+        .map(G1Affine::to_compressed)
+        .chain(nonce_commitments.iter().map(G1Projective::to_compressed))
+        .collect();
+
+        let index = self.output_index.to_le_bytes();
+        let mut material: Vec<&[u8]> = vec![DISCLOSURE_DOMAIN, tx_id, &index];
+        material.extend(points.iter().map(|p| p.as_slice()));
+        hash_to_scalar(&material)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{mock::random_decoys, MlsagMaterial, Output, StealthKeys, TrueInput};
+
+    #[test]
+    fn test_view_key_discloses_transaction() {
+        let keys = StealthKeys::random(&mut OsRng);
+        let view_key = keys.view_key();
+        let address = keys.address();
+
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(10, OsRng),
+        );
+        let decoys = random_decoys(3);
+        let material = RingCtMaterial {
+            inputs: vec![MlsagMaterial::new(true_input, decoys, &mut OsRng).unwrap()],
+            outputs: vec![
+                Output::to_stealth_address(&address, 4, &mut OsRng),
+                Output::new(G1Projective::random(OsRng), 1),
+                Output::to_stealth_address(&address, 5, &mut OsRng),
+            ],
+            fee: Amount::ZERO,
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+        let (tx, _) = material
+            .sign(&mut OsRng)
+            .expect("Failed to sign transaction");
+
+        // the view key finds the same outputs and amounts as the full keys
+        let viewed = view_key.scan(&tx);
+        let owned = keys.scan(&tx);
+        assert_eq!(viewed.len(), 2);
+        for (viewed, owned) in viewed.iter().zip(owned.iter()) {
+            assert_eq!(viewed.output_index, owned.output_index);
+            assert_eq!(viewed.amount_secrets, owned.amount_secrets);
+        }
+
+        let disclosure = view_key
+            .disclose_transaction(&tx, &mut OsRng)
+            .expect("Failed to disclose transaction");
+        assert_eq!(disclosure.verify(&tx), Ok(()));
+        assert_eq!(disclosure.total_amount(), Ok(Amount::new(9)));
+
+        let decoded = TransactionDisclosure::from_bytes(&disclosure.to_bytes()).unwrap();
+        assert_eq!(decoded, disclosure);
+
+        // a disclosure can't overstate an amount or be attributed to another address
+        let mut overstated = disclosure.clone();
+        overstated.outputs[0].amount_secrets = AmountSecrets::from(RevealedCommitment {
+            value: 40,
+            blinding: overstated.outputs[0].amount_secrets.blinding_factor(),
+        });
+        assert_eq!(
+            overstated.verify(&tx),
+            Err(Error::InvalidTransactionDisclosure)
+        );
+
+        let mut misattributed = disclosure.clone();
+        misattributed.address.view_public_key =
+            StealthKeys::random(&mut OsRng).address().view_public_key;
+        assert_eq!(
+            misattributed.verify(&tx),
+            Err(Error::InvalidTransactionDisclosure)
+        );
+
+        let mut duplicated = disclosure;
+        duplicated.outputs[1] = duplicated.outputs[0].clone();
+        assert_eq!(
+            duplicated.verify(&tx),
+            Err(Error::InvalidTransactionDisclosure)
+        );
+    }
+}
@@ -20,14 +20,264 @@ pub enum Error {
     InputPseudoCommitmentsDoNotSumToOutputCommitments,
     #[error("The MLSAG ring signature is not valid")]
     InvalidRingSignature,
+    #[error("The ring signature has an empty ring")]
+    EmptyRing,
     #[error("KeyImage is not on the BLS12-381 G1 Curve")]
     KeyImageNotOnCurve,
+    #[error("The {0} is the identity point")]
+    PointIsIdentity(String),
+    #[error("The {0} is not on the BLS12-381 G1 curve")]
+    PointNotOnCurve(String),
+    #[error("The {0} is not in the prime order subgroup of G1")]
+    PointNotInSubgroup(String),
     #[error("BulletProofs Error: {0}")]
     BulletProofs(#[from] bls_bulletproofs::ProofError),
     #[error("The DBC transaction must have at least one input")]
     TransactionMustHaveAnInput,
-    #[error("key image is not unique across all transaction inputs")]
-    KeyImageNotUniqueAcrossInputs,
-    #[error("public key is not unique across all transaction inputs")]
-    PublicKeyNotUniqueAcrossInputs,
+    #[error(
+        "Key image {key_image} of input {input_index} is not unique across all transaction inputs"
+    )]
+    KeyImageNotUniqueAcrossInputs {
+        input_index: usize,
+        key_image: String,
+    },
+    #[error("Public key {public_key} in the ring of input {input_index} is not unique across all transaction inputs")]
+    PublicKeyNotUniqueAcrossInputs {
+        input_index: usize,
+        public_key: String,
+    },
+    #[error("The MLSAG of input {input_index} with key image {key_image} is invalid: {reason}")]
+    MlsagInvalid {
+        input_index: usize,
+        key_image: String,
+        reason: Box<Error>,
+    },
+    #[error("The matrix MLSAG over all inputs is invalid: {reason}")]
+    MatrixMlsagInvalid { reason: Box<Error> },
+    #[error("The CLSAG of input {input_index} with key image {key_image} is invalid: {reason}")]
+    ClsagInvalid {
+        input_index: usize,
+        key_image: String,
+        reason: Box<Error>,
+    },
+    #[error("The range proof of output {output_index} with public key {public_key} is invalid: {reason}")]
+    RangeProofInvalid {
+        output_index: usize,
+        public_key: String,
+        reason: Box<Error>,
+    },
+    #[error("The transaction must have at least one output")]
+    NoOutputs,
+    #[error("The transaction is not a genesis transaction")]
+    NotAGenesisTransaction,
+    #[error("A genesis transaction must not have inputs or a fee")]
+    GenesisTransactionHasInputs,
+    #[error("The genesis outputs do not sum to the supply")]
+    GenesisOutputsDoNotSumToSupply,
+    #[error("{requested} decoys were requested but only {available} outputs are available")]
+    NotEnoughDecoys { requested: usize, available: usize },
+    #[error("The transaction has {actual} inputs but at most {max} are allowed")]
+    TooManyInputs { max: usize, actual: usize },
+    #[error("The transaction has {actual} outputs but at most {max} are allowed")]
+    TooManyOutputs { max: usize, actual: usize },
+    #[error("Output {output_index} holds {amount} but at least {min} is required")]
+    DustOutput {
+        output_index: usize,
+        amount: u64,
+        min: u64,
+    },
+    #[error("Input {input_index} has {actual} decoys but at most {max} are allowed")]
+    TooManyDecoys {
+        input_index: usize,
+        max: usize,
+        actual: usize,
+    },
+    #[error("Decoy {decoy_index} is the true input")]
+    DecoyIsTrueInput { decoy_index: usize },
+    #[error("{actual} decoys were given but the ring size requires {expected}")]
+    DecoyCountMismatch { expected: usize, actual: usize },
+    #[error("Input {input_index} has no decoys in its ring")]
+    MissingDecoys { input_index: usize },
+    #[error(
+        "The true input of input {input_index} is not in the same ring position as the others"
+    )]
+    TrueInputsNotAligned { input_index: usize },
+    #[error("The ring has {0} members but its size must be a power of two")]
+    RingSizeNotPowerOfTwo(usize),
+    #[error("The secret key is zero")]
+    SecretKeyIsZero,
+    #[error("Input {input_index} has a ring of size {actual} but {expected} is required")]
+    InvalidRingSize {
+        input_index: usize,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("Input amount {input_amount} does not match output amount {output_amount}")]
+    InvalidBalance {
+        input_amount: u64,
+        output_amount: u64,
+    },
+    #[error("Sum of amounts overflows")]
+    AmountOverflow,
+    #[error("No commitment found for public key {public_key} in the ring of input {input_index}")]
+    CommitmentNotFound {
+        input_index: usize,
+        public_key: String,
+    },
+    #[error("Unsupported transaction version: {0}")]
+    UnsupportedTransactionVersion(u16),
+    #[error("The ring signatures do not match the scheme required by the transaction version")]
+    InvalidRingSignatureLayout,
+    #[error("The range proofs do not match the layout required by the transaction version")]
+    InvalidRangeProofLayout,
+    #[error("Failed to decrypt the amount secrets")]
+    AmountDecryptionFailed,
+    #[error("The amount secrets do not open the output commitment")]
+    AmountSecretsDoNotMatchCommitment,
+    #[error("The secret key does not own the output")]
+    SecretKeyDoesNotOwnOutput,
+    #[error("The range proof can't be rewound with the key to recover the amount")]
+    RangeProofRewindFailed,
+    #[error("Unexpected end of bytes while decoding")]
+    UnexpectedEndOfBytes,
+    #[error("Unexpected trailing bytes after decoding")]
+    TrailingBytes,
+    #[error("Bytes are not a valid compressed G1 point")]
+    InvalidPointEncoding,
+    #[error("Bytes are not a valid canonical scalar")]
+    InvalidScalarEncoding,
+    #[error("A threshold of {threshold} is invalid for {num_shares} key shares")]
+    InvalidThreshold { threshold: usize, num_shares: usize },
+    #[error("{signers} co-signers committed but {threshold} are required")]
+    NotEnoughSigners { threshold: usize, signers: usize },
+    #[error("Unexpected co-signer with key share index {0}")]
+    UnexpectedSigner(u32),
+    #[error("Missing the partial signature of co-signer {0}")]
+    MissingPartialSignature(u32),
+    #[error("The signing session is not ready for this step")]
+    InvalidSigningSessionState,
+    #[error("Input {input_index} is missing its ring signature")]
+    MissingRingSignature { input_index: usize },
+    #[error("The partially signed transactions are not copies of the same transaction")]
+    PartiallySignedTransactionMismatch,
+    #[error("The signing material of input {input_index} doesn't match the transaction's ring, key image or pseudo commitment")]
+    InputMaterialMismatch { input_index: usize },
+    #[error("External signer error: {0}")]
+    ExternalSigner(String),
+    #[error("Invalid presence flag {0} while decoding an optional field")]
+    InvalidPresenceFlag(u8),
+    #[error("The transaction has no output {0}")]
+    OutputIndexOutOfRange(usize),
+    #[error("The output was not paid to a stealth address")]
+    NotAStealthOutput,
+    #[error("The payment proof is invalid")]
+    InvalidPaymentProof,
+    #[error("The outputs hold {total} but at least {minimum} was claimed")]
+    InsufficientReserves { total: u64, minimum: u64 },
+    #[error("Output {0} is counted more than once in the reserve proof")]
+    DuplicateReservedOutput(String),
+    #[error("The reserve proof is invalid")]
+    InvalidReserveProof,
+    #[error("The transaction disclosure is invalid")]
+    InvalidTransactionDisclosure,
+    #[error("Input {input_index} spends key image {key_image} which is already spent")]
+    DoubleSpend {
+        input_index: usize,
+        key_image: String,
+    },
+    #[error("The 128-bit amount range proof is invalid")]
+    InvalidWideRangeProof,
+    #[error("Range proofs of {0} bits are not supported, only 8, 16, 32 or 64 bits")]
+    UnsupportedRangeProofBits(usize),
+    #[error("The amount of output {output_index} doesn't fit in a {bits} bit range proof")]
+    AmountExceedsRangeProofBits { output_index: usize, bits: usize },
+    #[error("The extra data of the {location} is {len} bytes, more than the maximum allowed")]
+    ExtraTooLong { location: String, len: usize },
+    #[error(
+        "Output {output_index} has no blinding factor, though other outputs of the transaction do"
+    )]
+    MissingOutputBlinding { output_index: usize },
+    #[error("Output {public_key} is locked until {lock}")]
+    OutputLocked { public_key: String, lock: u64 },
+    #[error("The ring of input {input_index} contains a burn output")]
+    BurnOutputInRing { input_index: usize },
+    #[error("The burn proof is invalid")]
+    InvalidBurnProof,
+    #[error("The adaptor secret doesn't match the adaptor signature")]
+    InvalidAdaptorSecret,
+    #[error("The issuance request is invalid")]
+    InvalidIssuanceRequest,
+    #[error("The mint's signature on the issued output is invalid")]
+    InvalidBlindSignature,
+    #[error("I/O error: {0}")]
+    Io(String),
+    #[error("The encoding is longer than the maximum of {0} bytes")]
+    EncodingTooLong(usize),
+    #[error("The pruned transaction is inconsistent")]
+    InvalidPrunedTransaction,
+    #[error("The output inclusion proof is invalid")]
+    InvalidInclusionProof,
+    #[error("The owner signature of {public_key} is invalid")]
+    InvalidOwnerSignature { public_key: String },
+    #[error("Owner {public_key} has not signed the transaction")]
+    MissingOwnerSignature { public_key: String },
+    #[error("The spent proof of key image {key_image} is invalid")]
+    InvalidSpentProof { key_image: String },
+    #[error("Input {input_index} has no spent proof")]
+    MissingSpentProof { input_index: usize },
+    #[error("The spent proof shares are for different key images or transactions")]
+    SpentProofSharesMismatch,
+    #[error("The inputs hold {available} but {required} is required")]
+    InsufficientFunds { available: u64, required: u64 },
+    #[error("The commitments are not proven to commit to the same value")]
+    InvalidEqualValueProof,
+    #[error("The commitment is not proven to commit to the value")]
+    InvalidOpeningProof,
+    #[error("The pseudo commitment of input {input_index} is not proven to commit to its true input's value")]
+    InvalidPseudoCommitmentProof { input_index: usize },
+    #[error("A sum of {terms} amounts of {bits} bits could wrap around the group order")]
+    BalanceMayWrap { terms: usize, bits: usize },
+    #[error("The range proofs are not aggregated into one proof")]
+    RangeProofsNotAggregated,
+    #[error("The ownership proof is invalid")]
+    InvalidOwnershipProof,
+    #[error("The key image of {public_key} is not proven to be its key image")]
+    InvalidKeyImageProof { public_key: String },
+    #[error("The wallet has no output with public key {public_key}")]
+    UnknownOutput { public_key: String },
+    #[error("Transaction {tx_index} is not bound to the bundle of the transactions")]
+    TransactionNotInBundle { tx_index: usize },
+    #[error("Expected a list of ring commitments for each transaction of the bundle")]
+    ExpectedPublicCommitmentsForEachTransaction,
+    #[error("Transaction is bound to network {actual:?}, expected {expected}")]
+    WrongNetwork {
+        expected: String,
+        actual: Option<String>,
+    },
+    #[error("The network id of transaction version {0:#06x} is missing or unexpected")]
+    NetworkIdDoesNotMatchVersion(u16),
+    #[error(
+        "Transaction expired after height {not_valid_after}, it can't be included at {height}"
+    )]
+    TransactionExpired { not_valid_after: u64, height: u64 },
+    #[error("The expiry height of transaction version {0:#06x} is missing or unexpected")]
+    ExpiryDoesNotMatchVersion(u16),
+    #[error("Decoding {len} {field} exceeds the limit of {max}")]
+    DecodeLimitExceeded {
+        field: String,
+        max: usize,
+        len: usize,
+    },
+    #[error("Transaction violates the consensus rules: {0}")]
+    PolicyViolation(String),
+    #[error("The range proofs of transaction version {0:#06x} can't be verified on their own")]
+    RangeProofNotIndependent(u16),
+    #[error("Invalid decoy selector parameter: {0}")]
+    InvalidSelectorParameter(String),
+    #[error("The nonce of co-signer {0} already answered a signing challenge")]
+    NonceReused(u32),
+    #[error("The signing challenge doesn't close a ring over the signer's public key")]
+    InvalidSigningChallenge,
+    #[error("The transaction doesn't verify to the validity statement")]
+    ValidityStatementMismatch,
 }
@@ -0,0 +1,324 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, Scalar},
+    group::{prime::PrimeCurveAffine, Curve},
+    rand::{CryptoRng, RngCore},
+};
+
+use crate::{
+    ringct::{
+        check_extra, field_flags, gen_message_for_signing, Amount, RingCtTransaction,
+        CURRENT_TX_VERSION,
+    },
+    threshold::{PartialSignature, SigningCommitment, SigningSession},
+    DecoyInput, Error, KeyImage, Output, Result, RevealedCommitment, RingCtMaterial,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// Index used for the external signer in the underlying 1-of-1 signing session
+const SIGNER_INDEX: u32 = 1;
+
+/// A device, eg. a hardware wallet or HSM, holding the secret keys of true
+/// inputs and computing their part of the ring signatures.
+pub trait ExternalSigner {
+    /// The key image `x Hp(P)` of the secret key `x` behind `public_key`
+    fn key_image(&self, public_key: &G1Affine) -> Result<KeyImage>;
+
+    /// Commits to a fresh nonce `a` for signing with `public_key`, returning `(aG, a Hp(P))`
+    fn commit_nonce(&mut self, public_key: &G1Affine) -> Result<(G1Affine, G1Affine)>;
+
+    /// Returns the response `a - c x` to the challenge `c` and forgets the nonce
+    fn respond(&mut self, public_key: &G1Affine, challenge: Scalar) -> Result<Scalar>;
+}
+
+/// A true input whose secret key is held by an ExternalSigner
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct ExternalInput {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub public_key: G1Affine,
+    pub revealed_commitment: RevealedCommitment,
+    pub decoy_inputs: Vec<DecoyInput>,
+}
+
+/// Like RingCtMaterial, but without the true inputs' secret keys
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default)]
+pub struct ExternalRingCtMaterial {
+    pub inputs: Vec<ExternalInput>,
+    pub outputs: Vec<Output>,
+    pub fee: Amount,
+    pub extra: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub network_id: Option<[u8; 32]>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub not_valid_after: Option<u64>,
+}
+
+impl ExternalRingCtMaterial {
+    /// Same checks as `RingCtMaterial::validate()`
+    pub fn validate(&self) -> Result<()> {
+        if self.inputs.is_empty() {
+            return Err(Error::TransactionMustHaveAnInput);
+        }
+        if self.outputs.is_empty() {
+            return Err(Error::NoOutputs);
+        }
+        check_extra(&self.extra, &self.outputs)?;
+        if let Some(input_index) = self.inputs.iter().position(|i| i.decoy_inputs.is_empty()) {
+            return Err(Error::MissingDecoys { input_index });
+        }
+
+        let input_amount = Amount::checked_sum(
+            self.inputs
+                .iter()
+                .map(|i| Amount::new(i.revealed_commitment.value)),
+        )?;
+        let output_amount = Amount::checked_sum(
+            self.outputs
+                .iter()
+                .map(Output::amount)
+                .chain(std::iter::once(self.fee)),
+        )?;
+        if input_amount != output_amount {
+            return Err(Error::InvalidBalance {
+                input_amount: input_amount.value(),
+                output_amount: output_amount.value(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Signs the transaction, asking `signer` for the key images and the
+    /// ring signature responses of the true inputs.
+    pub fn sign_with(
+        &self,
+        signer: &mut impl ExternalSigner,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<(RingCtTransaction, Vec<RevealedCommitment>)> {
+        self.validate()?;
+        let version =
+            CURRENT_TX_VERSION | field_flags(self.network_id.as_ref(), self.not_valid_after);
+        let pc_gens = RingCtMaterial::pc_gens();
+
+        let mut sessions = Vec::with_capacity(self.inputs.len());
+        for input in self.inputs.iter() {
+            let mut session = SigningSession::new(
+                input.public_key,
+                input.revealed_commitment,
+                input.decoy_inputs.clone(),
+                1,
+                &mut *rng,
+            );
+            let (nonce_commitment, nonce_key_image) = signer.commit_nonce(&input.public_key)?;
+            // A lone signer has no co-signers to bind its nonce to
+            session.add_commitment(SigningCommitment {
+                index: SIGNER_INDEX,
+                nonce_commitment,
+                nonce_key_image,
+                binding_nonce_commitment: G1Affine::identity(),
+                binding_nonce_key_image: G1Affine::identity(),
+                partial_key_image: signer.key_image(&input.public_key)?.to_affine(),
+            })?;
+            sessions.push(session);
+        }
+
+        #[cfg_attr(not(feature = "zeroize"), allow(unused_mut))]
+        let mut revealed_pseudo_commitments: Vec<RevealedCommitment> = self
+            .inputs
+            .iter()
+            .map(|i| RevealedCommitment::from_value(i.revealed_commitment.value, &mut *rng))
+            .collect();
+        let pseudo_commitments: Vec<G1Affine> = revealed_pseudo_commitments
+            .iter()
+            .map(|r| r.commit(pc_gens).to_affine())
+            .collect();
+
+        // The outputs don't depend on the inputs' secret keys, so their proofs
+        // are generated the same way as for RingCtMaterial.
+        let output_material = RingCtMaterial {
+            inputs: vec![],
+            outputs: self.outputs.clone(),
+            fee: self.fee,
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+        let key_images = sessions
+            .iter()
+            .map(SigningSession::key_image)
+            .collect::<Result<Vec<_>>>()?;
+        let (revealed_output_commitments, output_proofs, aggregated_range_proof) = output_material
+            .prove_outputs(
+                version,
+                &key_images,
+                &revealed_pseudo_commitments,
+                &mut *rng,
+            )?;
+
+        let public_keys: Vec<G1Affine> = sessions.iter().flat_map(|s| s.public_keys()).collect();
+        let msg = gen_message_for_signing(
+            version,
+            self.fee,
+            self.network_id.as_ref(),
+            self.not_valid_after,
+            &self.extra,
+            &public_keys,
+            &key_images,
+            &pseudo_commitments,
+            &output_proofs,
+            aggregated_range_proof.as_ref(),
+        );
+
+        let mut mlsags = Vec::with_capacity(sessions.len());
+        for ((mut session, input), revealed_pseudo_commitment) in sessions
+            .into_iter()
+            .zip(self.inputs.iter())
+            .zip(revealed_pseudo_commitments.iter())
+        {
+            let challenge = session.challenge(&msg, revealed_pseudo_commitment, pc_gens)?;
+            let response = signer.respond(
+                &input.public_key,
+                challenge.challenge_for(input.public_key)?,
+            )?;
+            session.add_partial_signature(PartialSignature {
+                index: SIGNER_INDEX,
+                response,
+            })?;
+            mlsags.push(session.finalize()?);
+        }
+
+        #[cfg(feature = "zeroize")]
+        revealed_pseudo_commitments.zeroize();
+
+        Ok((
+            RingCtTransaction {
+                version,
+                mlsags,
+                clsags: vec![],
+                matrix_mlsag: None,
+                outputs: output_proofs,
+                fee: self.fee,
+                extra: self.extra.clone(),
+                network_id: self.network_id,
+                not_valid_after: self.not_valid_after,
+                aggregated_range_proof,
+            },
+            revealed_output_commitments,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use bls_bulletproofs::{
+        blstrs::G1Projective,
+        group::{ff::Field, Group},
+        rand::rngs::OsRng,
+    };
+
+    use super::*;
+
+    #[derive(Default)]
+    struct SoftwareSigner {
+        secret_keys: BTreeMap<[u8; 48], Scalar>,
+        nonces: BTreeMap<[u8; 48], Scalar>,
+    }
+
+    impl SoftwareSigner {
+        fn secret_key(&self, public_key: &G1Affine) -> Result<Scalar> {
+            self.secret_keys
+                .get(&public_key.to_compressed())
+                .copied()
+                .ok_or_else(|| Error::ExternalSigner("unknown key".to_string()))
+        }
+    }
+
+    impl ExternalSigner for SoftwareSigner {
+        fn key_image(&self, public_key: &G1Affine) -> Result<KeyImage> {
+            Ok(crate::key_image(self.secret_key(public_key)?).into())
+        }
+
+        fn commit_nonce(&mut self, public_key: &G1Affine) -> Result<(G1Affine, G1Affine)> {
+            let nonce = Scalar::random(OsRng);
+            self.nonces.insert(public_key.to_compressed(), nonce);
+            Ok((
+                crate::public_key(nonce).to_affine(),
+                (crate::hash_to_curve((*public_key).into()) * nonce).to_affine(),
+            ))
+        }
+
+        fn respond(&mut self, public_key: &G1Affine, challenge: Scalar) -> Result<Scalar> {
+            let nonce = self
+                .nonces
+                .remove(&public_key.to_compressed())
+                .ok_or_else(|| Error::ExternalSigner("no nonce".to_string()))?;
+            Ok(nonce - challenge * self.secret_key(public_key)?)
+        }
+    }
+
+    #[test]
+    fn test_sign_with_external_signer() {
+        let mut rng = OsRng;
+        let pc_gens = RingCtMaterial::pc_gens();
+        let mut signer = SoftwareSigner::default();
+
+        let secret_key = Scalar::random(&mut rng);
+        let public_key = crate::public_key(secret_key).to_affine();
+        signer
+            .secret_keys
+            .insert(public_key.to_compressed(), secret_key);
+
+        let revealed_commitment = RevealedCommitment::from_value(3, &mut rng);
+        let decoy_inputs = Vec::from_iter((0..2).map(|_| DecoyInput {
+            public_key: G1Projective::random(&mut rng).to_affine(),
+            commitment: G1Projective::random(&mut rng).to_affine(),
+        }));
+        let mut ledger: BTreeMap<[u8; 48], G1Affine> = decoy_inputs
+            .iter()
+            .map(|d| (d.public_key.to_compressed(), d.commitment))
+            .collect();
+        ledger.insert(
+            public_key.to_compressed(),
+            revealed_commitment.commit(pc_gens).to_affine(),
+        );
+
+        let material = ExternalRingCtMaterial {
+            inputs: vec![ExternalInput {
+                public_key,
+                revealed_commitment,
+                decoy_inputs,
+            }],
+            outputs: vec![Output::new(G1Projective::random(&mut rng), 3)],
+            fee: Amount::ZERO,
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+
+        let (tx, _revealed_output_commitments) = material.sign_with(&mut signer, &mut rng).unwrap();
+        assert_eq!(
+            tx.key_images(),
+            vec![KeyImage::from(crate::key_image(secret_key))]
+        );
+
+        let public_commitments = Vec::from_iter(
+            tx.public_keys_per_ring()
+                .into_iter()
+                .map(|pks| Vec::from_iter(pks.into_iter().map(|pk| ledger[&pk.to_compressed()]))),
+        );
+        assert!(tx.verify(&public_commitments).is_ok());
+    }
+}
@@ -0,0 +1,297 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! C API for embedding the crate, eg. in mobile apps.
+//!
+//! Signed transactions are returned as opaque handles which must be released
+//! with `blst_ringct_tx_free()`, byte buffers returned by the library must be
+//! released with `blst_ringct_bytes_free()`.
+//!
+//! `blst_ringct_sign()` takes the transaction to sign encoded little-endian,
+//! with `u32` counts, points as 48 byte compressed G1 and scalars as 32 bytes:
+//!
+//! ```text
+//! fee: u64
+//! num_inputs: u32
+//!     secret_key: scalar, value: u64, blinding: scalar
+//!     num_decoys: u32
+//!         public_key: point, commitment: point
+//! num_outputs: u32
+//!     public_key: point, amount: u64
+//! ```
+
+use std::{ptr, slice};
+
+use bls_bulletproofs::blstrs::G1Affine;
+
+use crate::{
+    codec::Reader, ringct::RingCtTransaction, DecoyInput, Output, Result, RevealedCommitment,
+    RingCtTransactionBuilder, TrueInput,
+};
+
+pub const BLST_RINGCT_OK: i32 = 0;
+pub const BLST_RINGCT_ERR_NULL_POINTER: i32 = 1;
+pub const BLST_RINGCT_ERR_DECODE: i32 = 2;
+pub const BLST_RINGCT_ERR_SIGN: i32 = 3;
+pub const BLST_RINGCT_ERR_VERIFY: i32 = 4;
+
+/// Opaque handle to a signed transaction
+pub struct BlstRingctTx(RingCtTransaction);
+
+/// A byte buffer owned by the library
+#[repr(C)]
+pub struct BlstRingctBytes {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+/// Signs the encoded transaction and stores a handle to it in `out_tx`.
+///
+/// # Safety
+///
+/// `request` must point to `request_len` readable bytes and `out_tx` must be
+/// a valid pointer to write the handle to.
+#[no_mangle]
+pub unsafe extern "C" fn blst_ringct_sign(
+    request: *const u8,
+    request_len: usize,
+    out_tx: *mut *mut BlstRingctTx,
+) -> i32 {
+    if request.is_null() || out_tx.is_null() {
+        return BLST_RINGCT_ERR_NULL_POINTER;
+    }
+    let request = slice::from_raw_parts(request, request_len);
+
+    let builder = match decode_sign_request(request) {
+        Ok(builder) => builder,
+        Err(_) => return BLST_RINGCT_ERR_DECODE,
+    };
+    match builder.sign() {
+        Ok((tx, _revealed_output_commitments)) => {
+            *out_tx = Box::into_raw(Box::new(BlstRingctTx(tx)));
+            BLST_RINGCT_OK
+        }
+        Err(_) => BLST_RINGCT_ERR_SIGN,
+    }
+}
+
+/// Parses a transaction from `RingCtTransaction::to_bytes()` and stores a handle to it in `out_tx`.
+///
+/// # Safety
+///
+/// `bytes` must point to `len` readable bytes and `out_tx` must be a valid
+/// pointer to write the handle to.
+#[no_mangle]
+pub unsafe extern "C" fn blst_ringct_tx_from_bytes(
+    bytes: *const u8,
+    len: usize,
+    out_tx: *mut *mut BlstRingctTx,
+) -> i32 {
+    if bytes.is_null() || out_tx.is_null() {
+        return BLST_RINGCT_ERR_NULL_POINTER;
+    }
+    match RingCtTransaction::from_bytes(slice::from_raw_parts(bytes, len)) {
+        Ok(tx) => {
+            *out_tx = Box::into_raw(Box::new(BlstRingctTx(tx)));
+            BLST_RINGCT_OK
+        }
+        Err(_) => BLST_RINGCT_ERR_DECODE,
+    }
+}
+
+/// Encodes the transaction into `out_bytes`, to be released with `blst_ringct_bytes_free()`.
+///
+/// # Safety
+///
+/// `tx` must be a handle returned by this library and `out_bytes` a valid
+/// pointer to write the buffer to.
+#[no_mangle]
+pub unsafe extern "C" fn blst_ringct_tx_to_bytes(
+    tx: *const BlstRingctTx,
+    out_bytes: *mut BlstRingctBytes,
+) -> i32 {
+    if tx.is_null() || out_bytes.is_null() {
+        return BLST_RINGCT_ERR_NULL_POINTER;
+    }
+    let bytes = (*tx).0.to_bytes().into_boxed_slice();
+    let len = bytes.len();
+    *out_bytes = BlstRingctBytes {
+        data: Box::into_raw(bytes) as *mut u8,
+        len,
+    };
+    BLST_RINGCT_OK
+}
+
+/// Verifies the transaction given the concatenated commitments of every ring
+/// member, ring by ring in the order the rings appear in the transaction.
+///
+/// # Safety
+///
+/// `tx` must be a handle returned by this library and `public_commitments`
+/// must point to `public_commitments_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn blst_ringct_verify(
+    tx: *const BlstRingctTx,
+    public_commitments: *const u8,
+    public_commitments_len: usize,
+) -> i32 {
+    if tx.is_null() || public_commitments.is_null() {
+        return BLST_RINGCT_ERR_NULL_POINTER;
+    }
+    let tx = &(*tx).0;
+    let public_commitments = slice::from_raw_parts(public_commitments, public_commitments_len);
+
+    let mut reader = Reader::new(public_commitments);
+    let public_commitments_per_ring = match tx
+        .public_keys_per_ring()
+        .iter()
+        .map(|ring| ring.iter().map(|_| reader.read_point()).collect())
+        .collect::<Result<Vec<Vec<G1Affine>>>>()
+        .and_then(|commitments| reader.finish().map(|_| commitments))
+    {
+        Ok(commitments) => commitments,
+        Err(_) => return BLST_RINGCT_ERR_DECODE,
+    };
+
+    match tx.verify(&public_commitments_per_ring) {
+        Ok(()) => BLST_RINGCT_OK,
+        Err(_) => BLST_RINGCT_ERR_VERIFY,
+    }
+}
+
+/// Releases a transaction handle, null is ignored.
+///
+/// # Safety
+///
+/// `tx` must be null or a handle returned by this library which hasn't been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn blst_ringct_tx_free(tx: *mut BlstRingctTx) {
+    if !tx.is_null() {
+        drop(Box::from_raw(tx));
+    }
+}
+
+/// Releases a byte buffer returned by this library.
+///
+/// # Safety
+///
+/// `bytes` must have been returned by this library and not been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn blst_ringct_bytes_free(bytes: BlstRingctBytes) {
+    if !bytes.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            bytes.data, bytes.len,
+        )));
+    }
+}
+
+fn decode_sign_request(request: &[u8]) -> Result<RingCtTransactionBuilder> {
+    let mut reader = Reader::new(request);
+    let mut builder = RingCtTransactionBuilder::new().set_fee(reader.read_u64()?);
+
+    let num_inputs = reader.read_len()?;
+    for _ in 0..num_inputs {
+        let secret_key = reader.read_scalar()?;
+        let revealed_commitment = RevealedCommitment {
+            value: reader.read_u64()?,
+            blinding: reader.read_scalar()?,
+        };
+        let decoys = reader.read_vec(|r| {
+            Ok(DecoyInput {
+                public_key: r.read_point()?,
+                commitment: r.read_point()?,
+            })
+        })?;
+        builder = builder
+            .add_true_input(TrueInput::new(secret_key, revealed_commitment))
+            .add_decoys(decoys);
+    }
+
+    let outputs = reader.read_vec(|r| Ok(Output::new(r.read_point()?, r.read_u64()?)))?;
+    for output in outputs {
+        builder = builder.add_output(output);
+    }
+    reader.finish()?;
+
+    Ok(builder)
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{
+        blstrs::{G1Projective, Scalar},
+        group::{ff::Field, Curve, Group},
+        rand::rngs::OsRng,
+    };
+
+    use super::*;
+    use crate::codec;
+
+    #[test]
+    fn test_ffi_sign_and_verify() {
+        let secret_key = Scalar::random(OsRng);
+        let revealed_commitment = RevealedCommitment::from_value(3, OsRng);
+        let decoy = DecoyInput {
+            public_key: G1Projective::random(OsRng).to_affine(),
+            commitment: G1Projective::random(OsRng).to_affine(),
+        };
+
+        let mut request = vec![];
+        request.extend(0u64.to_le_bytes());
+        codec::write_len(&mut request, 1);
+        request.extend(secret_key.to_bytes_le());
+        request.extend(revealed_commitment.to_bytes());
+        codec::write_len(&mut request, 1);
+        request.extend(decoy.public_key.to_compressed());
+        request.extend(decoy.commitment.to_compressed());
+        codec::write_len(&mut request, 1);
+        request.extend(G1Projective::random(OsRng).to_affine().to_compressed());
+        request.extend(3u64.to_le_bytes());
+
+        let true_public_key = crate::public_key(secret_key).to_affine();
+        let true_commitment = revealed_commitment
+            .commit(&crate::pedersen_gens())
+            .to_affine();
+
+        unsafe {
+            let mut tx = ptr::null_mut();
+            assert_eq!(
+                blst_ringct_sign(request.as_ptr(), request.len(), &mut tx),
+                BLST_RINGCT_OK
+            );
+
+            let mut public_commitments = vec![];
+            for pk in (*tx).0.public_keys_per_ring().concat() {
+                let commitment = if pk == true_public_key {
+                    true_commitment
+                } else {
+                    decoy.commitment
+                };
+                public_commitments.extend(commitment.to_compressed());
+            }
+            assert_eq!(
+                blst_ringct_verify(tx, public_commitments.as_ptr(), public_commitments.len()),
+                BLST_RINGCT_OK
+            );
+
+            let mut bytes = BlstRingctBytes {
+                data: ptr::null_mut(),
+                len: 0,
+            };
+            assert_eq!(blst_ringct_tx_to_bytes(tx, &mut bytes), BLST_RINGCT_OK);
+            let mut parsed = ptr::null_mut();
+            assert_eq!(
+                blst_ringct_tx_from_bytes(bytes.data, bytes.len, &mut parsed),
+                BLST_RINGCT_OK
+            );
+            assert_eq!((*parsed).0, (*tx).0);
+
+            blst_ringct_bytes_free(bytes);
+            blst_ringct_tx_free(parsed);
+            blst_ringct_tx_free(tx);
+        }
+    }
+}
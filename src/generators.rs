@@ -0,0 +1,58 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! The crate's Pedersen generators.
+//!
+//! Commitments are `vB + bB'`. The value generator `B` is hashed to the curve
+//! with a crate-specific tag, so that its discrete log is unknown and
+//! commitments can't be confused with those of other protocols built on
+//! `PedersenGens::default()`. The blinding generator `B'` is the G1
+//! generator, as the ring signatures prove knowledge of `b` in `bG`.
+//! Transactions of versions with `TX_DOMAIN_SEPARATED_GENERATORS` set use them.
+
+use bls_bulletproofs::{
+    blstrs::G1Projective,
+    group::{Curve, Group},
+    PedersenGens,
+};
+use std::sync::OnceLock;
+
+/// The domain separation tag the value generator is hashed to the curve with
+pub const PEDERSEN_DST: &[u8] = b"BLST_RINGCT_PEDERSEN";
+
+/// The Pedersen generators of the crate's commitments
+pub fn pedersen_gens() -> PedersenGens {
+    static VALUE_GENERATOR: OnceLock<G1Projective> = OnceLock::new();
+    PedersenGens {
+        B: *VALUE_GENERATOR.get_or_init(|| {
+            G1Projective::hash_to_curve(
+                &G1Projective::generator().to_affine().to_compressed(),
+                PEDERSEN_DST,
+                &[],
+            )
+        }),
+        B_blinding: G1Projective::generator(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::blstrs::Scalar;
+
+    use super::*;
+
+    #[test]
+    fn test_generators_are_domain_separated() {
+        let pc_gens = pedersen_gens();
+        assert_ne!(pc_gens.B, PedersenGens::default().B);
+        assert_ne!(pc_gens.B, pc_gens.B_blinding);
+        assert_eq!(pc_gens.B, pedersen_gens().B);
+        assert_ne!(
+            pc_gens.commit(Scalar::from(5u64), Scalar::from(7u64)),
+            PedersenGens::default().commit(Scalar::from(5u64), Scalar::from(7u64))
+        );
+    }
+}
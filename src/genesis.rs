@@ -0,0 +1,166 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use bls_bulletproofs::{
+    blstrs::Scalar,
+    group::ff::Field,
+    rand::{CryptoRng, RngCore},
+};
+
+use crate::{
+    ringct::{check_extra, Amount, RingCtTransaction, TX_VERSION_GENESIS},
+    Error, Output, Result, RevealedCommitment, RingCtMaterial,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The outputs minting a network's initial supply. The genesis transaction
+/// creates them without spending any inputs.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default)]
+pub struct GenesisMaterial {
+    pub outputs: Vec<Output>,
+}
+
+impl GenesisMaterial {
+    /// The total amount minted
+    pub fn supply(&self) -> Result<Amount> {
+        Amount::checked_sum(self.outputs.iter().map(Output::amount))
+    }
+
+    /// Creates the genesis transaction. The output commitments are blinded to sum
+    /// to a commitment to the supply with a zero blinding factor, which lets
+    /// anyone check the supply with `RingCtTransaction::verify_genesis()`.
+    pub fn create(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<(RingCtTransaction, Vec<RevealedCommitment>)> {
+        if self.outputs.is_empty() {
+            return Err(Error::NoOutputs);
+        }
+        check_extra(&[], &self.outputs)?;
+        let supply = RevealedCommitment {
+            value: self.supply()?.value(),
+            blinding: Scalar::zero(),
+        };
+
+        let material = RingCtMaterial {
+            inputs: vec![],
+            outputs: self.outputs.clone(),
+            fee: Amount::ZERO,
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+        let (revealed_output_commitments, output_proofs, _) =
+            material.prove_outputs(TX_VERSION_GENESIS, &[], &[supply], rng)?;
+
+        Ok((
+            RingCtTransaction {
+                version: TX_VERSION_GENESIS,
+                mlsags: vec![],
+                clsags: vec![],
+                matrix_mlsag: None,
+                outputs: output_proofs,
+                fee: Amount::ZERO,
+                extra: vec![],
+                network_id: None,
+                not_valid_after: None,
+                aggregated_range_proof: None,
+            },
+            revealed_output_commitments,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{
+        blstrs::{G1Affine, G1Projective},
+        group::{prime::PrimeCurveAffine, Curve, Group},
+        rand::rngs::OsRng,
+    };
+
+    use super::*;
+    use crate::ringct::{DEFAULT_MAX_OUTPUTS, MAX_EXTRA_LEN};
+
+    fn genesis_tx() -> (RingCtTransaction, Vec<RevealedCommitment>) {
+        let mut rng = OsRng;
+        let material = GenesisMaterial {
+            outputs: vec![
+                Output::new(G1Projective::random(&mut rng).to_affine(), 700),
+                Output::new(G1Projective::random(&mut rng).to_affine(), 300),
+            ],
+        };
+        material.create(&mut rng).expect("Failed to create genesis")
+    }
+
+    #[test]
+    fn test_genesis_tx_verifies_against_its_supply() {
+        let (tx, revealed_output_commitments) = genesis_tx();
+        assert_eq!(tx.verify_genesis(1000), Ok(()));
+        assert_eq!(
+            tx.verify_genesis(1001),
+            Err(Error::GenesisOutputsDoNotSumToSupply)
+        );
+        assert_eq!(revealed_output_commitments[0].value, 700);
+
+        // a genesis transaction is not a regular transaction, and vice versa
+        assert_eq!(
+            tx.verify(&[]),
+            Err(Error::UnsupportedTransactionVersion(TX_VERSION_GENESIS))
+        );
+
+        let decoded = RingCtTransaction::from_bytes(&tx.to_bytes()).expect("Failed to decode");
+        assert_eq!(decoded, tx);
+    }
+
+    #[test]
+    fn test_verify_genesis_rejects_other_transactions() {
+        let (mut tx, _) = genesis_tx();
+        tx.version = crate::ringct::TX_VERSION_1;
+        assert_eq!(tx.verify_genesis(1000), Err(Error::NotAGenesisTransaction));
+
+        assert_eq!(
+            GenesisMaterial::default().create(&mut OsRng).err(),
+            Some(Error::NoOutputs)
+        );
+    }
+
+    #[test]
+    fn test_verify_genesis_validates_the_transaction() {
+        let (tx, _) = genesis_tx();
+
+        let mut tampered = tx.clone();
+        tampered.outputs[0].commitment = G1Affine::identity();
+        assert_eq!(
+            tampered.verify_genesis(1000),
+            Err(Error::PointIsIdentity("output 0 commitment".to_string()))
+        );
+
+        let mut tampered = tx.clone();
+        tampered.extra = vec![0; MAX_EXTRA_LEN + 1];
+        assert!(matches!(
+            tampered.verify_genesis(1000),
+            Err(Error::ExtraTooLong { .. })
+        ));
+
+        let mut tampered = tx.clone();
+        tampered.network_id = Some([1; 32]);
+        assert_eq!(
+            tampered.verify_genesis(1000),
+            Err(Error::NetworkIdDoesNotMatchVersion(TX_VERSION_GENESIS))
+        );
+
+        let mut tampered = tx;
+        tampered.outputs = vec![tampered.outputs[0].clone(); DEFAULT_MAX_OUTPUTS + 1];
+        assert!(matches!(
+            tampered.verify_genesis(1000),
+            Err(Error::TooManyOutputs { .. })
+        ));
+    }
+}
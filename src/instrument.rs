@@ -0,0 +1,72 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! `tracing` spans and counters around signing and verification, with the
+//! `tracing` feature. Without it these compile to nothing.
+//!
+//! Each phase is a `ringct` span at debug level with a `phase` field, eg.
+//! `verify` with `ring_signatures`, `range_proofs`, `uniqueness` and
+//! `balance` within it, so a subscriber timing spans gets per-phase
+//! latencies. Counters are debug events with `monotonic_counter.` fields, as
+//! `tracing-opentelemetry`'s metrics layer expects.
+
+use crate::Result;
+
+/// A phase of signing or verification
+pub(crate) struct Phase {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl Phase {
+    /// Opens the phase as a child of the current span. The span is created
+    /// here, so the phase keeps its parent when run on another thread.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn new(name: &'static str) -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!("ringct", phase = name),
+        }
+    }
+
+    /// Runs `f` within the phase
+    pub(crate) fn in_scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        #[cfg(feature = "tracing")]
+        {
+            self.span.in_scope(f)
+        }
+        #[cfg(not(feature = "tracing"))]
+        {
+            f()
+        }
+    }
+}
+
+/// Counts a transaction signed, or failed to sign
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn record_signing<T>(result: &Result<T>) {
+    #[cfg(feature = "tracing")]
+    match result {
+        Ok(_) => tracing::debug!(monotonic_counter.ringct_signed_transactions = 1u64),
+        Err(err) => tracing::debug!(
+            monotonic_counter.ringct_signing_failures = 1u64,
+            error = %err
+        ),
+    }
+}
+
+/// Counts a transaction verified, or rejected
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn record_verification(result: &Result<()>) {
+    #[cfg(feature = "tracing")]
+    match result {
+        Ok(()) => tracing::debug!(monotonic_counter.ringct_verified_transactions = 1u64),
+        Err(err) => tracing::debug!(
+            monotonic_counter.ringct_rejected_transactions = 1u64,
+            error = %err
+        ),
+    }
+}
@@ -0,0 +1,415 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Blind issuance: a mint certifies an output without learning its public
+//! key, its commitment or its amount.
+//!
+//! The mint's certificate is a BLS signature `S = x H(P, C)` on the output's
+//! public key `P` and commitment `C`, verified with `e(S, g2) = e(H(P, C), X)`.
+//! The user sends the mint a commitment `C' = vB + b'B'` with a range proof,
+//! and `n` candidate messages `M_i = r_i H(P_i, C' - s_i B')` for fresh keys
+//! `P_i`, blinded by random `r_i` and `s_i`. The mint keeps one candidate at
+//! random and the user opens the others, which shows that they are well formed.
+//! The mint signs the kept message, and the user unblinds the signature to a
+//! certificate on `(P, C)`, neither of which the mint has seen.
+//!
+//! A user can only cheat by guessing the kept candidate, with probability
+//! `1/n`. The mint can check `C'` against what is being issued, eg. that the
+//! requests of a transaction sum to its outputs, without learning `v`.
+
+use bls_bulletproofs::{
+    blstrs::{pairing, G1Affine, G1Projective, G2Affine, G2Projective, Scalar},
+    group::{ff::Field, prime::PrimeCurveAffine, Curve, Group},
+    merlin::Transcript,
+    rand::{CryptoRng, Rng, RngCore},
+    RangeProof,
+};
+
+use crate::{
+    codec::{self, Reader},
+    ringct::{MERLIN_TRANSCRIPT_LABEL, RANGE_PROOF_BITS},
+    Error, Result, RevealedCommitment, RingCtMaterial,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+const ISSUANCE_DOMAIN: &[u8] = b"blst-ringct-blind-issuance";
+
+/// The mint's signing key
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct MintSecretKey(Scalar);
+
+/// Verifies the mint's certificates on issued outputs
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MintPublicKey(pub G2Affine);
+
+/// A request to certify one of `blinded_messages`
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct IssuanceRequest {
+    /// A commitment to the amount of the output, which the output's
+    /// commitment is a rerandomisation of
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub commitment: G1Affine,
+    pub range_proof: RangeProof,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub blinded_messages: Vec<G1Affine>,
+}
+
+/// The secrets of a candidate, revealed when the mint doesn't keep it
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CandidateOpening {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub public_key: G1Affine,
+    /// `s_i`, the output's commitment being `C' - s_i B'`
+    pub commitment_offset: Scalar,
+    /// `r_i`, the blinding of the message
+    pub message_blinding: Scalar,
+}
+
+/// What the user keeps from a request to unblind the mint's signature
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct IssuanceSecrets {
+    revealed_commitment: RevealedCommitment,
+    candidates: Vec<CandidateOpening>,
+}
+
+/// The mint's signature on a kept candidate, still blinded
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlindSignature {
+    pub kept_index: u32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub signature: G1Affine,
+}
+
+/// An output certified by the mint
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssuedOutput {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub public_key: G1Affine,
+    pub revealed_commitment: RevealedCommitment,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub signature: G1Affine,
+}
+
+impl MintSecretKey {
+    pub fn random(rng: impl RngCore) -> Self {
+        Self(Scalar::random(rng))
+    }
+
+    pub fn public_key(&self) -> MintPublicKey {
+        MintPublicKey((G2Projective::generator() * self.0).to_affine())
+    }
+
+    /// Picks the candidate of `request` to sign, the user must then open the others
+    pub fn choose_candidate(&self, request: &IssuanceRequest, mut rng: impl RngCore) -> usize {
+        rng.gen_range(0..request.blinded_messages.len().max(1))
+    }
+
+    /// Checks the request's range proof and that every candidate but
+    /// `kept_index` opens to a rerandomisation of its commitment, then signs
+    /// the kept candidate
+    pub fn blind_sign(
+        &self,
+        request: &IssuanceRequest,
+        kept_index: usize,
+        openings: &[CandidateOpening],
+    ) -> Result<BlindSignature> {
+        let num_candidates = request.blinded_messages.len();
+        if num_candidates < 2
+            || kept_index >= num_candidates
+            || openings.len() + 1 != num_candidates
+        {
+            return Err(Error::InvalidIssuanceRequest);
+        }
+        request.verify_range_proof()?;
+
+        let opened = (0..num_candidates).filter(|i| *i != kept_index);
+        for (i, opening) in opened.zip(openings) {
+            if opening.blinded_message(&request.commitment) != request.blinded_messages[i] {
+                return Err(Error::InvalidIssuanceRequest);
+            }
+        }
+
+        Ok(BlindSignature {
+            kept_index: kept_index as u32,
+            signature: (request.blinded_messages[kept_index] * self.0).to_affine(),
+        })
+    }
+}
+
+impl MintPublicKey {
+    /// Checks that the mint certified the output `public_key` with `commitment`
+    pub fn verify(
+        &self,
+        public_key: &G1Affine,
+        commitment: &G1Affine,
+        signature: &G1Affine,
+    ) -> Result<()> {
+        if pairing(signature, &G2Affine::generator())
+            != pairing(&message(public_key, commitment).to_affine(), &self.0)
+        {
+            return Err(Error::InvalidBlindSignature);
+        }
+        Ok(())
+    }
+}
+
+impl IssuanceRequest {
+    /// Requests the certification of an output of `revealed_commitment.value`,
+    /// one candidate for each of `public_keys`, which the user must own. Any
+    /// of them may become the output's key, the others are revealed to the mint.
+    pub fn new(
+        revealed_commitment: &RevealedCommitment,
+        public_keys: &[G1Affine],
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<(Self, IssuanceSecrets)> {
+        if public_keys.len() < 2 {
+            return Err(Error::InvalidIssuanceRequest);
+        }
+        let (range_proof, commitment) = RangeProof::prove_single_with_rng(
+            &RingCtMaterial::bp_gens(),
+            RingCtMaterial::pc_gens(),
+            &mut issuance_transcript(),
+            revealed_commitment.value,
+            &revealed_commitment.blinding,
+            RANGE_PROOF_BITS,
+            &mut *rng,
+        )?;
+
+        let candidates: Vec<CandidateOpening> = public_keys
+            .iter()
+            .map(|public_key| CandidateOpening {
+                public_key: *public_key,
+                commitment_offset: Scalar::random(&mut *rng),
+                message_blinding: Scalar::random(&mut *rng),
+            })
+            .collect();
+        let blinded_messages = candidates
+            .iter()
+            .map(|c| c.blinded_message(&commitment))
+            .collect();
+
+        Ok((
+            Self {
+                commitment,
+                range_proof,
+                blinded_messages,
+            },
+            IssuanceSecrets {
+                revealed_commitment: *revealed_commitment,
+                candidates,
+            },
+        ))
+    }
+
+    fn verify_range_proof(&self) -> Result<()> {
+        self.range_proof
+            .verify_single(
+                &RingCtMaterial::bp_gens(),
+                RingCtMaterial::pc_gens(),
+                &mut issuance_transcript(),
+                &self.commitment,
+                RANGE_PROOF_BITS,
+            )
+            .map_err(|_| Error::InvalidIssuanceRequest)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Default::default();
+        v.extend(self.commitment.to_compressed());
+        let range_proof = self.range_proof.to_bytes();
+        codec::write_len(&mut v, range_proof.len());
+        v.extend(range_proof);
+        codec::write_len(&mut v, self.blinded_messages.len());
+        for m in self.blinded_messages.iter() {
+            v.extend(m.to_compressed());
+        }
+        v
+    }
+
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let commitment = reader.read_point()?;
+        let range_proof_len = reader.read_len()?;
+        let range_proof = RangeProof::from_bytes(&reader.take(range_proof_len)?)?;
+        let blinded_messages = reader.read_vec(|r| r.read_point())?;
+        reader.finish()?;
+
+        Ok(Self {
+            commitment,
+            range_proof,
+            blinded_messages,
+        })
+    }
+}
+
+impl IssuanceSecrets {
+    /// The openings of every candidate but `kept_index`, for the mint to check
+    pub fn open(&self, kept_index: usize) -> Vec<CandidateOpening> {
+        self.candidates
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != kept_index)
+            .map(|(_, c)| c.clone())
+            .collect()
+    }
+
+    /// Unblinds the mint's signature into a certificate on the kept output
+    pub fn unblind(
+        &self,
+        blind_signature: &BlindSignature,
+        mint_public_key: &MintPublicKey,
+    ) -> Result<IssuedOutput> {
+        let kept = self
+            .candidates
+            .get(blind_signature.kept_index as usize)
+            .ok_or(Error::InvalidBlindSignature)?;
+        let unblinding = Option::<Scalar>::from(kept.message_blinding.invert())
+            .ok_or(Error::InvalidBlindSignature)?;
+
+        let issued = IssuedOutput {
+            public_key: kept.public_key,
+            revealed_commitment: RevealedCommitment {
+                value: self.revealed_commitment.value,
+                blinding: self.revealed_commitment.blinding - kept.commitment_offset,
+            },
+            signature: (blind_signature.signature * unblinding).to_affine(),
+        };
+        issued.verify(mint_public_key)?;
+        Ok(issued)
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for IssuanceSecrets {
+    fn zeroize(&mut self) {
+        self.revealed_commitment.zeroize();
+        for c in self.candidates.iter_mut() {
+            crate::zeroize_scalar(&mut c.commitment_offset);
+            crate::zeroize_scalar(&mut c.message_blinding);
+        }
+    }
+}
+
+impl IssuedOutput {
+    pub fn commitment(&self) -> G1Affine {
+        self.revealed_commitment
+            .commit(RingCtMaterial::pc_gens())
+            .to_affine()
+    }
+
+    pub fn verify(&self, mint_public_key: &MintPublicKey) -> Result<()> {
+        mint_public_key.verify(&self.public_key, &self.commitment(), &self.signature)
+    }
+}
+
+impl CandidateOpening {
+    fn blinded_message(&self, request_commitment: &G1Affine) -> G1Affine {
+        let commitment = G1Projective::from(request_commitment)
+            - RingCtMaterial::pc_gens().B_blinding * self.commitment_offset;
+        (message(&self.public_key, &commitment.to_affine()) * self.message_blinding).to_affine()
+    }
+}
+
+/// `H(P, C)`, the message the mint signs for an output
+fn message(public_key: &G1Affine, commitment: &G1Affine) -> G1Projective {
+    let msg = [public_key.to_compressed(), commitment.to_compressed()].concat();
+    G1Projective::hash_to_curve(&msg, ISSUANCE_DOMAIN, &[])
+}
+
+fn issuance_transcript() -> Transcript {
+    let mut transcript = Transcript::new(MERLIN_TRANSCRIPT_LABEL);
+    transcript.append_message(b"dom-sep", ISSUANCE_DOMAIN);
+    transcript
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::rand::rngs::OsRng;
+
+    use super::*;
+
+    fn public_keys(n: usize) -> Vec<G1Affine> {
+        (0..n)
+            .map(|_| G1Projective::random(OsRng).to_affine())
+            .collect()
+    }
+
+    #[test]
+    fn test_blind_issuance() {
+        let mint = MintSecretKey::random(OsRng);
+        let mint_public_key = mint.public_key();
+
+        let revealed_commitment = RevealedCommitment::from_value(42, OsRng);
+        let public_keys = public_keys(4);
+        let (request, secrets) =
+            IssuanceRequest::new(&revealed_commitment, &public_keys, &mut OsRng)
+                .expect("Failed to create request");
+        let request = IssuanceRequest::from_bytes(&request.to_bytes()).unwrap();
+
+        let kept_index = mint.choose_candidate(&request, OsRng);
+        let blind_signature = mint
+            .blind_sign(&request, kept_index, &secrets.open(kept_index))
+            .expect("Failed to sign");
+        let issued = secrets
+            .unblind(&blind_signature, &mint_public_key)
+            .expect("Failed to unblind");
+
+        // the certified output is one the mint hasn't seen, of the same amount
+        assert_eq!(issued.public_key, public_keys[kept_index]);
+        assert_eq!(issued.revealed_commitment.value, 42);
+        assert_ne!(issued.commitment(), request.commitment);
+        assert_eq!(issued.verify(&mint_public_key), Ok(()));
+
+        let other_mint = MintSecretKey::random(OsRng).public_key();
+        assert_eq!(
+            issued.verify(&other_mint),
+            Err(Error::InvalidBlindSignature)
+        );
+        let mut inflated = issued;
+        inflated.revealed_commitment.value = 43;
+        assert_eq!(
+            inflated.verify(&mint_public_key),
+            Err(Error::InvalidBlindSignature)
+        );
+    }
+
+    #[test]
+    fn test_mint_rejects_malformed_candidates() {
+        let mint = MintSecretKey::random(OsRng);
+        let revealed_commitment = RevealedCommitment::from_value(42, OsRng);
+        let (mut request, secrets) =
+            IssuanceRequest::new(&revealed_commitment, &public_keys(2), &mut OsRng)
+                .expect("Failed to create request");
+
+        // a candidate for a different commitment is caught when opened
+        let other = RevealedCommitment::from_value(1000, OsRng)
+            .commit(RingCtMaterial::pc_gens())
+            .to_affine();
+        request.blinded_messages[1] = secrets.candidates[1].blinded_message(&other);
+        assert_eq!(
+            mint.blind_sign(&request, 0, &secrets.open(0)),
+            Err(Error::InvalidIssuanceRequest)
+        );
+
+        // every candidate but the kept one must be opened
+        assert_eq!(
+            mint.blind_sign(&request, 0, &[]),
+            Err(Error::InvalidIssuanceRequest)
+        );
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! JSON schemas of the serde encodings of transactions, for explorers and
+//! gateways validating payloads. The types here only describe fields whose
+//! types don't derive `JsonSchema`, with
+//! `#[schemars(with = "crate::json_schema::HexPoint")]` and the like.
+
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, Metadata, Schema, SchemaObject, StringValidation},
+    JsonSchema,
+};
+
+/// A compressed G1 point, as `serde_hex` writes it in JSON
+pub(crate) struct HexPoint;
+
+impl JsonSchema for HexPoint {
+    fn schema_name() -> String {
+        "HexPoint".to_string()
+    }
+
+    fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            metadata: Some(Box::new(Metadata {
+                description: Some("A compressed G1 point, in hex".to_string()),
+                ..Default::default()
+            })),
+            instance_type: Some(InstanceType::String.into()),
+            string: Some(Box::new(StringValidation {
+                min_length: Some(96),
+                max_length: Some(96),
+                pattern: Some("^[0-9a-fA-F]{96}$".to_string()),
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+/// A scalar, as blstrs serializes it: four little-endian u64 limbs of its
+/// canonical little-endian encoding
+pub(crate) struct ScalarLimbs;
+
+impl JsonSchema for ScalarLimbs {
+    fn schema_name() -> String {
+        "Scalar".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let mut schema = <[u64; 4]>::json_schema(gen).into_object();
+        schema.metadata().description =
+            Some("A scalar, as four little-endian u64 limbs".to_string());
+        schema.into()
+    }
+}
+
+/// A range proof, as the bytes of `RangeProof::to_bytes()`
+pub(crate) struct RangeProofBytes;
+
+impl JsonSchema for RangeProofBytes {
+    fn schema_name() -> String {
+        "RangeProof".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let mut schema = <Vec<u8>>::json_schema(gen).into_object();
+        schema.metadata().description = Some("A range proof, as its bytes".to_string());
+        schema.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{
+        blstrs::{G1Projective, Scalar},
+        group::{ff::Field, Group},
+        rand::rngs::OsRng,
+    };
+    use schemars::schema_for;
+
+    use crate::{
+        mock::random_decoys,
+        ringct::{Amount, RingCtTransaction},
+        MlsagMaterial, Output, RevealedCommitment, RingCtMaterial, TrueInput,
+    };
+
+    #[test]
+    fn test_transaction_schema_describes_its_json() {
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(3, OsRng),
+        );
+        let decoy_inputs = random_decoys(2);
+        let material = RingCtMaterial {
+            inputs: vec![MlsagMaterial::new(true_input, decoy_inputs, &mut OsRng).unwrap()],
+            outputs: vec![Output::new(G1Projective::random(OsRng), 3)],
+            fee: Amount::ZERO,
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+        let (tx, _revealed_output_commitments) = material
+            .sign(&mut OsRng)
+            .expect("Failed to sign transaction");
+
+        let schema = serde_json::to_value(schema_for!(RingCtTransaction)).unwrap();
+        let json = serde_json::to_value(&tx).unwrap();
+        for field in json.as_object().unwrap().keys() {
+            assert!(
+                schema["properties"].get(field).is_some(),
+                "{} is missing from the schema",
+                field
+            );
+        }
+
+        let definitions = &schema["definitions"];
+        assert_eq!(definitions["HexPoint"]["pattern"], "^[0-9a-fA-F]{96}$");
+        assert_eq!(
+            definitions["MlsagSignature"]["properties"]["pseudo_commitment"]["$ref"],
+            "#/definitions/HexPoint"
+        );
+        let pseudo_commitment = json["mlsags"][0]["pseudo_commitment"].as_str().unwrap();
+        assert_eq!(pseudo_commitment.len(), 96);
+        assert_eq!(json["mlsags"][0]["c0"].as_array().unwrap().len(), 4);
+    }
+}
@@ -0,0 +1,146 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, G1Projective},
+    group::Curve,
+};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeSet, HashSet},
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+use crate::{codec, Error, Result};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A KeyImage identifies the true input spent by a ring signature, I = x * Hp(P).
+/// Spending the same input twice yields the same KeyImage.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyImage(
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    #[cfg_attr(
+        feature = "json-schema",
+        schemars(with = "crate::json_schema::HexPoint")
+    )]
+    G1Affine,
+);
+
+impl KeyImage {
+    pub fn to_bytes(&self) -> [u8; 48] {
+        self.0.to_compressed()
+    }
+
+    pub fn from_bytes(bytes: &[u8; 48]) -> Result<Self> {
+        Option::from(G1Affine::from_compressed(bytes))
+            .map(Self)
+            .ok_or(Error::InvalidPointEncoding)
+    }
+
+    pub fn to_affine(&self) -> G1Affine {
+        self.0
+    }
+}
+
+impl From<G1Affine> for KeyImage {
+    fn from(p: G1Affine) -> Self {
+        Self(p)
+    }
+}
+
+impl From<G1Projective> for KeyImage {
+    fn from(p: G1Projective) -> Self {
+        Self(p.to_affine())
+    }
+}
+
+impl From<KeyImage> for G1Affine {
+    fn from(k: KeyImage) -> Self {
+        k.0
+    }
+}
+
+impl AsRef<G1Affine> for KeyImage {
+    fn as_ref(&self) -> &G1Affine {
+        &self.0
+    }
+}
+
+impl PartialOrd for KeyImage {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for KeyImage {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.to_bytes().cmp(&other.to_bytes())
+    }
+}
+
+impl Hash for KeyImage {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_bytes().hash(state)
+    }
+}
+
+impl fmt::Display for KeyImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&codec::to_hex(&self.to_bytes()))
+    }
+}
+
+/// The key images of spent inputs, eg. a ledger's spent set
+pub trait KeyImageStore {
+    fn contains(&self, key_image: &KeyImage) -> bool;
+
+    /// Records `key_image` as spent, returning false if it already was
+    fn insert(&mut self, key_image: KeyImage) -> bool;
+}
+
+impl KeyImageStore for HashSet<KeyImage> {
+    fn contains(&self, key_image: &KeyImage) -> bool {
+        HashSet::contains(self, key_image)
+    }
+
+    fn insert(&mut self, key_image: KeyImage) -> bool {
+        HashSet::insert(self, key_image)
+    }
+}
+
+impl KeyImageStore for BTreeSet<KeyImage> {
+    fn contains(&self, key_image: &KeyImage) -> bool {
+        BTreeSet::contains(self, key_image)
+    }
+
+    fn insert(&mut self, key_image: KeyImage) -> bool {
+        BTreeSet::insert(self, key_image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{blstrs::Scalar, group::ff::Field, rand::rngs::OsRng};
+
+    use super::*;
+
+    #[test]
+    fn test_key_image_bytes_round_trip() {
+        let key_image = KeyImage::from(crate::key_image(Scalar::random(OsRng)));
+
+        assert_eq!(KeyImage::from_bytes(&key_image.to_bytes()), Ok(key_image));
+        assert_eq!(key_image.to_string().len(), 96);
+        assert_eq!(
+            KeyImage::from_bytes(&[0xff; 48]),
+            Err(Error::InvalidPointEncoding)
+        );
+    }
+}
@@ -0,0 +1,153 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Key image export, with which an offline signer tells a watch-only wallet
+//! the key images of its outputs, so that it can see when they're spent.
+//!
+//! Each key image comes with a proof that it was made with the secret key of
+//! the output's public key, `I = xHp(P)` for `P = xG`, so that a compromised
+//! exporter can't make spent outputs look unspent.
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::{ff::Field, Curve, Group},
+    rand::{CryptoRng, RngCore},
+};
+
+use crate::{codec, codec::Reader, mlsag::hash_to_scalar, Error, KeyImage, Result};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const KEY_IMAGE_EXPORT_DOMAIN: &[u8] = b"blst-ringct-key-image-export";
+
+/// An output's key image, with a proof of equality of the discrete logs of
+/// `P` to `G` and of `I` to `Hp(P)`
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignedKeyImage {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub public_key: G1Affine,
+    pub key_image: KeyImage,
+    pub challenge: Scalar,
+    pub response: Scalar,
+}
+
+impl SignedKeyImage {
+    /// The key image of the output owned by `secret_key`, eg. a
+    /// `ScannedOutput`'s one-time secret key
+    pub fn sign(secret_key: impl Into<Scalar>, rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        let secret_key = secret_key.into();
+        let public_key = crate::public_key(secret_key);
+        let base = crate::hash_to_curve(public_key);
+        let key_image = KeyImage::from(base * secret_key);
+
+        let nonce = Scalar::random(&mut *rng);
+        let challenge = challenge(
+            &public_key.to_affine(),
+            &key_image,
+            &(G1Projective::generator() * nonce),
+            &(base * nonce),
+        );
+        Self {
+            public_key: public_key.to_affine(),
+            key_image,
+            challenge,
+            response: nonce - challenge * secret_key,
+        }
+    }
+
+    /// Checks that `key_image` is the key image of `public_key`
+    pub fn verify(&self) -> Result<()> {
+        let public_key = G1Projective::from(self.public_key);
+        let key_image = G1Projective::from(self.key_image.to_affine());
+        let base = crate::hash_to_curve(public_key);
+        let nonce_commitment =
+            G1Projective::generator() * self.response + public_key * self.challenge;
+        let base_nonce_commitment = base * self.response + key_image * self.challenge;
+
+        if bool::from(public_key.is_identity())
+            || bool::from(key_image.is_identity())
+            || challenge(
+                &self.public_key,
+                &self.key_image,
+                &nonce_commitment,
+                &base_nonce_commitment,
+            ) != self.challenge
+        {
+            return Err(Error::InvalidKeyImageProof {
+                public_key: codec::to_hex(&self.public_key.to_compressed()),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Default::default();
+        v.extend(self.public_key.to_compressed());
+        v.extend(self.key_image.to_bytes());
+        v.extend(self.challenge.to_bytes_le());
+        v.extend(self.response.to_bytes_le());
+        v
+    }
+
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let signed_key_image = Self {
+            public_key: reader.read_point()?,
+            key_image: KeyImage::from(reader.read_point()?),
+            challenge: reader.read_scalar()?,
+            response: reader.read_scalar()?,
+        };
+        reader.finish()?;
+        Ok(signed_key_image)
+    }
+}
+
+fn challenge(
+    public_key: &G1Affine,
+    key_image: &KeyImage,
+    nonce_commitment: &G1Projective,
+    base_nonce_commitment: &G1Projective,
+) -> Scalar {
+    hash_to_scalar(&[
+        KEY_IMAGE_EXPORT_DOMAIN,
+        &public_key.to_compressed(),
+        &key_image.to_bytes(),
+        &nonce_commitment.to_compressed(),
+        &base_nonce_commitment.to_compressed(),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::rand::rngs::OsRng;
+
+    use super::*;
+
+    #[test]
+    fn test_signed_key_image() {
+        let secret_key = Scalar::random(OsRng);
+        let signed = SignedKeyImage::sign(secret_key, &mut OsRng);
+        assert_eq!(
+            signed.key_image,
+            KeyImage::from(crate::key_image(secret_key))
+        );
+        assert_eq!(signed.verify(), Ok(()));
+        assert_eq!(SignedKeyImage::from_bytes(&signed.to_bytes()), Ok(signed));
+
+        let other = SignedKeyImage::sign(Scalar::random(OsRng), &mut OsRng);
+        let forged = SignedKeyImage {
+            key_image: other.key_image,
+            ..signed
+        };
+        assert!(matches!(
+            forged.verify(),
+            Err(Error::InvalidKeyImageProof { .. })
+        ));
+    }
+}
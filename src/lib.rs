@@ -4,39 +4,171 @@
 // This SAFE Network Software is licensed under the BSD-3-Clause license.
 // Please see the LICENSE file for more details.
 
+pub mod amount_secrets;
+#[cfg(feature = "async")]
+pub mod async_lookup;
+pub mod backend;
+#[cfg(feature = "blsttc")]
+pub mod blsttc_adaptor;
+pub mod builder;
+pub mod bundle;
+pub mod burn;
+mod chain;
+pub mod clsag;
+mod codec;
+pub mod commitment_proof;
+pub mod conflict;
+pub mod decoys;
+pub mod denominations;
+pub mod derivation;
+pub mod disclosure;
 pub mod error;
+pub mod external;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod generators;
+pub mod genesis;
+mod instrument;
+pub mod issuance;
+#[cfg(feature = "json-schema")]
+mod json_schema;
+mod key_image;
+pub mod key_image_export;
+pub mod matrix_mlsag;
+pub mod merkle;
 pub mod mlsag;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+pub mod owner;
+pub mod ownership_proof;
+pub mod payment_proof;
+pub mod pruned;
+pub mod psrt;
+mod range_proof_batch;
+pub mod reserve_proof;
+mod rewind;
+pub mod ring_cache;
 pub mod ringct;
+#[cfg(feature = "serde")]
+mod serde_hex;
+#[cfg(feature = "experimental-snark")]
+pub mod snark;
+pub mod spent_proof;
+pub mod stealth;
+#[cfg(feature = "proptest")]
+pub mod strategies;
+pub mod summary;
+pub mod threshold;
+#[cfg(feature = "experimental-triptych")]
+pub mod triptych;
+pub mod tx_policy;
+pub mod types;
+pub mod verifier;
+pub mod wallet;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod wide_amount;
 
 // re-export deps used in our public API
 pub use bls_bulletproofs::{self, blstrs, group, rand};
+#[cfg(feature = "json-schema")]
+pub use schemars;
 #[cfg(feature = "serde")]
 pub use serde;
+#[cfg(feature = "zeroize")]
+pub use zeroize;
 
 use bls_bulletproofs::{
-    blstrs::{G1Projective, Scalar},
+    blstrs::{G1Affine, G1Projective, Scalar},
     group::{ff::Field, Group},
     rand::RngCore,
     PedersenGens,
 };
+use std::{
+    iter::Sum,
+    ops::{Add, Sub},
+};
 
+pub use amount_secrets::{AmountSecrets, EncryptedAmount};
+#[cfg(feature = "async")]
+pub use async_lookup::AsyncCommitmentLookup;
+pub use backend::{CpuBackend, VerificationBackend};
+pub use builder::RingCtTransactionBuilder;
+pub use bundle::TransactionBundle;
+pub use burn::{burn_public_key, BurnProof};
+pub use clsag::ClsagSignature;
+pub use commitment_proof::{EqualValueProof, OpeningProof};
+pub use conflict::ConflictGraph;
+pub use decoys::{DecoySelector, DecoyStore, GammaSelector, UniformSelector};
+pub use denominations::{denominations, OutputPlan};
+pub use derivation::{derive_blinding, derive_owner_key};
+pub use disclosure::{DisclosedOutput, TransactionDisclosure};
 pub use error::Error;
-pub use mlsag::{DecoyInput, MlsagMaterial, MlsagSignature, TrueInput};
-pub use ringct::{Output, RingCtMaterial};
+pub use external::{ExternalInput, ExternalRingCtMaterial, ExternalSigner};
+pub use generators::pedersen_gens;
+pub use genesis::GenesisMaterial;
+pub use issuance::{
+    BlindSignature, CandidateOpening, IssuanceRequest, IssuanceSecrets, IssuedOutput,
+    MintPublicKey, MintSecretKey,
+};
+pub use key_image::{KeyImage, KeyImageStore};
+pub use key_image_export::SignedKeyImage;
+pub use matrix_mlsag::MatrixMlsagSignature;
+pub use merkle::OutputInclusionProof;
+pub use mlsag::{
+    DecoyInput, MlsagAdaptorSignature, MlsagMaterial, MlsagSignature, RingPositionAudit, TrueInput,
+};
+pub use owner::{sign_as_owner, OwnerSignature};
+pub use ownership_proof::OwnershipProof;
+pub use payment_proof::PaymentProof;
+pub use pruned::PrunedRingCtTransaction;
+pub use psrt::PartiallySignedRingCtTransaction;
+pub use reserve_proof::{ReserveProof, ReservedOutput};
+pub use ring_cache::RingCache;
+pub use ringct::{
+    CommitmentLookup, DecodeLimits, LockLookup, Output, RangeProofContext, RangeProofVerification,
+    RingCtConfig, RingCtMaterial, RingCtPolicy, VerificationReport, VerifyOpts,
+};
+pub use spent_proof::{SpentProof, SpentProofShare};
+pub use stealth::{OneTimeKey, OwnedOutput, StealthAddress, StealthKeys, ViewKey, ViewedOutput};
+pub use summary::TransactionSummary;
+pub use threshold::{
+    KeyShare, PartialSignature, SigningChallenge, SigningCommitment, SigningSession,
+};
+pub use tx_policy::{DefaultPolicy, TxPolicy};
+pub use types::{BlindingFactor, Commitment, PublicKey, SecretKey};
+pub use verifier::RingCtVerifier;
+pub use wallet::{ScannedOutput, Wallet, WatchOnlyWallet, WatchedOutput};
+pub use wide_amount::{WideAmount, WideRangeProof, WideRevealedCommitment};
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct RevealedCommitment {
     pub value: u64,
     pub blinding: Scalar,
 }
 
+impl std::hash::Hash for RevealedCommitment {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::hash::Hash::hash(&self.to_bytes(), state)
+    }
+}
+
 impl RevealedCommitment {
+    pub fn new(value: u64, blinding: impl Into<BlindingFactor>) -> Self {
+        Self {
+            value,
+            blinding: blinding.into().0,
+        }
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut v: Vec<u8> = Default::default();
         v.extend(self.value.to_le_bytes());
@@ -44,6 +176,17 @@ impl RevealedCommitment {
         v
     }
 
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = codec::Reader::new(bytes);
+        let revealed_commitment = Self {
+            value: reader.read_u64()?,
+            blinding: reader.read_scalar()?,
+        };
+        reader.finish()?;
+        Ok(revealed_commitment)
+    }
+
     /// Construct a revealed commitment from a value, generating a blinding randomly
     pub fn from_value(value: u64, mut rng: impl RngCore) -> Self {
         Self {
@@ -63,12 +206,89 @@ impl RevealedCommitment {
     pub fn blinding(&self) -> Scalar {
         self.blinding
     }
+
+    /// Checks that this opens `commitment`, eg. one read from the ledger
+    pub fn verify_opens(&self, commitment: &G1Affine, pc_gens: &PedersenGens) -> Result<()> {
+        if self.commit(pc_gens) != G1Projective::from(commitment) {
+            return Err(Error::AmountSecretsDoNotMatchCommitment);
+        }
+        Ok(())
+    }
+
+    /// Opens the sum of the commitments, or `None` if the values overflow
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        Some(Self {
+            value: self.value.checked_add(other.value)?,
+            blinding: self.blinding + other.blinding,
+        })
+    }
+
+    /// Opens the difference of the commitments, or `None` if the values underflow
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        Some(Self {
+            value: self.value.checked_sub(other.value)?,
+            blinding: self.blinding - other.blinding,
+        })
+    }
+}
+
+/// Panics if the values overflow, as `u64` addition does, see `checked_add()`
+impl Add for RevealedCommitment {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        self.checked_add(other)
+            .expect("RevealedCommitment values overflow")
+    }
 }
 
-/// Hashes a point to another point on the G1 curve
+/// Panics if the values underflow, as `u64` subtraction does, see `checked_sub()`
+impl Sub for RevealedCommitment {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self.checked_sub(other)
+            .expect("RevealedCommitment values underflow")
+    }
+}
+
+impl Sum for RevealedCommitment {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::new(0, Scalar::zero()), Add::add)
+    }
+}
+
+impl<'a> Sum<&'a RevealedCommitment> for RevealedCommitment {
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for RevealedCommitment {
+    fn zeroize(&mut self) {
+        self.value.zeroize();
+        zeroize_scalar(&mut self.blinding);
+    }
+}
+
+/// Overwrites a secret scalar in a way the compiler will not optimise away
+#[cfg(feature = "zeroize")]
+pub(crate) fn zeroize_scalar(s: &mut Scalar) {
+    // Safety: `s` is a valid, aligned and exclusive reference
+    unsafe { std::ptr::write_volatile(s, Scalar::zero()) };
+    std::sync::atomic::compiler_fence(std::sync::atomic::Ordering::SeqCst);
+}
+
+/// The domain separation tag of `hash_to_curve()`, in the RFC 9380 format
+pub const HASH_TO_CURVE_DST: &[u8] = b"BLST-RINGCT-V01-CS01-with-BLS12381G1_XMD:SHA-256_SSWU_RO_";
+
+/// Hashes a point to another point on the G1 curve, the base of its key
+/// image, with the RFC 9380 `BLS12381G1_XMD:SHA-256_SSWU_RO_` suite over its
+/// compressed encoding. Transactions of versions with `TX_RFC9380_KEY_IMAGES`
+/// set use it.
 pub fn hash_to_curve(p: G1Projective) -> G1Projective {
-    const DOMAIN: &[u8; 25] = b"blst-ringct-hash-to-curve";
-    G1Projective::hash_to_curve(&p.to_compressed(), DOMAIN, &[])
+    G1Projective::hash_to_curve(&p.to_compressed(), HASH_TO_CURVE_DST, &[])
 }
 
 pub fn public_key<S: Into<Scalar>>(secret_key: S) -> G1Projective {
@@ -0,0 +1,503 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Matrix MLSAG: a single MLSAG signing every input of a transaction.
+//!
+//! The rings of the inputs form the rows of a key matrix whose columns are
+//! signed together, with the true inputs all in the same column. One chain of
+//! challenges covers every input, so the inputs can't be separated or
+//! recombined with other inputs, and the signature needs a single `c0`.
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::{ff::Field, prime::PrimeCurveAffine, Curve, Group},
+    rand::{CryptoRng, RngCore},
+    PedersenGens,
+};
+use subtle::{Choice, ConditionallySelectable};
+
+use crate::{
+    codec::{self, Reader},
+    mlsag::{
+        append_ring, ct_get, ct_set, ct_wrap, draw_nonce_pairs, hash_to_scalar, nonce_transcript,
+    },
+    Error, KeyImage, MlsagMaterial, Result, RevealedCommitment,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct MatrixMlsagSignature {
+    #[cfg_attr(
+        feature = "json-schema",
+        schemars(with = "crate::json_schema::ScalarLimbs")
+    )]
+    pub c0: Scalar,
+    /// The responses of each input, one pair per ring member
+    #[cfg_attr(
+        feature = "json-schema",
+        schemars(
+            with = "Vec<Vec<(crate::json_schema::ScalarLimbs, crate::json_schema::ScalarLimbs)>>"
+        )
+    )]
+    pub r: Vec<Vec<(Scalar, Scalar)>>,
+    pub key_images: Vec<KeyImage>,
+    /// The ring of each input, all of the same size
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    #[cfg_attr(
+        feature = "json-schema",
+        schemars(with = "Vec<Vec<(crate::json_schema::HexPoint, crate::json_schema::HexPoint)>>")
+    )]
+    pub rings: Vec<Vec<(G1Affine, G1Affine)>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    #[cfg_attr(
+        feature = "json-schema",
+        schemars(with = "Vec<crate::json_schema::HexPoint>")
+    )]
+    pub pseudo_commitments: Vec<G1Affine>,
+}
+
+impl MatrixMlsagSignature {
+    /// Signs every input in `inputs` with a single signature. The rings must
+    /// all have the same size and place the true input at the same position.
+    /// The nonces are drawn as `MlsagMaterial::sign()` draws them, from a
+    /// transcript of every ring rekeyed with every true input's secrets.
+    pub fn sign(
+        inputs: &[MlsagMaterial],
+        msg: &[u8],
+        revealed_pseudo_commitments: &[RevealedCommitment],
+        pc_gens: &PedersenGens,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Self> {
+        #[allow(non_snake_case)]
+        let G1 = G1Projective::generator();
+
+        let first = inputs.first().ok_or(Error::TransactionMustHaveAnInput)?;
+        let (ring_len, pi) = (first.count_inputs(), first.pi());
+        for (input_index, input) in inputs.iter().enumerate() {
+            if input.count_inputs() != ring_len {
+                return Err(Error::InvalidRingSize {
+                    input_index,
+                    expected: ring_len,
+                    actual: input.count_inputs(),
+                });
+            }
+            if input.pi() != pi {
+                return Err(Error::TrueInputsNotAligned { input_index });
+            }
+        }
+        if revealed_pseudo_commitments.len() != inputs.len() {
+            return Err(Error::ExpectedAPublicCommitmentsForEachRingEntry);
+        }
+
+        let pseudo_commitments: Vec<G1Projective> = revealed_pseudo_commitments
+            .iter()
+            .map(|r| r.commit(pc_gens))
+            .collect();
+        let rings: Vec<Vec<(G1Affine, G1Affine)>> = inputs
+            .iter()
+            .zip(&pseudo_commitments)
+            .map(|(input, pseudo_commitment)| {
+                input
+                    .public_keys()
+                    .into_iter()
+                    .zip(input.commitments(pc_gens))
+                    .map(|(pk, commitment)| (pk, (commitment - *pseudo_commitment).to_affine()))
+                    .collect()
+            })
+            .collect();
+        let key_images: Vec<G1Projective> =
+            inputs.iter().map(|m| m.true_input.key_image()).collect();
+        let columns: Vec<(Vec<G1Affine>, Vec<G1Affine>)> = rings
+            .iter()
+            .map(|ring| ring.iter().copied().unzip())
+            .collect();
+
+        let mut transcript = nonce_transcript(b"matrix-mlsag", msg);
+        for (ring, pseudo_commitment) in rings.iter().zip(&pseudo_commitments) {
+            append_ring(&mut transcript, ring, &pseudo_commitment.to_affine());
+        }
+        let mut nonce_rng = inputs
+            .iter()
+            .fold(transcript.build_rng(), |builder, input| {
+                input.true_input.rekey_nonces(builder)
+            })
+            .finalize(rng);
+        #[cfg_attr(not(feature = "zeroize"), allow(unused_mut))]
+        let (mut alphas, r): (Vec<(Scalar, Scalar)>, Vec<Vec<(Scalar, Scalar)>>) = inputs
+            .iter()
+            .map(|_| draw_nonce_pairs(&mut nonce_rng, ring_len))
+            .unzip();
+        let (mut r0, mut r1): (Vec<Vec<Scalar>>, Vec<Vec<Scalar>>) = r
+            .into_iter()
+            .map(|r| r.into_iter().unzip::<_, _, Vec<_>, Vec<_>>())
+            .unzip();
+
+        let mut c: Vec<Scalar> = (0..ring_len).map(|_| Scalar::zero()).collect();
+
+        // As with MLSAG signing, every step does the same work and reads and
+        // writes the rings by scanning all of them, so nothing depends on `pi`.
+        for offset in 0..ring_len {
+            let n = ct_wrap(pi + offset, ring_len);
+            let is_true_input = Choice::from((offset == 0) as u8);
+            let c_n = Scalar::conditional_select(
+                &ct_get(&c, n, Scalar::zero()),
+                &Scalar::zero(),
+                is_true_input,
+            );
+            let c_next = c_hash(
+                msg,
+                alphas
+                    .iter()
+                    .zip(&columns)
+                    .zip(r0.iter().zip(&r1))
+                    .zip(&key_images)
+                    .map(
+                        |(((alpha, (public_keys, hidden_commitments)), (r0, r1)), key_image)| {
+                            let public_key = ct_get(public_keys, n, G1Affine::identity());
+                            let hidden_commitment =
+                                ct_get(hidden_commitments, n, G1Affine::identity());
+                            let r_n = (
+                                Scalar::conditional_select(
+                                    &ct_get(r0, n, Scalar::zero()),
+                                    &alpha.0,
+                                    is_true_input,
+                                ),
+                                Scalar::conditional_select(
+                                    &ct_get(r1, n, Scalar::zero()),
+                                    &alpha.1,
+                                    is_true_input,
+                                ),
+                            );
+                            [
+                                G1 * r_n.0 + public_key * c_n,
+                                G1 * r_n.1 + hidden_commitment * c_n,
+                                crate::hash_to_curve(public_key.into()) * r_n.0 + *key_image * c_n,
+                            ]
+                        },
+                    ),
+            );
+            ct_set(&mut c, ct_wrap(n + 1, ring_len), &c_next);
+        }
+
+        let c_pi = ct_get(&c, pi, Scalar::zero());
+        for (((input, revealed_pseudo_commitment), alpha), (r0, r1)) in inputs
+            .iter()
+            .zip(revealed_pseudo_commitments)
+            .zip(alphas.iter_mut())
+            .zip(r0.iter_mut().zip(r1.iter_mut()))
+        {
+            #[cfg_attr(not(feature = "zeroize"), allow(unused_mut))]
+            let mut secret_keys = (
+                input.true_input.secret_key,
+                input.true_input.revealed_commitment.blinding - revealed_pseudo_commitment.blinding,
+            );
+            ct_set(r0, pi, &(alpha.0 - c_pi * secret_keys.0));
+            ct_set(r1, pi, &(alpha.1 - c_pi * secret_keys.1));
+
+            #[cfg(feature = "zeroize")]
+            for s in [
+                &mut alpha.0,
+                &mut alpha.1,
+                &mut secret_keys.0,
+                &mut secret_keys.1,
+            ] {
+                crate::zeroize_scalar(s);
+            }
+        }
+
+        let r = r0
+            .into_iter()
+            .zip(r1)
+            .map(|(r0, r1)| r0.into_iter().zip(r1).collect())
+            .collect();
+
+        Ok(Self {
+            c0: c[0],
+            r,
+            key_images: key_images.into_iter().map(KeyImage::from).collect(),
+            rings,
+            pseudo_commitments: pseudo_commitments
+                .into_iter()
+                .map(|c| c.to_affine())
+                .collect(),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Default::default();
+        v.extend(self.c0.to_bytes_le());
+        codec::write_len(&mut v, self.rings.len());
+        let inputs = self
+            .r
+            .iter()
+            .zip(&self.key_images)
+            .zip(&self.rings)
+            .zip(&self.pseudo_commitments);
+        for (((r, key_image), ring), pseudo_commitment) in inputs {
+            codec::write_len(&mut v, r.len());
+            for (x, y) in r.iter() {
+                v.extend(x.to_bytes_le());
+                v.extend(y.to_bytes_le());
+            }
+            v.extend(key_image.to_bytes());
+            codec::write_len(&mut v, ring.len());
+            for (x, y) in ring.iter() {
+                v.extend(x.to_compressed());
+                v.extend(y.to_compressed());
+            }
+            v.extend(pseudo_commitment.to_compressed());
+        }
+        v
+    }
+
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let sig = Self::decode(&mut reader)?;
+        reader.finish()?;
+        Ok(sig)
+    }
+
+    pub(crate) fn decode(reader: &mut Reader) -> Result<Self> {
+        let limits = reader.limits();
+        let c0 = reader.read_scalar()?;
+        let inputs = reader.read_vec_max(limits.max_inputs, "inputs", |reader| {
+            let r = reader.read_vec_max(limits.max_ring_size, "ring members", |r| {
+                Ok((r.read_scalar()?, r.read_scalar()?))
+            })?;
+            let key_image = KeyImage::from(reader.read_point()?);
+            let ring = reader.read_vec_max(limits.max_ring_size, "ring members", |r| {
+                Ok((r.read_point()?, r.read_point()?))
+            })?;
+            let pseudo_commitment = reader.read_point()?;
+            Ok((r, key_image, ring, pseudo_commitment))
+        })?;
+
+        let mut sig = Self {
+            c0,
+            r: Vec::with_capacity(inputs.len()),
+            key_images: Vec::with_capacity(inputs.len()),
+            rings: Vec::with_capacity(inputs.len()),
+            pseudo_commitments: Vec::with_capacity(inputs.len()),
+        };
+        for (r, key_image, ring, pseudo_commitment) in inputs {
+            sig.r.push(r);
+            sig.key_images.push(key_image);
+            sig.rings.push(ring);
+            sig.pseudo_commitments.push(pseudo_commitment);
+        }
+        Ok(sig)
+    }
+
+    pub fn public_keys_per_ring(&self) -> Vec<Vec<G1Affine>> {
+        self.rings
+            .iter()
+            .map(|ring| ring.iter().map(|(pk, _)| *pk).collect())
+            .collect()
+    }
+
+    /// Verifies the signature over `msg`, given the public commitments of
+    /// each input's ring
+    pub fn verify(&self, msg: &[u8], public_commitments_per_ring: &[Vec<G1Affine>]) -> Result<()> {
+        let ring_len = match self.rings.first() {
+            Some(ring) if !ring.is_empty() => ring.len(),
+            _ => return Err(Error::EmptyRing),
+        };
+        let num_inputs = self.rings.len();
+        if [
+            self.r.len(),
+            self.key_images.len(),
+            self.pseudo_commitments.len(),
+            public_commitments_per_ring.len(),
+        ]
+        .iter()
+        .any(|len| *len != num_inputs)
+        {
+            return Err(Error::ExpectedAPublicCommitmentsForEachRingEntry);
+        }
+        for (input_index, ((ring, r), public_commitments)) in self
+            .rings
+            .iter()
+            .zip(&self.r)
+            .zip(public_commitments_per_ring)
+            .enumerate()
+        {
+            if ring.len() != ring_len {
+                return Err(Error::InvalidRingSize {
+                    input_index,
+                    expected: ring_len,
+                    actual: ring.len(),
+                });
+            }
+            if r.len() != ring_len || public_commitments.len() != ring_len {
+                return Err(Error::ExpectedAPublicCommitmentsForEachRingEntry);
+            }
+        }
+
+        // Check that hidden commitments in the rings where computed with: C - C'
+        let rows = self
+            .rings
+            .iter()
+            .zip(&self.pseudo_commitments)
+            .zip(public_commitments_per_ring);
+        for ((ring, pseudo_commitment), public_commitments) in rows {
+            for ((_, hidden_commitment), public_commitment) in ring.iter().zip(public_commitments) {
+                if G1Projective::from(hidden_commitment)
+                    != public_commitment - G1Projective::from(pseudo_commitment)
+                {
+                    return Err(Error::InvalidHiddenCommitmentInRing);
+                }
+            }
+        }
+
+        #[allow(non_snake_case)]
+        let G1 = G1Projective::generator();
+
+        let key_images: Vec<G1Projective> = self
+            .key_images
+            .iter()
+            .map(|key_image| G1Projective::from(key_image.to_affine()))
+            .collect();
+
+        let mut c = self.c0;
+        for n in 0..ring_len {
+            c =
+                c_hash(
+                    msg,
+                    self.rings.iter().zip(&self.r).zip(&key_images).map(
+                        |((ring, r), key_image)| {
+                            let pk = G1Projective::from(ring[n].0);
+                            let hidden_commitment = G1Projective::from(ring[n].1);
+                            let (r0, r1) = r[n];
+                            [
+                                G1Projective::multi_exp(&[G1, pk], &[r0, c]),
+                                G1Projective::multi_exp(&[G1, hidden_commitment], &[r1, c]),
+                                G1Projective::multi_exp(
+                                    &[crate::hash_to_curve(pk), *key_image],
+                                    &[r0, c],
+                                ),
+                            ]
+                        },
+                    ),
+                );
+        }
+
+        if self.c0 != c {
+            Err(Error::InvalidRingSignature)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// The challenge for the next column, over the points of every input in the column
+fn c_hash(msg: &[u8], points: impl Iterator<Item = [G1Projective; 3]>) -> Scalar {
+    let points: Vec<[u8; 48]> = points.flatten().map(|p| p.to_compressed()).collect();
+    let material: Vec<&[u8]> = std::iter::once(msg)
+        .chain(points.iter().map(|p| p.as_slice()))
+        .collect();
+    hash_to_scalar(&material)
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{mock::random_decoys, TrueInput};
+
+    fn material(ring_len: usize, pi_base: u32) -> (MlsagMaterial, RevealedCommitment) {
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(5, OsRng),
+        );
+        let revealed_pseudo_commitment = true_input.random_pseudo_commitment(OsRng);
+        let decoys = random_decoys(ring_len - 1);
+        let mut material = MlsagMaterial::new(true_input, decoys, &mut OsRng).unwrap();
+        material.pi_base = pi_base;
+        (material, revealed_pseudo_commitment)
+    }
+
+    #[test]
+    fn test_matrix_mlsag_signs_every_input() {
+        let pc_gens = crate::pedersen_gens();
+        let (inputs, revealed_pseudo_commitments): (Vec<_>, Vec<_>) =
+            (0..3).map(|_| material(4, 2)).unzip();
+        let public_commitments: Vec<_> = inputs.iter().map(|m| m.commitments(&pc_gens)).collect();
+
+        let sig = MatrixMlsagSignature::sign(
+            &inputs,
+            b"msg",
+            &revealed_pseudo_commitments,
+            &pc_gens,
+            &mut OsRng,
+        )
+        .unwrap();
+        assert_eq!(sig.verify(b"msg", &public_commitments), Ok(()));
+        assert_eq!(
+            sig.verify(b"other msg", &public_commitments),
+            Err(Error::InvalidRingSignature)
+        );
+
+        let decoded = MatrixMlsagSignature::from_bytes(&sig.to_bytes()).unwrap();
+        assert_eq!(decoded, sig);
+
+        // the inputs can't be separated from each other
+        let mut partial = sig.clone();
+        partial.rings.pop();
+        partial.r.pop();
+        partial.key_images.pop();
+        partial.pseudo_commitments.pop();
+        assert_eq!(
+            partial.verify(b"msg", &public_commitments[..2]),
+            Err(Error::InvalidRingSignature)
+        );
+
+        let mut tampered = sig;
+        tampered.r[1][0].1 += Scalar::one();
+        assert_eq!(
+            tampered.verify(b"msg", &public_commitments),
+            Err(Error::InvalidRingSignature)
+        );
+    }
+
+    #[test]
+    fn test_matrix_mlsag_requires_aligned_rings() {
+        let pc_gens = crate::pedersen_gens();
+        let (a, a_pseudo) = material(4, 1);
+        let (b, b_pseudo) = material(3, 1);
+        assert_eq!(
+            MatrixMlsagSignature::sign(
+                &[a.clone(), b],
+                b"msg",
+                &[a_pseudo, b_pseudo],
+                &pc_gens,
+                &mut OsRng
+            ),
+            Err(Error::InvalidRingSize {
+                input_index: 1,
+                expected: 4,
+                actual: 3
+            })
+        );
+
+        let (c, c_pseudo) = material(4, 2);
+        assert_eq!(
+            MatrixMlsagSignature::sign(
+                &[a, c],
+                b"msg",
+                &[a_pseudo, c_pseudo],
+                &pc_gens,
+                &mut OsRng
+            ),
+            Err(Error::TrueInputsNotAligned { input_index: 1 })
+        );
+    }
+}
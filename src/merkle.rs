@@ -0,0 +1,231 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! A Merkle tree over the outputs of a transaction, so that a light client
+//! can check an output belongs to a transaction without downloading it.
+//!
+//! Each leaf hashes the transaction id, the output's index and the output, so
+//! a root only proves outputs of that transaction. The transaction id itself
+//! covers the full outputs rather than the root, so light clients must learn
+//! the root alongside the id from a source they trust, eg. a signed block
+//! header committing to both.
+
+use tiny_keccak::{Hasher, Sha3};
+
+use crate::{
+    codec::{self, Reader},
+    ringct::{OutputProof, RingCtTransaction},
+    Error, Result,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const LEAF_DOMAIN: &[u8] = b"blst-ringct-outputs-merkle-leaf";
+const NODE_DOMAIN: &[u8] = b"blst-ringct-outputs-merkle-node";
+
+/// Proves that an output is at `output_index` among the outputs of a transaction
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputInclusionProof {
+    pub output_index: u32,
+    pub num_outputs: u32,
+    /// The sibling of each node on the path from the leaf to the root,
+    /// skipping levels where the node has none
+    pub siblings: Vec<[u8; 32]>,
+}
+
+impl RingCtTransaction {
+    /// The root of the Merkle tree over the transaction's outputs
+    pub fn outputs_merkle_root(&self) -> [u8; 32] {
+        let mut level = self.output_leaves();
+        while level.len() > 1 {
+            level = next_level(&level);
+        }
+        // a transaction always has outputs, but hash an empty tree regardless
+        level.pop().unwrap_or_else(|| hash(&[NODE_DOMAIN]))
+    }
+
+    /// Proves the inclusion of the output at `output_index`
+    pub fn output_inclusion_proof(&self, output_index: usize) -> Result<OutputInclusionProof> {
+        if output_index >= self.outputs.len() {
+            return Err(Error::OutputIndexOutOfRange(output_index));
+        }
+
+        let mut siblings = vec![];
+        let mut level = self.output_leaves();
+        let mut index = output_index;
+        while level.len() > 1 {
+            if let Some(sibling) = level.get(index ^ 1) {
+                siblings.push(*sibling);
+            }
+            level = next_level(&level);
+            index /= 2;
+        }
+
+        Ok(OutputInclusionProof {
+            output_index: output_index as u32,
+            num_outputs: self.outputs.len() as u32,
+            siblings,
+        })
+    }
+
+    fn output_leaves(&self) -> Vec<[u8; 32]> {
+        let txid = self.id();
+        self.outputs
+            .iter()
+            .enumerate()
+            .map(|(i, output)| leaf(&txid, i as u32, output))
+            .collect()
+    }
+}
+
+impl OutputInclusionProof {
+    /// Checks that `output` is an output of the transaction `txid`, whose
+    /// outputs have the Merkle root `root`
+    pub fn verify(&self, output: &OutputProof, txid: &[u8; 32], root: &[u8; 32]) -> Result<()> {
+        if self.output_index >= self.num_outputs {
+            return Err(Error::InvalidInclusionProof);
+        }
+
+        let mut node = leaf(txid, self.output_index, output);
+        let mut index = self.output_index;
+        let mut len = self.num_outputs;
+        let mut siblings = self.siblings.iter();
+        while len > 1 {
+            // the last node of a level with an odd length is promoted as is
+            if index % 2 == 1 || index + 1 < len {
+                let sibling = siblings.next().ok_or(Error::InvalidInclusionProof)?;
+                node = match index % 2 {
+                    0 => hash(&[NODE_DOMAIN, &node, sibling]),
+                    _ => hash(&[NODE_DOMAIN, sibling, &node]),
+                };
+            }
+            index /= 2;
+            len = len.div_ceil(2);
+        }
+
+        if siblings.next().is_some() || node != *root {
+            return Err(Error::InvalidInclusionProof);
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Default::default();
+        v.extend(self.output_index.to_le_bytes());
+        v.extend(self.num_outputs.to_le_bytes());
+        codec::write_len(&mut v, self.siblings.len());
+        for sibling in self.siblings.iter() {
+            v.extend(sibling);
+        }
+        v
+    }
+
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let proof = Self {
+            output_index: reader.read_u32()?,
+            num_outputs: reader.read_u32()?,
+            siblings: reader.read_vec(|r| r.read_array())?,
+        };
+        reader.finish()?;
+        Ok(proof)
+    }
+}
+
+fn leaf(txid: &[u8; 32], output_index: u32, output: &OutputProof) -> [u8; 32] {
+    hash(&[
+        LEAF_DOMAIN,
+        txid,
+        &output_index.to_le_bytes(),
+        &output.to_bytes(),
+    ])
+}
+
+fn next_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => hash(&[NODE_DOMAIN, left, right]),
+            [node] => *node,
+            _ => unreachable!("chunks of two"),
+        })
+        .collect()
+}
+
+fn hash(parts: &[&[u8]]) -> [u8; 32] {
+    let mut sha3 = Sha3::v256();
+    for part in parts {
+        sha3.update(part);
+    }
+    let mut hash = [0; 32];
+    sha3.finalize(&mut hash);
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{
+        blstrs::{G1Projective, Scalar},
+        group::{ff::Field, Group},
+        rand::rngs::OsRng,
+    };
+
+    use super::*;
+    use crate::{
+        mock::random_decoys, ringct::Amount, MlsagMaterial, Output, RevealedCommitment,
+        RingCtMaterial, TrueInput,
+    };
+
+    #[test]
+    fn test_output_inclusion_proofs() {
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(5, OsRng),
+        );
+        let decoy_inputs = random_decoys(2);
+        let material = RingCtMaterial {
+            inputs: vec![MlsagMaterial::new(true_input, decoy_inputs, &mut OsRng).unwrap()],
+            outputs: (0..5)
+                .map(|_| Output::new(G1Projective::random(OsRng), 1))
+                .collect(),
+            fee: Amount::ZERO,
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+        let (tx, _revealed_output_commitments) = material
+            .sign(&mut OsRng)
+            .expect("Failed to sign transaction");
+
+        let txid = tx.id();
+        let root = tx.outputs_merkle_root();
+        for (i, output) in tx.outputs.iter().enumerate() {
+            let proof = tx.output_inclusion_proof(i).unwrap();
+            assert_eq!(proof.verify(output, &txid, &root), Ok(()));
+            assert_eq!(
+                OutputInclusionProof::from_bytes(&proof.to_bytes()),
+                Ok(proof)
+            );
+        }
+
+        let proof = tx.output_inclusion_proof(4).unwrap();
+        assert_eq!(
+            proof.verify(&tx.outputs[3], &txid, &root),
+            Err(Error::InvalidInclusionProof)
+        );
+        assert_eq!(
+            proof.verify(&tx.outputs[4], &[0; 32], &root),
+            Err(Error::InvalidInclusionProof)
+        );
+        assert_eq!(
+            tx.output_inclusion_proof(5),
+            Err(Error::OutputIndexOutOfRange(5))
+        );
+    }
+}
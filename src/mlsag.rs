@@ -6,16 +6,32 @@
 
 use bls_bulletproofs::{
     blstrs::{G1Affine, G1Projective, Scalar},
-    group::{ff::Field, Curve, Group, GroupEncoding},
-    rand::RngCore,
+    group::{ff::Field, prime::PrimeCurveAffine, Curve, Group, GroupEncoding},
+    merlin::{Transcript, TranscriptRng, TranscriptRngBuilder},
+    rand::{seq::SliceRandom, CryptoRng, Rng, RngCore},
     PedersenGens,
 };
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+use std::{
+    collections::BTreeMap,
+    io::{Read, Write},
+};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, ConstantTimeGreater};
 use tiny_keccak::{Hasher, Sha3};
 
-use crate::{Error, Result, RevealedCommitment};
+use crate::{
+    backend::VerificationBackend,
+    codec::{self, Reader},
+    ringct::{validate_point, OutputProof, MAX_STREAMED_LEN},
+    Commitment, Error, KeyImage, PublicKey, Result, RevealedCommitment, SecretKey,
+};
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+const NONCE_TRANSCRIPT_LABEL: &[u8] = b"blst-ringct-mlsag-nonces";
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone)]
@@ -25,13 +41,36 @@ pub struct TrueInput {
 }
 
 impl TrueInput {
-    pub fn new<S: Into<Scalar>>(secret_key: S, revealed_commitment: RevealedCommitment) -> Self {
+    pub fn new<S: Into<SecretKey>>(secret_key: S, revealed_commitment: RevealedCommitment) -> Self {
         Self {
-            secret_key: secret_key.into(),
+            secret_key: secret_key.into().0,
             revealed_commitment,
         }
     }
 
+    /// The input spending a previous transaction's `output`, checking that
+    /// `secret_key` owns it and that `revealed_commitment` (eg. its recovered
+    /// `AmountSecrets`) opens its commitment.
+    pub fn from_output<S: Into<SecretKey>, R: Into<RevealedCommitment>>(
+        output: &OutputProof,
+        secret_key: S,
+        revealed_commitment: R,
+    ) -> Result<Self> {
+        let true_input = Self::new(secret_key, revealed_commitment.into());
+        if true_input.public_key().to_affine() != *output.public_key() {
+            return Err(Error::SecretKeyDoesNotOwnOutput);
+        }
+        if true_input
+            .revealed_commitment
+            .commit(&crate::pedersen_gens())
+            .to_affine()
+            != G1Affine::from(output.commitment())
+        {
+            return Err(Error::AmountSecretsDoNotMatchCommitment);
+        }
+        Ok(true_input)
+    }
+
     pub fn public_key(&self) -> G1Projective {
         crate::public_key(self.secret_key)
     }
@@ -50,16 +89,50 @@ impl TrueInput {
     pub fn random_pseudo_commitment(&self, rng: impl RngCore) -> RevealedCommitment {
         RevealedCommitment::from_value(self.revealed_commitment.value, rng)
     }
+
+    /// Rekeys a nonce transcript with this input's secrets
+    pub(crate) fn rekey_nonces(&self, builder: TranscriptRngBuilder) -> TranscriptRngBuilder {
+        builder
+            .rekey_with_witness_bytes(b"secret_key", &self.secret_key.to_bytes_le())
+            .rekey_with_witness_bytes(b"revealed_commitment", &self.revealed_commitment.to_bytes())
+    }
 }
 
+#[cfg(feature = "zeroize")]
+impl Zeroize for TrueInput {
+    fn zeroize(&mut self) {
+        crate::zeroize_scalar(&mut self.secret_key);
+        self.revealed_commitment.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for TrueInput {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for TrueInput {}
+
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, Copy)]
 pub struct DecoyInput {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub public_key: G1Affine,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub commitment: G1Affine,
 }
 
 impl DecoyInput {
+    pub fn new(public_key: PublicKey, commitment: Commitment) -> Self {
+        Self {
+            public_key: public_key.0,
+            commitment: commitment.0,
+        }
+    }
+
     pub fn public_key(&self) -> G1Affine {
         self.public_key
     }
@@ -75,31 +148,62 @@ pub struct MlsagMaterial {
     pub true_input: TrueInput,
     pub decoy_inputs: Vec<DecoyInput>,
     pub pi_base: u32,
-    pub alpha: (Scalar, Scalar),
-    pub r: Vec<(Scalar, Scalar)>,
 }
 
 impl MlsagMaterial {
+    /// Mixes the true input amongst `decoy_inputs`, which must be valid
+    /// points of the prime order subgroup and must not include the true input.
     pub fn new(
         true_input: TrueInput,
         decoy_inputs: Vec<DecoyInput>,
-        mut rng: impl RngCore,
-    ) -> Self {
-        let pi_base = rng.next_u32();
-
-        let ring_len = decoy_inputs.len() + 1;
-        let alpha = (Scalar::random(&mut rng), Scalar::random(&mut rng));
-        let r: Vec<(Scalar, Scalar)> = (0..ring_len)
-            .map(|_| (Scalar::random(&mut rng), Scalar::random(&mut rng)))
-            .collect();
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Self> {
+        let true_public_key = true_input.public_key().to_affine();
+        for (decoy_index, decoy) in decoy_inputs.iter().enumerate() {
+            validate_point(&decoy.public_key, || {
+                format!("public key of decoy {decoy_index}")
+            })?;
+            validate_point(&decoy.commitment, || {
+                format!("commitment of decoy {decoy_index}")
+            })?;
+            if decoy.public_key == true_public_key {
+                return Err(Error::DecoyIsTrueInput { decoy_index });
+            }
+        }
 
-        Self {
+        let mut decoy_inputs = decoy_inputs;
+        let pi_base = shuffle_ring(&mut decoy_inputs, rng);
+
+        Ok(Self {
             true_input,
             decoy_inputs,
             pi_base,
-            alpha,
-            r,
+        })
+    }
+
+    /// As `new()`, also checking that the ring has `ring_size` members
+    pub fn with_ring_size(
+        true_input: TrueInput,
+        decoy_inputs: Vec<DecoyInput>,
+        ring_size: usize,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Self> {
+        if decoy_inputs.len() + 1 != ring_size {
+            return Err(Error::DecoyCountMismatch {
+                expected: ring_size.saturating_sub(1),
+                actual: decoy_inputs.len(),
+            });
         }
+        Self::new(true_input, decoy_inputs, rng)
+    }
+
+    /// As `new()`, with the ring order reproducible from `seed`
+    pub fn from_seed(
+        true_input: TrueInput,
+        decoy_inputs: Vec<DecoyInput>,
+        seed: [u8; 32],
+    ) -> Result<Self> {
+        Self::new(true_input, decoy_inputs, &mut ChaCha20Rng::from_seed(seed))
     }
 
     pub fn count_inputs(&self) -> usize {
@@ -107,36 +211,96 @@ impl MlsagMaterial {
     }
 
     // Determines the index of the true input that will be randomly placed
-    // amongst the decoys
+    // amongst the decoys. Only the signer knows it, the signature's ring
+    // is ordered the same whichever member signed.
     pub fn pi(&self) -> usize {
         self.pi_base as usize % (self.decoy_inputs.len() + 1)
     }
 
     pub fn public_keys(&self) -> Vec<G1Affine> {
-        let mut keys = Vec::from_iter(self.decoy_inputs.iter().map(DecoyInput::public_key));
-        keys.insert(self.pi(), self.true_input.public_key().to_affine());
-        keys
+        let keys = Vec::from_iter(self.decoy_inputs.iter().map(DecoyInput::public_key));
+        ct_insert(&keys, self.pi(), self.true_input.public_key().to_affine())
     }
 
     pub fn commitments(&self, pc_gens: &PedersenGens) -> Vec<G1Affine> {
-        let mut cs = Vec::from_iter(self.decoy_inputs.iter().map(DecoyInput::commitment));
+        let cs = Vec::from_iter(self.decoy_inputs.iter().map(DecoyInput::commitment));
         let true_commitment = self.true_input.revealed_commitment.commit(pc_gens);
-        cs.insert(self.pi(), true_commitment.to_affine());
-        cs
+        ct_insert(&cs, self.pi(), true_commitment.to_affine())
     }
 
+    /// Signs `msg`. The nonces are drawn from a transcript of `msg` and the
+    /// ring, rekeyed with the true input's secrets and finalized with `rng`,
+    /// so that neither a weak `rng` nor signing the material again can
+    /// reuse them.
     pub fn sign(
         &self,
         msg: &[u8],
         revealed_pseudo_commitment: &RevealedCommitment,
         pc_gens: &PedersenGens,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> MlsagSignature {
+        self.sign_with_adaptor(
+            msg,
+            revealed_pseudo_commitment,
+            pc_gens,
+            G1Projective::identity(),
+            rng,
+        )
+    }
+
+    /// Produces a pre-signature that only becomes a valid signature once
+    /// completed with the secret `t` of `adaptor_point = tG`, which whoever
+    /// completes it then reveals, see `extract_secret()`.
+    pub fn sign_adaptor(
+        &self,
+        msg: &[u8],
+        revealed_pseudo_commitment: &RevealedCommitment,
+        adaptor_point: G1Affine,
+        pc_gens: &PedersenGens,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> MlsagAdaptorSignature {
+        MlsagAdaptorSignature {
+            pre_signature: self.sign_with_adaptor(
+                msg,
+                revealed_pseudo_commitment,
+                pc_gens,
+                adaptor_point.into(),
+                rng,
+            ),
+            adaptor_point,
+        }
+    }
+
+    /// The rng a signature's nonces are drawn from: `transcript`, which
+    /// holds what the signature signs, rekeyed with the true input's secrets
+    /// and finalized with `rng`
+    pub(crate) fn nonce_rng(
+        &self,
+        transcript: &Transcript,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> TranscriptRng {
+        self.true_input
+            .rekey_nonces(transcript.build_rng())
+            .finalize(rng)
+    }
+
+    // The adaptor point is added to the commitment column's nonce commitment
+    // at every ring position, so that the pre-signature doesn't reveal the
+    // true input. Adding `t` to every commitment response completes it.
+    fn sign_with_adaptor(
+        &self,
+        msg: &[u8],
+        revealed_pseudo_commitment: &RevealedCommitment,
+        pc_gens: &PedersenGens,
+        adaptor_point: G1Projective,
+        rng: &mut (impl RngCore + CryptoRng),
     ) -> MlsagSignature {
         #[allow(non_snake_case)]
         let G1 = G1Projective::generator(); // TAI: should we use pedersen.G instead?
 
         let public_keys = self.public_keys();
         let commitments = self.commitments(pc_gens);
-        let (pi, alpha, mut r) = (self.pi(), self.alpha, self.r.clone());
+        let pi = self.pi();
 
         let pseudo_commitment = revealed_pseudo_commitment.commit(pc_gens);
 
@@ -153,120 +317,148 @@ impl MlsagMaterial {
 
         let key_image = self.true_input.key_image();
 
-        let mut c: Vec<Scalar> = (0..ring.len()).map(|_| Scalar::zero()).collect();
+        let mut transcript = nonce_transcript(b"mlsag", msg);
+        append_ring(&mut transcript, &ring, &pseudo_commitment.to_affine());
+        transcript.append_message(b"adaptor_point", &adaptor_point.to_affine().to_compressed());
+        #[cfg_attr(not(feature = "zeroize"), allow(unused_mut))]
+        let (mut alpha, r) = draw_nonce_pairs(&mut self.nonce_rng(&transcript, rng), ring.len());
 
-        c[(pi + 1) % ring.len()] = c_hash(
-            msg,
-            G1 * alpha.0,
-            G1 * alpha.1,
-            crate::hash_to_curve(ring[pi].0.into()) * alpha.0,
-        );
+        let ring_len = ring.len();
+        let (public_keys, hidden_commitments): (Vec<G1Affine>, Vec<G1Affine>) =
+            ring.iter().copied().unzip();
+        let (mut r0, mut r1): (Vec<Scalar>, Vec<Scalar>) = r.into_iter().unzip();
+        let mut c: Vec<Scalar> = (0..ring_len).map(|_| Scalar::zero()).collect();
 
-        for offset in 1..ring.len() {
-            let n = (pi + offset) % ring.len();
-            c[(n + 1) % ring.len()] = c_hash(
+        // Every step of the challenge chain does the same work, starting with
+        // the true input's, and reads and writes the ring by scanning all of
+        // it, so that neither timing nor memory accesses depend on `pi`.
+        for offset in 0..ring_len {
+            let n = ct_wrap(pi + offset, ring_len);
+            let is_true_input = Choice::from((offset == 0) as u8);
+            let public_key = ct_get(&public_keys, n, G1Affine::identity());
+            let hidden_commitment = ct_get(&hidden_commitments, n, G1Affine::identity());
+            let c_n = Scalar::conditional_select(
+                &ct_get(&c, n, Scalar::zero()),
+                &Scalar::zero(),
+                is_true_input,
+            );
+            let r_n = (
+                Scalar::conditional_select(
+                    &ct_get(&r0, n, Scalar::zero()),
+                    &alpha.0,
+                    is_true_input,
+                ),
+                Scalar::conditional_select(
+                    &ct_get(&r1, n, Scalar::zero()),
+                    &alpha.1,
+                    is_true_input,
+                ),
+            );
+
+            let c_next = c_hash(
                 msg,
-                G1 * r[n].0 + ring[n].0 * c[n],
-                G1 * r[n].1 + ring[n].1 * c[n],
-                crate::hash_to_curve(ring[n].0.into()) * r[n].0 + key_image * c[n],
+                G1 * r_n.0 + public_key * c_n,
+                G1 * r_n.1 + hidden_commitment * c_n + adaptor_point,
+                crate::hash_to_curve(public_key.into()) * r_n.0 + key_image * c_n,
             );
+            ct_set(&mut c, ct_wrap(n + 1, ring_len), &c_next);
         }
 
-        let secret_keys = (
+        #[cfg_attr(not(feature = "zeroize"), allow(unused_mut))]
+        let mut secret_keys = (
             self.true_input.secret_key,
             self.true_input.revealed_commitment.blinding - revealed_pseudo_commitment.blinding,
         );
 
-        r[pi] = (
-            alpha.0 - c[pi] * secret_keys.0,
-            alpha.1 - c[pi] * secret_keys.1,
-        );
+        let c_pi = ct_get(&c, pi, Scalar::zero());
+        ct_set(&mut r0, pi, &(alpha.0 - c_pi * secret_keys.0));
+        ct_set(&mut r1, pi, &(alpha.1 - c_pi * secret_keys.1));
+        let r: Vec<(Scalar, Scalar)> = r0.into_iter().zip(r1).collect();
 
-        #[cfg(test)]
-        {
-            // For our sanity, check a few identities
-            assert_eq!(G1 * secret_keys.0, ring[pi].0.into());
-            assert_eq!(G1 * secret_keys.1, ring[pi].1.into());
-            assert_eq!(
-                G1 * (alpha.0 - c[pi] * secret_keys.0),
-                G1 * alpha.0 - G1 * (c[pi] * secret_keys.0)
-            );
-            assert_eq!(
-                G1 * (alpha.1 - c[pi] * secret_keys.1),
-                G1 * alpha.1 - G1 * (c[pi] * secret_keys.1)
-            );
-            assert_eq!(
-                G1 * (alpha.0 - c[pi] * secret_keys.0) + ring[pi].0 * c[pi],
-                G1 * alpha.0
-            );
-            assert_eq!(
-                G1 * (alpha.1 - c[pi] * secret_keys.1) + ring[pi].1 * c[pi],
-                G1 * alpha.1
-            );
-            assert_eq!(
-                G1 * r[pi].0 + ring[pi].0 * c[pi],
-                G1 * (alpha.0 - c[pi] * secret_keys.0) + ring[pi].0 * c[pi]
-            );
-            assert_eq!(
-                G1 * r[pi].1 + ring[pi].1 * c[pi],
-                G1 * (alpha.1 - c[pi] * secret_keys.1) + ring[pi].1 * c[pi]
-            );
-            assert_eq!(
-                crate::hash_to_curve(ring[pi].0.into()) * r[pi].0 + key_image * c[pi],
-                crate::hash_to_curve(ring[pi].0.into()) * (alpha.0 - c[pi] * secret_keys.0)
-                    + key_image * c[pi]
-            );
-            assert_eq!(
-                crate::hash_to_curve(ring[pi].1.into()) * r[pi].1 + key_image * c[pi],
-                crate::hash_to_curve(ring[pi].1.into()) * (alpha.1 - c[pi] * secret_keys.1)
-                    + key_image * c[pi]
-            );
-
-            assert_eq!(
-                crate::hash_to_curve(ring[pi].0.into()) * secret_keys.0,
-                key_image
-            );
-            assert_eq!(
-                crate::hash_to_curve(ring[pi].0.into()) * r[pi].0 + key_image * c[pi],
-                crate::hash_to_curve(ring[pi].0.into()) * (alpha.0 - c[pi] * secret_keys.0)
-                    + key_image * c[pi]
-            );
-            assert_eq!(
-                crate::hash_to_curve(ring[pi].1.into()) * r[pi].1 + key_image * c[pi],
-                crate::hash_to_curve(ring[pi].1.into()) * (alpha.1 - c[pi] * secret_keys.1)
-                    + key_image * c[pi]
-            );
+        #[cfg(feature = "zeroize")]
+        for s in [
+            &mut alpha.0,
+            &mut alpha.1,
+            &mut secret_keys.0,
+            &mut secret_keys.1,
+        ] {
+            crate::zeroize_scalar(s);
         }
 
         MlsagSignature {
             c0: c[0],
             r,
-            key_image: key_image.to_affine(),
+            key_image: key_image.into(),
             ring,
             pseudo_commitment: pseudo_commitment.to_affine(),
         }
     }
 }
 
+#[cfg(feature = "zeroize")]
+impl Zeroize for MlsagMaterial {
+    fn zeroize(&mut self) {
+        self.true_input.zeroize();
+        self.pi_base.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for MlsagMaterial {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for MlsagMaterial {}
+
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 #[derive(Eq, PartialEq, Debug, Clone)]
 pub struct MlsagSignature {
+    #[cfg_attr(
+        feature = "json-schema",
+        schemars(with = "crate::json_schema::ScalarLimbs")
+    )]
     pub c0: Scalar,
+    #[cfg_attr(
+        feature = "json-schema",
+        schemars(with = "Vec<(crate::json_schema::ScalarLimbs, crate::json_schema::ScalarLimbs)>")
+    )]
     pub r: Vec<(Scalar, Scalar)>,
-    pub key_image: G1Affine,
+    pub key_image: KeyImage,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    #[cfg_attr(
+        feature = "json-schema",
+        schemars(with = "Vec<(crate::json_schema::HexPoint, crate::json_schema::HexPoint)>")
+    )]
     pub ring: Vec<(G1Affine, G1Affine)>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    #[cfg_attr(
+        feature = "json-schema",
+        schemars(with = "crate::json_schema::HexPoint")
+    )]
     pub pseudo_commitment: G1Affine,
 }
 
+impl std::hash::Hash for MlsagSignature {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::hash::Hash::hash(&self.to_bytes(), state)
+    }
+}
+
 impl MlsagSignature {
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut v: Vec<u8> = Default::default();
         v.extend(self.c0.to_bytes_le());
+        codec::write_len(&mut v, self.r.len());
         for (x, y) in self.r.iter() {
             v.extend(x.to_bytes_le());
             v.extend(y.to_bytes_le());
         }
-        v.extend(self.key_image.to_bytes().as_ref());
+        v.extend(self.key_image.to_bytes());
+        codec::write_len(&mut v, self.ring.len());
         for (x, y) in self.ring.iter() {
             v.extend(x.to_bytes().as_ref());
             v.extend(y.to_bytes().as_ref());
@@ -275,47 +467,170 @@ impl MlsagSignature {
         v
     }
 
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let sig = Self::decode(&mut reader)?;
+        reader.finish()?;
+        Ok(sig)
+    }
+
+    /// Writes the encoding produced by `to_bytes()` to a stream
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<()> {
+        codec::write_all(writer, &self.to_bytes())
+    }
+
+    /// Reads the encoding produced by `to_bytes()` from a stream, consuming
+    /// at most `MAX_STREAMED_LEN` bytes
+    pub fn read_from(reader: &mut impl Read) -> Result<Self> {
+        Self::decode(&mut Reader::from_stream(reader, MAX_STREAMED_LEN))
+    }
+
+    pub(crate) fn decode(reader: &mut Reader) -> Result<Self> {
+        let max_ring_size = reader.limits().max_ring_size;
+        let c0 = reader.read_scalar()?;
+        let r = reader.read_vec_max(max_ring_size, "ring members", |r| {
+            Ok((r.read_scalar()?, r.read_scalar()?))
+        })?;
+        let key_image = KeyImage::from(reader.read_point()?);
+        let ring = reader.read_vec_max(max_ring_size, "ring members", |r| {
+            Ok((r.read_point()?, r.read_point()?))
+        })?;
+        let pseudo_commitment = reader.read_point()?;
+
+        Ok(Self {
+            c0,
+            r,
+            key_image,
+            ring,
+            pseudo_commitment,
+        })
+    }
+
     pub fn pseudo_commitment(&self) -> G1Affine {
         self.pseudo_commitment
     }
 
+    pub fn key_image(&self) -> KeyImage {
+        self.key_image
+    }
+
     pub fn public_keys(&self) -> Vec<G1Affine> {
         self.ring.iter().map(|(pk, _)| *pk).collect()
     }
 
     pub fn verify(&self, msg: &[u8], public_commitments: &[G1Affine]) -> Result<()> {
-        if self.ring.len() != public_commitments.len() {
-            return Err(Error::ExpectedAPublicCommitmentsForEachRingEntry);
-        }
-        // Check that hidden commitments in the ring where computed with: C - C'
-        for ((_, hidden_commitment), public_commitment) in self.ring.iter().zip(public_commitments)
-        {
-            if G1Projective::from(hidden_commitment)
-                != public_commitment - G1Projective::from(self.pseudo_commitment)
-            {
-                return Err(Error::InvalidHiddenCommitmentInRing);
-            }
-        }
+        self.verify_with_adaptor(msg, public_commitments, G1Projective::identity())
+    }
 
+    /// Verifies several MLSAGs, each with its message and the public commitments
+    /// of its ring, returning the result of each.
+    ///
+    /// The rings are walked in lockstep so that each step's multi-exponentiations
+    /// across all of them are handed to `backend` at once.
+    pub fn verify_batch(
+        batch: &[(&[u8], &MlsagSignature, &[G1Affine])],
+        backend: &dyn VerificationBackend,
+    ) -> Vec<Result<()>> {
         #[allow(non_snake_case)]
         let G1 = G1Projective::generator();
+        let key_images: Vec<Result<G1Projective>> = batch
+            .iter()
+            .map(|(_, sig, public_commitments)| sig.check_ring(public_commitments))
+            .collect();
+        let mut cprimes: Vec<Vec<Scalar>> = batch
+            .iter()
+            .map(|(_, sig, _)| {
+                let mut cprime = vec![Scalar::zero(); sig.ring.len()];
+                if let Some(c) = cprime.first_mut() {
+                    *c = sig.c0;
+                }
+                cprime
+            })
+            .collect();
+        let steps = batch
+            .iter()
+            .map(|(_, sig, _)| sig.ring.len())
+            .max()
+            .unwrap_or(0);
 
-        // Verify key image is in G
-        if !bool::from(self.key_image.is_on_curve()) {
-            // TODO: I don't think this is enough, we need to check that key_image is in the group as well
-            println!("Key images not on curve");
-            return Err(Error::KeyImageNotOnCurve);
+        for n in 0..steps {
+            let active: Vec<usize> = (0..batch.len())
+                .filter(|&i| key_images[i].is_ok() && n < batch[i].1.ring.len())
+                .collect();
+            let mut points = Vec::with_capacity(active.len());
+            let mut scalars = Vec::with_capacity(active.len());
+            for &i in &active {
+                let (_, sig, _) = batch[i];
+                let key_image = *key_images[i].as_ref().expect("filtered on success");
+                let pk = G1Projective::from(sig.ring[n].0);
+                let hidden_commitment = G1Projective::from(sig.ring[n].1);
+                let (r0, r1, c) = (sig.r[n].0, sig.r[n].1, cprimes[i][n]);
+                points.push([
+                    [G1, pk],
+                    [G1, hidden_commitment],
+                    [crate::hash_to_curve(pk), key_image],
+                ]);
+                scalars.push([[r0, c], [r1, c], [r0, c]]);
+            }
+            let jobs: Vec<(&[G1Projective], &[Scalar])> = points
+                .iter()
+                .zip(&scalars)
+                .flat_map(|(points, scalars)| {
+                    points
+                        .iter()
+                        .zip(scalars)
+                        .map(|(p, s)| (p.as_slice(), s.as_slice()))
+                })
+                .collect();
+            let sums = backend.batch_multi_exp(&jobs);
+            for (&i, sums) in active.iter().zip(sums.chunks(3)) {
+                let (msg, sig, _) = batch[i];
+                cprimes[i][(n + 1) % sig.ring.len()] = c_hash(msg, sums[0], sums[1], sums[2]);
+            }
         }
 
+        batch
+            .iter()
+            .zip(key_images)
+            .zip(cprimes)
+            .map(|(((_, sig, _), key_image), cprime)| {
+                key_image?;
+                if sig.c0 != cprime[0] {
+                    Err(Error::InvalidRingSignature)
+                } else {
+                    Ok(())
+                }
+            })
+            .collect()
+    }
+
+    fn verify_with_adaptor(
+        &self,
+        msg: &[u8],
+        public_commitments: &[G1Affine],
+        adaptor_point: G1Projective,
+    ) -> Result<()> {
+        let key_image = self.check_ring(public_commitments)?;
+
+        #[allow(non_snake_case)]
+        let G1 = G1Projective::generator();
         let mut cprime = Vec::from_iter((0..self.ring.len()).map(|_| Scalar::zero()));
         cprime[0] = self.c0;
 
+        // Each challenge hashes the points computed with the previous one, so the
+        // ring can't be folded into a single multi-exponentiation. Each pair of
+        // scalar multiplications is instead one 2-point multi-exponentiation,
+        // sharing the doublings.
         for (n, keys) in self.ring.iter().enumerate() {
+            let pk = G1Projective::from(keys.0);
+            let hidden_commitment = G1Projective::from(keys.1);
+            let (r0, r1, c) = (self.r[n].0, self.r[n].1, cprime[n]);
             cprime[(n + 1) % self.ring.len()] = c_hash(
                 msg,
-                G1 * self.r[n].0 + keys.0 * cprime[n],
-                G1 * self.r[n].1 + keys.1 * cprime[n],
-                crate::hash_to_curve(keys.0.into()) * self.r[n].0 + self.key_image * cprime[n],
+                G1Projective::multi_exp(&[G1, pk], &[r0, c]),
+                G1Projective::multi_exp(&[G1, hidden_commitment], &[r1, c]) + adaptor_point,
+                G1Projective::multi_exp(&[crate::hash_to_curve(pk), key_image], &[r0, c]),
             );
         }
 
@@ -325,9 +640,248 @@ impl MlsagSignature {
             Ok(())
         }
     }
+
+    /// The checks of the ring before its challenges are recomputed, returning
+    /// the key image
+    fn check_ring(&self, public_commitments: &[G1Affine]) -> Result<G1Projective> {
+        if self.ring.is_empty() {
+            return Err(Error::EmptyRing);
+        }
+        if self.ring.len() != public_commitments.len() || self.r.len() != self.ring.len() {
+            return Err(Error::ExpectedAPublicCommitmentsForEachRingEntry);
+        }
+        // Check that hidden commitments in the ring where computed with: C - C'
+        for ((_, hidden_commitment), public_commitment) in self.ring.iter().zip(public_commitments)
+        {
+            if G1Projective::from(hidden_commitment)
+                != public_commitment - G1Projective::from(self.pseudo_commitment)
+            {
+                return Err(Error::InvalidHiddenCommitmentInRing);
+            }
+        }
+
+        let key_image = self.key_image.to_affine();
+        validate_point(&key_image, || "key image".to_string())?;
+
+        Ok(G1Projective::from(key_image))
+    }
 }
 
-fn c_hash(msg: &[u8], l1: G1Projective, l2: G1Projective, r1: G1Projective) -> Scalar {
+/// An MLSAG pre-signature, see `MlsagMaterial::sign_adaptor()`
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct MlsagAdaptorSignature {
+    pub pre_signature: MlsagSignature,
+    /// `T = tG` for the secret `t` that completes the signature
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub adaptor_point: G1Affine,
+}
+
+impl MlsagAdaptorSignature {
+    /// Checks that completing the pre-signature with the secret of the adaptor
+    /// point gives a valid signature of `msg`
+    pub fn verify(&self, msg: &[u8], public_commitments: &[G1Affine]) -> Result<()> {
+        self.pre_signature
+            .verify_with_adaptor(msg, public_commitments, self.adaptor_point.into())
+    }
+
+    /// Completes the pre-signature with the secret of the adaptor point
+    pub fn complete(&self, adaptor_secret: Scalar) -> Result<MlsagSignature> {
+        if G1Projective::generator() * adaptor_secret != G1Projective::from(self.adaptor_point) {
+            return Err(Error::InvalidAdaptorSecret);
+        }
+        let mut signature = self.pre_signature.clone();
+        for r in signature.r.iter_mut() {
+            r.1 += adaptor_secret;
+        }
+        Ok(signature)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v = self.pre_signature.to_bytes();
+        v.extend(self.adaptor_point.to_compressed());
+        v
+    }
+
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let pre_signature = MlsagSignature::decode(&mut reader)?;
+        let adaptor_point = reader.read_point()?;
+        reader.finish()?;
+        Ok(Self {
+            pre_signature,
+            adaptor_point,
+        })
+    }
+}
+
+/// Recovers the adaptor secret `t` from a signature completed from `adaptor_sig`,
+/// eg. once the counterparty of an atomic swap has published it
+pub fn extract_secret(sig: &MlsagSignature, adaptor_sig: &MlsagAdaptorSignature) -> Result<Scalar> {
+    let pre_signature = &adaptor_sig.pre_signature;
+    if sig.c0 != pre_signature.c0
+        || sig.key_image != pre_signature.key_image
+        || sig.r.len() != pre_signature.r.len()
+    {
+        return Err(Error::InvalidAdaptorSecret);
+    }
+    let (r, pre_r) = (sig.r.first(), pre_signature.r.first());
+    let adaptor_secret = match (r, pre_r) {
+        (Some(r), Some(pre_r)) => r.1 - pre_r.1,
+        _ => return Err(Error::EmptyRing),
+    };
+    if adaptor_sig.complete(adaptor_secret)? != *sig {
+        return Err(Error::InvalidAdaptorSecret);
+    }
+    Ok(adaptor_secret)
+}
+
+// MLSAG, CLSAG and matrix MLSAG signing read and write the ring at the true
+// input's position `pi` through these helpers, which touch every item and select with `subtle`,
+// so that neither branches nor memory accesses depend on `pi`. Scanning for
+// owned outputs by view tag still branches on whether an output is ours.
+
+/// `index % len` for `index < 2 * len`, without branching on `index`
+pub(crate) fn ct_wrap(index: usize, len: usize) -> usize {
+    let (index, len) = (index as u64, len as u64);
+    let wraps = index.ct_gt(&(len - 1));
+    u64::conditional_select(&index, &index.wrapping_sub(len), wraps) as usize
+}
+
+/// Reads `items[index]`, or `default` if `index` is out of range, scanning
+/// every item so that the memory accessed doesn't depend on `index`
+pub(crate) fn ct_get<T: ConditionallySelectable>(items: &[T], index: usize, default: T) -> T {
+    items.iter().enumerate().fold(default, |acc, (i, item)| {
+        T::conditional_select(&acc, item, (i as u64).ct_eq(&(index as u64)))
+    })
+}
+
+/// Writes `items[index]`, scanning every item as `ct_get()` does
+pub(crate) fn ct_set<T: ConditionallySelectable>(items: &mut [T], index: usize, value: &T) {
+    for (i, item) in items.iter_mut().enumerate() {
+        item.conditional_assign(value, (i as u64).ct_eq(&(index as u64)));
+    }
+}
+
+/// `items` with `item` inserted at `index`, without memory accesses
+/// depending on `index`
+fn ct_insert<T: ConditionallySelectable>(items: &[T], index: usize, item: T) -> Vec<T> {
+    (0..=items.len())
+        .map(|i| {
+            // the items after `index` move up one
+            let i = i as u64;
+            let after = i.ct_gt(&(index as u64));
+            let from = u64::conditional_select(&i, &i.wrapping_sub(1), after) as usize;
+            T::conditional_select(&ct_get(items, from, item), &item, i.ct_eq(&(index as u64)))
+        })
+        .collect()
+}
+
+/// Shuffles the decoys and draws the position of the true input amongst them,
+/// uniformly over the whole ring so that the ring order reveals nothing about
+/// which member is real, even if the decoys were supplied in some order (eg. by age).
+pub(crate) fn shuffle_ring(decoy_inputs: &mut [DecoyInput], mut rng: impl RngCore) -> u32 {
+    decoy_inputs.shuffle(&mut rng);
+    rng.gen_range(0..=decoy_inputs.len()) as u32
+}
+
+/// A transcript of what a signature of `kind` signs, that its nonces are
+/// drawn from once the rings are appended, see `MlsagMaterial::nonce_rng()`
+pub(crate) fn nonce_transcript(kind: &'static [u8], msg: &[u8]) -> Transcript {
+    let mut transcript = Transcript::new(NONCE_TRANSCRIPT_LABEL);
+    transcript.append_message(b"kind", kind);
+    transcript.append_message(b"msg", msg);
+    transcript
+}
+
+/// Appends a ring of public keys and hidden commitments, and the pseudo
+/// commitment they're hidden with, to a nonce transcript
+pub(crate) fn append_ring(
+    transcript: &mut Transcript,
+    ring: &[(G1Affine, G1Affine)],
+    pseudo_commitment: &G1Affine,
+) {
+    transcript.append_u64(b"ring_len", ring.len() as u64);
+    for (public_key, hidden_commitment) in ring {
+        transcript.append_message(b"public_key", &public_key.to_compressed());
+        transcript.append_message(b"hidden_commitment", &hidden_commitment.to_compressed());
+    }
+    transcript.append_message(b"pseudo_commitment", &pseudo_commitment.to_compressed());
+}
+
+/// Draws `alpha` for the true input, then a pair of responses for each of
+/// the `ring_len` positions, the true input's being replaced once signed
+pub(crate) fn draw_nonce_pairs(
+    rng: &mut impl RngCore,
+    ring_len: usize,
+) -> ((Scalar, Scalar), Vec<(Scalar, Scalar)>) {
+    let alpha = (Scalar::random(&mut *rng), Scalar::random(&mut *rng));
+    let r = (0..ring_len)
+        .map(|_| (Scalar::random(&mut *rng), Scalar::random(&mut *rng)))
+        .collect();
+    (alpha, r)
+}
+
+/// Tallies the positions of true inputs in their rings, so that a signer can
+/// check its rings for ordering bias. Rings are grouped by size.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RingPositionAudit {
+    counts: BTreeMap<usize, Vec<usize>>,
+}
+
+impl RingPositionAudit {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn record(&mut self, material: &MlsagMaterial) {
+        self.record_position(material.count_inputs(), material.pi());
+    }
+
+    pub fn record_position(&mut self, ring_size: usize, position: usize) {
+        let counts = self
+            .counts
+            .entry(ring_size)
+            .or_insert_with(|| vec![0; ring_size]);
+        if let Some(count) = counts.get_mut(position) {
+            *count += 1;
+        }
+    }
+
+    /// How often the true input was at each position, for rings of `ring_size`
+    pub fn counts(&self, ring_size: usize) -> Option<&[usize]> {
+        self.counts.get(&ring_size).map(Vec::as_slice)
+    }
+
+    /// Pearson's chi-squared statistic against uniformly distributed positions,
+    /// summed over every ring size. Compare it with the chi-squared distribution
+    /// with `degrees_of_freedom()`: a large value indicates bias.
+    pub fn chi_squared(&self) -> f64 {
+        self.counts
+            .values()
+            .map(|counts| {
+                let expected = counts.iter().sum::<usize>() as f64 / counts.len() as f64;
+                if expected == 0.0 {
+                    return 0.0;
+                }
+                counts
+                    .iter()
+                    .map(|&observed| (observed as f64 - expected).powi(2) / expected)
+                    .sum::<f64>()
+            })
+            .sum()
+    }
+
+    pub fn degrees_of_freedom(&self) -> usize {
+        self.counts
+            .values()
+            .map(|counts| counts.len().saturating_sub(1))
+            .sum()
+    }
+}
+
+pub(crate) fn c_hash(msg: &[u8], l1: G1Projective, l2: G1Projective, r1: G1Projective) -> Scalar {
     hash_to_scalar(&[
         msg,
         &l1.to_compressed(),
@@ -337,7 +891,7 @@ fn c_hash(msg: &[u8], l1: G1Projective, l2: G1Projective, r1: G1Projective) -> S
 }
 
 /// Hashes given material to a Scalar, repeated hashing is used if a hash can not be interpreted as a Scalar
-fn hash_to_scalar(material: &[&[u8]]) -> Scalar {
+pub(crate) fn hash_to_scalar(material: &[&[u8]]) -> Scalar {
     let mut sha3 = Sha3::v256();
     for chunk in material {
         sha3.update(chunk);
@@ -355,3 +909,357 @@ fn hash_to_scalar(material: &[&[u8]]) -> Scalar {
         sha3.finalize(&mut hash);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::rand::rngs::OsRng;
+
+    use super::*;
+
+    fn seed(i: u64) -> [u8; 32] {
+        let mut seed = [0u8; 32];
+        seed[..8].copy_from_slice(&i.to_le_bytes());
+        seed
+    }
+
+    fn decoys(n: usize) -> Vec<DecoyInput> {
+        (0..n)
+            .map(|_| DecoyInput {
+                public_key: G1Projective::random(OsRng).to_affine(),
+                commitment: G1Projective::random(OsRng).to_affine(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_adaptor_signature() {
+        let pc_gens = crate::pedersen_gens();
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(3, OsRng),
+        );
+        let material = MlsagMaterial::new(true_input, decoys(4), &mut OsRng).unwrap();
+        let commitments = material.commitments(&pc_gens);
+        let pseudo_commitment = material.true_input.random_pseudo_commitment(OsRng);
+
+        let adaptor_secret = Scalar::random(OsRng);
+        let adaptor_point = (G1Projective::generator() * adaptor_secret).to_affine();
+        let adaptor_sig = material.sign_adaptor(
+            b"swap",
+            &pseudo_commitment,
+            adaptor_point,
+            &pc_gens,
+            &mut OsRng,
+        );
+        assert_eq!(adaptor_sig.verify(b"swap", &commitments), Ok(()));
+        assert_eq!(
+            MlsagAdaptorSignature::from_bytes(&adaptor_sig.to_bytes()),
+            Ok(adaptor_sig.clone())
+        );
+
+        // the pre-signature is not a valid signature until completed
+        assert_eq!(
+            adaptor_sig.pre_signature.verify(b"swap", &commitments),
+            Err(Error::InvalidRingSignature)
+        );
+        assert_eq!(
+            adaptor_sig.complete(Scalar::random(OsRng)),
+            Err(Error::InvalidAdaptorSecret)
+        );
+        let sig = adaptor_sig
+            .complete(adaptor_secret)
+            .expect("Failed to complete signature");
+        assert_eq!(sig.verify(b"swap", &commitments), Ok(()));
+
+        // publishing the completed signature reveals the secret
+        assert_eq!(extract_secret(&sig, &adaptor_sig), Ok(adaptor_secret));
+        let other = material.sign(b"swap", &pseudo_commitment, &pc_gens, &mut OsRng);
+        assert_eq!(
+            extract_secret(&other, &adaptor_sig),
+            Err(Error::InvalidAdaptorSecret)
+        );
+    }
+
+    #[test]
+    fn test_verify_rejects_an_invalid_key_image() {
+        let pc_gens = crate::pedersen_gens();
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(3, OsRng),
+        );
+        let material = MlsagMaterial::new(true_input, decoys(4), &mut OsRng).unwrap();
+        let commitments = material.commitments(&pc_gens);
+        let pseudo_commitment = material.true_input.random_pseudo_commitment(OsRng);
+        let mut sig = material.sign(b"msg", &pseudo_commitment, &pc_gens, &mut OsRng);
+
+        sig.key_image = KeyImage::from(G1Projective::identity());
+        assert_eq!(
+            sig.verify(b"msg", &commitments),
+            Err(Error::PointIsIdentity("key image".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_material_rejects_invalid_decoys() {
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(3, OsRng),
+        );
+
+        let mut with_true_input = decoys(3);
+        with_true_input[1].public_key = true_input.public_key().to_affine();
+        assert_eq!(
+            MlsagMaterial::new(true_input.clone(), with_true_input, &mut OsRng).err(),
+            Some(Error::DecoyIsTrueInput { decoy_index: 1 })
+        );
+
+        let mut with_identity = decoys(3);
+        with_identity[2].commitment = G1Affine::identity();
+        assert_eq!(
+            MlsagMaterial::new(true_input.clone(), with_identity, &mut OsRng).err(),
+            Some(Error::PointIsIdentity("commitment of decoy 2".to_string()))
+        );
+
+        assert_eq!(
+            MlsagMaterial::with_ring_size(true_input.clone(), decoys(3), 11, &mut OsRng).err(),
+            Some(Error::DecoyCountMismatch {
+                expected: 10,
+                actual: 3
+            })
+        );
+        assert!(MlsagMaterial::with_ring_size(true_input, decoys(3), 4, &mut OsRng).is_ok());
+    }
+
+    #[test]
+    fn test_nonces_are_bound_to_the_secret_key() {
+        let pc_gens = crate::pedersen_gens();
+        let revealed_commitment = RevealedCommitment::from_value(3, OsRng);
+        let pseudo_commitment = RevealedCommitment::from_value(3, OsRng);
+        let decoys = decoys(3);
+
+        // the same, weak, rng for two different keys still gives different nonces
+        let a = MlsagMaterial::from_seed(
+            TrueInput::new(Scalar::random(OsRng), revealed_commitment),
+            decoys.clone(),
+            seed(7),
+        )
+        .unwrap();
+        let b = MlsagMaterial::from_seed(
+            TrueInput::new(Scalar::random(OsRng), revealed_commitment),
+            decoys,
+            seed(7),
+        )
+        .unwrap();
+        assert_eq!(a.pi(), b.pi());
+        let sig_a = a.sign(
+            b"msg",
+            &pseudo_commitment,
+            &pc_gens,
+            &mut ChaCha20Rng::from_seed(seed(8)),
+        );
+        let sig_b = b.sign(
+            b"msg",
+            &pseudo_commitment,
+            &pc_gens,
+            &mut ChaCha20Rng::from_seed(seed(8)),
+        );
+        assert!(sig_a.r.iter().zip(&sig_b.r).all(|(x, y)| x != y));
+    }
+
+    #[test]
+    fn test_signatures_of_different_messages_have_unrelated_responses() {
+        let pc_gens = crate::pedersen_gens();
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(3, OsRng),
+        );
+        let material = MlsagMaterial::new(true_input, decoys(4), &mut OsRng).unwrap();
+        let commitments = material.commitments(&pc_gens);
+        let pseudo_commitment = material.true_input.random_pseudo_commitment(OsRng);
+
+        // even with a repeated rng, the nonces depend on the message: neither
+        // the decoys' responses nor the true input's are shared, so the two
+        // signatures neither reveal the true input nor solve for its key
+        let sign = |msg: &[u8]| {
+            material.sign(
+                msg,
+                &pseudo_commitment,
+                &pc_gens,
+                &mut ChaCha20Rng::from_seed(seed(7)),
+            )
+        };
+        let (sig_a, sig_b) = (sign(b"msg a"), sign(b"msg b"));
+        assert_eq!(sig_a.verify(b"msg a", &commitments), Ok(()));
+        assert_eq!(sig_b.verify(b"msg b", &commitments), Ok(()));
+        for (a, b) in sig_a.r.iter().zip(&sig_b.r) {
+            assert_ne!(a.0, b.0);
+            assert_ne!(a.1, b.1);
+        }
+
+        // while signing the same message with the same rng is deterministic
+        assert_eq!(sign(b"msg a"), sig_a);
+    }
+
+    #[test]
+    fn test_constant_time_ring_access() {
+        let items: Vec<u64> = (10..15).collect();
+        assert_eq!(ct_get(&items, 3, 0), 13);
+        assert_eq!(ct_get(&items, 5, 0), 0);
+        assert_eq!(ct_insert(&items, 0, 1), vec![1, 10, 11, 12, 13, 14]);
+        assert_eq!(ct_insert(&items, 2, 1), vec![10, 11, 1, 12, 13, 14]);
+        assert_eq!(ct_insert(&items, 5, 1), vec![10, 11, 12, 13, 14, 1]);
+        assert_eq!(
+            (0..10).map(|i| ct_wrap(i, 5)).collect::<Vec<_>>(),
+            [0, 1, 2, 3, 4, 0, 1, 2, 3, 4]
+        );
+
+        let mut items = items;
+        ct_set(&mut items, 4, &1);
+        assert_eq!(items, vec![10, 11, 12, 13, 1]);
+    }
+
+    #[test]
+    fn test_true_position_holds_the_signers_keys() {
+        #[allow(non_snake_case)]
+        let G1 = G1Projective::generator();
+        let pc_gens = crate::pedersen_gens();
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(3, OsRng),
+        );
+        let material = MlsagMaterial::new(true_input, decoys(4), &mut OsRng).unwrap();
+        let pseudo_commitment = material.true_input.random_pseudo_commitment(OsRng);
+        let sig = material.sign(b"msg", &pseudo_commitment, &pc_gens, &mut OsRng);
+
+        // For our sanity, check a few identities
+        let (pi, secret_keys) = (
+            material.pi(),
+            (
+                material.true_input.secret_key,
+                material.true_input.revealed_commitment.blinding - pseudo_commitment.blinding,
+            ),
+        );
+        assert_eq!(G1 * secret_keys.0, sig.ring[pi].0.into());
+        assert_eq!(G1 * secret_keys.1, sig.ring[pi].1.into());
+        assert_eq!(
+            crate::hash_to_curve(sig.ring[pi].0.into()) * secret_keys.0,
+            G1Projective::from(sig.key_image.to_affine())
+        );
+        assert_eq!(sig.verify(b"msg", &material.commitments(&pc_gens)), Ok(()));
+    }
+
+    // A dudect style check: times signing with the true input first and last
+    // in the ring, in random order, and applies Welch's t-test to the two
+    // timing distributions. |t| above ~10 is strong evidence of a leak.
+    // Timing is noisy under parallel tests, so run it alone:
+    // `cargo test --release timing -- --ignored --test-threads 1`
+    #[test]
+    #[ignore]
+    fn test_signing_timing_does_not_depend_on_true_position() {
+        const MEASUREMENTS: usize = 2000;
+        let pc_gens = crate::pedersen_gens();
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(3, OsRng),
+        );
+        let mut material = MlsagMaterial::new(true_input, decoys(10), &mut OsRng).unwrap();
+        let pseudo_commitment = material.true_input.random_pseudo_commitment(OsRng);
+
+        let mut timings: [Vec<f64>; 2] = [vec![], vec![]];
+        for _ in 0..MEASUREMENTS {
+            let class = OsRng.gen_range(0..2);
+            material.pi_base = [0, material.decoy_inputs.len() as u32][class];
+            let start = std::time::Instant::now();
+            let sig = material.sign(b"timing", &pseudo_commitment, &pc_gens, &mut OsRng);
+            timings[class].push(start.elapsed().as_nanos() as f64);
+            assert_eq!(sig.r.len(), 11);
+        }
+
+        let mean_var = |xs: &[f64]| {
+            let n = xs.len() as f64;
+            let mean = xs.iter().sum::<f64>() / n;
+            let var = xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+            (mean, var, n)
+        };
+        let (mean_0, var_0, n_0) = mean_var(&timings[0]);
+        let (mean_1, var_1, n_1) = mean_var(&timings[1]);
+        let t = (mean_0 - mean_1) / (var_0 / n_0 + var_1 / n_1).sqrt();
+        assert!(t.abs() < 10.0, "timing leaks the true position, t = {}", t);
+    }
+
+    #[test]
+    fn test_seeded_material_is_reproducible() {
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(3, OsRng),
+        );
+        let decoys = decoys(10);
+
+        let pseudo_commitment = true_input.random_pseudo_commitment(OsRng);
+
+        let a = MlsagMaterial::from_seed(true_input.clone(), decoys.clone(), seed(7)).unwrap();
+        let b = MlsagMaterial::from_seed(true_input, decoys, seed(7)).unwrap();
+        assert_eq!(a.pi(), b.pi());
+        assert_eq!(a.public_keys(), b.public_keys());
+
+        let pc_gens = crate::pedersen_gens();
+        let mut rng = ChaCha20Rng::from_seed(seed(8));
+        assert_eq!(
+            a.sign(b"msg", &pseudo_commitment, &pc_gens, &mut rng.clone()),
+            b.sign(b"msg", &pseudo_commitment, &pc_gens, &mut rng)
+        );
+    }
+
+    #[test]
+    fn test_signature_ring_does_not_reveal_true_position() {
+        const RING_SIZE: usize = 4;
+        const SIGNATURES: u64 = 400;
+
+        let pc_gens = crate::pedersen_gens();
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(3, OsRng),
+        );
+        let true_pk = true_input.public_key().to_affine();
+        let decoys = decoys(RING_SIZE - 1);
+        let revealed_pseudo_commitment = true_input.random_pseudo_commitment(OsRng);
+
+        let mut audit = RingPositionAudit::new();
+        let mut decoys_in_supplied_order = 0;
+        for i in 0..SIGNATURES {
+            let material =
+                MlsagMaterial::from_seed(true_input.clone(), decoys.clone(), seed(i)).unwrap();
+            let sig = material.sign(b"msg", &revealed_pseudo_commitment, &pc_gens, &mut OsRng);
+            let sig = MlsagSignature::from_bytes(&sig.to_bytes()).unwrap();
+
+            let ring = sig.public_keys();
+            let position = ring.iter().position(|pk| *pk == true_pk).unwrap();
+            assert_eq!(position, material.pi());
+            audit.record_position(ring.len(), position);
+
+            let decoy_order: Vec<_> = ring.into_iter().filter(|pk| *pk != true_pk).collect();
+            if decoy_order == Vec::from_iter(decoys.iter().map(DecoyInput::public_key)) {
+                decoys_in_supplied_order += 1;
+            }
+        }
+
+        assert_eq!(audit.degrees_of_freedom(), RING_SIZE - 1);
+        assert_eq!(
+            audit.counts(RING_SIZE).unwrap().iter().sum::<usize>(),
+            SIGNATURES as usize
+        );
+        // the 99.9th percentile of the chi-squared distribution with 3 degrees of freedom
+        assert!(audit.chi_squared() < 16.27, "biased: {:?}", audit);
+        // the decoys keep their supplied order in 1 of 3! shuffles
+        assert!(decoys_in_supplied_order < SIGNATURES / 3);
+    }
+
+    #[test]
+    fn test_audit_detects_a_fixed_position() {
+        let mut audit = RingPositionAudit::new();
+        for _ in 0..100 {
+            audit.record_position(4, 0);
+        }
+        assert!(audit.chi_squared() > 16.27);
+    }
+}
@@ -0,0 +1,221 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! An in-memory ledger for tests, enabled by the `mock` feature.
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, G1Projective},
+    group::{Curve, Group},
+    rand::rngs::OsRng,
+};
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    ringct::{OutputProof, RingCtTransaction},
+    CommitmentLookup, DecoyInput, DecoyStore, KeyImage, KeyImageStore, LockLookup, Result,
+};
+
+/// Records the outputs and spent key images of the transactions applied to it
+#[derive(Debug, Clone, Default)]
+pub struct MockLedger {
+    commitments: BTreeMap<[u8; 48], G1Affine>, // Compressed public keys -> Commitments
+    locks: BTreeMap<[u8; 48], u64>,
+    outputs: Vec<DecoyInput>, // In order of creation
+    spent: BTreeSet<KeyImage>,
+}
+
+impl MockLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an output, eg. a true input to spend in a test
+    pub fn log(&mut self, public_key: impl Into<G1Affine>, commitment: impl Into<G1Affine>) {
+        let public_key = public_key.into();
+        let commitment = commitment.into();
+        if self
+            .commitments
+            .insert(public_key.to_compressed(), commitment)
+            .is_none()
+        {
+            self.outputs.push(DecoyInput {
+                public_key,
+                commitment,
+            });
+        }
+    }
+
+    /// Records an output of a transaction, with its lock
+    pub fn log_output(&mut self, output: &OutputProof) {
+        self.log(*output.public_key(), output.commitment());
+        if let Some(lock) = output.lock() {
+            self.locks.insert(output.public_key().to_compressed(), lock);
+        }
+    }
+
+    /// Records `n` random outputs, the same ones for the same `seed`, eg. to
+    /// draw decoys from
+    pub fn fill(&mut self, n: usize, seed: u64) {
+        let mut rng = ChaCha20Rng::seed_from_u64(seed);
+        for _ in 0..n {
+            self.log(
+                G1Projective::random(&mut rng),
+                G1Projective::random(&mut rng),
+            );
+        }
+    }
+
+    pub fn lookup(&self, public_key: impl Into<G1Affine>) -> Option<G1Affine> {
+        self.commitments
+            .get(&public_key.into().to_compressed())
+            .copied()
+    }
+
+    /// The first `n` outputs whose public key isn't in `exclude`
+    pub fn fetch_decoys(&self, n: usize, exclude: &[G1Projective]) -> Vec<DecoyInput> {
+        let exclude_set = BTreeSet::from_iter(exclude.iter().map(G1Projective::to_compressed));
+
+        self.commitments
+            .iter()
+            .filter(|(pk, _)| !exclude_set.contains(*pk))
+            .map(|(pk, c)| DecoyInput {
+                public_key: G1Affine::from_compressed(pk).unwrap(),
+                commitment: *c,
+            })
+            .take(n)
+            .collect()
+    }
+
+    /// The commitments of each ring of `tx`, as `RingCtTransaction::verify()` takes them
+    pub fn ring_commitments(&self, tx: &RingCtTransaction) -> Result<Vec<Vec<G1Affine>>> {
+        tx.lookup_public_commitments(self)
+    }
+
+    pub fn is_spent(&self, key_image: &KeyImage) -> bool {
+        self.spent.contains(key_image)
+    }
+
+    /// Verifies `tx` against the ledger, then spends its key images and
+    /// records its outputs
+    pub fn apply(&mut self, tx: &RingCtTransaction) -> Result<()> {
+        tx.verify_with_lookup(self)?;
+        tx.spend_key_images(&mut self.spent)?;
+        for output in tx.outputs.iter() {
+            self.log_output(output);
+        }
+        Ok(())
+    }
+}
+
+impl CommitmentLookup for MockLedger {
+    fn commitment(&self, public_key: &G1Affine) -> Option<G1Affine> {
+        self.lookup(*public_key)
+    }
+}
+
+impl LockLookup for MockLedger {
+    fn lock(&self, public_key: &G1Affine) -> Option<u64> {
+        self.locks.get(&public_key.to_compressed()).copied()
+    }
+}
+
+impl KeyImageStore for MockLedger {
+    fn contains(&self, key_image: &KeyImage) -> bool {
+        self.is_spent(key_image)
+    }
+
+    fn insert(&mut self, key_image: KeyImage) -> bool {
+        self.spent.insert(key_image)
+    }
+}
+
+impl DecoyStore for MockLedger {
+    fn len(&self) -> usize {
+        self.outputs.len()
+    }
+
+    fn get(&self, index: usize) -> Option<DecoyInput> {
+        DecoyStore::get(&self.outputs, index)
+    }
+}
+
+/// `n` random decoys, eg. for test rings that are never looked up on a
+/// ledger. Unlike the outputs `MockLedger::fill()` records, they differ on
+/// every call.
+pub fn random_decoys(n: usize) -> Vec<DecoyInput> {
+    Vec::from_iter((0..n).map(|_| DecoyInput {
+        public_key: G1Projective::random(OsRng).to_affine(),
+        commitment: G1Projective::random(OsRng).to_affine(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{blstrs::Scalar, group::ff::Field, rand::rngs::OsRng};
+
+    use super::*;
+    use crate::{
+        ringct::Amount, Error, MlsagMaterial, Output, RevealedCommitment, RingCtMaterial, TrueInput,
+    };
+
+    #[test]
+    fn test_fill_is_deterministic() {
+        let mut ledger = MockLedger::new();
+        let mut other = MockLedger::new();
+        ledger.fill(5, 7);
+        other.fill(5, 7);
+
+        assert_eq!(ledger.len(), 5);
+        assert_eq!(
+            ledger.get(4).map(|d| d.public_key),
+            other.get(4).map(|d| d.public_key)
+        );
+    }
+
+    #[test]
+    fn test_apply_spends_inputs_and_records_outputs() {
+        let mut ledger = MockLedger::new();
+        ledger.fill(4, 0);
+
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(5, OsRng),
+        );
+        ledger.log(
+            true_input.public_key(),
+            true_input
+                .revealed_commitment
+                .commit(RingCtMaterial::pc_gens()),
+        );
+        let decoy_inputs = ledger.fetch_decoys(2, &[true_input.public_key()]);
+
+        let material = RingCtMaterial {
+            inputs: vec![MlsagMaterial::new(true_input, decoy_inputs, &mut OsRng).unwrap()],
+            outputs: vec![Output::new(G1Projective::random(OsRng), 5).with_lock(10)],
+            fee: Amount::ZERO,
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+        let (tx, _revealed_output_commitments) = material
+            .sign(&mut OsRng)
+            .expect("Failed to sign transaction");
+
+        assert_eq!(ledger.apply(&tx), Ok(()));
+        assert!(ledger.is_spent(&tx.mlsags[0].key_image));
+        assert_eq!(
+            ledger.lookup(*tx.outputs[0].public_key()),
+            Some(tx.outputs[0].commitment().0)
+        );
+        assert_eq!(ledger.lock(tx.outputs[0].public_key()), Some(10));
+
+        assert!(matches!(
+            ledger.apply(&tx),
+            Err(Error::DoubleSpend { input_index: 0, .. })
+        ));
+    }
+}
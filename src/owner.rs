@@ -0,0 +1,167 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Owner signatures, which let the owners of a transaction's inputs sign the
+//! finalized transaction in addition to its ring signatures, as DBC flows
+//! require.
+//!
+//! An owner signs `tx.hash()`, which covers the ring signatures too, with a
+//! BLS signature in G2 under its G1 public key: `e(G, S) = e(P, H(m))`.
+//!
+//! **Owner signatures deanonymize the inputs.** Signing with the key of a
+//! ring member, ie. the true input's one-time key, publishes which member is
+//! the real spend and defeats the ring. Only sign with a key that appears in
+//! no ring, eg. a DBC owner key kept apart from the outputs' one-time keys,
+//! or only use owner signatures where inputs aren't meant to be anonymous.
+
+use bls_bulletproofs::{
+    blstrs::{pairing, G1Affine, G2Affine, G2Projective, Scalar},
+    group::{prime::PrimeCurveAffine, Curve},
+};
+
+use crate::{codec, ringct::RingCtTransaction, Error, Result};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const OWNER_SIG_DOMAIN: &[u8] = b"blst-ringct-owner-signature";
+
+/// An owner's BLS signature over a transaction's hash
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OwnerSignature {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub public_key: G1Affine,
+    pub signature: G2Affine,
+}
+
+/// Signs `tx` as the owner of `secret_key`.
+///
+/// The signature reveals `secret_key`'s public key: never sign with the
+/// secret key of one of the transaction's ring members, see the module docs.
+pub fn sign_as_owner(secret_key: impl Into<Scalar>, tx: &RingCtTransaction) -> OwnerSignature {
+    let secret_key = secret_key.into();
+    OwnerSignature {
+        public_key: crate::public_key(secret_key).to_affine(),
+        signature: (message(tx) * secret_key).to_affine(),
+    }
+}
+
+impl OwnerSignature {
+    /// Checks the signature is the owner's signature over `tx`
+    pub fn verify(&self, tx: &RingCtTransaction) -> Result<()> {
+        if pairing(&G1Affine::generator(), &self.signature)
+            != pairing(&self.public_key, &message(tx).to_affine())
+        {
+            return Err(Error::InvalidOwnerSignature {
+                public_key: codec::to_hex(&self.public_key.to_compressed()),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Default::default();
+        v.extend(self.public_key.to_compressed());
+        v.extend(self.signature.to_compressed());
+        v
+    }
+
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = codec::Reader::new(bytes);
+        let public_key = reader.read_point()?;
+        let signature = Option::from(G2Affine::from_compressed(&reader.read_array()?))
+            .ok_or(Error::InvalidPointEncoding)?;
+        reader.finish()?;
+        Ok(Self {
+            public_key,
+            signature,
+        })
+    }
+}
+
+impl RingCtTransaction {
+    /// Checks that every one of `owners` signed the transaction among
+    /// `owner_sigs`
+    pub fn verify_owner_sigs(
+        &self,
+        owner_sigs: &[OwnerSignature],
+        owners: &[G1Affine],
+    ) -> Result<()> {
+        for owner in owners {
+            owner_sigs
+                .iter()
+                .find(|sig| sig.public_key == *owner)
+                .ok_or_else(|| Error::MissingOwnerSignature {
+                    public_key: codec::to_hex(&owner.to_compressed()),
+                })?
+                .verify(self)?;
+        }
+        Ok(())
+    }
+}
+
+fn message(tx: &RingCtTransaction) -> G2Projective {
+    G2Projective::hash_to_curve(&tx.hash(), OWNER_SIG_DOMAIN, &[])
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{
+        blstrs::G1Projective,
+        group::{ff::Field, Group},
+        rand::rngs::OsRng,
+    };
+
+    use super::*;
+    use crate::{
+        mock::random_decoys, ringct::Amount, MlsagMaterial, Output, RevealedCommitment,
+        RingCtMaterial, TrueInput,
+    };
+
+    #[test]
+    fn test_owner_sigs() {
+        let owner_secret_key = Scalar::random(OsRng);
+        let true_input = TrueInput::new(owner_secret_key, RevealedCommitment::from_value(2, OsRng));
+        let owner = true_input.public_key().to_affine();
+        let decoy_inputs = random_decoys(2);
+        let material = RingCtMaterial {
+            inputs: vec![MlsagMaterial::new(true_input, decoy_inputs, &mut OsRng).unwrap()],
+            outputs: vec![Output::new(G1Projective::random(OsRng), 2)],
+            fee: Amount::ZERO,
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+        let (tx, _revealed_output_commitments) = material
+            .sign(&mut OsRng)
+            .expect("Failed to sign transaction");
+
+        let owner_sig = sign_as_owner(owner_secret_key, &tx);
+        assert_eq!(tx.verify_owner_sigs(&[owner_sig], &[owner]), Ok(()));
+        assert_eq!(
+            OwnerSignature::from_bytes(&owner_sig.to_bytes()),
+            Ok(owner_sig)
+        );
+
+        assert_eq!(
+            tx.verify_owner_sigs(&[], &[owner]),
+            Err(Error::MissingOwnerSignature {
+                public_key: codec::to_hex(&owner.to_compressed())
+            })
+        );
+
+        let mut forged = sign_as_owner(Scalar::random(OsRng), &tx);
+        forged.public_key = owner;
+        assert_eq!(
+            tx.verify_owner_sigs(&[forged], &[owner]),
+            Err(Error::InvalidOwnerSignature {
+                public_key: codec::to_hex(&owner.to_compressed())
+            })
+        );
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Ownership proofs, with which the owner of an output proves it controls
+//! the output's key without spending it, eg. to a service which asks it to
+//! sign a fresh challenge.
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::{ff::Field, prime::PrimeCurveAffine, Curve, Group},
+    rand::{CryptoRng, RngCore},
+};
+
+use crate::{codec::Reader, mlsag::hash_to_scalar, Error, Result};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const OWNERSHIP_DOMAIN: &[u8] = b"blst-ringct-ownership-proof";
+
+/// A Schnorr proof of knowledge of the secret key `x` of a public key
+/// `P = xG`, bound to a message chosen by the verifier
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OwnershipProof {
+    pub challenge: Scalar,
+    pub response: Scalar,
+}
+
+impl OwnershipProof {
+    /// Proves knowledge of `secret_key` over `msg`, which should be fresh,
+    /// eg. a nonce from the verifier, so that the proof can't be replayed
+    pub fn sign(
+        secret_key: impl Into<Scalar>,
+        msg: &[u8],
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Self {
+        let secret_key = secret_key.into();
+        let nonce = Scalar::random(&mut *rng);
+        let challenge = challenge(
+            &crate::public_key(secret_key).to_affine(),
+            &(G1Projective::generator() * nonce),
+            msg,
+        );
+        Self {
+            challenge,
+            response: nonce - challenge * secret_key,
+        }
+    }
+
+    /// Checks the proof was made over `msg` by the owner of `public_key`,
+    /// eg. an `Output::public_key()`
+    pub fn verify(&self, public_key: &G1Affine, msg: &[u8]) -> Result<()> {
+        let nonce_commitment = G1Projective::generator() * self.response
+            + G1Projective::from(public_key) * self.challenge;
+        if bool::from(public_key.is_identity())
+            || challenge(public_key, &nonce_commitment, msg) != self.challenge
+        {
+            return Err(Error::InvalidOwnershipProof);
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Default::default();
+        v.extend(self.challenge.to_bytes_le());
+        v.extend(self.response.to_bytes_le());
+        v
+    }
+
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let proof = Self {
+            challenge: reader.read_scalar()?,
+            response: reader.read_scalar()?,
+        };
+        reader.finish()?;
+        Ok(proof)
+    }
+}
+
+fn challenge(public_key: &G1Affine, nonce_commitment: &G1Projective, msg: &[u8]) -> Scalar {
+    hash_to_scalar(&[
+        OWNERSHIP_DOMAIN,
+        &public_key.to_compressed(),
+        &nonce_commitment.to_compressed(),
+        &(msg.len() as u64).to_le_bytes(),
+        msg,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::rand::rngs::OsRng;
+
+    use super::*;
+    use crate::Output;
+
+    #[test]
+    fn test_ownership_proof() {
+        let secret_key = Scalar::random(OsRng);
+        let output = Output::new(crate::public_key(secret_key), 5);
+
+        let proof = OwnershipProof::sign(secret_key, b"challenge 1", &mut OsRng);
+        assert_eq!(proof.verify(&output.public_key(), b"challenge 1"), Ok(()));
+        assert_eq!(OwnershipProof::from_bytes(&proof.to_bytes()), Ok(proof));
+
+        assert_eq!(
+            proof.verify(&output.public_key(), b"challenge 2"),
+            Err(Error::InvalidOwnershipProof)
+        );
+        let other = G1Projective::random(OsRng).to_affine();
+        assert_eq!(
+            proof.verify(&other, b"challenge 1"),
+            Err(Error::InvalidOwnershipProof)
+        );
+        let forged = OwnershipProof::sign(Scalar::random(OsRng), b"challenge 1", &mut OsRng);
+        assert_eq!(
+            forged.verify(&output.public_key(), b"challenge 1"),
+            Err(Error::InvalidOwnershipProof)
+        );
+    }
+}
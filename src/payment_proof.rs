@@ -0,0 +1,305 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Proofs that a transaction paid an amount to a stealth address.
+//!
+//! The sender reveals the shared secret `D = rV` of an output, with a proof
+//! that it shares its discrete log with the output's ephemeral key `R = rG`,
+//! and the output's amount with a proof that the commitment opens to it.
+//! Anyone can then check that the output's one-time key was derived from the
+//! recipient's address, without learning any secret key or blinding factor.
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::{ff::Field, Curve, Group},
+    rand::{CryptoRng, RngCore},
+};
+
+use crate::{
+    codec::Reader,
+    mlsag::hash_to_scalar,
+    ringct::{Amount, OutputProof, RingCtTransaction},
+    stealth::shared_secret_scalar,
+    Error, Result, RevealedCommitment, RingCtMaterial, StealthAddress,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const PAYMENT_PROOF_DOMAIN: &[u8] = b"blst-ringct-payment-proof";
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentProof {
+    pub output_index: u32,
+    pub amount: Amount,
+    /// `D = rV` for the ephemeral secret `r` and the address's view key `V`
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub shared_secret: G1Affine,
+    pub challenge: Scalar,
+    pub ephemeral_response: Scalar,
+    pub blinding_response: Scalar,
+}
+
+impl PaymentProof {
+    /// Proves that output `output_index` of `tx` pays `revealed_commitment.value`
+    /// to `address`, given the ephemeral secret the output's one-time key was
+    /// derived from (see `StealthAddress::derive_one_time_key_from_secret()`).
+    /// `message` binds the proof to a context, eg. a challenge from the verifier.
+    pub fn create(
+        tx: &RingCtTransaction,
+        output_index: usize,
+        address: &StealthAddress,
+        ephemeral_secret: Scalar,
+        revealed_commitment: &RevealedCommitment,
+        message: &[u8],
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Self> {
+        let pc_gens = RingCtMaterial::pc_gens();
+        let shared_secret = (address.view_public_key * ephemeral_secret).to_affine();
+
+        let ephemeral_nonce = Scalar::random(&mut *rng);
+        let blinding_nonce = Scalar::random(&mut *rng);
+        let nonce_commitments = [
+            G1Projective::generator() * ephemeral_nonce,
+            address.view_public_key * ephemeral_nonce,
+            pc_gens.B_blinding * blinding_nonce,
+        ];
+
+        let mut proof = Self {
+            output_index: output_index as u32,
+            amount: Amount::new(revealed_commitment.value),
+            shared_secret,
+            challenge: Scalar::zero(),
+            ephemeral_response: Scalar::zero(),
+            blinding_response: Scalar::zero(),
+        };
+        let output = proof.output(tx)?;
+        proof.challenge = proof.challenge(tx, output, address, &nonce_commitments, message);
+        proof.ephemeral_response = ephemeral_nonce - proof.challenge * ephemeral_secret;
+        proof.blinding_response = blinding_nonce - proof.challenge * revealed_commitment.blinding;
+
+        // a proof of a payment that wasn't made can't verify, catch it here
+        tx.verify_payment_proof(&proof, address, message)?;
+        Ok(proof)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Default::default();
+        v.extend(self.output_index.to_le_bytes());
+        v.extend(self.amount.to_le_bytes());
+        v.extend(self.shared_secret.to_compressed());
+        v.extend(self.challenge.to_bytes_le());
+        v.extend(self.ephemeral_response.to_bytes_le());
+        v.extend(self.blinding_response.to_bytes_le());
+        v
+    }
+
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let proof = Self {
+            output_index: reader.read_u32()?,
+            amount: Amount::new(reader.read_u64()?),
+            shared_secret: reader.read_point()?,
+            challenge: reader.read_scalar()?,
+            ephemeral_response: reader.read_scalar()?,
+            blinding_response: reader.read_scalar()?,
+        };
+        reader.finish()?;
+        Ok(proof)
+    }
+
+    fn output<'a>(&self, tx: &'a RingCtTransaction) -> Result<&'a OutputProof> {
+        tx.outputs
+            .get(self.output_index as usize)
+            .ok_or(Error::OutputIndexOutOfRange(self.output_index as usize))
+    }
+
+    fn challenge(
+        &self,
+        tx: &RingCtTransaction,
+        output: &OutputProof,
+        address: &StealthAddress,
+        nonce_commitments: &[G1Projective; 3],
+        message: &[u8],
+    ) -> Scalar {
+        let points: Vec<[u8; 48]> = [
+            address.view_public_key,
+            address.spend_public_key,
+            *output.public_key(),
+            output.commitment().0,
+            self.shared_secret,
+        ]
+        .iter()
+        .map(G1Affine::to_compressed)
+        .chain(nonce_commitments.iter().map(G1Projective::to_compressed))
+        .collect();
+
+        let id = tx.id();
+        let (index, amount) = (self.output_index.to_le_bytes(), self.amount.to_le_bytes());
+        let mut material: Vec<&[u8]> = vec![PAYMENT_PROOF_DOMAIN, &id, &index, &amount];
+        material.extend(points.iter().map(|p| p.as_slice()));
+        material.push(message);
+        hash_to_scalar(&material)
+    }
+}
+
+impl RingCtTransaction {
+    /// Verifies that `proof` shows that this transaction paid `proof.amount`
+    /// to `address`, in the context of `message`
+    pub fn verify_payment_proof(
+        &self,
+        proof: &PaymentProof,
+        address: &StealthAddress,
+        message: &[u8],
+    ) -> Result<()> {
+        let pc_gens = RingCtMaterial::pc_gens();
+        let output = proof.output(self)?;
+        let ephemeral_public_key = output
+            .ephemeral_public_key()
+            .ok_or(Error::NotAStealthOutput)?;
+
+        // the one-time key was derived from the address with the shared secret
+        let one_time_key = G1Projective::generator() * shared_secret_scalar(&proof.shared_secret)
+            + address.spend_public_key;
+        if one_time_key.to_affine() != *output.public_key() {
+            return Err(Error::InvalidPaymentProof);
+        }
+
+        // R = rG and D = rV for the same r, and C - vB = bB' for some b
+        let c = proof.challenge;
+        let amount_commitment = G1Projective::from(output.commitment())
+            - pc_gens.B * Scalar::from(proof.amount.value());
+        let nonce_commitments = [
+            G1Projective::generator() * proof.ephemeral_response + ephemeral_public_key * c,
+            address.view_public_key * proof.ephemeral_response + proof.shared_secret * c,
+            pc_gens.B_blinding * proof.blinding_response + amount_commitment * c,
+        ];
+
+        if proof.challenge(self, output, address, &nonce_commitments, message) != c {
+            return Err(Error::InvalidPaymentProof);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::rand::rngs::OsRng;
+
+    use super::*;
+    use crate::{mock::random_decoys, MlsagMaterial, Output, StealthKeys, TrueInput};
+
+    fn pay(
+        address: &StealthAddress,
+        ephemeral_secret: Scalar,
+    ) -> (RingCtTransaction, Vec<RevealedCommitment>) {
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(10, OsRng),
+        );
+        let decoys = random_decoys(3);
+        let one_time_key = address.derive_one_time_key_from_secret(ephemeral_secret);
+        let material = RingCtMaterial {
+            inputs: vec![MlsagMaterial::new(true_input, decoys, &mut OsRng).unwrap()],
+            outputs: vec![
+                Output::new(G1Projective::random(OsRng), 3),
+                Output::to_one_time_key(&one_time_key, 7),
+            ],
+            fee: Amount::ZERO,
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+        material
+            .sign(&mut OsRng)
+            .expect("Failed to sign transaction")
+    }
+
+    #[test]
+    fn test_payment_proof_verifies_for_the_paid_address() {
+        let address = StealthKeys::random(&mut OsRng).address();
+        let ephemeral_secret = Scalar::random(OsRng);
+        let (tx, revealed_commitments) = pay(&address, ephemeral_secret);
+
+        let proof = PaymentProof::create(
+            &tx,
+            1,
+            &address,
+            ephemeral_secret,
+            &revealed_commitments[1],
+            b"invoice 42",
+            &mut OsRng,
+        )
+        .expect("Failed to create payment proof");
+        assert_eq!(proof.amount, 7);
+        assert_eq!(
+            tx.verify_payment_proof(&proof, &address, b"invoice 42"),
+            Ok(())
+        );
+
+        let decoded = PaymentProof::from_bytes(&proof.to_bytes()).unwrap();
+        assert_eq!(decoded, proof);
+
+        // the proof is bound to its message, address and amount
+        assert_eq!(
+            tx.verify_payment_proof(&proof, &address, b"invoice 43"),
+            Err(Error::InvalidPaymentProof)
+        );
+        let other_address = StealthKeys::random(&mut OsRng).address();
+        assert_eq!(
+            tx.verify_payment_proof(&proof, &other_address, b"invoice 42"),
+            Err(Error::InvalidPaymentProof)
+        );
+        let mut inflated = proof;
+        inflated.amount = Amount::new(8);
+        assert_eq!(
+            tx.verify_payment_proof(&inflated, &address, b"invoice 42"),
+            Err(Error::InvalidPaymentProof)
+        );
+    }
+
+    #[test]
+    fn test_payment_proof_requires_the_ephemeral_secret() {
+        let address = StealthKeys::random(&mut OsRng).address();
+        let ephemeral_secret = Scalar::random(OsRng);
+        let (tx, revealed_commitments) = pay(&address, ephemeral_secret);
+
+        let create = |output_index: usize,
+                      ephemeral_secret: Scalar,
+                      revealed_commitment: &RevealedCommitment| {
+            PaymentProof::create(
+                &tx,
+                output_index,
+                &address,
+                ephemeral_secret,
+                revealed_commitment,
+                b"",
+                &mut OsRng,
+            )
+        };
+        assert_eq!(
+            create(1, Scalar::random(OsRng), &revealed_commitments[1]),
+            Err(Error::InvalidPaymentProof)
+        );
+        assert_eq!(
+            create(0, ephemeral_secret, &revealed_commitments[0]),
+            Err(Error::NotAStealthOutput)
+        );
+        assert_eq!(
+            create(2, ephemeral_secret, &revealed_commitments[1]),
+            Err(Error::OutputIndexOutOfRange(2))
+        );
+
+        // a different blinding factor doesn't open the output's commitment
+        let wrong_opening = RevealedCommitment::from_value(7, OsRng);
+        assert_eq!(
+            create(1, ephemeral_secret, &wrong_opening),
+            Err(Error::InvalidPaymentProof)
+        );
+    }
+}
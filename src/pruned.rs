@@ -0,0 +1,248 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Archived transactions with their range proofs pruned.
+//!
+//! Range proofs are most of a transaction's size but are only needed once,
+//! when the transaction is first verified. A ledger may then keep a
+//! `PrunedRingCtTransaction`, which retains the key images, the commitments
+//! and the balance between them, and the transaction id. The id can no
+//! longer be recomputed, as it covers the range proofs, so it is taken from
+//! the verified transaction when pruning.
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::{ff::Field, Group},
+};
+use std::collections::BTreeSet;
+
+use crate::{
+    codec::{self, Reader},
+    ringct::{Amount, OutputProof, RingCtTransaction},
+    Error, KeyImage, MlsagSignature, Result, RingCtMaterial,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A verified transaction without its range proofs, and optionally without
+/// its ring signatures
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrunedRingCtTransaction {
+    pub id: [u8; 32],
+    pub version: u16,
+    pub fee: Amount,
+    pub extra: Vec<u8>,
+    pub key_images: Vec<KeyImage>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub pseudo_commitments: Vec<G1Affine>,
+    /// The outputs, without range proofs
+    pub outputs: Vec<OutputProof>,
+    /// The MLSAGs of the transaction, when kept while pruning
+    pub mlsags: Vec<MlsagSignature>,
+}
+
+impl RingCtTransaction {
+    /// Prunes the range proofs of a transaction which was verified, keeping
+    /// its MLSAGs if `keep_mlsags` is set.
+    pub fn prune(&self, keep_mlsags: bool) -> PrunedRingCtTransaction {
+        PrunedRingCtTransaction {
+            id: self.id(),
+            version: self.version,
+            fee: self.fee,
+            extra: self.extra.clone(),
+            key_images: self.key_images(),
+            pseudo_commitments: self.pseudo_commitments(),
+            outputs: self
+                .outputs
+                .iter()
+                .map(OutputProof::without_range_proof)
+                .collect(),
+            mlsags: if keep_mlsags {
+                self.mlsags.clone()
+            } else {
+                vec![]
+            },
+        }
+    }
+}
+
+impl PrunedRingCtTransaction {
+    /// Checks the invariants the pruned transaction retains: it has inputs,
+    /// its key images are unique, its pseudo commitments sum to its output
+    /// commitments and fee, and any MLSAGs kept match its inputs.
+    pub fn verify(&self) -> Result<()> {
+        if self.key_images.is_empty() {
+            return Err(Error::TransactionMustHaveAnInput);
+        }
+        if self.key_images.len() != self.pseudo_commitments.len()
+            || self.outputs.iter().any(|o| o.range_proof().is_some())
+        {
+            return Err(Error::InvalidPrunedTransaction);
+        }
+
+        let mut seen = BTreeSet::new();
+        for (input_index, key_image) in self.key_images.iter().enumerate() {
+            if !seen.insert(key_image) {
+                return Err(Error::KeyImageNotUniqueAcrossInputs {
+                    input_index,
+                    key_image: key_image.to_string(),
+                });
+            }
+        }
+
+        if !self.mlsags.is_empty() {
+            let kept_inputs_match = self.mlsags.len() == self.key_images.len()
+                && self
+                    .mlsags
+                    .iter()
+                    .zip(self.key_images.iter().zip(self.pseudo_commitments.iter()))
+                    .all(|(mlsag, (key_image, pseudo_commitment))| {
+                        mlsag.key_image() == *key_image
+                            && mlsag.pseudo_commitment() == *pseudo_commitment
+                    });
+            if !kept_inputs_match {
+                return Err(Error::InvalidPrunedTransaction);
+            }
+        }
+
+        let input_sum = self
+            .pseudo_commitments
+            .iter()
+            .fold(G1Projective::identity(), |sum, c| {
+                sum + G1Projective::from(*c)
+            });
+        let output_sum = self
+            .outputs
+            .iter()
+            .fold(G1Projective::identity(), |sum, o| {
+                sum + G1Projective::from(o.commitment())
+            });
+        let fee_commitment =
+            RingCtMaterial::pc_gens().commit(Scalar::from(self.fee.value()), Scalar::zero());
+        if input_sum != output_sum + fee_commitment {
+            return Err(Error::InputPseudoCommitmentsDoNotSumToOutputCommitments);
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Default::default();
+        v.extend(self.id);
+        v.extend(self.version.to_le_bytes());
+        v.extend(self.fee.to_le_bytes());
+        codec::write_len(&mut v, self.extra.len());
+        v.extend(&self.extra);
+        codec::write_len(&mut v, self.key_images.len());
+        for (key_image, pseudo_commitment) in
+            self.key_images.iter().zip(self.pseudo_commitments.iter())
+        {
+            v.extend(key_image.to_bytes());
+            v.extend(pseudo_commitment.to_compressed());
+        }
+        codec::write_len(&mut v, self.outputs.len());
+        for o in self.outputs.iter() {
+            v.extend(o.to_bytes());
+        }
+        codec::write_len(&mut v, self.mlsags.len());
+        for m in self.mlsags.iter() {
+            v.extend(m.to_bytes());
+        }
+        v
+    }
+
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let limits = reader.limits();
+        let id = reader.read_array()?;
+        let version = reader.read_u16()?;
+        let fee = Amount::new(reader.read_u64()?);
+        let extra_len = reader.read_len()?;
+        let extra = reader.take(extra_len)?.to_vec();
+        let (key_images, pseudo_commitments) = reader
+            .read_vec_max(limits.max_inputs, "inputs", |r| {
+                Ok((KeyImage::from(r.read_point()?), r.read_point()?))
+            })?
+            .into_iter()
+            .unzip();
+        let outputs = reader.read_vec_max(limits.max_outputs, "outputs", OutputProof::decode)?;
+        let mlsags = reader.read_vec_max(limits.max_inputs, "inputs", MlsagSignature::decode)?;
+        reader.finish()?;
+
+        Ok(Self {
+            id,
+            version,
+            fee,
+            extra,
+            key_images,
+            pseudo_commitments,
+            outputs,
+            mlsags,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{group::ff::Field, rand::rngs::OsRng};
+
+    use super::*;
+    use crate::{mock::random_decoys, MlsagMaterial, Output, RevealedCommitment, TrueInput};
+
+    fn signed_tx() -> RingCtTransaction {
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(5, OsRng),
+        );
+        let decoy_inputs = random_decoys(2);
+        let material = RingCtMaterial {
+            inputs: vec![MlsagMaterial::new(true_input, decoy_inputs, &mut OsRng).unwrap()],
+            outputs: vec![
+                Output::new(G1Projective::random(OsRng), 3),
+                Output::new(G1Projective::random(OsRng), 1),
+            ],
+            fee: Amount::new(1),
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+        let (tx, _revealed_output_commitments) = material
+            .sign(&mut OsRng)
+            .expect("Failed to sign transaction");
+        tx
+    }
+
+    #[test]
+    fn test_pruned_tx_keeps_id_and_balance() {
+        let tx = signed_tx();
+
+        let pruned = tx.prune(false);
+        assert_eq!(pruned.id, tx.id());
+        assert_eq!(pruned.key_images, tx.key_images());
+        assert_eq!(pruned.verify(), Ok(()));
+        assert!(pruned.to_bytes().len() * 2 < tx.to_bytes().len());
+        assert_eq!(
+            PrunedRingCtTransaction::from_bytes(&pruned.to_bytes()),
+            Ok(pruned.clone())
+        );
+
+        let with_mlsags = tx.prune(true);
+        assert_eq!(with_mlsags.verify(), Ok(()));
+        assert_eq!(
+            PrunedRingCtTransaction::from_bytes(&with_mlsags.to_bytes()),
+            Ok(with_mlsags)
+        );
+
+        let mut unbalanced = pruned;
+        unbalanced.fee = Amount::new(2);
+        assert_eq!(
+            unbalanced.verify(),
+            Err(Error::InputPseudoCommitmentsDoNotSumToOutputCommitments)
+        );
+    }
+}
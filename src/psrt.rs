@@ -0,0 +1,519 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, G1Projective},
+    group::Curve,
+    merlin::Transcript,
+    rand::{CryptoRng, RngCore},
+    RangeProof,
+};
+
+use crate::{
+    ringct::{
+        field_flags, gen_message_for_signing, range_proof_flags, tx_format, Amount, OutputProof,
+        RingCtTransaction, TX_FLAGS, TX_VERSION_1, TX_VERSION_2,
+    },
+    Error, KeyImage, MlsagMaterial, MlsagSignature, OpeningProof, Result, RevealedCommitment,
+    RingCtMaterial,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const PSEUDO_COMMITMENT_PROOF_LABEL: &[u8] = b"blst-ringct-pseudo-commitment-proof";
+
+/// A transaction whose outputs are final but whose inputs may not all be
+/// signed yet. Copies can be signed separately, eg. by an offline signer,
+/// then merged and finalized by a coordinator.
+///
+/// It carries only public data: the rings, key images and pseudo
+/// commitments of the inputs, and their signatures so far. Each signer
+/// brings its own input's `MlsagMaterial` and the revealed pseudo
+/// commitment the coordinator was given for it by `new()`.
+///
+/// Only the MLSAG transaction versions are supported.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct PartiallySignedRingCtTransaction {
+    version: u16,
+    fee: Amount,
+    extra: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    network_id: Option<[u8; 32]>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    not_valid_after: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    public_keys: Vec<Vec<G1Affine>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    commitments: Vec<Vec<G1Affine>>,
+    key_images: Vec<KeyImage>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pseudo_commitments: Vec<G1Affine>,
+    output_proofs: Vec<OutputProof>,
+    aggregated_range_proof: Option<RangeProof>,
+    mlsags: Vec<Option<MlsagSignature>>,
+    /// Optional proofs that each input's pseudo commitment commits to the
+    /// value of its true input, see `prove_pseudo_commitment()`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pseudo_commitment_proofs: Vec<Option<OpeningProof>>,
+}
+
+impl PartiallySignedRingCtTransaction {
+    /// Generates the pseudo commitments and output proofs, leaving every
+    /// input unsigned. Also returns the revealed pseudo commitments, one of
+    /// which each input's signer needs, and the revealed output commitments.
+    pub fn new(
+        material: &RingCtMaterial,
+        version: u16,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<(Self, Vec<RevealedCommitment>, Vec<RevealedCommitment>)> {
+        if !matches!(tx_format(version), TX_VERSION_1 | TX_VERSION_2) {
+            return Err(Error::UnsupportedTransactionVersion(version));
+        }
+        let version = version
+            | TX_FLAGS
+            | range_proof_flags(version)
+            | field_flags(material.network_id.as_ref(), material.not_valid_after);
+        let prepared = material.prepare(version, rng)?;
+        let pc_gens = RingCtMaterial::pc_gens();
+
+        let psrt = Self {
+            version,
+            fee: material.fee,
+            extra: material.extra.clone(),
+            network_id: material.network_id,
+            not_valid_after: material.not_valid_after,
+            public_keys: material.inputs.iter().map(|m| m.public_keys()).collect(),
+            commitments: material
+                .inputs
+                .iter()
+                .map(|m| m.commitments(pc_gens))
+                .collect(),
+            key_images: material.key_images(),
+            pseudo_commitments: material.pseudo_commitments(&prepared.revealed_pseudo_commitments),
+            output_proofs: prepared.output_proofs,
+            aggregated_range_proof: prepared.aggregated_range_proof,
+            mlsags: vec![None; material.inputs.len()],
+            pseudo_commitment_proofs: vec![None; material.inputs.len()],
+        };
+        Ok((
+            psrt,
+            prepared.revealed_pseudo_commitments,
+            prepared.revealed_output_commitments,
+        ))
+    }
+
+    pub fn output_proofs(&self) -> &[OutputProof] {
+        &self.output_proofs
+    }
+
+    /// The message every input's ring signature signs
+    pub fn message(&self) -> Vec<u8> {
+        gen_message_for_signing(
+            self.version,
+            self.fee,
+            self.network_id.as_ref(),
+            self.not_valid_after,
+            &self.extra,
+            &self.public_keys.concat(),
+            &self.key_images,
+            &self.pseudo_commitments,
+            &self.output_proofs,
+            self.aggregated_range_proof.as_ref(),
+        )
+    }
+
+    /// Signs the input at `input_index` with its `input` material and
+    /// revealed pseudo commitment, which must match the transaction's
+    pub fn sign_input(
+        &mut self,
+        input_index: usize,
+        input: &MlsagMaterial,
+        revealed_pseudo_commitment: &RevealedCommitment,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<()> {
+        self.check_input(input_index, input, revealed_pseudo_commitment)?;
+        let msg = self.message();
+
+        self.mlsags[input_index] = Some(input.sign(
+            &msg,
+            revealed_pseudo_commitment,
+            RingCtMaterial::pc_gens(),
+            rng,
+        ));
+        Ok(())
+    }
+
+    /// Proves that the pseudo commitment of the input at `input_index` commits
+    /// to the same value as its true input: their difference is a commitment
+    /// to zero, proven with the difference of their blindings. The proof
+    /// reveals the true input, so it's only for the parties to the
+    /// transaction, who check it with `verify_pseudo_commitment_proofs()`
+    /// before trusting that no value was moved between inputs.
+    pub fn prove_pseudo_commitment(
+        &mut self,
+        input_index: usize,
+        input: &MlsagMaterial,
+        revealed_pseudo_commitment: &RevealedCommitment,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<()> {
+        self.check_input(input_index, input, revealed_pseudo_commitment)?;
+        let msg = self.message();
+
+        let proof = OpeningProof::create_zero(
+            input.true_input.revealed_commitment.blinding - revealed_pseudo_commitment.blinding,
+            RingCtMaterial::pc_gens(),
+            &mut pseudo_commitment_transcript(&msg, input_index),
+            rng,
+        );
+        self.pseudo_commitment_proofs[input_index] = Some(proof);
+        Ok(())
+    }
+
+    /// Checks every pseudo commitment proof made so far, each against the
+    /// members of its input's ring
+    pub fn verify_pseudo_commitment_proofs(&self) -> Result<()> {
+        let msg = self.message();
+        let pc_gens = RingCtMaterial::pc_gens();
+
+        for (input_index, proof) in self.pseudo_commitment_proofs.iter().enumerate() {
+            let proof = match proof {
+                Some(proof) => proof,
+                None => continue,
+            };
+            let pseudo_commitment = G1Projective::from(self.pseudo_commitments[input_index]);
+            let proven = self.commitments[input_index].iter().any(|commitment| {
+                let difference = G1Projective::from(commitment) - pseudo_commitment;
+                proof
+                    .verify_zero(
+                        &difference.to_affine(),
+                        pc_gens,
+                        &mut pseudo_commitment_transcript(&msg, input_index),
+                    )
+                    .is_ok()
+            });
+            if !proven {
+                return Err(Error::InvalidPseudoCommitmentProof { input_index });
+            }
+        }
+        Ok(())
+    }
+
+    /// Signs every input which isn't signed yet, given every input's
+    /// material and revealed pseudo commitment
+    pub fn sign_all(
+        &mut self,
+        inputs: &[MlsagMaterial],
+        revealed_pseudo_commitments: &[RevealedCommitment],
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<()> {
+        for input_index in 0..self.mlsags.len() {
+            if self.mlsags[input_index].is_none() {
+                let (input, revealed_pseudo_commitment) = inputs
+                    .get(input_index)
+                    .zip(revealed_pseudo_commitments.get(input_index))
+                    .ok_or(Error::MissingRingSignature { input_index })?;
+                self.sign_input(input_index, input, revealed_pseudo_commitment, rng)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.mlsags.iter().all(Option::is_some)
+    }
+
+    /// Takes the ring signatures from a copy of the same transaction that
+    /// were missing here.
+    pub fn merge(&mut self, other: Self) -> Result<()> {
+        if self.mlsags.len() != other.mlsags.len() || self.message() != other.message() {
+            return Err(Error::PartiallySignedTransactionMismatch);
+        }
+
+        for (mlsag, other_mlsag) in self.mlsags.iter_mut().zip(other.mlsags) {
+            if mlsag.is_none() {
+                *mlsag = other_mlsag;
+            }
+        }
+        for (proof, other_proof) in self
+            .pseudo_commitment_proofs
+            .iter_mut()
+            .zip(other.pseudo_commitment_proofs)
+        {
+            if proof.is_none() {
+                *proof = other_proof;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks every input's ring signature and any pseudo commitment proofs,
+    /// and assembles the transaction
+    pub fn finalize(self) -> Result<RingCtTransaction> {
+        self.verify_pseudo_commitment_proofs()?;
+        let msg = self.message();
+
+        let mlsags = self
+            .mlsags
+            .into_iter()
+            .zip(self.commitments.iter())
+            .enumerate()
+            .map(|(input_index, (mlsag, commitments))| {
+                let mlsag = mlsag.ok_or(Error::MissingRingSignature { input_index })?;
+                mlsag.verify(&msg, commitments)?;
+                Ok(mlsag)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(RingCtTransaction {
+            version: self.version,
+            mlsags,
+            clsags: vec![],
+            matrix_mlsag: None,
+            outputs: self.output_proofs,
+            fee: self.fee,
+            extra: self.extra,
+            network_id: self.network_id,
+            not_valid_after: self.not_valid_after,
+            aggregated_range_proof: self.aggregated_range_proof,
+        })
+    }
+
+    // The signer's material must have the ring, key image and pseudo
+    // commitment the transaction has for the input
+    fn check_input(
+        &self,
+        input_index: usize,
+        input: &MlsagMaterial,
+        revealed_pseudo_commitment: &RevealedCommitment,
+    ) -> Result<()> {
+        let pc_gens = RingCtMaterial::pc_gens();
+        let matches = input_index < self.mlsags.len()
+            && input.public_keys() == self.public_keys[input_index]
+            && input.commitments(pc_gens) == self.commitments[input_index]
+            && KeyImage::from(input.true_input.key_image()) == self.key_images[input_index]
+            && revealed_pseudo_commitment.commit(pc_gens).to_affine()
+                == self.pseudo_commitments[input_index];
+        if matches {
+            Ok(())
+        } else {
+            Err(Error::InputMaterialMismatch { input_index })
+        }
+    }
+}
+
+fn pseudo_commitment_transcript(msg: &[u8], input_index: usize) -> Transcript {
+    let mut transcript = Transcript::new(PSEUDO_COMMITMENT_PROOF_LABEL);
+    transcript.append_message(b"msg", msg);
+    transcript.append_u64(b"input_index", input_index as u64);
+    transcript
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{
+        blstrs::{G1Affine, G1Projective, Scalar},
+        group::{ff::Field, Curve, Group},
+        rand::rngs::OsRng,
+    };
+
+    use crate::{mock::random_decoys, ringct::Amount, MlsagMaterial, Output, TrueInput};
+
+    use super::*;
+
+    fn two_input_material(
+        mut rng: impl RngCore + CryptoRng,
+    ) -> (RingCtMaterial, Vec<Vec<G1Affine>>) {
+        let pc_gens = crate::pedersen_gens();
+        let inputs = Vec::from_iter((0..2).map(|_| {
+            let true_input = TrueInput::new(
+                Scalar::random(&mut rng),
+                RevealedCommitment::from_value(2, &mut rng),
+            );
+            let decoy_inputs = random_decoys(2);
+            MlsagMaterial::new(true_input, decoy_inputs, &mut rng).unwrap()
+        }));
+        let public_commitments = Vec::from_iter(inputs.iter().map(|m| m.commitments(&pc_gens)));
+
+        let material = RingCtMaterial {
+            inputs,
+            outputs: vec![Output::new(G1Projective::random(&mut rng), 4)],
+            fee: Amount::ZERO,
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+        (material, public_commitments)
+    }
+
+    #[test]
+    fn test_inputs_signed_separately_are_merged() {
+        let mut rng = OsRng;
+        let (material, public_commitments) = two_input_material(rng);
+
+        let (mut psrt, revealed_pseudo_commitments, _revealed_output_commitments) =
+            PartiallySignedRingCtTransaction::new(&material, TX_VERSION_1, &mut rng).unwrap();
+        let mut offline_copy = psrt.clone();
+
+        psrt.sign_input(
+            0,
+            &material.inputs[0],
+            &revealed_pseudo_commitments[0],
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(
+            psrt.clone().finalize().map(|_| ()),
+            Err(Error::MissingRingSignature { input_index: 1 })
+        );
+
+        offline_copy
+            .sign_input(
+                1,
+                &material.inputs[1],
+                &revealed_pseudo_commitments[1],
+                &mut rng,
+            )
+            .unwrap();
+        psrt.merge(offline_copy).unwrap();
+        assert!(psrt.is_complete());
+
+        let tx = psrt.finalize().unwrap();
+        assert!(tx.verify(&public_commitments).is_ok());
+    }
+
+    #[test]
+    fn test_signing_needs_the_inputs_own_material() {
+        let mut rng = OsRng;
+        let (material, _public_commitments) = two_input_material(rng);
+
+        let (mut psrt, revealed_pseudo_commitments, _revealed_output_commitments) =
+            PartiallySignedRingCtTransaction::new(&material, TX_VERSION_1, &mut rng).unwrap();
+
+        assert_eq!(
+            psrt.sign_input(
+                0,
+                &material.inputs[1],
+                &revealed_pseudo_commitments[1],
+                &mut rng
+            ),
+            Err(Error::InputMaterialMismatch { input_index: 0 })
+        );
+        assert_eq!(
+            psrt.sign_input(
+                0,
+                &material.inputs[0],
+                &revealed_pseudo_commitments[1],
+                &mut rng
+            ),
+            Err(Error::InputMaterialMismatch { input_index: 0 })
+        );
+        assert_eq!(
+            psrt.sign_input(
+                2,
+                &material.inputs[0],
+                &revealed_pseudo_commitments[0],
+                &mut rng
+            ),
+            Err(Error::InputMaterialMismatch { input_index: 2 })
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialized_transaction_has_no_secrets() {
+        let mut rng = OsRng;
+        let (material, _public_commitments) = two_input_material(rng);
+
+        let (mut psrt, revealed_pseudo_commitments, revealed_output_commitments) =
+            PartiallySignedRingCtTransaction::new(&material, TX_VERSION_1, &mut rng).unwrap();
+        psrt.sign_input(
+            0,
+            &material.inputs[0],
+            &revealed_pseudo_commitments[0],
+            &mut rng,
+        )
+        .unwrap();
+
+        let bytes = bincode::serialize(&psrt).unwrap();
+        let secrets = material
+            .inputs
+            .iter()
+            .flat_map(|m| {
+                [
+                    m.true_input.secret_key,
+                    m.true_input.revealed_commitment.blinding,
+                ]
+            })
+            .chain(revealed_pseudo_commitments.iter().map(|r| r.blinding))
+            .chain(revealed_output_commitments.iter().map(|r| r.blinding));
+        for secret in secrets {
+            let secret = secret.to_bytes_le();
+            assert!(!bytes.windows(secret.len()).any(|w| w == secret));
+        }
+    }
+
+    #[test]
+    fn test_pseudo_commitment_proofs() {
+        let mut rng = OsRng;
+        let pc_gens = crate::pedersen_gens();
+        let (material, public_commitments) = two_input_material(rng);
+
+        let (mut psrt, revealed_pseudo_commitments, _revealed_output_commitments) =
+            PartiallySignedRingCtTransaction::new(&material, TX_VERSION_1, &mut rng).unwrap();
+        let mut offline_copy = psrt.clone();
+        psrt.prove_pseudo_commitment(
+            0,
+            &material.inputs[0],
+            &revealed_pseudo_commitments[0],
+            &mut rng,
+        )
+        .unwrap();
+        offline_copy
+            .prove_pseudo_commitment(
+                1,
+                &material.inputs[1],
+                &revealed_pseudo_commitments[1],
+                &mut rng,
+            )
+            .unwrap();
+        psrt.merge(offline_copy).unwrap();
+        assert_eq!(psrt.verify_pseudo_commitment_proofs(), Ok(()));
+
+        // a pseudo commitment shifting value to another input is caught
+        let mut shifted = psrt.clone();
+        shifted.pseudo_commitments[0] =
+            (G1Projective::from(shifted.pseudo_commitments[0]) + pc_gens.B).to_affine();
+        shifted.pseudo_commitments[1] =
+            (G1Projective::from(shifted.pseudo_commitments[1]) - pc_gens.B).to_affine();
+        assert_eq!(
+            shifted.verify_pseudo_commitment_proofs(),
+            Err(Error::InvalidPseudoCommitmentProof { input_index: 0 })
+        );
+
+        psrt.sign_all(&material.inputs, &revealed_pseudo_commitments, &mut rng)
+            .unwrap();
+        let tx = psrt.finalize().unwrap();
+        assert!(tx.verify(&public_commitments).is_ok());
+    }
+
+    #[test]
+    fn test_merge_rejects_a_different_transaction() {
+        let mut rng = OsRng;
+        let (material, _public_commitments) = two_input_material(rng);
+
+        let (mut psrt, _, _) =
+            PartiallySignedRingCtTransaction::new(&material, TX_VERSION_1, &mut rng).unwrap();
+        let (other, _, _) =
+            PartiallySignedRingCtTransaction::new(&material, TX_VERSION_1, &mut rng).unwrap();
+
+        assert_eq!(
+            psrt.merge(other),
+            Err(Error::PartiallySignedTransactionMismatch)
+        );
+    }
+}
@@ -0,0 +1,426 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Batch verification of range proofs made separately for each output.
+//!
+//! `RangeProof::verify_single()` ends each proof with its own multi-scalar
+//! multiplication. Here each proof's transcript is replayed as it does, and
+//! the final checks are summed with random weights into one multi-scalar
+//! multiplication, the terms of the shared generators added together. A
+//! batch holding an invalid proof fails, except with negligible probability,
+//! but doesn't say which proof it was, so callers verify each proof on its
+//! own to report it.
+//!
+//! The replay follows `bls_bulletproofs` 1.1.1 for single party proofs, whose
+//! transcript protocol and generators aren't exposed, so the dependency is
+//! pinned to that exact version and the tests check the replay against its
+//! verifier. Rewinding a proof replays it the same way, up to its bit
+//! challenges.
+
+use std::{iter, sync::OnceLock};
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::{ff::Field, Group},
+    merlin::Transcript,
+    rand::{CryptoRng, RngCore, SeedableRng},
+    PedersenGens, ProofError, RangeProof,
+};
+use rand_chacha::ChaCha20Rng;
+use tiny_keccak::{Hasher, Sha3};
+
+use crate::{backend::VerificationBackend, ringct::RANGE_PROOF_BITS};
+
+/// The weighted checks of several range proofs, to be verified at once
+pub(crate) struct RangeProofBatch {
+    bits: usize,
+    basepoint_scalar: Scalar,
+    blinding_scalar: Scalar,
+    g_scalars: Vec<Scalar>,
+    h_scalars: Vec<Scalar>,
+    points: Vec<G1Projective>,
+    scalars: Vec<Scalar>,
+}
+
+impl RangeProofBatch {
+    pub(crate) fn new(bits: usize) -> Result<Self, ProofError> {
+        if !(bits == 8 || bits == 16 || bits == 32 || bits == 64) {
+            return Err(ProofError::InvalidBitsize);
+        }
+        Ok(Self {
+            bits,
+            basepoint_scalar: Scalar::zero(),
+            blinding_scalar: Scalar::zero(),
+            g_scalars: vec![Scalar::zero(); bits],
+            h_scalars: vec![Scalar::zero(); bits],
+            points: vec![],
+            scalars: vec![],
+        })
+    }
+
+    /// Replays the transcript of a proof of `commitment`, leaving
+    /// `transcript` as `verify_single()` would, and adds its check to the batch
+    pub(crate) fn push(
+        &mut self,
+        proof: &RangeProof,
+        commitment: &G1Affine,
+        transcript: &mut Transcript,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<(), ProofError> {
+        let n = self.bits;
+        let proof = ParsedRangeProof::from_bytes(&proof.to_bytes())?;
+        let lg_n = proof.l_vec.len();
+        if n != 1 << lg_n {
+            return Err(ProofError::VerificationError);
+        }
+
+        let (y, z, x) = replay_bit_challenges(&proof, commitment, transcript, n)?;
+        let zz = z * z;
+        append_scalar(transcript, b"t_x", &proof.t_x);
+        append_scalar(transcript, b"t_x_blinding", &proof.t_x_blinding);
+        append_scalar(transcript, b"e_blinding", &proof.e_blinding);
+        let w = challenge_scalar(transcript, b"w");
+
+        // the inner product argument's challenges u_k..u_1
+        innerproduct_domain_sep(transcript, n as u64);
+        let mut u = Vec::with_capacity(lg_n);
+        for (l, r) in proof.l_vec.iter().zip(proof.r_vec.iter()) {
+            validate_and_append_point(transcript, b"L", l)?;
+            validate_and_append_point(transcript, b"R", r)?;
+            u.push(challenge_scalar(transcript, b"u"));
+        }
+        let u_inv = u
+            .iter()
+            .map(|u| Option::from(u.invert()).ok_or(ProofError::FormatError))
+            .collect::<Result<Vec<Scalar>, _>>()?;
+        let u_sq: Vec<Scalar> = u.iter().map(|u| u * u).collect();
+        let u_inv_sq: Vec<Scalar> = u_inv.iter().map(|u| u * u).collect();
+        let mut s = Vec::with_capacity(n);
+        s.push(u_inv.iter().fold(Scalar::one(), |product, u| product * u));
+        for i in 1..n {
+            let lg_i = (usize::BITS - 1 - i.leading_zeros()) as usize;
+            let k = 1 << lg_i;
+            s.push(s[i - k] * u_sq[(lg_n - 1) - lg_i]);
+        }
+
+        // c batches the statements of the proof as in verify_single(),
+        // weight the proof's check within the batch
+        let c = Scalar::random(&mut *rng);
+        let weight = Scalar::random(&mut *rng);
+        let y_inv: Scalar = Option::from(y.invert()).ok_or(ProofError::FormatError)?;
+        let (a, b) = (proof.a, proof.b);
+
+        let two = Scalar::from(2u64);
+        let mut exp_y_inv = Scalar::one();
+        let mut exp_2 = Scalar::one();
+        for i in 0..n {
+            self.g_scalars[i] += weight * (-z - a * s[i]);
+            self.h_scalars[i] += weight * (z + exp_y_inv * (zz * exp_2 - b * s[n - 1 - i]));
+            exp_y_inv *= y_inv;
+            exp_2 *= two;
+        }
+        self.basepoint_scalar +=
+            weight * (w * (proof.t_x - a * b) + c * (delta(n, &y, &z) - proof.t_x));
+        self.blinding_scalar += weight * (-proof.e_blinding - c * proof.t_x_blinding);
+
+        self.points.extend(
+            [
+                proof.a_commitment,
+                proof.s_commitment,
+                proof.t_1,
+                proof.t_2,
+                commitment.into(),
+            ]
+            .into_iter()
+            .chain(proof.l_vec)
+            .chain(proof.r_vec),
+        );
+        self.scalars.extend(
+            [Scalar::one(), x, c * x, c * x * x, c * zz]
+                .into_iter()
+                .chain(u_sq)
+                .chain(u_inv_sq)
+                .map(|scalar| weight * scalar),
+        );
+        Ok(())
+    }
+
+    /// Checks every proof added at once
+    pub(crate) fn verify(
+        self,
+        pc_gens: &PedersenGens,
+        backend: &dyn VerificationBackend,
+    ) -> Result<(), ProofError> {
+        let (g, h) = generators();
+        let points: Vec<G1Projective> = self
+            .points
+            .into_iter()
+            .chain([pc_gens.B, pc_gens.B_blinding])
+            .chain(g[..self.bits].iter().copied())
+            .chain(h[..self.bits].iter().copied())
+            .collect();
+        let scalars: Vec<Scalar> = self
+            .scalars
+            .into_iter()
+            .chain([self.basepoint_scalar, self.blinding_scalar])
+            .chain(self.g_scalars)
+            .chain(self.h_scalars)
+            .collect();
+
+        if bool::from(backend.multi_exp(&points, &scalars).is_identity()) {
+            Ok(())
+        } else {
+            Err(ProofError::VerificationError)
+        }
+    }
+}
+
+/// Replays the transcript of a single party proof of `commitment` up to the
+/// challenges its bit commitments answer, returning `(y, z, x)`
+pub(crate) fn replay_bit_challenges(
+    proof: &ParsedRangeProof,
+    commitment: &G1Affine,
+    transcript: &mut Transcript,
+    n: usize,
+) -> Result<(Scalar, Scalar, Scalar), ProofError> {
+    rangeproof_domain_sep(transcript, n as u64, 1);
+    append_point(transcript, b"V", &G1Projective::from(commitment));
+    validate_and_append_point(transcript, b"A", &proof.a_commitment)?;
+    validate_and_append_point(transcript, b"S", &proof.s_commitment)?;
+    let y = challenge_scalar(transcript, b"y");
+    let z = challenge_scalar(transcript, b"z");
+    validate_and_append_point(transcript, b"T_1", &proof.t_1)?;
+    validate_and_append_point(transcript, b"T_2", &proof.t_2)?;
+    let x = challenge_scalar(transcript, b"x");
+    Ok((y, z, x))
+}
+
+/// The fields of a `RangeProof`, read back from its encoding
+pub(crate) struct ParsedRangeProof {
+    a_commitment: G1Projective,
+    s_commitment: G1Projective,
+    t_1: G1Projective,
+    t_2: G1Projective,
+    t_x: Scalar,
+    pub(crate) t_x_blinding: Scalar,
+    pub(crate) e_blinding: Scalar,
+    l_vec: Vec<G1Projective>,
+    r_vec: Vec<G1Projective>,
+    a: Scalar,
+    b: Scalar,
+}
+
+impl ParsedRangeProof {
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Self, ProofError> {
+        let point = |at: usize| -> Result<G1Projective, ProofError> {
+            let bytes: [u8; 48] = bytes
+                .get(at..at + 48)
+                .and_then(|b| b.try_into().ok())
+                .ok_or(ProofError::FormatError)?;
+            Option::from(G1Projective::from_compressed(&bytes)).ok_or(ProofError::FormatError)
+        };
+        let scalar = |at: usize| -> Result<Scalar, ProofError> {
+            let bytes: [u8; 32] = bytes
+                .get(at..at + 32)
+                .and_then(|b| b.try_into().ok())
+                .ok_or(ProofError::FormatError)?;
+            Option::from(Scalar::from_bytes_le(&bytes)).ok_or(ProofError::FormatError)
+        };
+
+        // A, S, T_1, T_2, t_x, t_x_blinding, e_blinding, then the inner
+        // product proof's (L, R) pairs, a and b
+        let ipp_start = 4 * 48 + 3 * 32;
+        let num_points = bytes
+            .len()
+            .checked_sub(ipp_start + 2 * 32)
+            .filter(|len| len % 96 == 0)
+            .ok_or(ProofError::FormatError)?
+            / 48;
+        let lg_n = num_points / 2;
+        let (l_vec, r_vec) = (0..lg_n)
+            .map(|i| Ok((point(ipp_start + 96 * i)?, point(ipp_start + 96 * i + 48)?)))
+            .collect::<Result<Vec<_>, ProofError>>()?
+            .into_iter()
+            .unzip();
+        let end = ipp_start + num_points * 48;
+
+        Ok(Self {
+            a_commitment: point(0)?,
+            s_commitment: point(48)?,
+            t_1: point(2 * 48)?,
+            t_2: point(3 * 48)?,
+            t_x: scalar(4 * 48)?,
+            t_x_blinding: scalar(4 * 48 + 32)?,
+            e_blinding: scalar(4 * 48 + 2 * 32)?,
+            l_vec,
+            r_vec,
+            a: scalar(end)?,
+            b: scalar(end + 32)?,
+        })
+    }
+}
+
+/// The first party's `G` and `H` generators of `BulletproofGens`
+fn generators() -> &'static (Vec<G1Projective>, Vec<G1Projective>) {
+    static GENERATORS: OnceLock<(Vec<G1Projective>, Vec<G1Projective>)> = OnceLock::new();
+    GENERATORS.get_or_init(|| {
+        let chain = |label: &[u8]| {
+            let mut rng = ChaCha20Rng::from_seed(sha3(&[b"GeneratorsChain", label]));
+            Vec::from_iter((0..RANGE_PROOF_BITS).map(|_| G1Projective::random(&mut rng)))
+        };
+        (chain(b"G\0\0\0\0"), chain(b"H\0\0\0\0"))
+    })
+}
+
+// (z - z^2) <1, y^n> - z^3 <1, 2^n>
+fn delta(n: usize, y: &Scalar, z: &Scalar) -> Scalar {
+    let sum_of_powers = |x: Scalar| {
+        iter::successors(Some(Scalar::one()), |p| Some(p * x))
+            .take(n)
+            .fold(Scalar::zero(), |sum, p| sum + p)
+    };
+    (z - z * z) * sum_of_powers(*y) - z * z * z * sum_of_powers(Scalar::from(2u64))
+}
+
+fn sha3(material: &[&[u8]]) -> [u8; 32] {
+    let mut sha3 = Sha3::v256();
+    for chunk in material {
+        sha3.update(chunk);
+    }
+    let mut hash = [0u8; 32];
+    sha3.finalize(&mut hash);
+    hash
+}
+
+fn rangeproof_domain_sep(transcript: &mut Transcript, n: u64, m: u64) {
+    transcript.append_message(b"dom-sep", b"rangeproof v1");
+    transcript.append_u64(b"n", n);
+    transcript.append_u64(b"m", m);
+}
+
+fn innerproduct_domain_sep(transcript: &mut Transcript, n: u64) {
+    transcript.append_message(b"dom-sep", b"ipp v1");
+    transcript.append_u64(b"n", n);
+}
+
+fn append_scalar(transcript: &mut Transcript, label: &'static [u8], scalar: &Scalar) {
+    transcript.append_message(label, &scalar.to_bytes_le());
+}
+
+fn append_point(transcript: &mut Transcript, label: &'static [u8], point: &G1Projective) {
+    transcript.append_message(label, &point.to_compressed());
+}
+
+fn validate_and_append_point(
+    transcript: &mut Transcript,
+    label: &'static [u8],
+    point: &G1Projective,
+) -> Result<(), ProofError> {
+    if bool::from(point.is_identity()) {
+        return Err(ProofError::VerificationError);
+    }
+    append_point(transcript, label, point);
+    Ok(())
+}
+
+fn challenge_scalar(transcript: &mut Transcript, label: &'static [u8]) -> Scalar {
+    let mut buf = [0u8; 64];
+    transcript.challenge_bytes(label, &mut buf);
+    let mut rng = ChaCha20Rng::from_seed(sha3(&[b"TranscriptChallenge", &buf]));
+    Scalar::random(&mut rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{rand::rngs::OsRng, BulletproofGens};
+
+    use super::*;
+    use crate::backend::CpuBackend;
+
+    const BIT_LENGTHS: [usize; 4] = [8, 16, 32, 64];
+
+    fn proofs(count: u64, bits: usize) -> Vec<(RangeProof, G1Affine)> {
+        let bp_gens = BulletproofGens::new(RANGE_PROOF_BITS, 1);
+        let pc_gens = crate::pedersen_gens();
+        Vec::from_iter((0..count).map(|value| {
+            let (proof, commitment) = RangeProof::prove_single_with_rng(
+                &bp_gens,
+                &pc_gens,
+                &mut Transcript::new(b"test"),
+                value,
+                &Scalar::random(OsRng),
+                bits,
+                &mut OsRng,
+            )
+            .unwrap();
+            (proof, commitment)
+        }))
+    }
+
+    fn verify_batch(proofs: &[(RangeProof, G1Affine)], bits: usize) -> Result<(), ProofError> {
+        let mut batch = RangeProofBatch::new(bits)?;
+        for (proof, commitment) in proofs {
+            batch.push(proof, commitment, &mut Transcript::new(b"test"), &mut OsRng)?;
+        }
+        batch.verify(&crate::pedersen_gens(), &CpuBackend)
+    }
+
+    // The replay copies bls_bulletproofs' transcript protocol and generators,
+    // this fails if they diverge from its own verifier.
+    #[test]
+    fn test_batch_matches_verify_single() {
+        let bp_gens = BulletproofGens::new(RANGE_PROOF_BITS, 1);
+        let pc_gens = crate::pedersen_gens();
+
+        for bits in BIT_LENGTHS {
+            let proofs = proofs(3, bits);
+            let (proof, commitment) = &proofs[0];
+            let mut transcript = Transcript::new(b"test");
+            let mut replayed = Transcript::new(b"test");
+            proof
+                .verify_single(&bp_gens, &pc_gens, &mut transcript, commitment, bits)
+                .unwrap();
+            let mut batch = RangeProofBatch::new(bits).unwrap();
+            batch
+                .push(proof, commitment, &mut replayed, &mut OsRng)
+                .unwrap();
+            // the transcripts are left in the same state
+            assert_eq!(
+                challenge_scalar(&mut transcript, b"next"),
+                challenge_scalar(&mut replayed, b"next")
+            );
+
+            assert_eq!(verify_batch(&proofs, bits), Ok(()));
+            assert_eq!(verify_batch(&[], bits), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_batch_rejects_an_invalid_proof() {
+        for bits in BIT_LENGTHS {
+            let mut proofs = proofs(16, bits);
+            // the proof of another commitment
+            proofs[9].1 = proofs[10].1;
+            assert_eq!(
+                verify_batch(&proofs, bits),
+                Err(ProofError::VerificationError)
+            );
+        }
+    }
+
+    #[test]
+    fn test_batch_rejects_proofs_of_another_bit_length() {
+        let proofs = proofs(2, 32);
+        assert_eq!(
+            verify_batch(&proofs, 64),
+            Err(ProofError::VerificationError)
+        );
+        assert_eq!(
+            RangeProofBatch::new(12).err(),
+            Some(ProofError::InvalidBitsize)
+        );
+    }
+}
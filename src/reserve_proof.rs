@@ -0,0 +1,344 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Proofs of reserves: a wallet proves that it owns a set of outputs holding
+//! at least some amount, without revealing the amounts of the outputs.
+//!
+//! Ownership is proven with a Schnorr proof per output key rather than a ring
+//! signature, so no key image is produced and the outputs stay spendable.
+//! The amounts are proven with a range proof over the sum of the output
+//! commitments, less the claimed minimum.
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::{ff::Field, Curve, Group},
+    merlin::Transcript,
+    rand::{CryptoRng, RngCore},
+    RangeProof,
+};
+use std::collections::BTreeSet;
+
+use crate::{
+    codec::{self, Reader},
+    mlsag::hash_to_scalar,
+    ringct::{Amount, MERLIN_TRANSCRIPT_LABEL, RANGE_PROOF_BITS},
+    CommitmentLookup, Error, Result, RingCtMaterial, TrueInput,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const RESERVE_PROOF_DOMAIN: &[u8] = b"blst-ringct-reserve-proof";
+
+/// An output whose ownership is proven by a ReserveProof
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReservedOutput {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub public_key: G1Affine,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub commitment: G1Affine,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct ReserveProof {
+    pub outputs: Vec<ReservedOutput>,
+    /// The amount the outputs are proven to hold at least
+    pub minimum: Amount,
+    /// Proves that the outputs' commitments sum to at least `minimum`
+    pub range_proof: RangeProof,
+    pub challenge: Scalar,
+    /// One response per output, proving knowledge of its secret key
+    pub key_responses: Vec<Scalar>,
+}
+
+impl ReserveProof {
+    /// Proves that the outputs `owned` hold at least `minimum` in total.
+    /// `message` binds the proof to a context, eg. an auditor's challenge.
+    pub fn create(
+        owned: &[TrueInput],
+        minimum: impl Into<Amount>,
+        message: &[u8],
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Self> {
+        let pc_gens = RingCtMaterial::pc_gens();
+        let outputs: Vec<ReservedOutput> = owned
+            .iter()
+            .map(|input| ReservedOutput {
+                public_key: input.public_key().to_affine(),
+                commitment: input.revealed_commitment.commit(pc_gens).to_affine(),
+            })
+            .collect();
+        check_outputs_unique(&outputs)?;
+
+        let minimum = minimum.into();
+        let total = Amount::checked_sum(
+            owned
+                .iter()
+                .map(|input| Amount::new(input.revealed_commitment.value)),
+        )?;
+        if total < minimum {
+            return Err(Error::InsufficientReserves {
+                total: total.value(),
+                minimum: minimum.value(),
+            });
+        }
+        let blinding = owned.iter().fold(Scalar::zero(), |sum, input| {
+            sum + input.revealed_commitment.blinding
+        });
+
+        let (range_proof, _) = RangeProof::prove_single_with_rng(
+            &RingCtMaterial::bp_gens(),
+            pc_gens,
+            &mut reserve_transcript(&outputs, minimum, message),
+            total.value() - minimum.value(),
+            &blinding,
+            RANGE_PROOF_BITS,
+            &mut *rng,
+        )?;
+
+        let nonces: Vec<Scalar> = owned.iter().map(|_| Scalar::random(&mut *rng)).collect();
+        let nonce_commitments: Vec<G1Projective> = nonces
+            .iter()
+            .map(|k| G1Projective::generator() * *k)
+            .collect();
+
+        let mut proof = Self {
+            outputs,
+            minimum,
+            range_proof,
+            challenge: Scalar::zero(),
+            key_responses: Vec::new(),
+        };
+        proof.challenge = proof.challenge(&nonce_commitments, message);
+        proof.key_responses = nonces
+            .iter()
+            .zip(owned)
+            .map(|(k, input)| *k - proof.challenge * input.secret_key)
+            .collect();
+
+        Ok(proof)
+    }
+
+    /// Verifies the proof, checking through `lookup` that each output is on
+    /// the ledger with the commitment the proof claims for it
+    pub fn verify(&self, lookup: &impl CommitmentLookup, message: &[u8]) -> Result<()> {
+        if self.key_responses.len() != self.outputs.len() {
+            return Err(Error::InvalidReserveProof);
+        }
+        check_outputs_unique(&self.outputs)?;
+        for output in self.outputs.iter() {
+            if lookup.commitment(&output.public_key) != Some(output.commitment) {
+                return Err(Error::InvalidReserveProof);
+            }
+        }
+
+        // the sum of the commitments less the minimum commits to a value in range
+        let pc_gens = RingCtMaterial::pc_gens();
+        let excess = self
+            .outputs
+            .iter()
+            .fold(G1Projective::identity(), |sum, output| {
+                sum + output.commitment
+            })
+            - pc_gens.B * Scalar::from(self.minimum.value());
+        self.range_proof
+            .verify_single(
+                &RingCtMaterial::bp_gens(),
+                pc_gens,
+                &mut reserve_transcript(&self.outputs, self.minimum, message),
+                &excess.to_affine(),
+                RANGE_PROOF_BITS,
+            )
+            .map_err(|_| Error::InvalidReserveProof)?;
+
+        let nonce_commitments: Vec<G1Projective> = self
+            .outputs
+            .iter()
+            .zip(self.key_responses.iter())
+            .map(|(output, s)| G1Projective::generator() * *s + output.public_key * self.challenge)
+            .collect();
+        if self.challenge(&nonce_commitments, message) != self.challenge {
+            return Err(Error::InvalidReserveProof);
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Default::default();
+        codec::write_len(&mut v, self.outputs.len());
+        for output in self.outputs.iter() {
+            v.extend(output.public_key.to_compressed());
+            v.extend(output.commitment.to_compressed());
+        }
+        v.extend(self.minimum.to_le_bytes());
+        let range_proof = self.range_proof.to_bytes();
+        codec::write_len(&mut v, range_proof.len());
+        v.extend(range_proof);
+        v.extend(self.challenge.to_bytes_le());
+        codec::write_len(&mut v, self.key_responses.len());
+        for s in self.key_responses.iter() {
+            v.extend(s.to_bytes_le());
+        }
+        v
+    }
+
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let outputs = reader.read_vec(|r| {
+            Ok(ReservedOutput {
+                public_key: r.read_point()?,
+                commitment: r.read_point()?,
+            })
+        })?;
+        let minimum = Amount::new(reader.read_u64()?);
+        let range_proof_len = reader.read_len()?;
+        let range_proof = RangeProof::from_bytes(&reader.take(range_proof_len)?)?;
+        let challenge = reader.read_scalar()?;
+        let key_responses = reader.read_vec(|r| r.read_scalar())?;
+        reader.finish()?;
+
+        Ok(Self {
+            outputs,
+            minimum,
+            range_proof,
+            challenge,
+            key_responses,
+        })
+    }
+
+    fn challenge(&self, nonce_commitments: &[G1Projective], message: &[u8]) -> Scalar {
+        let points: Vec<[u8; 48]> = self
+            .outputs
+            .iter()
+            .flat_map(|o| [o.public_key.to_compressed(), o.commitment.to_compressed()])
+            .chain(nonce_commitments.iter().map(G1Projective::to_compressed))
+            .collect();
+        let minimum = self.minimum.to_le_bytes();
+        let range_proof = self.range_proof.to_bytes();
+
+        let mut material: Vec<&[u8]> = vec![RESERVE_PROOF_DOMAIN, &minimum, &range_proof];
+        material.extend(points.iter().map(|p| p.as_slice()));
+        material.push(message);
+        hash_to_scalar(&material)
+    }
+}
+
+/// Each output may only be counted once towards the reserves
+fn check_outputs_unique(outputs: &[ReservedOutput]) -> Result<()> {
+    if outputs.is_empty() {
+        return Err(Error::InvalidReserveProof);
+    }
+    let mut seen = BTreeSet::new();
+    for output in outputs.iter() {
+        if !seen.insert(output.public_key.to_compressed()) {
+            return Err(Error::DuplicateReservedOutput(codec::to_hex(
+                &output.public_key.to_compressed(),
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The transcript of the range proof, binding it to the outputs and context
+fn reserve_transcript(outputs: &[ReservedOutput], minimum: Amount, message: &[u8]) -> Transcript {
+    let mut transcript = Transcript::new(MERLIN_TRANSCRIPT_LABEL);
+    transcript.append_message(b"dom-sep", RESERVE_PROOF_DOMAIN);
+    for output in outputs.iter() {
+        transcript.append_message(b"output_public_key", &output.public_key.to_compressed());
+        transcript.append_message(b"output_commitment", &output.commitment.to_compressed());
+    }
+    transcript.append_message(b"minimum", &minimum.to_le_bytes());
+    transcript.append_message(b"message", message);
+    transcript
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::rand::rngs::OsRng;
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::RevealedCommitment;
+
+    fn owned_outputs(amounts: &[u64]) -> (Vec<TrueInput>, BTreeMap<[u8; 48], G1Affine>) {
+        let owned: Vec<TrueInput> = amounts
+            .iter()
+            .map(|amount| {
+                TrueInput::new(
+                    Scalar::random(OsRng),
+                    RevealedCommitment::from_value(*amount, OsRng),
+                )
+            })
+            .collect();
+        let ledger = owned
+            .iter()
+            .map(|input| {
+                (
+                    input.public_key().to_affine().to_compressed(),
+                    input
+                        .revealed_commitment
+                        .commit(RingCtMaterial::pc_gens())
+                        .to_affine(),
+                )
+            })
+            .collect();
+        (owned, ledger)
+    }
+
+    #[test]
+    fn test_reserve_proof() {
+        let (owned, ledger) = owned_outputs(&[5, 20, 17]);
+        let lookup = |pk: &G1Affine| ledger.get(&pk.to_compressed()).copied();
+
+        let proof = ReserveProof::create(&owned, 40, b"audit 2022", &mut OsRng)
+            .expect("Failed to create reserve proof");
+        assert_eq!(proof.verify(&lookup, b"audit 2022"), Ok(()));
+        assert_eq!(
+            proof.verify(&lookup, b"audit 2023"),
+            Err(Error::InvalidReserveProof)
+        );
+
+        let decoded = ReserveProof::from_bytes(&proof.to_bytes()).unwrap();
+        assert_eq!(decoded.to_bytes(), proof.to_bytes());
+        assert_eq!(decoded.verify(&lookup, b"audit 2022"), Ok(()));
+
+        // claiming a higher minimum invalidates the range proof
+        let mut inflated = proof.clone();
+        inflated.minimum = Amount::new(41);
+        assert_eq!(
+            inflated.verify(&lookup, b"audit 2022"),
+            Err(Error::InvalidReserveProof)
+        );
+
+        // outputs that aren't on the ledger don't count
+        let empty_ledger = |_: &G1Affine| None;
+        assert_eq!(
+            proof.verify(&empty_ledger, b"audit 2022"),
+            Err(Error::InvalidReserveProof)
+        );
+    }
+
+    #[test]
+    fn test_reserve_proof_rejects_insufficient_or_duplicate_outputs() {
+        let (owned, _) = owned_outputs(&[5, 20]);
+        assert!(matches!(
+            ReserveProof::create(&owned, 26, b"", &mut OsRng),
+            Err(Error::InsufficientReserves {
+                total: 25,
+                minimum: 26
+            })
+        ));
+
+        let duplicated = vec![owned[1].clone(), owned[1].clone()];
+        assert!(matches!(
+            ReserveProof::create(&duplicated, 40, b"", &mut OsRng),
+            Err(Error::DuplicateReservedOutput(_))
+        ));
+    }
+}
@@ -0,0 +1,371 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Range proofs the output's recipient can rewind to read its amount.
+//!
+//! Four of the prover's nonces are drawn from the transcript rekeyed with a
+//! key shared with the recipient: a mask, which added to the value is the
+//! blinding `α` of the bit commitment `A`, `ρ`, `τ₁` and `τ₂`. The proof
+//! reveals `e_blinding = α + ρx` and `t_x_blinding = τ₂x² + τ₁x + z²γ`, so the
+//! recipient redraws them and solves for the value and the commitment's
+//! blinding factor `γ`. Without the key they're as random as any other rng's.
+//! The blindings of `s_L` and `s_R`, which rewinding doesn't need, are drawn
+//! from the caller's rng.
+//!
+//! This follows the order `bls_bulletproofs` 1.1.1 draws a single party
+//! prover's randomness in: `α`, `ρ`, the `n` blindings of `s_L` then those of
+//! `s_R`, then `τ₁` and `τ₂`. That order isn't part of its API, which is why
+//! the dependency is pinned to that exact version.
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, Scalar},
+    group::{ff::Field, Curve},
+    merlin::{Transcript, TranscriptRng},
+    rand::{CryptoRng, RngCore, SeedableRng},
+    BulletproofGens, PedersenGens, RangeProof,
+};
+use rand_chacha::{rand_core, ChaCha20Rng};
+
+use crate::{
+    range_proof_batch::{replay_bit_challenges, ParsedRangeProof},
+    Error, Result, RevealedCommitment,
+};
+
+const REWIND_KEY_LABEL: &[u8] = b"blst-ringct-rewind-key";
+
+/// Proves that `value` is in range, from the state of `transcript` a
+/// verifier will find, with the nonces that carry the value and blinding
+/// drawn so that the holder of `rewind_key` can redraw them, and the rest
+/// drawn from `rng`
+pub(crate) fn prove(
+    bp_gens: &BulletproofGens,
+    pc_gens: &PedersenGens,
+    transcript: &mut Transcript,
+    revealed_commitment: &RevealedCommitment,
+    bits: usize,
+    rewind_key: &[u8; 32],
+    rng: &mut (impl RngCore + CryptoRng),
+) -> Result<(RangeProof, G1Affine)> {
+    let nonces = RewindNonces::draw(transcript, rewind_key);
+    let mut prover_rng = RewindRng::new(
+        prover_draws(&nonces, revealed_commitment.value, bits, rng),
+        rng,
+    );
+    let proven = RangeProof::prove_single_with_rng(
+        bp_gens,
+        pc_gens,
+        transcript,
+        revealed_commitment.value,
+        &revealed_commitment.blinding,
+        bits,
+        &mut prover_rng,
+    )?;
+    // a prover drawing its randomness in another order or number would make
+    // a proof that can't be rewound
+    if !prover_rng.is_drained() {
+        return Err(Error::RangeProofRewindFailed);
+    }
+    Ok(proven)
+}
+
+/// Recovers the value and blinding factor of `commitment` from a proof made by
+/// `prove()` with `rewind_key`, `transcript` being in the state it was made from
+pub(crate) fn rewind(
+    range_proof: &RangeProof,
+    commitment: &G1Affine,
+    pc_gens: &PedersenGens,
+    transcript: &mut Transcript,
+    bits: usize,
+    rewind_key: &[u8; 32],
+) -> Result<RevealedCommitment> {
+    let nonces = RewindNonces::draw(transcript, rewind_key);
+
+    let proof = ParsedRangeProof::from_bytes(&range_proof.to_bytes())
+        .map_err(|_| Error::RangeProofRewindFailed)?;
+    let (_, z, x) = replay_bit_challenges(&proof, commitment, transcript, bits)
+        .map_err(|_| Error::RangeProofRewindFailed)?;
+
+    let value = (proof.e_blinding - nonces.s_blinding * x - nonces.mask).to_bytes_le();
+    if value[8..].iter().any(|b| *b != 0) {
+        return Err(Error::RangeProofRewindFailed);
+    }
+    let mut value_bytes = [0u8; 8];
+    value_bytes.copy_from_slice(&value[..8]);
+
+    let z_sq_inv: Scalar = Option::from((z * z).invert()).ok_or(Error::RangeProofRewindFailed)?;
+    let revealed_commitment = RevealedCommitment {
+        value: u64::from_le_bytes(value_bytes),
+        blinding: (proof.t_x_blinding - nonces.t_2_blinding * x * x - nonces.t_1_blinding * x)
+            * z_sq_inv,
+    };
+
+    if revealed_commitment.commit(pc_gens).to_affine() != *commitment {
+        return Err(Error::RangeProofRewindFailed);
+    }
+    Ok(revealed_commitment)
+}
+
+/// The prover's nonces that the holder of the rewind key can redraw
+struct RewindNonces {
+    mask: Scalar,
+    s_blinding: Scalar,
+    t_1_blinding: Scalar,
+    t_2_blinding: Scalar,
+}
+
+impl RewindNonces {
+    fn draw(transcript: &Transcript, rewind_key: &[u8; 32]) -> Self {
+        let mut rng = nonce_rng(transcript, rewind_key);
+        Self {
+            mask: Scalar::random(&mut rng),
+            s_blinding: Scalar::random(&mut rng),
+            t_1_blinding: Scalar::random(&mut rng),
+            t_2_blinding: Scalar::random(&mut rng),
+        }
+    }
+}
+
+fn nonce_rng(transcript: &Transcript, rewind_key: &[u8; 32]) -> TranscriptRng {
+    transcript
+        .build_rng()
+        .rekey_with_witness_bytes(REWIND_KEY_LABEL, rewind_key)
+        .finalize(&mut ChaCha20Rng::from_seed(*rewind_key))
+}
+
+/// The scalars a single party prover of `bits` draws, in order: `α`, `ρ`, the
+/// blindings of `s_L` and `s_R` from `rng`, `τ₁` and `τ₂`
+fn prover_draws(
+    nonces: &RewindNonces,
+    value: u64,
+    bits: usize,
+    rng: &mut (impl RngCore + CryptoRng),
+) -> Vec<Scalar> {
+    let mut draws = vec![nonces.mask + Scalar::from(value), nonces.s_blinding];
+    draws.extend((0..2 * bits).map(|_| Scalar::random(&mut *rng)));
+    draws.extend([nonces.t_1_blinding, nonces.t_2_blinding]);
+    draws
+}
+
+/// The little endian limbs `Scalar::random()` reads `scalar` back from. A
+/// scalar is canonical, so it's read back from exactly these four limbs.
+fn limbs(scalar: &Scalar) -> [u64; 4] {
+    let bytes = scalar.to_bytes_le();
+    let mut limbs = [0u64; 4];
+    for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks(8)) {
+        let mut limb_bytes = [0u8; 8];
+        limb_bytes.copy_from_slice(chunk);
+        *limb = u64::from_le_bytes(limb_bytes);
+    }
+    limbs
+}
+
+/// Serves the given scalars to the prover's scalar draws, and `rng`'s output
+/// to any draw after them
+struct RewindRng<'a, R> {
+    limbs: Vec<u64>,
+    served: usize,
+    rng: &'a mut R,
+}
+
+impl<'a, R: RngCore + CryptoRng> RewindRng<'a, R> {
+    fn new(scalars: Vec<Scalar>, rng: &'a mut R) -> Self {
+        Self {
+            limbs: scalars.iter().flat_map(limbs).collect(),
+            served: 0,
+            rng,
+        }
+    }
+
+    /// Whether the prover drew every scalar and nothing else
+    fn is_drained(&self) -> bool {
+        self.served == self.limbs.len()
+    }
+}
+
+impl<R: RngCore + CryptoRng> RngCore for RewindRng<'_, R> {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self.limbs.get(self.served) {
+            Some(limb) => {
+                self.served += 1;
+                *limb
+            }
+            None => {
+                // counts draws past the scalars, so is_drained() reports them
+                self.served += 1;
+                self.rng.next_u64()
+            }
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        rand_core::impls::fill_bytes_via_next(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> std::result::Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl<R: RngCore + CryptoRng> CryptoRng for RewindRng<'_, R> {}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::rand::rngs::OsRng;
+
+    use super::*;
+
+    fn gens() -> (BulletproofGens, PedersenGens) {
+        (BulletproofGens::new(64, 1), PedersenGens::default())
+    }
+
+    fn random_key() -> [u8; 32] {
+        let mut rewind_key = [0u8; 32];
+        OsRng.fill_bytes(&mut rewind_key);
+        rewind_key
+    }
+
+    #[test]
+    fn test_rewind_recovers_the_value_and_blinding() {
+        let (bp_gens, pc_gens) = gens();
+        let revealed_commitment = RevealedCommitment::from_value(u64::MAX - 3, &mut OsRng);
+        let rewind_key = random_key();
+
+        let transcript = Transcript::new(b"test");
+        let (proof, commitment) = prove(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript.clone(),
+            &revealed_commitment,
+            64,
+            &rewind_key,
+            &mut OsRng,
+        )
+        .unwrap();
+
+        assert!(proof
+            .verify_single(&bp_gens, &pc_gens, &mut transcript.clone(), &commitment, 64)
+            .is_ok());
+        assert_eq!(
+            rewind(
+                &proof,
+                &commitment,
+                &pc_gens,
+                &mut transcript.clone(),
+                64,
+                &rewind_key
+            ),
+            Ok(revealed_commitment)
+        );
+
+        let mut other_key = rewind_key;
+        other_key[0] ^= 1;
+        assert_eq!(
+            rewind(
+                &proof,
+                &commitment,
+                &pc_gens,
+                &mut transcript.clone(),
+                64,
+                &other_key
+            ),
+            Err(Error::RangeProofRewindFailed)
+        );
+        assert_eq!(
+            rewind(
+                &proof,
+                &commitment,
+                &pc_gens,
+                &mut Transcript::new(b"other"),
+                64,
+                &rewind_key
+            ),
+            Err(Error::RangeProofRewindFailed)
+        );
+    }
+
+    #[test]
+    fn test_nonces_rewinding_does_not_need_come_from_the_rng() {
+        let (bp_gens, pc_gens) = gens();
+        let revealed_commitment = RevealedCommitment::from_value(7, &mut OsRng);
+        let rewind_key = random_key();
+        let transcript = Transcript::new(b"test");
+
+        let proofs: Vec<_> = (0..2)
+            .map(|_| {
+                prove(
+                    &bp_gens,
+                    &pc_gens,
+                    &mut transcript.clone(),
+                    &revealed_commitment,
+                    64,
+                    &rewind_key,
+                    &mut OsRng,
+                )
+                .unwrap()
+            })
+            .collect();
+
+        // the same key and transcript, but the blindings of s_L and s_R differ
+        assert_ne!(proofs[0].0.to_bytes(), proofs[1].0.to_bytes());
+        for (proof, commitment) in &proofs {
+            assert_eq!(
+                rewind(
+                    proof,
+                    commitment,
+                    &pc_gens,
+                    &mut transcript.clone(),
+                    64,
+                    &rewind_key
+                ),
+                Ok(revealed_commitment)
+            );
+        }
+    }
+
+    // Rewinding relies on the order and number of the prover's scalar draws,
+    // which aren't part of bls_bulletproofs' API. This fails if they change.
+    #[test]
+    fn test_prover_draws_its_randomness_in_the_expected_order() {
+        let (bp_gens, pc_gens) = gens();
+        let revealed_commitment = RevealedCommitment::from_value(42, &mut OsRng);
+
+        for bits in [8, 16, 32, 64] {
+            let scalars: Vec<Scalar> = (0..2 * bits + 4)
+                .map(|_| Scalar::random(&mut OsRng))
+                .collect();
+            let transcript = Transcript::new(b"test");
+            let mut entropy = OsRng;
+            let mut rng = RewindRng::new(scalars.clone(), &mut entropy);
+            let (range_proof, commitment) = RangeProof::prove_single_with_rng(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript.clone(),
+                revealed_commitment.value,
+                &revealed_commitment.blinding,
+                bits,
+                &mut rng,
+            )
+            .unwrap();
+            assert!(rng.is_drained());
+
+            let proof = ParsedRangeProof::from_bytes(&range_proof.to_bytes()).unwrap();
+            let (_, z, x) =
+                replay_bit_challenges(&proof, &commitment, &mut transcript.clone(), bits).unwrap();
+            let (alpha, rho) = (scalars[0], scalars[1]);
+            let (t_1_blinding, t_2_blinding) = (scalars[2 * bits + 2], scalars[2 * bits + 3]);
+            assert_eq!(proof.e_blinding, alpha + rho * x);
+            assert_eq!(
+                proof.t_x_blinding,
+                t_2_blinding * x * x + t_1_blinding * x + z * z * revealed_commitment.blinding
+            );
+        }
+    }
+}
@@ -0,0 +1,213 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! A cache for nodes which see the same transactions and rings repeatedly,
+//! eg. gossiped duplicates or transactions re-verified after a reorg.
+//!
+//! It memoizes the commitments a lookup resolved for each ring, keyed by a
+//! hash of the ring's public keys, and the transactions that verified, keyed
+//! by the transaction's hash and the hash of its rings' commitments, so that
+//! re-verifying a transaction against the same rings is a few hashes. The
+//! ring entries assume the lookup's commitments don't change; `clear()` the
+//! cache if they may, eg. after a reorg removing outputs.
+
+use bls_bulletproofs::blstrs::G1Affine;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use tiny_keccak::{Hasher, Sha3};
+
+use crate::{codec, ringct::RingCtTransaction, CommitmentLookup, Error, Result};
+
+/// Memoizes ring commitments and verified transactions, keeping at most
+/// `capacity` of each and evicting the oldest first
+#[derive(Debug, Clone)]
+pub struct RingCache {
+    capacity: usize,
+    rings: BTreeMap<[u8; 32], Vec<G1Affine>>,
+    ring_order: VecDeque<[u8; 32]>,
+    verified: BTreeSet<([u8; 32], [u8; 32])>,
+    verified_order: VecDeque<([u8; 32], [u8; 32])>,
+}
+
+impl RingCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            rings: BTreeMap::new(),
+            ring_order: VecDeque::new(),
+            verified: BTreeSet::new(),
+            verified_order: VecDeque::new(),
+        }
+    }
+
+    /// The commitments of the ring members `public_keys` of the input at
+    /// `input_index`, from the cache or else from `lookup`
+    pub fn ring_commitments(
+        &mut self,
+        input_index: usize,
+        public_keys: &[G1Affine],
+        lookup: &impl CommitmentLookup,
+    ) -> Result<Vec<G1Affine>> {
+        let key = hash_points(public_keys);
+        if let Some(commitments) = self.rings.get(&key) {
+            return Ok(commitments.clone());
+        }
+
+        let commitments = public_keys
+            .iter()
+            .map(|pk| {
+                lookup
+                    .commitment(pk)
+                    .ok_or_else(|| Error::CommitmentNotFound {
+                        input_index,
+                        public_key: codec::to_hex(&pk.to_compressed()),
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        insert_bounded(&mut self.ring_order, self.capacity, key, |evicted| {
+            self.rings.remove(evicted);
+        });
+        self.rings.insert(key, commitments.clone());
+        Ok(commitments)
+    }
+
+    /// Verifies `tx` like `RingCtTransaction::verify_with_lookup()`, skipping
+    /// the verification if it already verified against the same commitments
+    pub fn verify(&mut self, tx: &RingCtTransaction, lookup: &impl CommitmentLookup) -> Result<()> {
+        let public_commitments_per_ring = tx
+            .public_keys_per_ring()
+            .iter()
+            .enumerate()
+            .map(|(input_index, public_keys)| {
+                self.ring_commitments(input_index, public_keys, lookup)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let key = (
+            tx.hash(),
+            hash_points(&public_commitments_per_ring.concat()),
+        );
+        if self.verified.contains(&key) {
+            return Ok(());
+        }
+
+        tx.verify(&public_commitments_per_ring)?;
+        insert_bounded(&mut self.verified_order, self.capacity, key, |evicted| {
+            self.verified.remove(evicted);
+        });
+        self.verified.insert(key);
+        Ok(())
+    }
+
+    /// Forgets every ring and verified transaction
+    pub fn clear(&mut self) {
+        self.rings.clear();
+        self.ring_order.clear();
+        self.verified.clear();
+        self.verified_order.clear();
+    }
+
+    /// The number of rings cached
+    pub fn len(&self) -> usize {
+        self.rings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rings.is_empty() && self.verified.is_empty()
+    }
+}
+
+/// Records `key` as the newest of `order`, evicting the oldest keys beyond
+/// `capacity` with `evict`
+fn insert_bounded<K: Copy>(
+    order: &mut VecDeque<K>,
+    capacity: usize,
+    key: K,
+    mut evict: impl FnMut(&K),
+) {
+    order.push_back(key);
+    while order.len() > capacity {
+        if let Some(evicted) = order.pop_front() {
+            evict(&evicted);
+        }
+    }
+}
+
+fn hash_points(points: &[G1Affine]) -> [u8; 32] {
+    let mut sha3 = Sha3::v256();
+    for point in points {
+        sha3.update(&point.to_compressed());
+    }
+    let mut hash = [0; 32];
+    sha3.finalize(&mut hash);
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{
+        blstrs::{G1Projective, Scalar},
+        group::{ff::Field, Group},
+        rand::rngs::OsRng,
+    };
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::{
+        mock::MockLedger, ringct::Amount, MlsagMaterial, Output, RevealedCommitment,
+        RingCtMaterial, TrueInput,
+    };
+
+    #[test]
+    fn test_ring_cache_skips_repeated_verification() {
+        let mut ledger = MockLedger::new();
+        ledger.fill(4, 0);
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(3, OsRng),
+        );
+        ledger.log(
+            true_input.public_key(),
+            true_input
+                .revealed_commitment
+                .commit(RingCtMaterial::pc_gens()),
+        );
+        let decoy_inputs = ledger.fetch_decoys(3, &[true_input.public_key()]);
+        let material = RingCtMaterial {
+            inputs: vec![MlsagMaterial::new(true_input, decoy_inputs, &mut OsRng).unwrap()],
+            outputs: vec![Output::new(G1Projective::random(OsRng), 3)],
+            fee: Amount::ZERO,
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+        let (tx, _revealed_output_commitments) = material
+            .sign(&mut OsRng)
+            .expect("Failed to sign transaction");
+
+        let lookups = Cell::new(0);
+        let lookup = |pk: &G1Affine| {
+            lookups.set(lookups.get() + 1);
+            ledger.lookup(*pk)
+        };
+
+        let mut cache = RingCache::new(8);
+        assert_eq!(cache.verify(&tx, &lookup), Ok(()));
+        assert_eq!(lookups.get(), 4);
+        assert_eq!(cache.verify(&tx, &lookup), Ok(()));
+        assert_eq!(lookups.get(), 4);
+        assert_eq!(cache.len(), 1);
+
+        let mut tampered = tx;
+        tampered.fee = Amount::new(1);
+        assert!(cache.verify(&tampered, &lookup).is_err());
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert!(cache
+            .ring_commitments(0, &[G1Projective::random(OsRng).into()], &lookup)
+            .is_err());
+    }
+}
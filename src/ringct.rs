@@ -7,42 +7,488 @@
 use bls_bulletproofs::{
     blstrs::{G1Affine, G1Projective, Scalar},
     group::ff::Field,
+    group::prime::PrimeCurveAffine,
     group::Curve,
     group::GroupEncoding,
     merlin::Transcript,
-    rand::{CryptoRng, RngCore},
+    rand::{rngs::OsRng, CryptoRng, RngCore},
     BulletproofGens, PedersenGens, RangeProof,
 };
-use std::{cmp::Ordering, collections::BTreeSet};
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::BTreeSet,
+    fmt,
+    io::{Read, Write},
+    sync::OnceLock,
+};
 use tiny_keccak::{Hasher, Sha3};
 
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
 
-use crate::{Error, MlsagMaterial, MlsagSignature, Result, RevealedCommitment};
-pub(crate) const RANGE_PROOF_BITS: usize = 64; // note: Range Proof max-bits is 64. allowed are: 8, 16, 32, 64 (only)
+use crate::{
+    amount_secrets::ENCRYPTED_AMOUNT_LEN,
+    backend::{CpuBackend, VerificationBackend},
+    burn::burn_public_key,
+    codec::{self, DigestWriter, MessageDigest, Reader},
+    instrument::{self, Phase},
+    range_proof_batch::RangeProofBatch,
+    rewind, stealth,
+    tx_policy::{DefaultPolicy, TxPolicy},
+    AmountSecrets, BlindingFactor, ClsagSignature, Commitment, EncryptedAmount, Error, KeyImage,
+    KeyImageStore, MatrixMlsagSignature, MlsagMaterial, MlsagSignature, OneTimeKey, PublicKey,
+    Result, RevealedCommitment, StealthAddress,
+};
+pub(crate) const RANGE_PROOF_BITS: usize = 64; // note: Range Proof max-bits is 64. allowed are: 8, 16, 32, 64 (only), see version_with_range_proof_bits()
                                                //       This limits our amount field to 64 bits also.
 pub(crate) const RANGE_PROOF_PARTIES: usize = 1; // The maximum number of parties that can produce an aggregated proof
 pub(crate) const MERLIN_TRANSCRIPT_LABEL: &[u8] = b"BLST_RINGCT";
+const TXID_DOMAIN: &[u8] = b"blst-ringct-txid";
+
+/// The largest extra data, in bytes, of an output or a transaction
+pub const MAX_EXTRA_LEN: usize = 1024;
+
+/// The largest encoding, in bytes, read by the `read_from()` methods
+pub const MAX_STREAMED_LEN: usize = 16 * 1024 * 1024;
+
+/// Transaction format with one MLSAG per input and one range proof per output.
+pub const TX_VERSION_1: u16 = 1;
+/// Transaction format with one MLSAG per input and a single aggregated range proof
+/// covering all outputs.
+pub const TX_VERSION_2: u16 = 2;
+/// Transaction format with one CLSAG per input and one range proof per output.
+pub const TX_VERSION_3: u16 = 3;
+/// Transaction format with a single matrix MLSAG signing all inputs and one
+/// range proof per output. The inputs' rings must be aligned with
+/// `RingCtMaterial::align_rings()` before signing.
+pub const TX_VERSION_4: u16 = 4;
+/// Transaction format minting the initial supply, with no inputs and one range
+/// proof per output. Only valid under `RingCtTransaction::verify_genesis()`.
+pub const TX_VERSION_GENESIS: u16 = 0;
+/// Set in the version of every transaction whose key images are based on
+/// the RFC 9380 `hash_to_curve()`. Earlier transactions used an ad-hoc
+/// hash, so their key images can't be compared with these, and versions
+/// without it are rejected. Signing always sets it.
+pub const TX_RFC9380_KEY_IMAGES: u16 = 1 << 15;
+/// Set in the version of every transaction whose commitments use the
+/// crate's `generators::pedersen_gens()` rather than `PedersenGens::default()`.
+/// Versions without it are rejected. Signing always sets it.
+pub const TX_DOMAIN_SEPARATED_GENERATORS: u16 = 1 << 14;
+/// The flags every supported transaction version has set
+pub const TX_FLAGS: u16 = TX_RFC9380_KEY_IMAGES | TX_DOMAIN_SEPARATED_GENERATORS;
+/// Set in the version of transactions bound to a network by their
+/// `network_id`, which is then encoded after the fee. Signing sets it when
+/// `RingCtMaterial::network_id` is set.
+pub const TX_NETWORK_ID: u16 = 1 << 13;
+/// Set in the version of transactions with a `not_valid_after` height, which
+/// is then encoded after the network id. Signing sets it when
+/// `RingCtMaterial::not_valid_after` is set.
+pub const TX_EXPIRY: u16 = 1 << 12;
+/// Set in the version of transactions whose per-output range proofs each
+/// have their own transcript, keyed by the output's index, rather than
+/// sharing one in order, so that they can be proven and verified
+/// concurrently. Signing sets it for every format with per-output range
+/// proofs, and versions of `TX_VERSION_2`, whose single aggregated proof has
+/// no per-output transcripts, are rejected with it.
+pub const TX_INDEPENDENT_RANGE_PROOFS: u16 = 1 << 11;
+/// The transaction format produced by `RingCtMaterial::sign()`.
+pub const CURRENT_TX_VERSION: u16 = TX_VERSION_1 | TX_FLAGS | TX_INDEPENDENT_RANGE_PROOFS;
+
+// A version's low byte is its format, the high byte its range proof bit length
+// and flags
+const TX_OPTIONAL_FLAGS: u16 = TX_NETWORK_ID | TX_EXPIRY | TX_INDEPENDENT_RANGE_PROOFS;
+const TX_FORMAT_MASK: u16 = 0xff;
+const RANGE_PROOF_BITS_SHIFT: u16 = 8;
+
+/// The version of format `version` (eg. `TX_VERSION_1`) with range proofs of
+/// `bits` bits, one of 8, 16, 32 or 64, limiting every output amount to below
+/// 2^bits. Smaller proofs are smaller and faster to verify. With 64 bits the
+/// version is the format itself.
+pub fn version_with_range_proof_bits(version: u16, bits: usize) -> Result<u16> {
+    let code = match bits {
+        64 => 0,
+        32 => 1,
+        16 => 2,
+        8 => 3,
+        _ => return Err(Error::UnsupportedRangeProofBits(bits)),
+    };
+    Ok(tx_format(version)
+        | (version & (TX_FLAGS | TX_OPTIONAL_FLAGS))
+        | code << RANGE_PROOF_BITS_SHIFT)
+}
+
+/// The format of `version`, regardless of its range proof bit length
+pub fn tx_format(version: u16) -> u16 {
+    version & TX_FORMAT_MASK
+}
+
+/// The range proof bit length of transactions of `version`
+pub fn range_proof_bits(version: u16) -> Result<usize> {
+    match (version & !(TX_FLAGS | TX_OPTIONAL_FLAGS)) >> RANGE_PROOF_BITS_SHIFT {
+        0 => Ok(64),
+        1 => Ok(32),
+        2 => Ok(16),
+        3 => Ok(8),
+        _ => Err(Error::UnsupportedTransactionVersion(version)),
+    }
+}
+
+/// Represents a Dbc's value. Arithmetic is checked, so that an overflowing
+/// sum is an error rather than a wrapped, unbalanced amount.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Amount(u64);
+
+impl Amount {
+    pub const ZERO: Self = Self(0);
+    pub const MAX: Self = Self(u64::MAX);
+
+    pub const fn new(value: u64) -> Self {
+        Self(value)
+    }
+
+    pub const fn value(self) -> u64 {
+        self.0
+    }
+
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        self.0.checked_add(other.0).map(Self)
+    }
+
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.0.checked_sub(other.0).map(Self)
+    }
+
+    /// Sums `amounts`, failing with `Error::AmountOverflow` if the sum doesn't fit
+    pub fn checked_sum(amounts: impl IntoIterator<Item = Self>) -> Result<Self> {
+        amounts
+            .into_iter()
+            .try_fold(Self::ZERO, Self::checked_add)
+            .ok_or(Error::AmountOverflow)
+    }
+
+    pub fn to_le_bytes(self) -> [u8; 8] {
+        self.0.to_le_bytes()
+    }
+}
+
+impl From<u64> for Amount {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Amount> for u64 {
+    fn from(amount: Amount) -> Self {
+        amount.0
+    }
+}
+
+impl PartialEq<u64> for Amount {
+    fn eq(&self, other: &u64) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// The number of decoys per input used by `RingCtConfig::default()`, giving rings of 11.
+pub const DEFAULT_DECOYS_PER_INPUT: usize = 10;
+
+/// Network policy for the shape of transactions, so that every input
+/// has the same anonymity set.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RingCtConfig {
+    /// The exact number of decoys each input's ring must contain
+    pub decoys_per_input: usize,
+}
+
+impl Default for RingCtConfig {
+    fn default() -> Self {
+        Self {
+            decoys_per_input: DEFAULT_DECOYS_PER_INPUT,
+        }
+    }
+}
+
+impl RingCtConfig {
+    pub fn ring_size(&self) -> usize {
+        self.decoys_per_input + 1 // + 1 for the true input
+    }
+
+    pub(crate) fn check_ring_size(&self, input_index: usize, ring_size: usize) -> Result<()> {
+        if ring_size != self.ring_size() {
+            return Err(Error::InvalidRingSize {
+                input_index,
+                expected: self.ring_size(),
+                actual: ring_size,
+            });
+        }
+        Ok(())
+    }
+}
+
+pub const DEFAULT_MAX_INPUTS: usize = 64;
+pub const DEFAULT_MAX_OUTPUTS: usize = 16;
+
+// Aggregated proofs of up to the default maximum number of outputs use cached generators
+const CACHED_BP_GENS_PARTIES: usize = DEFAULT_MAX_OUTPUTS.next_power_of_two();
+pub const DEFAULT_MAX_DECOYS: usize = 64;
+
+/// Caps on the lengths declared in encodings of transactions, checked as
+/// they're decoded so that a short message declaring a huge vector fails
+/// before anything is allocated or looped over. Serde formats don't check
+/// these, so bound the size of their input instead, eg. with bincode's
+/// `with_limit()`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    pub max_inputs: usize,
+    pub max_outputs: usize,
+    /// The most members, true input included, of any ring
+    pub max_ring_size: usize,
+    /// The longest extra data, at most `MAX_EXTRA_LEN`
+    pub max_extra_len: usize,
+    /// The longest encoding, in bytes
+    pub max_encoded_len: usize,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self::from_policy(&RingCtPolicy::default())
+    }
+}
+
+impl DecodeLimits {
+    /// The limits of transactions `policy` accepts, so that nothing is
+    /// decoded which verification would reject
+    pub fn from_policy(policy: &RingCtPolicy) -> Self {
+        Self {
+            max_inputs: policy.max_inputs,
+            max_outputs: policy.max_outputs,
+            max_ring_size: policy.max_decoys.saturating_add(1),
+            max_extra_len: MAX_EXTRA_LEN,
+            max_encoded_len: MAX_STREAMED_LEN,
+        }
+    }
+}
+
+/// Resource limits on the shape of transactions, bounding the work of verifying
+/// one so that oversized transactions can't be used to DoS verifiers.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RingCtPolicy {
+    pub max_inputs: usize,
+    pub max_outputs: usize,
+    /// The most decoys any input's ring may contain
+    pub max_decoys: usize,
+    /// The least amount an output may hold, eg. `Amount::new(1)` to reject
+    /// zero-amount outputs. Amounts are hidden from verifiers, so this is
+    /// checked by the builder and by `RingCtTransaction::check_dust()`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub min_output_amount: Amount,
+}
+
+impl Default for RingCtPolicy {
+    fn default() -> Self {
+        Self {
+            max_inputs: DEFAULT_MAX_INPUTS,
+            max_outputs: DEFAULT_MAX_OUTPUTS,
+            max_decoys: DEFAULT_MAX_DECOYS,
+            min_output_amount: Amount::ZERO,
+        }
+    }
+}
+
+/// How `RingCtTransaction::verify_with_opts()` verifies range proofs
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeProofVerification {
+    /// As the transaction's version lays them out: one verification per
+    /// output, or a single one of an aggregated proof
+    AsProven,
+    /// Like `AsProven`, except that the range proofs made separately for
+    /// each output are checked together, their checks summed with random
+    /// weights into one multi-scalar multiplication. If the batch fails each
+    /// proof is verified on its own, to report the failing output.
+    Batched,
+    /// Only accept an aggregated range proof, as made for `TX_VERSION_2`,
+    /// proven over every output at once
+    RequireAggregated,
+}
+
+/// Options for `RingCtTransaction::verify_with_opts()`
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifyOpts {
+    pub policy: RingCtPolicy,
+    pub range_proofs: RangeProofVerification,
+    /// The network transactions must be bound to, see
+    /// `RingCtTransaction::check_network()`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub network_id: Option<[u8; 32]>,
+}
+
+impl Default for VerifyOpts {
+    fn default() -> Self {
+        Self {
+            policy: RingCtPolicy::default(),
+            range_proofs: RangeProofVerification::Batched,
+            network_id: None,
+        }
+    }
+}
+
+impl RingCtPolicy {
+    pub(crate) fn check(
+        &self,
+        num_outputs: usize,
+        ring_sizes: impl ExactSizeIterator<Item = usize>,
+    ) -> Result<()> {
+        if ring_sizes.len() > self.max_inputs {
+            return Err(Error::TooManyInputs {
+                max: self.max_inputs,
+                actual: ring_sizes.len(),
+            });
+        }
+        if num_outputs > self.max_outputs {
+            return Err(Error::TooManyOutputs {
+                max: self.max_outputs,
+                actual: num_outputs,
+            });
+        }
+        for (input_index, ring_size) in ring_sizes.enumerate() {
+            let decoys = ring_size.saturating_sub(1); // - 1 for the true input
+            if decoys > self.max_decoys {
+                return Err(Error::TooManyDecoys {
+                    input_index,
+                    max: self.max_decoys,
+                    actual: decoys,
+                });
+            }
+        }
+        Ok(())
+    }
 
-/// Represents a Dbc's value.
-pub type Amount = u64;
+    pub(crate) fn check_output_amounts(
+        &self,
+        amounts: impl IntoIterator<Item = Amount>,
+    ) -> Result<()> {
+        for (output_index, amount) in amounts.into_iter().enumerate() {
+            if amount < self.min_output_amount {
+                return Err(Error::DustOutput {
+                    output_index,
+                    amount: amount.value(),
+                    min: self.min_output_amount.value(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone)]
 pub struct Output {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub public_key: G1Affine,
     pub amount: Amount,
+    /// Set for outputs paid to a stealth address, lets the recipient
+    /// recognise the one-time `public_key` as theirs.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub ephemeral_public_key: Option<G1Affine>,
+    pub view_tag: Option<u8>,
+    /// The stealth address's view key, which the amount secrets are encrypted
+    /// to so that the view key alone can read the amount. Not part of the
+    /// transaction.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub view_public_key: Option<G1Affine>,
+    /// Application data, eg. a payment id or an encrypted note, signed with
+    /// the transaction. At most `MAX_EXTRA_LEN` bytes.
+    pub extra: Vec<u8>,
+    /// The block height or timestamp, as the ledger counts time, before which
+    /// the output can't be spent. Signed with the transaction.
+    pub lock: Option<u64>,
+    /// The blinding factor to commit to the amount with, eg. one derived
+    /// from a wallet seed, drawn from the rng when unset. Either every output
+    /// of a transaction has one or none does. Not part of the transaction.
+    pub blinding: Option<Scalar>,
+    /// Set for outputs paid to a stealth address, see `OneTimeKey::rewind_key`.
+    /// With versions that have `TX_INDEPENDENT_RANGE_PROOFS` the amount is
+    /// then read by rewinding the range proof instead of being encrypted
+    /// alongside it. Not part of the transaction.
+    pub rewind_key: Option<[u8; 32]>,
 }
 
 impl Output {
-    pub fn new<G: Into<G1Affine>>(public_key: G, amount: Amount) -> Self {
+    pub fn new<G: Into<PublicKey>, A: Into<Amount>>(public_key: G, amount: A) -> Self {
+        Self {
+            public_key: public_key.into().0,
+            amount: amount.into(),
+            ephemeral_public_key: None,
+            view_tag: None,
+            view_public_key: None,
+            extra: vec![],
+            lock: None,
+            blinding: None,
+            rewind_key: None,
+        }
+    }
+
+    /// An output to a fresh one-time public key derived from `address`
+    pub fn to_stealth_address(
+        address: &StealthAddress,
+        amount: impl Into<Amount>,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Self {
+        Self::to_one_time_key(&address.derive_one_time_key(rng), amount)
+    }
+
+    /// An output to a one-time key already derived from a stealth address
+    pub fn to_one_time_key(one_time_key: &OneTimeKey, amount: impl Into<Amount>) -> Self {
         Self {
-            public_key: public_key.into(),
-            amount,
+            public_key: one_time_key.public_key,
+            amount: amount.into(),
+            ephemeral_public_key: Some(one_time_key.ephemeral_public_key),
+            view_tag: Some(one_time_key.view_tag),
+            view_public_key: Some(one_time_key.view_public_key),
+            extra: vec![],
+            lock: None,
+            blinding: None,
+            rewind_key: Some(one_time_key.rewind_key),
         }
     }
 
+    /// The output with `extra` attached, see `Output::extra`
+    pub fn with_extra(mut self, extra: impl Into<Vec<u8>>) -> Self {
+        self.extra = extra.into();
+        self
+    }
+
+    /// The output, locked until `lock`, see `Output::lock`
+    pub fn with_lock(mut self, lock: u64) -> Self {
+        self.lock = Some(lock);
+        self
+    }
+
+    /// The output, committed to with `blinding`, see `Output::blinding`
+    pub fn with_blinding(mut self, blinding: impl Into<BlindingFactor>) -> Self {
+        self.blinding = Some(blinding.into().0);
+        self
+    }
+
     pub fn public_key(&self) -> G1Affine {
         self.public_key
     }
@@ -51,38 +497,206 @@ impl Output {
         self.amount
     }
 
-    /// Generate a commitment to the input amount
+    /// Generate a commitment to the output amount, with the output's
+    /// blinding factor if it has one
     pub fn random_commitment(&self, rng: impl RngCore) -> RevealedCommitment {
-        RevealedCommitment::from_value(self.amount, rng)
+        match self.blinding {
+            Some(blinding) => RevealedCommitment::new(self.amount.value(), blinding),
+            None => RevealedCommitment::from_value(self.amount.value(), rng),
+        }
+    }
+}
+
+/// Resolves the commitment of a ring member from its public key, eg. from a ledger
+pub trait CommitmentLookup {
+    fn commitment(&self, public_key: &G1Affine) -> Option<G1Affine>;
+}
+
+impl<F: Fn(&G1Affine) -> Option<G1Affine>> CommitmentLookup for F {
+    fn commitment(&self, public_key: &G1Affine) -> Option<G1Affine> {
+        self(public_key)
+    }
+}
+
+/// Resolves the lock of a ring member from its public key, eg. from a ledger,
+/// `None` for outputs that aren't locked
+pub trait LockLookup {
+    fn lock(&self, public_key: &G1Affine) -> Option<u64>;
+}
+
+impl<F: Fn(&G1Affine) -> Option<u64>> LockLookup for F {
+    fn lock(&self, public_key: &G1Affine) -> Option<u64> {
+        self(public_key)
     }
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone)]
 struct RevealedOutputCommitment {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
     pub public_key: G1Affine,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub ephemeral_public_key: Option<G1Affine>,
+    pub view_tag: Option<u8>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub view_public_key: Option<G1Affine>,
+    pub extra: Vec<u8>,
+    pub lock: Option<u64>,
+    pub rewind_key: Option<[u8; 32]>,
     pub revealed_commitment: RevealedCommitment,
 }
 
+impl RevealedOutputCommitment {
+    fn encrypt_amount(&self, rng: impl RngCore) -> EncryptedAmount {
+        let recipient = self.view_public_key.unwrap_or(self.public_key);
+        AmountSecrets::from(self.revealed_commitment).encrypt_to(&recipient, rng)
+    }
+}
+
+pub(crate) struct PreparedTransaction {
+    pub(crate) revealed_pseudo_commitments: Vec<RevealedCommitment>,
+    pub(crate) revealed_output_commitments: Vec<RevealedCommitment>,
+    pub(crate) output_proofs: Vec<OutputProof>,
+    pub(crate) aggregated_range_proof: Option<RangeProof>,
+    pub(crate) msg: Vec<u8>,
+}
+
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, Default)]
 pub struct RingCtMaterial {
     pub inputs: Vec<MlsagMaterial>,
     pub outputs: Vec<Output>,
+    /// A transparent fee, committed to with a zero blinding factor
+    pub fee: Amount,
+    /// Application data signed with the transaction, at most `MAX_EXTRA_LEN` bytes
+    pub extra: Vec<u8>,
+    /// The network the transaction is signed for, eg. its genesis hash or
+    /// chain id, so that it can't be replayed on another network
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub network_id: Option<[u8; 32]>,
+    /// The last height at which the transaction may be included, so that an
+    /// unconfirmed transaction can be left to expire rather than cancelled
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub not_valid_after: Option<u64>,
 }
 
 impl RingCtMaterial {
     pub fn sign(
         &self,
-        mut rng: impl RngCore + CryptoRng,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<(RingCtTransaction, Vec<RevealedCommitment>)> {
+        self.sign_with_version(CURRENT_TX_VERSION, rng)
+    }
+
+    /// Signs using the given transaction format, eg. `TX_VERSION_2` to produce
+    /// a single aggregated range proof for all outputs, `TX_VERSION_3` to
+    /// sign inputs with CLSAGs or `TX_VERSION_4` to sign them with one matrix MLSAG.
+    /// See `version_with_range_proof_bits()` for range proofs of fewer bits.
+    pub fn sign_with_version(
+        &self,
+        version: u16,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<(RingCtTransaction, Vec<RevealedCommitment>)> {
+        let result = Phase::new("sign").in_scope(|| self.sign_phases(version, rng));
+        instrument::record_signing(&result);
+        result
+    }
+
+    fn sign_phases(
+        &self,
+        version: u16,
+        rng: &mut (impl RngCore + CryptoRng),
     ) -> Result<(RingCtTransaction, Vec<RevealedCommitment>)> {
+        let version = version
+            | TX_FLAGS
+            | range_proof_flags(version)
+            | field_flags(self.network_id.as_ref(), self.not_valid_after);
+        #[cfg_attr(not(feature = "zeroize"), allow(unused_mut))]
+        let mut prepared = self.prepare(version, &mut *rng)?;
+        let msg = &prepared.msg;
+
+        // We create a ring signature for each input, concurrently with the
+        // `parallel` feature, each with an rng seeded from `rng`
+        let signing_inputs: Vec<_> = self
+            .inputs
+            .iter()
+            .zip(prepared.revealed_pseudo_commitments.iter())
+            .map(|(m, r)| {
+                let mut seed = [0u8; 32];
+                rng.fill_bytes(&mut seed);
+                (m, r, seed)
+            })
+            .collect();
+        let ring_signatures = Phase::new("ring_signatures");
+        let (mlsags, clsags): (Vec<MlsagSignature>, Vec<ClsagSignature>) = ring_signatures
+            .in_scope(|| match tx_format(version) {
+                TX_VERSION_3 => (
+                    vec![],
+                    map_indexed(&signing_inputs, |_, (m, r, seed)| {
+                        m.sign_clsag(msg, r, Self::pc_gens(), &mut ChaCha20Rng::from_seed(*seed))
+                    }),
+                ),
+                TX_VERSION_4 => (vec![], vec![]),
+                _ => (
+                    map_indexed(&signing_inputs, |_, (m, r, seed)| {
+                        m.sign(msg, r, Self::pc_gens(), &mut ChaCha20Rng::from_seed(*seed))
+                    }),
+                    vec![],
+                ),
+            });
+        let matrix_mlsag = match tx_format(version) {
+            TX_VERSION_4 => Some(ring_signatures.in_scope(|| {
+                MatrixMlsagSignature::sign(
+                    &self.inputs,
+                    msg,
+                    &prepared.revealed_pseudo_commitments,
+                    Self::pc_gens(),
+                    rng,
+                )
+            })?),
+            _ => None,
+        };
+
+        #[cfg(feature = "zeroize")]
+        prepared.revealed_pseudo_commitments.zeroize();
+
+        Ok((
+            RingCtTransaction {
+                version,
+                mlsags,
+                clsags,
+                matrix_mlsag,
+                outputs: prepared.output_proofs,
+                fee: self.fee,
+                extra: self.extra.clone(),
+                network_id: self.network_id,
+                not_valid_after: self.not_valid_after,
+                aggregated_range_proof: prepared.aggregated_range_proof,
+            },
+            prepared.revealed_output_commitments,
+        ))
+    }
+
+    /// Generates everything but the ring signatures: the pseudo commitments,
+    /// the output proofs and the message the ring signatures sign.
+    pub(crate) fn prepare(
+        &self,
+        version: u16,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<PreparedTransaction> {
+        self.validate()?;
+        if tx_format(version) == TX_VERSION_4 {
+            self.check_rings_aligned()?;
+        }
+
         // We need to gather a bunch of things for our message to sign.
         //   All public keys in all (input) rings
         //   All key-images,
         //   All PseudoCommitments
+        //   The fee and extra data
         //   All output public keys.
         //   All output commitments
-        //   All output range proofs
+        //   All output range proofs (or the aggregated range proof)
         //
         //   notes:
         //     1. the real pk is randomly mixed with decoys by MlsagMaterial
@@ -92,26 +706,84 @@ impl RingCtMaterial {
         //        to make RingCtMaterial deterministic by instantiating with a seed.
         let revealed_pseudo_commitments = self.revealed_pseudo_commitments(&mut rng);
         let pseudo_commitments = self.pseudo_commitments(&revealed_pseudo_commitments);
-        let revealed_output_commitments =
-            self.revealed_output_commitments(&revealed_pseudo_commitments, &mut rng);
-        let output_proofs = self.output_range_proofs(&revealed_output_commitments, &mut rng)?;
+        let (revealed_output_commitments, output_proofs, aggregated_range_proof) = self
+            .prove_outputs(
+                version,
+                &self.key_images(),
+                &revealed_pseudo_commitments,
+                &mut rng,
+            )?;
 
         // Generate message to sign.
         // note: must match message generated by RingCtTransaction::verify()
         let msg = gen_message_for_signing(
+            version,
+            self.fee,
+            self.network_id.as_ref(),
+            self.not_valid_after,
+            &self.extra,
             &self.public_keys(),
             &self.key_images(),
             &pseudo_commitments,
             &output_proofs,
+            aggregated_range_proof.as_ref(),
         );
 
-        // We create a ring signature for each input
-        let mlsags: Vec<MlsagSignature> = self
-            .inputs
-            .iter()
-            .zip(revealed_pseudo_commitments.iter())
-            .map(|(m, r)| m.sign(&msg, r, &Self::pc_gens()))
-            .collect();
+        Ok(PreparedTransaction {
+            revealed_pseudo_commitments,
+            revealed_output_commitments,
+            output_proofs,
+            aggregated_range_proof,
+            msg,
+        })
+    }
+
+    /// Blinds the outputs so they balance the pseudo commitments and generates
+    /// their range proofs in the layout required by `version`. Only the outputs
+    /// are read from `self`.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn prove_outputs(
+        &self,
+        version: u16,
+        key_images: &[KeyImage],
+        revealed_pseudo_commitments: &[RevealedCommitment],
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<(
+        Vec<RevealedCommitment>,
+        Vec<OutputProof>,
+        Option<RangeProof>,
+    )> {
+        let bits = range_proof_bits(version)?;
+        for (output_index, output) in self.outputs.iter().enumerate() {
+            if bits < 64 && output.amount.value() >> bits != 0 {
+                return Err(Error::AmountExceedsRangeProofBits { output_index, bits });
+            }
+        }
+
+        let revealed_output_commitments =
+            self.revealed_output_commitments(revealed_pseudo_commitments, &mut rng)?;
+        let (output_proofs, aggregated_range_proof) =
+            Phase::new("range_proofs").in_scope(|| match tx_format(version) {
+                TX_VERSION_1 | TX_VERSION_3 | TX_VERSION_4 | TX_VERSION_GENESIS => Ok((
+                    self.output_range_proofs(
+                        version,
+                        key_images,
+                        &revealed_output_commitments,
+                        &mut rng,
+                    )?,
+                    None,
+                )),
+                TX_VERSION_2 => {
+                    let (output_proofs, range_proof) = self.aggregated_output_range_proof(
+                        version,
+                        key_images,
+                        &revealed_output_commitments,
+                        &mut rng,
+                    )?;
+                    Ok((output_proofs, Some(range_proof)))
+                }
+                _ => Err(Error::UnsupportedTransactionVersion(version)),
+            })?;
 
         let revealed_output_commitments = revealed_output_commitments
             .iter()
@@ -119,47 +791,167 @@ impl RingCtMaterial {
             .collect::<Vec<_>>();
 
         Ok((
-            RingCtTransaction {
-                mlsags,
-                outputs: output_proofs,
-            },
             revealed_output_commitments,
+            output_proofs,
+            aggregated_range_proof,
         ))
     }
 
-    fn bp_gens() -> BulletproofGens {
-        BulletproofGens::new(RANGE_PROOF_BITS, RANGE_PROOF_PARTIES)
+    /// Moves the true input of every input to the ring position of the first
+    /// input's, as signing with `TX_VERSION_4` requires. The rings must all have
+    /// the same size. This changes the order of the rings, so their commitments
+    /// must be gathered afterwards.
+    pub fn align_rings(&mut self) -> Result<()> {
+        let pi_base = match self.inputs.first() {
+            Some(first) => first.pi_base,
+            None => return Ok(()),
+        };
+        for input in self.inputs.iter_mut() {
+            input.pi_base = pi_base;
+        }
+        self.check_rings_aligned()
+    }
+
+    fn check_rings_aligned(&self) -> Result<()> {
+        let first = match self.inputs.first() {
+            Some(first) => first,
+            None => return Ok(()),
+        };
+        for (input_index, input) in self.inputs.iter().enumerate() {
+            if input.count_inputs() != first.count_inputs() {
+                return Err(Error::InvalidRingSize {
+                    input_index,
+                    expected: first.count_inputs(),
+                    actual: input.count_inputs(),
+                });
+            }
+            if input.pi() != first.pi() {
+                return Err(Error::TrueInputsNotAligned { input_index });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that there is at least one input and one output, and that the
+    /// input amounts match the output amounts plus the fee, as otherwise signing
+    /// would produce a transaction that can never verify.
+    pub fn validate(&self) -> Result<()> {
+        if self.inputs.is_empty() {
+            return Err(Error::TransactionMustHaveAnInput);
+        }
+        if self.outputs.is_empty() {
+            return Err(Error::NoOutputs);
+        }
+        check_extra(&self.extra, &self.outputs)?;
+        // Set blindings are balanced through the last pseudo commitment, which
+        // only works if none is left for the last output to balance instead
+        if self.outputs.iter().any(|o| o.blinding.is_some()) {
+            if let Some(output_index) = self.outputs.iter().position(|o| o.blinding.is_none()) {
+                return Err(Error::MissingOutputBlinding { output_index });
+            }
+        }
+        let burn_public_key = burn_public_key();
+        if let Some(input_index) = self
+            .inputs
+            .iter()
+            .position(|m| m.public_keys().contains(&burn_public_key))
+        {
+            return Err(Error::BurnOutputInRing { input_index });
+        }
+
+        let input_amount = Amount::checked_sum(
+            self.inputs
+                .iter()
+                .map(|m| Amount::new(m.true_input.revealed_commitment.value)),
+        )?;
+        let output_amount = Amount::checked_sum(
+            self.outputs
+                .iter()
+                .map(Output::amount)
+                .chain(std::iter::once(self.fee)),
+        )?;
+        if input_amount != output_amount {
+            return Err(Error::InvalidBalance {
+                input_amount: input_amount.value(),
+                output_amount: output_amount.value(),
+            });
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn bp_gens() -> Cow<'static, BulletproofGens> {
+        Self::aggregated_bp_gens(RANGE_PROOF_PARTIES)
+    }
+
+    /// Generators for an aggregated proof over `parties` outputs. Generating
+    /// them is expensive, so they are built once for up to
+    /// CACHED_BP_GENS_PARTIES parties and shared by every proof and verification.
+    pub(crate) fn aggregated_bp_gens(parties: usize) -> Cow<'static, BulletproofGens> {
+        static BP_GENS: OnceLock<BulletproofGens> = OnceLock::new();
+        if parties <= CACHED_BP_GENS_PARTIES {
+            // a party's generators don't depend on the capacity, so smaller
+            // proofs can use a prefix of the cached generators
+            Cow::Borrowed(
+                BP_GENS
+                    .get_or_init(|| BulletproofGens::new(RANGE_PROOF_BITS, CACHED_BP_GENS_PARTIES)),
+            )
+        } else {
+            Cow::Owned(BulletproofGens::new(RANGE_PROOF_BITS, parties))
+        }
     }
 
-    fn pc_gens() -> PedersenGens {
-        Default::default()
+    pub(crate) fn pc_gens() -> &'static PedersenGens {
+        static PC_GENS: OnceLock<PedersenGens> = OnceLock::new();
+        PC_GENS.get_or_init(crate::pedersen_gens)
     }
 
     pub fn public_keys(&self) -> Vec<G1Affine> {
         self.inputs.iter().flat_map(|m| m.public_keys()).collect()
     }
 
-    pub fn key_images(&self) -> Vec<G1Affine> {
+    pub fn key_images(&self) -> Vec<KeyImage> {
         self.inputs
             .iter()
-            .map(|m| m.true_input.key_image().to_affine())
+            .map(|m| m.true_input.key_image().into())
             .collect()
     }
 
     fn revealed_pseudo_commitments(&self, mut rng: impl RngCore) -> Vec<RevealedCommitment> {
-        self.inputs
+        let mut revealed_pseudo_commitments: Vec<RevealedCommitment> = self
+            .inputs
             .iter()
             .map(|m| m.true_input.random_pseudo_commitment(&mut rng))
-            .collect()
+            .collect();
+
+        // When every output has its blinding set, the last pseudo commitment
+        // balances them instead of the last output, so that none is random.
+        let output_blindings: Option<Vec<Scalar>> =
+            self.outputs.iter().map(|o| o.blinding).collect();
+        if let (Some(output_blindings), Some((last, others))) = (
+            output_blindings,
+            revealed_pseudo_commitments.split_last_mut(),
+        ) {
+            // todo: replace fold() with sum() when supported in blstrs
+            let output_sum = output_blindings
+                .iter()
+                .fold(Scalar::zero(), |sum, x| sum + x);
+            let others_sum = others
+                .iter()
+                .map(RevealedCommitment::blinding)
+                .fold(Scalar::zero(), |sum, x| sum + x);
+            last.blinding = output_sum - others_sum;
+        }
+        revealed_pseudo_commitments
     }
 
-    fn pseudo_commitments(
+    pub(crate) fn pseudo_commitments(
         &self,
         revealed_pseudo_commitments: &[RevealedCommitment],
     ) -> Vec<G1Affine> {
         revealed_pseudo_commitments
             .iter()
-            .map(|r| r.commit(&Self::pc_gens()).to_affine())
+            .map(|r| r.commit(Self::pc_gens()).to_affine())
             .collect()
     }
 
@@ -167,20 +959,21 @@ impl RingCtMaterial {
         &self,
         revealed_pseudo_commitments: &[RevealedCommitment],
         mut rng: impl RngCore,
-    ) -> Vec<RevealedOutputCommitment> {
-        // avoid subtraction underflow in next step.
-        if self.outputs.is_empty() {
-            return vec![];
-        }
+    ) -> Result<Vec<RevealedOutputCommitment>> {
+        let (last_output, outputs) = self.outputs.split_last().ok_or(Error::NoOutputs)?;
 
-        let mut revealed_output_commitments: Vec<RevealedOutputCommitment> = self
-            .outputs
+        let mut revealed_output_commitments: Vec<RevealedOutputCommitment> = outputs
             .iter()
             .map(|out| RevealedOutputCommitment {
                 public_key: out.public_key,
+                ephemeral_public_key: out.ephemeral_public_key,
+                view_tag: out.view_tag,
+                view_public_key: out.view_public_key,
+                extra: out.extra.clone(),
+                lock: out.lock,
+                rewind_key: out.rewind_key,
                 revealed_commitment: out.random_commitment(&mut rng),
             })
-            .take(self.outputs.len() - 1)
             .collect();
 
         // todo: replace fold() with sum() when supported in blstrs
@@ -197,336 +990,3571 @@ impl RingCtMaterial {
 
         let output_blinding_correction = input_sum - output_sum;
 
-        if let Some(last_output) = self.outputs.last() {
-            revealed_output_commitments.push(RevealedOutputCommitment {
-                public_key: last_output.public_key,
-                revealed_commitment: RevealedCommitment {
-                    value: last_output.amount,
-                    blinding: output_blinding_correction,
-                },
-            });
-        } else {
-            panic!("Expected at least one output")
-        }
-        revealed_output_commitments
+        revealed_output_commitments.push(RevealedOutputCommitment {
+            public_key: last_output.public_key,
+            ephemeral_public_key: last_output.ephemeral_public_key,
+            view_tag: last_output.view_tag,
+            view_public_key: last_output.view_public_key,
+            extra: last_output.extra.clone(),
+            lock: last_output.lock,
+            rewind_key: last_output.rewind_key,
+            revealed_commitment: RevealedCommitment {
+                value: last_output.amount.value(),
+                blinding: output_blinding_correction,
+            },
+        });
+        Ok(revealed_output_commitments)
     }
 
     fn output_range_proofs(
         &self,
+        version: u16,
+        key_images: &[KeyImage],
         revealed_output_commitments: &[RevealedOutputCommitment],
         mut rng: impl RngCore + CryptoRng,
     ) -> Result<Vec<OutputProof>> {
-        let mut prover_ts = Transcript::new(MERLIN_TRANSCRIPT_LABEL);
+        let mut prover_ts = range_proof_transcript(version, key_images);
+        let bits = range_proof_bits(version)?;
 
         let bp_gens = Self::bp_gens();
 
-        revealed_output_commitments
+        if version & TX_INDEPENDENT_RANGE_PROOFS == 0 {
+            return revealed_output_commitments
+                .iter()
+                .enumerate()
+                .map(|(output_index, c)| {
+                    append_output(&mut prover_ts, output_index, &c.public_key);
+                    Self::prove_output(c, &bp_gens, bits, &mut prover_ts, &mut rng)
+                })
+                .collect();
+        }
+
+        // Each output gets its own transcript and an rng seeded from `rng`,
+        // so the proofs come out the same however they're scheduled
+        let outputs: Vec<_> = revealed_output_commitments
             .iter()
             .map(|c| {
-                let (range_proof, commitment) = RangeProof::prove_single_with_rng(
+                let mut seed = [0u8; 32];
+                rng.fill_bytes(&mut seed);
+                (c, seed)
+            })
+            .collect();
+        map_indexed(&outputs, |output_index, (c, seed)| {
+            let mut transcript = output_transcript(&prover_ts, output_index, &c.public_key);
+            let mut rng = ChaCha20Rng::from_seed(*seed);
+            match &c.rewind_key {
+                Some(rewind_key) => Self::prove_rewindable_output(
+                    c,
                     &bp_gens,
-                    &Self::pc_gens(),
-                    &mut prover_ts,
-                    c.revealed_commitment.value,
-                    &c.revealed_commitment.blinding,
-                    RANGE_PROOF_BITS,
+                    bits,
+                    &mut transcript,
+                    rewind_key,
                     &mut rng,
-                )?;
-
-                Ok(OutputProof {
-                    public_key: c.public_key,
-                    range_proof,
-                    commitment,
-                })
-            })
-            .collect::<Result<Vec<_>>>()
+                ),
+                None => Self::prove_output(c, &bp_gens, bits, &mut transcript, &mut rng),
+            }
+        })
+        .into_iter()
+        .collect()
     }
-}
 
-// note: used by both RingCtMaterial::sign and RingCtTransaction::verify()
-//       which must match.
-fn gen_message_for_signing(
-    public_keys: &[G1Affine],
-    key_images: &[G1Affine],
-    pseudo_commitments: &[G1Affine],
-    output_proofs: &[OutputProof],
-) -> Vec<u8> {
-    // Generate message to sign.
-    let mut msg: Vec<u8> = Default::default();
-    for pk in public_keys.iter() {
-        msg.extend(pk.to_bytes().as_ref());
-    }
-    for t in key_images.iter() {
-        msg.extend(t.to_bytes().as_ref());
-    }
-    for r in pseudo_commitments.iter() {
-        msg.extend(r.to_bytes().as_ref());
-    }
-    for o in output_proofs.iter() {
-        msg.extend(o.to_bytes());
+    fn prove_output(
+        c: &RevealedOutputCommitment,
+        bp_gens: &BulletproofGens,
+        bits: usize,
+        transcript: &mut Transcript,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<OutputProof> {
+        let (range_proof, commitment) = RangeProof::prove_single_with_rng(
+            bp_gens,
+            Self::pc_gens(),
+            transcript,
+            c.revealed_commitment.value,
+            &c.revealed_commitment.blinding,
+            bits,
+            &mut rng,
+        )?;
+
+        Ok(OutputProof {
+            public_key: c.public_key,
+            range_proof: Some(range_proof),
+            commitment,
+            ephemeral_public_key: c.ephemeral_public_key,
+            view_tag: c.view_tag,
+            encrypted_amount: Some(c.encrypt_amount(&mut rng)),
+            extra: c.extra.clone(),
+            lock: c.lock,
+        })
     }
-    msg
-}
 
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Eq, PartialEq, Debug, Clone)]
-pub struct OutputProof {
-    public_key: G1Affine,
-    range_proof: RangeProof,
-    commitment: G1Affine,
-}
+    /// Proves the output with the nonces that carry the amount secrets drawn
+    /// with its rewind key, in place of an encrypted copy, and the others
+    /// from `rng`
+    fn prove_rewindable_output(
+        c: &RevealedOutputCommitment,
+        bp_gens: &BulletproofGens,
+        bits: usize,
+        transcript: &mut Transcript,
+        rewind_key: &[u8; 32],
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<OutputProof> {
+        let (range_proof, commitment) = rewind::prove(
+            bp_gens,
+            Self::pc_gens(),
+            transcript,
+            &c.revealed_commitment,
+            bits,
+            rewind_key,
+            rng,
+        )?;
 
-impl OutputProof {
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut v: Vec<u8> = Default::default();
-        v.extend(self.public_key.to_bytes().as_ref());
-        v.extend(&self.range_proof.to_bytes());
-        v.extend(self.commitment.to_bytes().as_ref());
-        v
+        Ok(OutputProof {
+            public_key: c.public_key,
+            range_proof: Some(range_proof),
+            commitment,
+            ephemeral_public_key: c.ephemeral_public_key,
+            view_tag: c.view_tag,
+            encrypted_amount: None,
+            extra: c.extra.clone(),
+            lock: c.lock,
+        })
     }
 
-    pub fn public_key(&self) -> &G1Affine {
-        &self.public_key
-    }
+    fn aggregated_output_range_proof(
+        &self,
+        version: u16,
+        key_images: &[KeyImage],
+        revealed_output_commitments: &[RevealedOutputCommitment],
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Result<(Vec<OutputProof>, RangeProof)> {
+        let mut prover_ts = range_proof_transcript(version, key_images);
+        for (output_index, c) in revealed_output_commitments.iter().enumerate() {
+            append_output(&mut prover_ts, output_index, &c.public_key);
+        }
+
+        // Aggregation requires a power of two number of values, we pad with
+        // commitments to zero using a zero blinding factor which the verifier can recompute.
+        let parties = aggregated_range_proof_parties(revealed_output_commitments.len());
+        let mut values: Vec<u64> = revealed_output_commitments
+            .iter()
+            .map(|c| c.revealed_commitment.value)
+            .collect();
+        let mut blindings: Vec<Scalar> = revealed_output_commitments
+            .iter()
+            .map(|c| c.revealed_commitment.blinding)
+            .collect();
+        values.resize(parties, 0);
+        blindings.resize(parties, Scalar::zero());
+
+        let (range_proof, commitments) = RangeProof::prove_multiple_with_rng(
+            &Self::aggregated_bp_gens(parties),
+            Self::pc_gens(),
+            &mut prover_ts,
+            &values,
+            &blindings,
+            range_proof_bits(version)?,
+            &mut rng,
+        )?;
+
+        let output_proofs = revealed_output_commitments
+            .iter()
+            .zip(commitments)
+            .map(|(c, commitment)| OutputProof {
+                public_key: c.public_key,
+                range_proof: None,
+                commitment,
+                ephemeral_public_key: c.ephemeral_public_key,
+                view_tag: c.view_tag,
+                encrypted_amount: Some(c.encrypt_amount(&mut rng)),
+                extra: c.extra.clone(),
+                lock: c.lock,
+            })
+            .collect();
 
-    pub fn range_proof(&self) -> &RangeProof {
-        &self.range_proof
+        Ok((output_proofs, range_proof))
     }
+}
 
-    pub fn commitment(&self) -> G1Affine {
-        self.commitment
+/// The transcript range proofs are made with, labelled with the transaction
+/// version so that a proof is only valid in the format it was made for, and
+/// with the key images of its inputs so that it's only valid in the
+/// transaction it was made for.
+fn range_proof_transcript(version: u16, key_images: &[KeyImage]) -> Transcript {
+    let mut transcript = Transcript::new(MERLIN_TRANSCRIPT_LABEL);
+    transcript.append_message(b"tx_version", &version.to_le_bytes());
+    transcript.append_u64(b"num_key_images", key_images.len() as u64);
+    for key_image in key_images {
+        transcript.append_message(b"key_image", &key_image.to_bytes());
     }
+    transcript
 }
 
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Eq, PartialEq, Debug, Clone)]
-pub struct RingCtTransaction {
-    pub mlsags: Vec<MlsagSignature>,
-    pub outputs: Vec<OutputProof>,
+/// Binds the following range proof to its output, so it can't be swapped
+/// onto another output.
+fn append_output(transcript: &mut Transcript, output_index: usize, public_key: &G1Affine) {
+    transcript.append_message(b"dom-sep", b"output-range-proof");
+    transcript.append_message(b"output_index", &(output_index as u64).to_le_bytes());
+    transcript.append_message(b"output_public_key", &public_key.to_compressed());
 }
 
-impl PartialOrd for RingCtTransaction {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+/// The transcript of the range proof of output `output_index`, for versions
+/// with `TX_INDEPENDENT_RANGE_PROOFS`: a copy of the transaction's transcript
+/// bound to the output, so that it doesn't depend on the other proofs.
+fn output_transcript(
+    transaction_transcript: &Transcript,
+    output_index: usize,
+    public_key: &G1Affine,
+) -> Transcript {
+    let mut transcript = transaction_transcript.clone();
+    append_output(&mut transcript, output_index, public_key);
+    transcript
+}
+
+/// The scalar field's order `r` is between 2^254 and 2^255
+const SCALAR_ORDER_BITS: usize = 255;
+
+// Any number of 64-bit amounts that fits a usize sums to below `r`, so
+// `check_balance()` needs no runtime check. Wider amounts use
+// `check_sum_cannot_wrap()`.
+const _: () = assert!(usize::BITS as usize + RANGE_PROOF_BITS < SCALAR_ORDER_BITS);
+
+/// Checks that `terms` amounts of less than `bits` bits each sum to less than
+/// the group order, so that a sum of their commitments can't wrap around and
+/// balance a transaction which mints value. Amounts below 2^bits are what
+/// range proofs prove, and the sum is below `terms * 2^bits`.
+pub(crate) fn check_sum_cannot_wrap(terms: usize, bits: usize) -> Result<()> {
+    let terms_bits = (usize::BITS - terms.leading_zeros()) as usize;
+    if terms_bits + bits >= SCALAR_ORDER_BITS {
+        return Err(Error::BalanceMayWrap { terms, bits });
     }
+    Ok(())
 }
 
-impl Ord for RingCtTransaction {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.hash().cmp(&other.hash())
+/// Rejects points that are the identity or outside the prime order subgroup,
+/// with `location` describing where in the transaction the point is.
+pub(crate) fn validate_point(point: &G1Affine, location: impl FnOnce() -> String) -> Result<()> {
+    if bool::from(point.is_identity()) {
+        return Err(Error::PointIsIdentity(location()));
+    }
+    if !bool::from(point.is_on_curve()) {
+        return Err(Error::PointNotOnCurve(location()));
+    }
+    if !bool::from(point.is_torsion_free()) {
+        return Err(Error::PointNotInSubgroup(location()));
     }
+    Ok(())
 }
 
-impl RingCtTransaction {
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut v: Vec<u8> = Default::default();
-        for m in self.mlsags.iter() {
-            v.extend(&m.to_bytes());
-        }
-        for o in self.outputs.iter() {
-            v.extend(&o.to_bytes());
-        }
-        v
+/// Rejects extra data longer than `MAX_EXTRA_LEN`
+fn validate_extra(extra: &[u8], location: impl FnOnce() -> String) -> Result<()> {
+    if extra.len() > MAX_EXTRA_LEN {
+        return Err(Error::ExtraTooLong {
+            location: location(),
+            len: extra.len(),
+        });
     }
+    Ok(())
+}
 
-    pub fn hash(&self) -> [u8; 32] {
-        let mut sha3 = Sha3::v256();
+/// Checks the extra data of a transaction and of each of its outputs
+pub(crate) fn check_extra(extra: &[u8], outputs: &[Output]) -> Result<()> {
+    validate_extra(extra, || "transaction".to_string())?;
+    for (i, output) in outputs.iter().enumerate() {
+        validate_extra(&output.extra, || format!("output {}", i))?;
+    }
+    Ok(())
+}
 
-        sha3.update(&self.to_bytes());
+fn write_extra(v: &mut Vec<u8>, extra: &[u8]) {
+    codec::write_len(v, extra.len());
+    v.extend(extra);
+}
 
-        let mut hash = [0; 32];
-        sha3.finalize(&mut hash);
-        hash
+fn read_extra(reader: &mut Reader, location: impl FnOnce() -> String) -> Result<Vec<u8>> {
+    let len = reader.read_len()?;
+    if len > MAX_EXTRA_LEN.min(reader.limits().max_extra_len) {
+        return Err(Error::ExtraTooLong {
+            location: location(),
+            len,
+        });
     }
+    Ok(reader.take(len)?.to_vec())
+}
 
-    // note: must match message generated by RingCtMaterial::sign()
-    pub fn gen_message(&self) -> Vec<u8> {
-        // All public keys in all rings
-        let public_keys: Vec<G1Affine> = self.mlsags.iter().flat_map(|m| m.public_keys()).collect();
+fn verify_spendable(public_key: &G1Affine, lock: Option<u64>, current_height: u64) -> Result<()> {
+    match lock {
+        Some(lock) if current_height < lock => Err(Error::OutputLocked {
+            public_key: codec::to_hex(&public_key.to_compressed()),
+            lock,
+        }),
+        _ => Ok(()),
+    }
+}
 
-        // All key-images (of true inputs),
-        let key_images: Vec<G1Affine> = self.mlsags.iter().map(|m| m.key_image).collect();
+/// The version flags of transactions with the given optional fields
+pub(crate) fn field_flags(network_id: Option<&[u8; 32]>, not_valid_after: Option<u64>) -> u16 {
+    let network_id_flag = match network_id {
+        Some(_) => TX_NETWORK_ID,
+        None => 0,
+    };
+    let expiry_flag = match not_valid_after {
+        Some(_) => TX_EXPIRY,
+        None => 0,
+    };
+    network_id_flag | expiry_flag
+}
 
-        // All PseudoCommitments.
-        let pseudo_commitments: Vec<G1Affine> =
-            self.mlsags.iter().map(|m| m.pseudo_commitment()).collect();
+// The range proof flags signing sets in `version`: only formats with
+// per-output range proofs can prove them in independent transcripts
+pub(crate) fn range_proof_flags(version: u16) -> u16 {
+    match tx_format(version) {
+        TX_VERSION_2 => 0,
+        _ => TX_INDEPENDENT_RANGE_PROOFS,
+    }
+}
 
-        gen_message_for_signing(
-            &public_keys,
-            &key_images,
-            &pseudo_commitments,
-            &self.outputs,
+fn is_supported_version(version: u16) -> bool {
+    range_proof_bits(version).is_ok()
+        && version & TX_FLAGS == TX_FLAGS
+        && version & TX_INDEPENDENT_RANGE_PROOFS & !range_proof_flags(version) == 0
+        && matches!(
+            tx_format(version),
+            TX_VERSION_1 | TX_VERSION_2 | TX_VERSION_3 | TX_VERSION_4
         )
-    }
+}
 
-    pub fn verify(&self, public_commitments_per_ring: &[Vec<G1Affine>]) -> Result<()> {
-        let msg = self.gen_message();
-        for (mlsag, public_commitments) in self.mlsags.iter().zip(public_commitments_per_ring) {
-            mlsag.verify(&msg, public_commitments)?
-        }
+/// Maps `f` over `items` with their indices, concurrently with the
+/// `parallel` feature
+#[cfg(feature = "parallel")]
+fn map_indexed<T: Sync, U: Send>(items: &[T], f: impl Fn(usize, &T) -> U + Sync) -> Vec<U> {
+    items
+        .par_iter()
+        .enumerate()
+        .map(|(i, item)| f(i, item))
+        .collect()
+}
 
-        let mut prover_ts = Transcript::new(MERLIN_TRANSCRIPT_LABEL);
-        let bp_gens = RingCtMaterial::bp_gens();
+/// Maps `f` over `items` with their indices
+#[cfg(not(feature = "parallel"))]
+fn map_indexed<T, U>(items: &[T], f: impl Fn(usize, &T) -> U) -> Vec<U> {
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| f(i, item))
+        .collect()
+}
 
-        for output in self.outputs.iter() {
-            // Verification requires a transcript with identical initial state:
-            output.range_proof.verify_single(
-                &bp_gens,
-                &RingCtMaterial::pc_gens(),
-                &mut prover_ts,
-                &output.commitment,
-                RANGE_PROOF_BITS,
-            )?;
-        }
+/// Verifies each ring signature against the public commitments of its ring
+#[cfg(feature = "parallel")]
+fn verify_rings<S: Sync>(
+    signatures: &[S],
+    public_commitments_per_ring: &[Vec<G1Affine>],
+    verify: impl Fn(usize, &S, &[G1Affine]) -> Result<()> + Sync,
+) -> Result<()> {
+    if signatures.len() != public_commitments_per_ring.len() {
+        return Err(Error::ExpectedAPublicCommitmentsForEachRingEntry);
+    }
+    signatures
+        .par_iter()
+        .zip(public_commitments_per_ring.par_iter())
+        .enumerate()
+        .try_for_each(|(i, (sig, public_commitments))| verify(i, sig, public_commitments))
+}
 
-        // Verify that the tx has at least one input
-        if self.mlsags.is_empty() {
-            return Err(Error::TransactionMustHaveAnInput);
-        }
+/// Verifies each ring signature against the public commitments of its ring
+#[cfg(not(feature = "parallel"))]
+fn verify_rings<S>(
+    signatures: &[S],
+    public_commitments_per_ring: &[Vec<G1Affine>],
+    verify: impl Fn(usize, &S, &[G1Affine]) -> Result<()>,
+) -> Result<()> {
+    if signatures.len() != public_commitments_per_ring.len() {
+        return Err(Error::ExpectedAPublicCommitmentsForEachRingEntry);
+    }
+    signatures
+        .iter()
+        .zip(public_commitments_per_ring)
+        .enumerate()
+        .try_for_each(|(i, (sig, public_commitments))| verify(i, sig, public_commitments))
+}
 
-        // Verify that each KeyImage is unique in this tx.
-        let keyimage_unique: BTreeSet<_> = self
-            .mlsags
+/// Verifies each ring signature against the public commitments of its ring,
+/// reporting the result of every ring
+fn ring_results<S>(
+    signatures: &[S],
+    public_commitments_per_ring: &[Vec<G1Affine>],
+    verify: impl Fn(usize, &S, &[G1Affine]) -> Result<()>,
+) -> Vec<Result<()>> {
+    if signatures.len() != public_commitments_per_ring.len() {
+        return signatures
             .iter()
-            .map(|m| m.key_image.to_compressed())
+            .map(|_| Err(Error::ExpectedAPublicCommitmentsForEachRingEntry))
             .collect();
-        if keyimage_unique.len() != self.mlsags.len() {
-            return Err(Error::KeyImageNotUniqueAcrossInputs);
-        }
+    }
+    signatures
+        .iter()
+        .zip(public_commitments_per_ring)
+        .enumerate()
+        .map(|(i, (sig, public_commitments))| verify(i, sig, public_commitments))
+        .collect()
+}
 
-        // Verify that each public_key is unique across all input mlsag
-        let pk_unique: BTreeSet<_> = self
-            .mlsags
-            .iter()
-            .flat_map(|m| {
-                m.public_keys()
-                    .iter()
-                    .map(|pk| pk.to_compressed())
-                    .collect::<Vec<[u8; 48]>>()
-            })
-            .collect();
+/// Verifies the MLSAGs of a transaction through `backend`, identifying a
+/// failed input in the error
+fn verify_mlsags(
+    mlsags: &[MlsagSignature],
+    msg: &[u8],
+    public_commitments_per_ring: &[Vec<G1Affine>],
+    backend: &dyn VerificationBackend,
+) -> Result<()> {
+    if mlsags.len() != public_commitments_per_ring.len() {
+        return Err(Error::ExpectedAPublicCommitmentsForEachRingEntry);
+    }
+    let batch: Vec<_> = mlsags
+        .iter()
+        .zip(public_commitments_per_ring)
+        .map(|(mlsag, public_commitments)| (msg, mlsag, public_commitments.as_slice()))
+        .collect();
+    let mut results = MlsagSignature::verify_batch(&batch, backend).into_iter();
+    mlsags
+        .iter()
+        .enumerate()
+        .try_for_each(|(input_index, mlsag)| match results.next() {
+            Some(result) => result.map_err(|reason| Error::MlsagInvalid {
+                input_index,
+                key_image: mlsag.key_image().to_string(),
+                reason: Box::new(reason),
+            }),
+            None => Err(Error::ExpectedAPublicCommitmentsForEachRingEntry),
+        })
+}
 
-        let pk_count = self.mlsags.iter().map(|m| m.public_keys().len()).sum();
+/// Verifies an MLSAG, identifying its input in the error
+fn verify_mlsag(
+    input_index: usize,
+    mlsag: &MlsagSignature,
+    msg: &[u8],
+    public_commitments: &[G1Affine],
+) -> Result<()> {
+    mlsag
+        .verify(msg, public_commitments)
+        .map_err(|reason| Error::MlsagInvalid {
+            input_index,
+            key_image: mlsag.key_image().to_string(),
+            reason: Box::new(reason),
+        })
+}
 
-        if pk_unique.len() != pk_count {
-            return Err(Error::PublicKeyNotUniqueAcrossInputs);
-        }
+fn verify_matrix_mlsag(
+    matrix_mlsag: &MatrixMlsagSignature,
+    msg: &[u8],
+    public_commitments_per_ring: &[Vec<G1Affine>],
+) -> Result<()> {
+    matrix_mlsag
+        .verify(msg, public_commitments_per_ring)
+        .map_err(|reason| Error::MatrixMlsagInvalid {
+            reason: Box::new(reason),
+        })
+}
 
-        let input_sum: G1Projective = self
-            .mlsags
-            .iter()
-            .map(MlsagSignature::pseudo_commitment)
-            .map(G1Projective::from)
-            .sum();
-        let output_sum: G1Projective = self
-            .outputs
-            .iter()
-            .map(OutputProof::commitment)
-            .map(G1Projective::from)
-            .sum();
+/// Verifies a CLSAG, identifying its input in the error
+fn verify_clsag(
+    input_index: usize,
+    clsag: &ClsagSignature,
+    msg: &[u8],
+    public_commitments: &[G1Affine],
+) -> Result<()> {
+    clsag
+        .verify(msg, public_commitments)
+        .map_err(|reason| Error::ClsagInvalid {
+            input_index,
+            key_image: clsag.key_image().to_string(),
+            reason: Box::new(reason),
+        })
+}
 
-        if input_sum != output_sum {
-            Err(Error::InputPseudoCommitmentsDoNotSumToOutputCommitments)
-        } else {
-            Ok(())
+fn aggregated_range_proof_parties(num_outputs: usize) -> usize {
+    num_outputs.next_power_of_two()
+}
+
+fn write_range_proof(v: &mut Vec<u8>, range_proof: Option<&RangeProof>) {
+    // A range proof is never empty, so a zero length marks an absent proof.
+    match range_proof {
+        Some(range_proof) => {
+            let bytes = range_proof.to_bytes();
+            codec::write_len(v, bytes.len());
+            v.extend(&bytes);
         }
+        None => codec::write_len(v, 0),
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use std::collections::{BTreeMap, BTreeSet};
+// A single party range proof holds the points A, S, T1 and T2, the scalars
+// t_x, t_x_blinding and e_blinding, then an inner product proof of
+// 2 log2(bits) points and two scalars.
+fn range_proof_size(bits: usize) -> usize {
+    let lg_bits = bits.trailing_zeros() as usize;
+    (4 + 2 * lg_bits) * 48 + (3 + 2) * 32
+}
 
-    use bls_bulletproofs::{
-        group::{ff::Field, Curve, Group},
-        rand::rngs::OsRng,
-    };
+fn read_range_proof(reader: &mut Reader) -> Result<Option<RangeProof>> {
+    match reader.read_len()? {
+        0 => Ok(None),
+        len => Ok(Some(RangeProof::from_bytes(&reader.take(len)?)?)),
+    }
+}
 
-    use crate::{DecoyInput, MlsagMaterial, TrueInput};
+// note: used by both RingCtMaterial::sign and RingCtTransaction::verify()
+//       which must match.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn gen_message_for_signing(
+    version: u16,
+    fee: Amount,
+    network_id: Option<&[u8; 32]>,
+    not_valid_after: Option<u64>,
+    extra: &[u8],
+    public_keys: &[G1Affine],
+    key_images: &[KeyImage],
+    pseudo_commitments: &[G1Affine],
+    output_proofs: &[OutputProof],
+    aggregated_range_proof: Option<&RangeProof>,
+) -> Vec<u8> {
+    let mut msg: Vec<u8> = Default::default();
+    absorb_message_for_signing(
+        &mut msg,
+        version,
+        fee,
+        network_id,
+        not_valid_after,
+        extra,
+        public_keys,
+        key_images,
+        pseudo_commitments,
+        output_proofs,
+        aggregated_range_proof,
+    );
+    msg
+}
 
-    use super::*;
+/// Feeds the message `gen_message_for_signing()` returns to `digest`, one
+/// component at a time
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn absorb_message_for_signing(
+    digest: &mut impl MessageDigest,
+    version: u16,
+    fee: Amount,
+    network_id: Option<&[u8; 32]>,
+    not_valid_after: Option<u64>,
+    extra: &[u8],
+    public_keys: &[G1Affine],
+    key_images: &[KeyImage],
+    pseudo_commitments: &[G1Affine],
+    output_proofs: &[OutputProof],
+    aggregated_range_proof: Option<&RangeProof>,
+) {
+    digest.absorb(&version.to_le_bytes());
+    digest.absorb(&fee.to_le_bytes());
+    if let Some(network_id) = network_id {
+        digest.absorb(network_id);
+    }
+    if let Some(height) = not_valid_after {
+        digest.absorb(&height.to_le_bytes());
+    }
+    digest.absorb(&(extra.len() as u32).to_le_bytes());
+    digest.absorb(extra);
+    for pk in public_keys.iter() {
+        digest.absorb(pk.to_bytes().as_ref());
+    }
+    for t in key_images.iter() {
+        digest.absorb(&t.to_bytes());
+    }
+    for r in pseudo_commitments.iter() {
+        digest.absorb(r.to_bytes().as_ref());
+    }
+    for o in output_proofs.iter() {
+        digest.absorb(&o.to_bytes());
+    }
+    if let Some(range_proof) = aggregated_range_proof {
+        digest.absorb(&range_proof.to_bytes());
+    }
+}
+
+/// What an output's range proof is bound to in its transaction, for checking
+/// it with `OutputProof::verify()` without the rest of the transaction
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct RangeProofContext {
+    /// The version of the transaction, which must have
+    /// `TX_INDEPENDENT_RANGE_PROOFS`
+    pub version: u16,
+    /// The key images of the transaction's inputs, in order
+    pub key_images: Vec<KeyImage>,
+    /// The index of the output in the transaction
+    pub output_index: usize,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct OutputProof {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    #[cfg_attr(
+        feature = "json-schema",
+        schemars(with = "crate::json_schema::HexPoint")
+    )]
+    public_key: G1Affine,
+    #[cfg_attr(
+        feature = "json-schema",
+        schemars(with = "Option<crate::json_schema::RangeProofBytes>")
+    )]
+    range_proof: Option<RangeProof>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    #[cfg_attr(
+        feature = "json-schema",
+        schemars(with = "crate::json_schema::HexPoint")
+    )]
+    commitment: G1Affine,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    #[cfg_attr(
+        feature = "json-schema",
+        schemars(with = "Option<crate::json_schema::HexPoint>")
+    )]
+    ephemeral_public_key: Option<G1Affine>,
+    view_tag: Option<u8>,
+    encrypted_amount: Option<EncryptedAmount>,
+    extra: Vec<u8>,
+    lock: Option<u64>,
+}
 
-    #[derive(Default)]
-    struct TestLedger {
-        commitments: BTreeMap<[u8; 48], G1Affine>, // Compressed public keys -> Commitments
+impl std::hash::Hash for OutputProof {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::hash::Hash::hash(&self.to_bytes(), state)
     }
+}
 
-    impl TestLedger {
-        fn log(&mut self, public_key: impl Into<G1Affine>, commitment: impl Into<G1Affine>) {
-            self.commitments
-                .insert(public_key.into().to_compressed(), commitment.into());
+impl OutputProof {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Default::default();
+        v.extend(self.public_key.to_bytes().as_ref());
+        write_range_proof(&mut v, self.range_proof.as_ref());
+        v.extend(self.commitment.to_bytes().as_ref());
+        match &self.ephemeral_public_key {
+            Some(ephemeral_public_key) => {
+                v.push(1);
+                v.extend(ephemeral_public_key.to_compressed());
+            }
+            None => v.push(0),
         }
-
-        fn lookup(&self, public_key: impl Into<G1Affine>) -> Option<G1Affine> {
-            self.commitments
-                .get(&public_key.into().to_compressed())
-                .copied()
+        match self.view_tag {
+            Some(view_tag) => v.extend([1, view_tag]),
+            None => v.push(0),
+        }
+        match &self.encrypted_amount {
+            Some(encrypted_amount) => {
+                v.push(1);
+                v.extend(encrypted_amount.to_bytes());
+            }
+            None => v.push(0),
         }
+        write_extra(&mut v, &self.extra);
+        match self.lock {
+            Some(lock) => {
+                v.push(1);
+                v.extend(lock.to_le_bytes());
+            }
+            None => v.push(0),
+        }
+        v
+    }
 
-        fn fetch_decoys(&self, n: usize, exclude: &[G1Projective]) -> Vec<DecoyInput> {
-            let exclude_set = BTreeSet::from_iter(exclude.iter().map(G1Projective::to_compressed));
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let output = Self::decode(&mut reader)?;
+        reader.finish()?;
+        Ok(output)
+    }
 
-            self.commitments
-                .iter()
-                .filter(|(pk, _)| !exclude_set.contains(*pk))
-                .map(|(pk, c)| DecoyInput {
-                    public_key: G1Affine::from_compressed(pk).unwrap(),
-                    commitment: *c,
-                })
-                .take(n)
-                .collect()
-        }
+    /// Writes the encoding produced by `to_bytes()` to a stream
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<()> {
+        codec::write_all(writer, &self.to_bytes())
     }
 
-    #[test]
-    fn test_ringct_sign() {
-        let mut rng = OsRng::default();
-        let pc_gens = PedersenGens::default();
+    /// Reads the encoding produced by `to_bytes()` from a stream, consuming
+    /// at most `MAX_STREAMED_LEN` bytes
+    pub fn read_from(reader: &mut impl Read) -> Result<Self> {
+        Self::decode(&mut Reader::from_stream(reader, MAX_STREAMED_LEN))
+    }
 
-        let true_input = TrueInput {
-            secret_key: Scalar::random(&mut rng),
-            revealed_commitment: RevealedCommitment {
-                value: 3,
-                blinding: 5.into(),
-            },
+    pub(crate) fn decode(reader: &mut Reader) -> Result<Self> {
+        let public_key = reader.read_point()?;
+        let range_proof = read_range_proof(reader)?;
+        let commitment = reader.read_point()?;
+        let ephemeral_public_key = match reader.read_u8()? {
+            0 => None,
+            1 => Some(reader.read_point()?),
+            flag => return Err(Error::InvalidPresenceFlag(flag)),
+        };
+        let view_tag = match reader.read_u8()? {
+            0 => None,
+            1 => Some(reader.read_u8()?),
+            flag => return Err(Error::InvalidPresenceFlag(flag)),
+        };
+        let encrypted_amount = match reader.read_u8()? {
+            0 => None,
+            1 => Some(EncryptedAmount::decode(reader)?),
+            flag => return Err(Error::InvalidPresenceFlag(flag)),
+        };
+        let extra = read_extra(reader, || "output".to_string())?;
+        let lock = match reader.read_u8()? {
+            0 => None,
+            1 => Some(reader.read_u64()?),
+            flag => return Err(Error::InvalidPresenceFlag(flag)),
         };
 
-        let mut ledger = TestLedger::default();
-        ledger.log(
-            true_input.public_key(),
-            true_input.revealed_commitment.commit(&pc_gens),
-        );
-        ledger.log(
-            G1Projective::random(&mut rng),
-            G1Projective::random(&mut rng),
-        );
-        ledger.log(
-            G1Projective::random(&mut rng),
-            G1Projective::random(&mut rng),
-        );
+        Ok(Self {
+            public_key,
+            range_proof,
+            commitment,
+            ephemeral_public_key,
+            view_tag,
+            encrypted_amount,
+            extra,
+            lock,
+        })
+    }
 
-        let decoy_inputs = ledger.fetch_decoys(2, &[true_input.public_key()]);
+    pub fn public_key(&self) -> &G1Affine {
+        &self.public_key
+    }
 
-        let ring_ct = RingCtMaterial {
-            inputs: vec![MlsagMaterial::new(true_input, decoy_inputs, &mut rng)],
-            outputs: vec![Output {
-                public_key: G1Projective::random(&mut rng).to_affine(),
-                amount: 3,
-            }],
-        };
+    pub(crate) fn without_range_proof(&self) -> Self {
+        Self {
+            range_proof: None,
+            ..self.clone()
+        }
+    }
 
-        let (signed_tx, _revealed_output_commitments) =
-            ring_ct.sign(rng).expect("Failed to sign transaction");
+    /// The output's own range proof, absent when the transaction carries an
+    /// aggregated range proof instead.
+    pub fn range_proof(&self) -> Option<&RangeProof> {
+        self.range_proof.as_ref()
+    }
 
-        let public_commitments = Vec::from_iter(signed_tx.mlsags.iter().map(|mlsag| {
-            Vec::from_iter(
-                mlsag
-                    .public_keys()
-                    .into_iter()
-                    .map(|pk| ledger.lookup(pk).unwrap()),
-            )
-        }));
+    pub fn commitment(&self) -> Commitment {
+        Commitment(self.commitment)
+    }
 
-        assert!(signed_tx.verify(&public_commitments).is_ok());
+    /// The sender's ephemeral key, present when the output was paid to a stealth address
+    pub fn ephemeral_public_key(&self) -> Option<G1Affine> {
+        self.ephemeral_public_key
+    }
+
+    /// A byte of the stealth shared secret, see `StealthKeys::scan_outputs()`
+    pub fn view_tag(&self) -> Option<u8> {
+        self.view_tag
+    }
+
+    /// The output's amount secrets, encrypted to its public key by the sender
+    pub fn encrypted_amount(&self) -> Option<&EncryptedAmount> {
+        self.encrypted_amount.as_ref()
+    }
+
+    /// The application data attached to the output by the sender
+    pub fn extra(&self) -> &[u8] {
+        &self.extra
+    }
+
+    /// The block height or timestamp before which the output can't be spent
+    pub fn lock(&self) -> Option<u64> {
+        self.lock
+    }
+
+    /// Checks that the output's lock, if any, has expired at `current_height`
+    pub fn verify_spendable(&self, current_height: u64) -> Result<()> {
+        verify_spendable(&self.public_key, self.lock, current_height)
+    }
+
+    /// Verifies the output's range proof on its own, as made within the
+    /// transaction `context` describes. Only proofs of versions with
+    /// `TX_INDEPENDENT_RANGE_PROOFS` can be checked without the proofs of the
+    /// outputs before them, genesis and aggregated proofs can't.
+    pub fn verify(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        context: &RangeProofContext,
+    ) -> Result<()> {
+        match tx_format(context.version) {
+            TX_VERSION_1 | TX_VERSION_3 | TX_VERSION_4
+                if context.version & TX_INDEPENDENT_RANGE_PROOFS != 0 => {}
+            _ => return Err(Error::RangeProofNotIndependent(context.version)),
+        }
+        let bits = range_proof_bits(context.version)?;
+        let mut transcript = output_transcript(
+            &range_proof_transcript(context.version, &context.key_images),
+            context.output_index,
+            &self.public_key,
+        );
+        self.verify_range_proof(
+            context.output_index,
+            bp_gens,
+            pc_gens,
+            &mut transcript,
+            bits,
+        )
+    }
+
+    fn verify_range_proof(
+        &self,
+        output_index: usize,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        bits: usize,
+    ) -> Result<()> {
+        let range_proof = self
+            .range_proof
+            .as_ref()
+            .ok_or(Error::InvalidRangeProofLayout)?;
+        range_proof
+            .verify_single(bp_gens, pc_gens, transcript, &self.commitment, bits)
+            .map_err(|reason| Error::RangeProofInvalid {
+                output_index,
+                public_key: codec::to_hex(&self.public_key.to_compressed()),
+                reason: Box::new(reason.into()),
+            })
+    }
+
+    /// Recovers the amount secrets of an output paid to a stealth address by
+    /// rewinding its range proof with the view secret key, for outputs made
+    /// within the transaction `context` describes without an encrypted amount
+    pub fn rewind_amount<S: Into<Scalar>>(
+        &self,
+        view_secret_key: S,
+        context: &RangeProofContext,
+    ) -> Result<AmountSecrets> {
+        let ephemeral_public_key = self
+            .ephemeral_public_key
+            .ok_or(Error::RangeProofRewindFailed)?;
+        let shared_secret = (ephemeral_public_key * view_secret_key.into()).to_affine();
+        self.rewind_amount_with_key(&stealth::rewind_key(&shared_secret), context)
+    }
+
+    pub(crate) fn rewind_amount_with_key(
+        &self,
+        rewind_key: &[u8; 32],
+        context: &RangeProofContext,
+    ) -> Result<AmountSecrets> {
+        match tx_format(context.version) {
+            TX_VERSION_1 | TX_VERSION_3 | TX_VERSION_4
+                if context.version & TX_INDEPENDENT_RANGE_PROOFS != 0 => {}
+            _ => return Err(Error::RangeProofNotIndependent(context.version)),
+        }
+        let range_proof = self
+            .range_proof
+            .as_ref()
+            .ok_or(Error::InvalidRangeProofLayout)?;
+        let mut transcript = output_transcript(
+            &range_proof_transcript(context.version, &context.key_images),
+            context.output_index,
+            &self.public_key,
+        );
+        rewind::rewind(
+            range_proof,
+            &self.commitment,
+            RingCtMaterial::pc_gens(),
+            &mut transcript,
+            range_proof_bits(context.version)?,
+            rewind_key,
+        )
+        .map(AmountSecrets::from)
+    }
+
+    /// Decrypts the amount secrets with the output's secret key, or the view
+    /// secret key for outputs paid to a stealth address, and checks that they
+    /// open the output's commitment.
+    pub fn try_decrypt_amount<S: Into<Scalar>>(&self, secret_key: S) -> Result<AmountSecrets> {
+        let amount_secrets = self
+            .encrypted_amount
+            .as_ref()
+            .ok_or(Error::AmountDecryptionFailed)?
+            .try_decrypt(secret_key)?;
+
+        if amount_secrets
+            .revealed_commitment()
+            .commit(RingCtMaterial::pc_gens())
+            .to_affine()
+            != self.commitment
+        {
+            return Err(Error::AmountSecretsDoNotMatchCommitment);
+        }
+        Ok(amount_secrets)
+    }
+}
+
+/// The outcome of each check made when verifying a transaction
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct VerificationReport {
+    pub version: Result<()>,
+    /// The transaction is within the limits of the default `RingCtPolicy`
+    pub policy: Result<()>,
+    /// Every point is a valid, non-identity subgroup point
+    pub points: Result<()>,
+    /// The transaction has inputs signed with the scheme of its version,
+    /// and there are public commitments for each of their rings.
+    pub ring_layout: Result<()>,
+    /// The ring signature result of each input
+    pub ring_signatures: Vec<Result<()>>,
+    /// The range proof result of each output
+    pub range_proofs: Vec<Result<()>>,
+    pub key_images_unique: Result<()>,
+    pub public_keys_unique: Result<()>,
+    pub balance: Result<()>,
+}
+
+impl VerificationReport {
+    pub fn is_valid(&self) -> bool {
+        self.errors().is_empty()
+    }
+
+    /// Every failure, in the order `verify()` would encounter them
+    pub fn errors(&self) -> Vec<&Error> {
+        [&self.version, &self.policy, &self.points, &self.ring_layout]
+            .into_iter()
+            .chain(self.ring_signatures.iter())
+            .chain(self.range_proofs.iter())
+            .chain([
+                &self.key_images_unique,
+                &self.public_keys_unique,
+                &self.balance,
+            ])
+            .filter_map(|result| result.as_ref().err())
+            .collect()
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct RingCtTransaction {
+    pub version: u16,
+    pub mlsags: Vec<MlsagSignature>,
+    pub clsags: Vec<ClsagSignature>,
+    /// The signature of all inputs in `TX_VERSION_4` transactions
+    pub matrix_mlsag: Option<MatrixMlsagSignature>,
+    pub outputs: Vec<OutputProof>,
+    pub fee: Amount,
+    /// Application data signed with the transaction, at most `MAX_EXTRA_LEN` bytes
+    pub extra: Vec<u8>,
+    /// The network the transaction is bound to, set iff the version has
+    /// `TX_NETWORK_ID` set
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub network_id: Option<[u8; 32]>,
+    /// The last height at which the transaction may be included, set iff the
+    /// version has `TX_EXPIRY` set
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub not_valid_after: Option<u64>,
+    #[cfg_attr(
+        feature = "json-schema",
+        schemars(with = "Option<crate::json_schema::RangeProofBytes>")
+    )]
+    pub aggregated_range_proof: Option<RangeProof>,
+}
+
+impl TryFrom<&[u8]> for RingCtTransaction {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+// Consistent with the derived equality, as equal transactions have equal
+// encodings. Hashes the transaction's `hash()` rather than the full encoding.
+impl std::hash::Hash for RingCtTransaction {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::hash::Hash::hash(&RingCtTransaction::hash(self), state)
+    }
+}
+
+impl PartialOrd for RingCtTransaction {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RingCtTransaction {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.hash().cmp(&other.hash())
+    }
+}
+
+impl RingCtTransaction {
+    /// The format of the transaction's version, eg. `TX_VERSION_1`
+    pub fn format(&self) -> u16 {
+        tx_format(self.version)
+    }
+
+    /// The bit length of the transaction's range proofs
+    pub fn range_proof_bits(&self) -> Result<usize> {
+        range_proof_bits(self.version)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Default::default();
+        self.write_to(&mut v).expect("Writing to a Vec never fails");
+        v
+    }
+
+    /// The length of `to_bytes()`, without allocating it
+    pub fn serialized_size(&self) -> usize {
+        let mut counter = codec::ByteCounter::default();
+        self.write_to(&mut counter)
+            .expect("Counting bytes never fails");
+        counter.0
+    }
+
+    /// The serialized size of a `CURRENT_TX_VERSION` transaction with
+    /// `num_inputs` rings of `ring_size` members each and `num_outputs` plain
+    /// outputs, without extra data. Stealth outputs are larger, see
+    /// `Output::to_stealth_address()`.
+    pub fn estimate_size(num_inputs: usize, ring_size: usize, num_outputs: usize) -> usize {
+        const POINT: usize = 48;
+        const SCALAR: usize = 32;
+        const LEN: usize = 4;
+
+        // c0, r, key image, ring and pseudo commitment
+        let mlsag =
+            SCALAR + LEN + ring_size * 2 * SCALAR + POINT + LEN + ring_size * 2 * POINT + POINT;
+        // public key, range proof, commitment, then the flags of the ephemeral
+        // public key, view tag and encrypted amount, the encrypted amount
+        // itself, no extra and the lock flag
+        let output = POINT
+            + LEN
+            + range_proof_size(RANGE_PROOF_BITS)
+            + POINT
+            + 3
+            + ENCRYPTED_AMOUNT_LEN
+            + LEN
+            + 1;
+        // version, fee and no extra
+        let header = 2 + 8 + LEN;
+
+        header + LEN + num_inputs * mlsag + LEN + num_outputs * output
+    }
+
+    /// Writes the encoding produced by `to_bytes()` to a stream, one
+    /// signature and output at a time
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<()> {
+        let mut header: Vec<u8> = Default::default();
+        header.extend(self.version.to_le_bytes());
+        header.extend(self.fee.to_le_bytes());
+        if let Some(network_id) = &self.network_id {
+            header.extend(network_id);
+        }
+        if let Some(height) = self.not_valid_after {
+            header.extend(height.to_le_bytes());
+        }
+        write_extra(&mut header, &self.extra);
+        codec::write_all(writer, &header)?;
+        codec::write_len_to(writer, self.mlsags.len())?;
+        for m in self.mlsags.iter() {
+            m.write_to(writer)?;
+        }
+        if self.format() == TX_VERSION_3 {
+            codec::write_len_to(writer, self.clsags.len())?;
+            for c in self.clsags.iter() {
+                codec::write_all(writer, &c.to_bytes())?;
+            }
+        }
+        if self.format() == TX_VERSION_4 {
+            match &self.matrix_mlsag {
+                Some(m) => {
+                    codec::write_all(writer, &[1])?;
+                    codec::write_all(writer, &m.to_bytes())?;
+                }
+                None => codec::write_all(writer, &[0])?,
+            }
+        }
+        codec::write_len_to(writer, self.outputs.len())?;
+        for o in self.outputs.iter() {
+            o.write_to(writer)?;
+        }
+        if self.format() == TX_VERSION_2 {
+            let mut range_proof: Vec<u8> = Default::default();
+            write_range_proof(&mut range_proof, self.aggregated_range_proof.as_ref());
+            codec::write_all(writer, &range_proof)?;
+        }
+        Ok(())
+    }
+
+    /// Parses the encoding produced by `to_bytes()`, within the default
+    /// `DecodeLimits`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Self::from_bytes_with_limits(bytes, &DecodeLimits::default())
+    }
+
+    /// Parses the encoding produced by `to_bytes()`, eg. within
+    /// `DecodeLimits::from_policy()` of a policy wider than the default
+    pub fn from_bytes_with_limits(bytes: &[u8], limits: &DecodeLimits) -> Result<Self> {
+        if bytes.len() > limits.max_encoded_len {
+            return Err(Error::EncodingTooLong(limits.max_encoded_len));
+        }
+        let mut reader = Reader::new(bytes).with_limits(*limits);
+        let tx = Self::decode(&mut reader)?;
+        reader.finish()?;
+        tx.validate()?;
+        Ok(tx)
+    }
+
+    /// Reads the encoding produced by `to_bytes()` from a stream, consuming
+    /// at most `MAX_STREAMED_LEN` bytes
+    pub fn read_from(reader: &mut impl Read) -> Result<Self> {
+        Self::read_from_with_limits(reader, &DecodeLimits::default())
+    }
+
+    /// Reads the encoding produced by `to_bytes()` from a stream, consuming
+    /// at most `limits.max_encoded_len` bytes
+    pub fn read_from_with_limits(reader: &mut impl Read, limits: &DecodeLimits) -> Result<Self> {
+        let tx = Self::decode(
+            &mut Reader::from_stream(reader, limits.max_encoded_len).with_limits(*limits),
+        )?;
+        tx.validate()?;
+        Ok(tx)
+    }
+
+    fn decode(reader: &mut Reader) -> Result<Self> {
+        let limits = reader.limits();
+        let version = reader.read_u16()?;
+        if !is_supported_version(version) && version != TX_VERSION_GENESIS {
+            return Err(Error::UnsupportedTransactionVersion(version));
+        }
+        let fee = Amount::new(reader.read_u64()?);
+        let network_id = match version & TX_NETWORK_ID {
+            0 => None,
+            _ => Some(reader.read_array()?),
+        };
+        let not_valid_after = match version & TX_EXPIRY {
+            0 => None,
+            _ => Some(reader.read_u64()?),
+        };
+        let extra = read_extra(reader, || "transaction".to_string())?;
+        let mlsags = reader.read_vec_max(limits.max_inputs, "inputs", MlsagSignature::decode)?;
+        let clsags = match tx_format(version) {
+            TX_VERSION_3 => {
+                reader.read_vec_max(limits.max_inputs, "inputs", ClsagSignature::decode)?
+            }
+            _ => vec![],
+        };
+        let matrix_mlsag = match tx_format(version) {
+            TX_VERSION_4 => match reader.read_u8()? {
+                0 => None,
+                1 => Some(MatrixMlsagSignature::decode(reader)?),
+                flag => return Err(Error::InvalidPresenceFlag(flag)),
+            },
+            _ => None,
+        };
+        let outputs = reader.read_vec_max(limits.max_outputs, "outputs", OutputProof::decode)?;
+        let aggregated_range_proof = match tx_format(version) {
+            TX_VERSION_2 => read_range_proof(reader)?,
+            _ => None,
+        };
+
+        Ok(Self {
+            version,
+            mlsags,
+            clsags,
+            matrix_mlsag,
+            outputs,
+            fee,
+            extra,
+            network_id,
+            not_valid_after,
+            aggregated_range_proof,
+        })
+    }
+
+    /// Checks the number of inputs, outputs and decoys against `policy`
+    pub fn check_policy(&self, policy: &RingCtPolicy) -> Result<()> {
+        let ring_sizes = self
+            .mlsags
+            .iter()
+            .map(|m| m.ring.len())
+            .chain(self.clsags.iter().map(|c| c.ring.len()))
+            .chain(
+                self.matrix_mlsag
+                    .iter()
+                    .flat_map(|m| m.rings.iter().map(Vec::len)),
+            )
+            .collect::<Vec<_>>();
+        policy.check(self.outputs.len(), ring_sizes.into_iter())
+    }
+
+    /// Checks the outputs against `policy.min_output_amount`, for verifiers
+    /// which know the output amounts, eg. from the sender. The revealed
+    /// output commitments must open the outputs' commitments, in order.
+    pub fn check_dust(
+        &self,
+        policy: &RingCtPolicy,
+        revealed_output_commitments: &[RevealedCommitment],
+    ) -> Result<()> {
+        if revealed_output_commitments.len() != self.outputs.len()
+            || self
+                .outputs
+                .iter()
+                .zip(revealed_output_commitments.iter())
+                .any(|(o, r)| r.commit(RingCtMaterial::pc_gens()).to_affine() != o.commitment)
+        {
+            return Err(Error::AmountSecretsDoNotMatchCommitment);
+        }
+        policy.check_output_amounts(
+            revealed_output_commitments
+                .iter()
+                .map(|r| Amount::new(r.value)),
+        )
+    }
+
+    /// Checks that every point in the transaction is a valid, non-identity point
+    /// of the prime order subgroup, that no ring contains a burn output and that
+    /// no extra data is longer than `MAX_EXTRA_LEN`. Decoding already checks
+    /// this, it's repeated by `verify()` for transactions built or deserialized
+    /// by other means.
+    pub fn validate(&self) -> Result<()> {
+        if self.version & TX_NETWORK_ID != field_flags(self.network_id.as_ref(), None) {
+            return Err(Error::NetworkIdDoesNotMatchVersion(self.version));
+        }
+        if self.version & TX_EXPIRY != field_flags(None, self.not_valid_after) {
+            return Err(Error::ExpiryDoesNotMatchVersion(self.version));
+        }
+        validate_extra(&self.extra, || "transaction".to_string())?;
+        let burn_public_key = burn_public_key();
+        let rings = self
+            .mlsags
+            .iter()
+            .map(|m| (&m.ring, m.key_image, m.pseudo_commitment, None))
+            .chain(self.clsags.iter().map(|c| {
+                (
+                    &c.ring,
+                    c.key_image,
+                    c.pseudo_commitment,
+                    Some(c.commitment_key_image),
+                )
+            }))
+            .chain(self.matrix_mlsag.iter().flat_map(|m| {
+                m.rings
+                    .iter()
+                    .zip(&m.key_images)
+                    .zip(&m.pseudo_commitments)
+                    .map(|((ring, key_image), pseudo_commitment)| {
+                        (ring, *key_image, *pseudo_commitment, None)
+                    })
+            }));
+        for (i, (ring, key_image, pseudo_commitment, commitment_key_image)) in rings.enumerate() {
+            validate_point(&key_image.to_affine(), || format!("input {} key image", i))?;
+            validate_point(&pseudo_commitment, || {
+                format!("input {} pseudo commitment", i)
+            })?;
+            if let Some(d) = commitment_key_image {
+                validate_point(&d, || format!("input {} commitment key image", i))?;
+            }
+            for (j, (public_key, hidden_commitment)) in ring.iter().enumerate() {
+                if *public_key == burn_public_key {
+                    return Err(Error::BurnOutputInRing { input_index: i });
+                }
+                validate_point(public_key, || {
+                    format!("input {} ring member {} public key", i, j)
+                })?;
+                validate_point(hidden_commitment, || {
+                    format!("input {} ring member {} commitment", i, j)
+                })?;
+            }
+        }
+
+        for (i, output) in self.outputs.iter().enumerate() {
+            validate_extra(&output.extra, || format!("output {}", i))?;
+            validate_point(&output.public_key, || format!("output {} public key", i))?;
+            validate_point(&output.commitment, || format!("output {} commitment", i))?;
+            if let Some(ephemeral_public_key) = &output.ephemeral_public_key {
+                validate_point(ephemeral_public_key, || {
+                    format!("output {} ephemeral public key", i)
+                })?;
+            }
+            if let Some(encrypted_amount) = &output.encrypted_amount {
+                validate_point(&encrypted_amount.ephemeral_public_key, || {
+                    format!("output {} encrypted amount ephemeral public key", i)
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The hash of the full encoding, including the ring signatures. Anyone
+    /// relaying the transaction could change parts of it that no signature covers,
+    /// eg. the responses of a ring signature, so ledgers should index by `id()`.
+    pub fn hash(&self) -> [u8; 32] {
+        let mut sha3 = Sha3::v256();
+
+        self.write_to(&mut DigestWriter(&mut sha3))
+            .expect("Hashing never fails");
+
+        let mut hash = [0; 32];
+        sha3.finalize(&mut hash);
+        hash
+    }
+
+    /// The transaction id, a hash of the message the ring signatures sign: the
+    /// version, fee, `network_id`, `not_valid_after`, extra data, ring public
+    /// keys, key images, pseudo commitments and the outputs with their range
+    /// proofs, encrypted amounts, extra data and locks. Changing any of these
+    /// invalidates every ring signature, so the id of a valid transaction can't
+    /// be changed without its inputs' secret keys.
+    ///
+    /// The ring signature responses and the hidden commitments of the rings are
+    /// not part of the id.
+    pub fn id(&self) -> [u8; 32] {
+        let mut sha3 = Sha3::v256();
+
+        sha3.update(TXID_DOMAIN);
+        self.absorb_message(&mut sha3);
+
+        let mut id = [0; 32];
+        sha3.finalize(&mut id);
+        id
+    }
+
+    /// The public keys of each input's ring, whichever ring signature scheme is used
+    pub fn public_keys_per_ring(&self) -> Vec<Vec<G1Affine>> {
+        self.mlsags
+            .iter()
+            .map(MlsagSignature::public_keys)
+            .chain(self.clsags.iter().map(ClsagSignature::public_keys))
+            .chain(
+                self.matrix_mlsag
+                    .iter()
+                    .flat_map(MatrixMlsagSignature::public_keys_per_ring),
+            )
+            .collect()
+    }
+
+    /// What the range proof of output `output_index` is bound to, for
+    /// verifying it with `OutputProof::verify()`
+    pub fn range_proof_context(&self, output_index: usize) -> RangeProofContext {
+        RangeProofContext {
+            version: self.version,
+            key_images: self.key_images(),
+            output_index,
+        }
+    }
+
+    /// The key image of each input's true input
+    pub fn key_images(&self) -> Vec<KeyImage> {
+        self.mlsags
+            .iter()
+            .map(MlsagSignature::key_image)
+            .chain(self.clsags.iter().map(ClsagSignature::key_image))
+            .chain(self.matrix_mlsag.iter().flat_map(|m| m.key_images.clone()))
+            .collect()
+    }
+
+    /// Checks that no input spends a key image already in `store`, or one
+    /// spent by an earlier input of this transaction
+    pub fn check_double_spend(&self, store: &impl KeyImageStore) -> Result<()> {
+        let mut seen = BTreeSet::new();
+        for (input_index, key_image) in self.key_images().into_iter().enumerate() {
+            if store.contains(&key_image) || !seen.insert(key_image) {
+                return Err(Error::DoubleSpend {
+                    input_index,
+                    key_image: key_image.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that no ring member is still locked at `current_height`. The true
+    /// input is hidden among the decoys, so every ring member must be spendable.
+    pub fn verify_rings_spendable(
+        &self,
+        lookup: &impl LockLookup,
+        current_height: u64,
+    ) -> Result<()> {
+        for public_key in self.public_keys_per_ring().iter().flatten() {
+            verify_spendable(public_key, lookup.lock(public_key), current_height)?;
+        }
+        Ok(())
+    }
+
+    /// Checks the transaction with `check_double_spend()`, then records its
+    /// key images in `store` as spent
+    pub fn spend_key_images(&self, store: &mut impl KeyImageStore) -> Result<()> {
+        self.check_double_spend(store)?;
+        for key_image in self.key_images() {
+            store.insert(key_image);
+        }
+        Ok(())
+    }
+
+    /// The pseudo commitment of each input
+    pub fn pseudo_commitments(&self) -> Vec<G1Affine> {
+        self.mlsags
+            .iter()
+            .map(MlsagSignature::pseudo_commitment)
+            .chain(self.clsags.iter().map(ClsagSignature::pseudo_commitment))
+            .chain(
+                self.matrix_mlsag
+                    .iter()
+                    .flat_map(|m| m.pseudo_commitments.clone()),
+            )
+            .collect()
+    }
+
+    // note: must match message generated by RingCtMaterial::sign()
+    pub fn gen_message(&self) -> Vec<u8> {
+        let mut msg: Vec<u8> = Default::default();
+        self.absorb_message(&mut msg);
+        msg
+    }
+
+    /// Feeds the message of `gen_message()` to `digest` without building it
+    pub(crate) fn absorb_message(&self, digest: &mut impl MessageDigest) {
+        // All public keys in all rings
+        let public_keys: Vec<G1Affine> = self.public_keys_per_ring().concat();
+
+        // All key-images (of true inputs),
+        let key_images = self.key_images();
+
+        // All PseudoCommitments.
+        let pseudo_commitments = self.pseudo_commitments();
+
+        absorb_message_for_signing(
+            digest,
+            self.version,
+            self.fee,
+            self.network_id.as_ref(),
+            self.not_valid_after,
+            &self.extra,
+            &public_keys,
+            &key_images,
+            &pseudo_commitments,
+            &self.outputs,
+            self.aggregated_range_proof.as_ref(),
+        );
+    }
+
+    /// Verifies the transaction and additionally that every ring has the size
+    /// required by `config`
+    pub fn verify_with_config(
+        &self,
+        public_commitments_per_ring: &[Vec<G1Affine>],
+        config: &RingCtConfig,
+    ) -> Result<()> {
+        for (input_index, public_keys) in self.public_keys_per_ring().iter().enumerate() {
+            config.check_ring_size(input_index, public_keys.len())?;
+        }
+        self.verify(public_commitments_per_ring)
+    }
+
+    /// Verifies the transaction under the default `RingCtPolicy`
+    pub fn verify(&self, public_commitments_per_ring: &[Vec<G1Affine>]) -> Result<()> {
+        self.verify_with_policy(public_commitments_per_ring, &RingCtPolicy::default())
+    }
+
+    /// Verifies the transaction, first checking that it is within the limits
+    /// of `policy` so that no work is spent on oversized transactions.
+    pub fn verify_with_policy(
+        &self,
+        public_commitments_per_ring: &[Vec<G1Affine>],
+        policy: &RingCtPolicy,
+    ) -> Result<()> {
+        self.verify_with_gens(
+            public_commitments_per_ring,
+            policy,
+            &self.bp_gens(),
+            RingCtMaterial::pc_gens(),
+            self.fee_commitment(),
+            &CpuBackend,
+            &DefaultPolicy,
+            RangeProofVerification::Batched,
+        )
+    }
+
+    /// Verifies the transaction like `verify()`, with the consensus rules of
+    /// `tx_policy` in place of `DefaultPolicy`
+    pub fn verify_with_tx_policy(
+        &self,
+        public_commitments_per_ring: &[Vec<G1Affine>],
+        tx_policy: &dyn TxPolicy,
+    ) -> Result<()> {
+        self.verify_with_gens(
+            public_commitments_per_ring,
+            &RingCtPolicy::default(),
+            &self.bp_gens(),
+            RingCtMaterial::pc_gens(),
+            self.fee_commitment(),
+            &CpuBackend,
+            tx_policy,
+            RangeProofVerification::Batched,
+        )
+    }
+
+    /// Verifies the transaction like `verify_with_policy()`, with the range
+    /// proofs verified as `opts` requires
+    pub fn verify_with_opts(
+        &self,
+        public_commitments_per_ring: &[Vec<G1Affine>],
+        opts: &VerifyOpts,
+    ) -> Result<()> {
+        if opts.range_proofs == RangeProofVerification::RequireAggregated
+            && self.aggregated_range_proof.is_none()
+        {
+            return Err(Error::RangeProofsNotAggregated);
+        }
+        if let Some(network_id) = &opts.network_id {
+            self.check_network(network_id)?;
+        }
+        self.verify_with_gens(
+            public_commitments_per_ring,
+            &opts.policy,
+            &self.bp_gens(),
+            RingCtMaterial::pc_gens(),
+            self.fee_commitment(),
+            &CpuBackend,
+            &DefaultPolicy,
+            opts.range_proofs,
+        )
+    }
+
+    /// Verifies the transaction for inclusion at `height`, failing if it
+    /// expired before it
+    pub fn verify_at(
+        &self,
+        public_commitments_per_ring: &[Vec<G1Affine>],
+        height: u64,
+    ) -> Result<()> {
+        self.check_expiry(height)?;
+        self.verify(public_commitments_per_ring)
+    }
+
+    /// Checks that the transaction may still be included at `height`
+    pub fn check_expiry(&self, height: u64) -> Result<()> {
+        match self.not_valid_after {
+            Some(not_valid_after) if height > not_valid_after => Err(Error::TransactionExpired {
+                not_valid_after,
+                height,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that the transaction is bound to network `network_id`, which
+    /// its signatures commit to
+    pub fn check_network(&self, network_id: &[u8; 32]) -> Result<()> {
+        if self.network_id.as_ref() != Some(network_id) {
+            return Err(Error::WrongNetwork {
+                expected: codec::to_hex(network_id),
+                actual: self.network_id.as_ref().map(|id| codec::to_hex(id)),
+            });
+        }
+        Ok(())
+    }
+
+    /// Verifies the transaction with the given generators, which must have
+    /// capacity for the transaction's aggregated range proof, if any, and
+    /// the multi-exponentiations of the ring signatures and batched range
+    /// proofs done by `backend`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn verify_with_gens(
+        &self,
+        public_commitments_per_ring: &[Vec<G1Affine>],
+        policy: &RingCtPolicy,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        fee_commitment: G1Projective,
+        backend: &dyn VerificationBackend,
+        tx_policy: &dyn TxPolicy,
+        range_proof_verification: RangeProofVerification,
+    ) -> Result<()> {
+        let result = Phase::new("verify").in_scope(|| {
+            self.verify_phases(
+                public_commitments_per_ring,
+                policy,
+                bp_gens,
+                pc_gens,
+                fee_commitment,
+                backend,
+                tx_policy,
+                range_proof_verification,
+            )
+        });
+        instrument::record_verification(&result);
+        result
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn verify_phases(
+        &self,
+        public_commitments_per_ring: &[Vec<G1Affine>],
+        policy: &RingCtPolicy,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        fee_commitment: G1Projective,
+        backend: &dyn VerificationBackend,
+        tx_policy: &dyn TxPolicy,
+        range_proof_verification: RangeProofVerification,
+    ) -> Result<()> {
+        if !is_supported_version(self.version) {
+            return Err(Error::UnsupportedTransactionVersion(self.version));
+        }
+
+        self.check_policy(policy)?;
+        self.validate()?;
+
+        let msg = self.gen_message();
+        let (ring_signatures, range_proofs) =
+            (Phase::new("ring_signatures"), Phase::new("range_proofs"));
+        let verify_ring_signatures = || {
+            ring_signatures.in_scope(|| {
+                self.verify_ring_signatures(&msg, public_commitments_per_ring, backend)
+            })
+        };
+        let verify_range_proofs = || -> Result<()> {
+            range_proofs.in_scope(|| {
+                self.verify_range_proofs_with_gens(
+                    bp_gens,
+                    pc_gens,
+                    backend,
+                    range_proof_verification,
+                )
+            })
+        };
+
+        // The range proofs are independent of the ring signatures. Batched,
+        // the per-output proofs end in a single multi-exponentiation. Each
+        // verified on its own, with `TX_INDEPENDENT_RANGE_PROOFS` each
+        // output's proof has its own transcript and they're verified
+        // concurrently, otherwise they share the transaction's transcript
+        // and must be verified in order.
+        #[cfg(feature = "parallel")]
+        {
+            let (ring_signatures_result, range_proofs_result) =
+                rayon::join(verify_ring_signatures, verify_range_proofs);
+            ring_signatures_result?;
+            range_proofs_result?;
+        }
+        #[cfg(not(feature = "parallel"))]
+        {
+            verify_ring_signatures()?;
+            verify_range_proofs()?;
+        }
+
+        tx_policy.check(self, fee_commitment)
+    }
+
+    /// Verifies the transaction like `verify()`, except for its range proofs,
+    /// which are most of the cost of verification.
+    ///
+    /// The range proofs are what stop an output committing to a "negative"
+    /// amount, ie. one wrapped around the group order, which would let the
+    /// transaction balance while minting value from nothing. Only use this
+    /// where the range proofs are already known to be valid, eg. to
+    /// re-validate a transaction a quorum has fully verified, trusting that
+    /// quorum.
+    pub fn verify_balance_and_signatures(
+        &self,
+        public_commitments_per_ring: &[Vec<G1Affine>],
+    ) -> Result<()> {
+        if !is_supported_version(self.version) {
+            return Err(Error::UnsupportedTransactionVersion(self.version));
+        }
+
+        self.check_policy(&RingCtPolicy::default())?;
+        self.validate()?;
+        self.verify_ring_signatures(
+            &self.gen_message(),
+            public_commitments_per_ring,
+            &CpuBackend,
+        )?;
+        DefaultPolicy.check(self, self.fee_commitment())
+    }
+
+    /// Verifies a genesis transaction: it has no inputs or fee, is within the
+    /// limits of the default `RingCtPolicy`, its points and extra data are
+    /// valid as `validate()` checks them, every output has a valid range
+    /// proof, and the outputs sum to `supply`.
+    pub fn verify_genesis(&self, supply: impl Into<Amount>) -> Result<()> {
+        let supply = supply.into();
+        if self.version != TX_VERSION_GENESIS {
+            return Err(Error::NotAGenesisTransaction);
+        }
+        if !self.mlsags.is_empty()
+            || !self.clsags.is_empty()
+            || self.matrix_mlsag.is_some()
+            || self.fee != Amount::ZERO
+        {
+            return Err(Error::GenesisTransactionHasInputs);
+        }
+        if self.outputs.is_empty() {
+            return Err(Error::NoOutputs);
+        }
+
+        self.check_policy(&RingCtPolicy::default())?;
+        self.validate()?;
+        self.verify_range_proofs()?;
+
+        let output_sum: G1Projective = self
+            .outputs
+            .iter()
+            .map(OutputProof::commitment)
+            .map(G1Projective::from)
+            .sum();
+        let supply_commitment =
+            RingCtMaterial::pc_gens().commit(Scalar::from(supply.value()), Scalar::zero());
+
+        if output_sum != supply_commitment {
+            return Err(Error::GenesisOutputsDoNotSumToSupply);
+        }
+        Ok(())
+    }
+
+    /// Runs every check `verify()` makes, rather than stopping at the first
+    /// failure, and reports the outcome of each.
+    pub fn verify_detailed(
+        &self,
+        public_commitments_per_ring: &[Vec<G1Affine>],
+    ) -> VerificationReport {
+        let version = if is_supported_version(self.version) {
+            Ok(())
+        } else {
+            Err(Error::UnsupportedTransactionVersion(self.version))
+        };
+
+        let msg = self.gen_message();
+        let ring_signatures = match (self.format(), &self.matrix_mlsag) {
+            (TX_VERSION_3, None) if self.mlsags.is_empty() => ring_results(
+                &self.clsags,
+                public_commitments_per_ring,
+                |i, clsag, pcs| verify_clsag(i, clsag, &msg, pcs),
+            ),
+            (TX_VERSION_1 | TX_VERSION_2, None) if self.clsags.is_empty() => ring_results(
+                &self.mlsags,
+                public_commitments_per_ring,
+                |i, mlsag, pcs| verify_mlsag(i, mlsag, &msg, pcs),
+            ),
+            // the matrix MLSAG's result is repeated for every input
+            (TX_VERSION_4, Some(matrix_mlsag))
+                if self.mlsags.is_empty() && self.clsags.is_empty() =>
+            {
+                vec![
+                    verify_matrix_mlsag(matrix_mlsag, &msg, public_commitments_per_ring);
+                    matrix_mlsag.rings.len()
+                ]
+            }
+            _ => vec![],
+        };
+        let ring_layout = if ring_signatures.is_empty() {
+            if self.mlsags.is_empty() && self.clsags.is_empty() && self.matrix_mlsag.is_none() {
+                Err(Error::TransactionMustHaveAnInput)
+            } else {
+                Err(Error::InvalidRingSignatureLayout)
+            }
+        } else if ring_signatures.len() != public_commitments_per_ring.len() {
+            Err(Error::ExpectedAPublicCommitmentsForEachRingEntry)
+        } else {
+            Ok(())
+        };
+
+        VerificationReport {
+            version,
+            policy: self.check_policy(&RingCtPolicy::default()),
+            points: self.validate(),
+            ring_layout,
+            ring_signatures,
+            range_proofs: self.range_proof_results(),
+            key_images_unique: self.verify_key_images_unique(),
+            public_keys_unique: self.verify_public_keys_unique(),
+            balance: self.verify_balance(),
+        }
+    }
+
+    // Verify that each KeyImage is unique in this tx.
+    pub(crate) fn verify_key_images_unique(&self) -> Result<()> {
+        let key_images = self.key_images();
+        let mut keyimage_unique = BTreeSet::new();
+        for (input_index, key_image) in key_images.iter().enumerate() {
+            if !keyimage_unique.insert(key_image) {
+                return Err(Error::KeyImageNotUniqueAcrossInputs {
+                    input_index,
+                    key_image: key_image.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // Verify that each public_key is unique across all input rings
+    pub(crate) fn verify_public_keys_unique(&self) -> Result<()> {
+        let mut pk_unique = BTreeSet::new();
+        for (input_index, public_keys) in self.public_keys_per_ring().iter().enumerate() {
+            for pk in public_keys {
+                let pk = pk.to_compressed();
+                if !pk_unique.insert(pk) {
+                    return Err(Error::PublicKeyNotUniqueAcrossInputs {
+                        input_index,
+                        public_key: codec::to_hex(&pk),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn verify_balance(&self) -> Result<()> {
+        self.check_balance(self.fee_commitment())
+    }
+
+    fn fee_commitment(&self) -> G1Projective {
+        RingCtMaterial::pc_gens().commit(Scalar::from(self.fee.value()), Scalar::zero())
+    }
+
+    pub(crate) fn check_balance(&self, fee_commitment: G1Projective) -> Result<()> {
+        // every amount, including the inputs' as outputs of earlier
+        // transactions, is range proven to at most RANGE_PROOF_BITS bits, so
+        // the sums can't wrap, as asserted beside `SCALAR_ORDER_BITS`
+        let input_sum: G1Projective = self
+            .pseudo_commitments()
+            .into_iter()
+            .map(G1Projective::from)
+            .sum();
+        let output_sum: G1Projective = self
+            .outputs
+            .iter()
+            .map(OutputProof::commitment)
+            .map(G1Projective::from)
+            .sum();
+
+        if input_sum != output_sum + fee_commitment {
+            Err(Error::InputPseudoCommitmentsDoNotSumToOutputCommitments)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn verify_ring_signatures(
+        &self,
+        msg: &[u8],
+        public_commitments_per_ring: &[Vec<G1Affine>],
+        backend: &dyn VerificationBackend,
+    ) -> Result<()> {
+        match (self.format(), &self.matrix_mlsag) {
+            (TX_VERSION_3, None) if self.mlsags.is_empty() => verify_rings(
+                &self.clsags,
+                public_commitments_per_ring,
+                |i, clsag, pcs| verify_clsag(i, clsag, msg, pcs),
+            ),
+            (TX_VERSION_1 | TX_VERSION_2, None) if self.clsags.is_empty() => {
+                verify_mlsags(&self.mlsags, msg, public_commitments_per_ring, backend)
+            }
+            (TX_VERSION_4, Some(matrix_mlsag))
+                if self.mlsags.is_empty() && self.clsags.is_empty() =>
+            {
+                verify_matrix_mlsag(matrix_mlsag, msg, public_commitments_per_ring)
+            }
+            _ => Err(Error::InvalidRingSignatureLayout),
+        }
+    }
+
+    /// Verifies the transaction, resolving the commitment of each ring member
+    /// through `lookup` rather than taking them pre-assembled
+    pub fn verify_with_lookup(&self, lookup: &impl CommitmentLookup) -> Result<()> {
+        self.verify(&self.lookup_public_commitments(lookup)?)
+    }
+
+    pub(crate) fn lookup_public_commitments(
+        &self,
+        lookup: &impl CommitmentLookup,
+    ) -> Result<Vec<Vec<G1Affine>>> {
+        self.public_keys_per_ring()
+            .iter()
+            .enumerate()
+            .map(|(input_index, public_keys)| {
+                public_keys
+                    .iter()
+                    .map(|pk| {
+                        lookup
+                            .commitment(pk)
+                            .ok_or_else(|| Error::CommitmentNotFound {
+                                input_index,
+                                public_key: codec::to_hex(&pk.to_compressed()),
+                            })
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .collect()
+    }
+
+    fn verify_range_proofs(&self) -> Result<()> {
+        self.range_proof_results().into_iter().collect()
+    }
+
+    /// Verifies the range proofs as `range_proof_verification` requires. A
+    /// failed batch falls back to verifying each proof, to name the output.
+    fn verify_range_proofs_with_gens(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        backend: &dyn VerificationBackend,
+        range_proof_verification: RangeProofVerification,
+    ) -> Result<()> {
+        if range_proof_verification == RangeProofVerification::Batched
+            && self.verify_range_proof_batch(pc_gens, backend).is_ok()
+        {
+            return Ok(());
+        }
+        self.range_proof_results_with_gens(bp_gens, pc_gens)
+            .into_iter()
+            .collect()
+    }
+
+    /// Verifies the range proofs made separately for each output as one
+    /// batch, see `RangeProofVerification::Batched`. The error doesn't tell
+    /// which output failed.
+    fn verify_range_proof_batch(
+        &self,
+        pc_gens: &PedersenGens,
+        backend: &dyn VerificationBackend,
+    ) -> Result<()> {
+        if !matches!(
+            (self.format(), &self.aggregated_range_proof),
+            (
+                TX_VERSION_1 | TX_VERSION_3 | TX_VERSION_4 | TX_VERSION_GENESIS,
+                None
+            )
+        ) {
+            return Err(Error::InvalidRangeProofLayout);
+        }
+
+        let mut batch = RangeProofBatch::new(range_proof_bits(self.version)?)?;
+        let mut prover_ts = range_proof_transcript(self.version, &self.key_images());
+        let mut rng = OsRng;
+        for (output_index, output) in self.outputs.iter().enumerate() {
+            let range_proof = output
+                .range_proof
+                .as_ref()
+                .ok_or(Error::InvalidRangeProofLayout)?;
+            if self.version & TX_INDEPENDENT_RANGE_PROOFS != 0 {
+                let mut transcript =
+                    output_transcript(&prover_ts, output_index, &output.public_key);
+                batch.push(range_proof, &output.commitment, &mut transcript, &mut rng)?;
+            } else {
+                append_output(&mut prover_ts, output_index, &output.public_key);
+                batch.push(range_proof, &output.commitment, &mut prover_ts, &mut rng)?;
+            }
+        }
+        Ok(batch.verify(pc_gens, backend)?)
+    }
+
+    // Generators with capacity for this transaction's range proofs
+    fn bp_gens(&self) -> Cow<'static, BulletproofGens> {
+        RingCtMaterial::aggregated_bp_gens(aggregated_range_proof_parties(self.outputs.len()))
+    }
+
+    /// The range proof result of each output. Without
+    /// `TX_INDEPENDENT_RANGE_PROOFS` the per-output proofs share a transcript,
+    /// so the outputs following a failed proof may fail too. An aggregated
+    /// proof's result is repeated for every output.
+    fn range_proof_results(&self) -> Vec<Result<()>> {
+        self.range_proof_results_with_gens(&self.bp_gens(), RingCtMaterial::pc_gens())
+    }
+
+    fn range_proof_results_with_gens(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+    ) -> Vec<Result<()>> {
+        // Verification requires a transcript with identical initial state:
+        let mut prover_ts = range_proof_transcript(self.version, &self.key_images());
+        let num_outputs = self.outputs.len();
+        let bits = match range_proof_bits(self.version) {
+            Ok(bits) => bits,
+            Err(err) => return vec![Err(err); num_outputs],
+        };
+
+        let verify_output = |output_index: usize,
+                             output: &OutputProof,
+                             transcript: &mut Transcript|
+         -> Result<()> {
+            output.verify_range_proof(output_index, bp_gens, pc_gens, transcript, bits)
+        };
+
+        match (self.format(), &self.aggregated_range_proof) {
+            (TX_VERSION_1 | TX_VERSION_3 | TX_VERSION_4 | TX_VERSION_GENESIS, None)
+                if self.version & TX_INDEPENDENT_RANGE_PROOFS != 0 =>
+            {
+                map_indexed(&self.outputs, |output_index, output| {
+                    let mut transcript =
+                        output_transcript(&prover_ts, output_index, &output.public_key);
+                    verify_output(output_index, output, &mut transcript)
+                })
+            }
+            (TX_VERSION_1 | TX_VERSION_3 | TX_VERSION_4 | TX_VERSION_GENESIS, None) => self
+                .outputs
+                .iter()
+                .enumerate()
+                .map(|(output_index, output)| {
+                    append_output(&mut prover_ts, output_index, &output.public_key);
+                    verify_output(output_index, output, &mut prover_ts)
+                })
+                .collect(),
+            (TX_VERSION_2, Some(range_proof)) => {
+                let result = if self.outputs.iter().any(|o| o.range_proof.is_some()) {
+                    Err(Error::InvalidRangeProofLayout)
+                } else {
+                    for (output_index, output) in self.outputs.iter().enumerate() {
+                        append_output(&mut prover_ts, output_index, &output.public_key);
+                    }
+                    let parties = aggregated_range_proof_parties(num_outputs);
+                    let mut commitments: Vec<G1Affine> =
+                        self.outputs.iter().map(|o| o.commitment).collect();
+                    // padding: commitments to zero with a zero blinding factor
+                    commitments.resize(parties, G1Affine::identity());
+                    range_proof
+                        .verify_multiple(bp_gens, pc_gens, &mut prover_ts, &commitments, bits)
+                        .map_err(Error::from)
+                };
+                vec![result; num_outputs]
+            }
+            (TX_VERSION_1 | TX_VERSION_2 | TX_VERSION_3 | TX_VERSION_4 | TX_VERSION_GENESIS, _) => {
+                vec![Err(Error::InvalidRangeProofLayout); num_outputs]
+            }
+            _ => vec![Err(Error::UnsupportedTransactionVersion(self.version)); num_outputs],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeSet, HashSet};
+
+    use bls_bulletproofs::{
+        group::{ff::Field, Curve, Group},
+        rand::rngs::OsRng,
+    };
+
+    use crate::{mock::MockLedger, MlsagMaterial, StealthKeys, TrueInput};
+
+    use super::*;
+
+    fn test_material(
+        output_amounts: &[u64],
+        mut rng: impl RngCore + CryptoRng,
+    ) -> (RingCtMaterial, MockLedger) {
+        let pc_gens = crate::pedersen_gens();
+        let mut ledger = MockLedger::new();
+
+        let true_input = TrueInput::new(
+            Scalar::random(&mut rng),
+            RevealedCommitment::from_value(output_amounts.iter().sum(), &mut rng),
+        );
+        ledger.log(
+            true_input.public_key(),
+            true_input.revealed_commitment.commit(&pc_gens),
+        );
+        for _ in 0..2 {
+            ledger.log(
+                G1Projective::random(&mut rng),
+                G1Projective::random(&mut rng),
+            );
+        }
+        let decoy_inputs = ledger.fetch_decoys(2, &[true_input.public_key()]);
+
+        let ring_ct = RingCtMaterial {
+            inputs: vec![MlsagMaterial::new(true_input, decoy_inputs, &mut rng).unwrap()],
+            outputs: output_amounts
+                .iter()
+                .map(|amount| Output::new(G1Projective::random(&mut rng), *amount))
+                .collect(),
+            fee: Amount::ZERO,
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+
+        (ring_ct, ledger)
+    }
+
+    fn ring_commitments(tx: &RingCtTransaction, ledger: &MockLedger) -> Vec<Vec<G1Affine>> {
+        Vec::from_iter(tx.public_keys_per_ring().into_iter().map(|public_keys| {
+            Vec::from_iter(public_keys.into_iter().map(|pk| ledger.lookup(pk).unwrap()))
+        }))
+    }
+
+    fn signed_test_tx(
+        mut rng: impl RngCore + CryptoRng,
+    ) -> (RingCtTransaction, Vec<Vec<G1Affine>>) {
+        let (ring_ct, ledger) = test_material(&[2, 1], &mut rng);
+        let (tx, _revealed_output_commitments) =
+            ring_ct.sign(&mut rng).expect("Failed to sign transaction");
+        let public_commitments = ring_commitments(&tx, &ledger);
+
+        (tx, public_commitments)
+    }
+
+    #[test]
+    fn test_ringct_sign() {
+        let mut rng = OsRng;
+        let pc_gens = crate::pedersen_gens();
+
+        let true_input = TrueInput {
+            secret_key: Scalar::random(&mut rng),
+            revealed_commitment: RevealedCommitment {
+                value: 3,
+                blinding: 5.into(),
+            },
+        };
+
+        let mut ledger = MockLedger::new();
+        ledger.log(
+            true_input.public_key(),
+            true_input.revealed_commitment.commit(&pc_gens),
+        );
+        ledger.log(
+            G1Projective::random(&mut rng),
+            G1Projective::random(&mut rng),
+        );
+        ledger.log(
+            G1Projective::random(&mut rng),
+            G1Projective::random(&mut rng),
+        );
+
+        let decoy_inputs = ledger.fetch_decoys(2, &[true_input.public_key()]);
+
+        let ring_ct = RingCtMaterial {
+            inputs: vec![MlsagMaterial::new(true_input, decoy_inputs, &mut rng).unwrap()],
+            outputs: vec![Output {
+                public_key: G1Projective::random(&mut rng).to_affine(),
+                amount: Amount::new(3),
+                ephemeral_public_key: None,
+                view_tag: None,
+                view_public_key: None,
+                extra: vec![],
+                lock: None,
+                blinding: None,
+                rewind_key: None,
+            }],
+            fee: Amount::ZERO,
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+
+        let (signed_tx, _revealed_output_commitments) =
+            ring_ct.sign(&mut rng).expect("Failed to sign transaction");
+
+        let public_commitments = Vec::from_iter(signed_tx.mlsags.iter().map(|mlsag| {
+            Vec::from_iter(
+                mlsag
+                    .public_keys()
+                    .into_iter()
+                    .map(|pk| ledger.lookup(pk).unwrap()),
+            )
+        }));
+
+        assert!(signed_tx.verify(&public_commitments).is_ok());
+    }
+
+    #[test]
+    fn test_tx_bytes_round_trip() {
+        let (tx, public_commitments) = signed_test_tx(OsRng);
+
+        let bytes = tx.to_bytes();
+        let parsed = RingCtTransaction::from_bytes(&bytes).expect("Failed to parse transaction");
+        assert_eq!(parsed, tx);
+        assert_eq!(parsed.to_bytes(), bytes);
+        assert_eq!(RingCtTransaction::try_from(bytes.as_slice()), Ok(tx));
+        assert!(parsed.verify(&public_commitments).is_ok());
+    }
+
+    #[test]
+    fn test_tx_from_bytes_rejects_truncated_and_trailing_bytes() {
+        let (tx, _) = signed_test_tx(OsRng);
+        let bytes = tx.to_bytes();
+
+        assert_eq!(
+            RingCtTransaction::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(Error::UnexpectedEndOfBytes)
+        );
+
+        let mut extended = bytes.clone();
+        extended.push(0);
+        assert_eq!(
+            RingCtTransaction::from_bytes(&extended),
+            Err(Error::TrailingBytes)
+        );
+    }
+
+    #[test]
+    fn test_transactions_deduplicate_in_hash_sets() {
+        let (tx, _) = signed_test_tx(OsRng);
+        let (other_tx, _) = signed_test_tx(OsRng);
+        let decoded = RingCtTransaction::from_bytes(&tx.to_bytes()).unwrap();
+
+        let txs = HashSet::from([tx.clone(), other_tx, decoded]);
+        assert_eq!(txs.len(), 2);
+        assert!(txs.contains(&tx));
+
+        let outputs =
+            HashSet::<OutputProof>::from_iter(tx.outputs.iter().chain(tx.outputs.iter()).cloned());
+        assert_eq!(outputs.len(), tx.outputs.len());
+
+        let mlsags = HashSet::<MlsagSignature>::from_iter(tx.mlsags.iter().cloned());
+        assert!(mlsags.contains(&tx.mlsags[0]));
+
+        let revealed = RevealedCommitment::from_value(3, OsRng);
+        let commitments = HashSet::from([revealed, revealed]);
+        assert_eq!(commitments.len(), 1);
+    }
+
+    #[test]
+    fn test_verify_balance_and_signatures() {
+        let (mut tx, public_commitments) = signed_test_tx(OsRng);
+        assert_eq!(
+            tx.verify_balance_and_signatures(&public_commitments),
+            Ok(())
+        );
+
+        let mut wrong_commitments = public_commitments.clone();
+        wrong_commitments[0].reverse();
+        assert!(tx
+            .verify_balance_and_signatures(&wrong_commitments)
+            .is_err());
+
+        tx.fee = Amount::new(1);
+        assert!(tx
+            .verify_balance_and_signatures(&public_commitments)
+            .is_err());
+    }
+
+    #[test]
+    fn test_serialized_size_and_estimate() {
+        let (tx, _) = signed_test_tx(OsRng);
+
+        assert_eq!(tx.serialized_size(), tx.to_bytes().len());
+        assert_eq!(
+            RingCtTransaction::estimate_size(
+                tx.mlsags.len(),
+                tx.mlsags[0].ring.len(),
+                tx.outputs.len()
+            ),
+            tx.serialized_size()
+        );
+    }
+
+    #[test]
+    fn test_tx_streams_through_write_and_read() {
+        let (tx, _) = signed_test_tx(OsRng);
+        let (other_tx, _) = signed_test_tx(OsRng);
+
+        let mut stream: Vec<u8> = Default::default();
+        tx.write_to(&mut stream).unwrap();
+        other_tx.write_to(&mut stream).unwrap();
+        tx.mlsags[0].write_to(&mut stream).unwrap();
+        tx.outputs[0].write_to(&mut stream).unwrap();
+        assert_eq!(&stream[..tx.to_bytes().len()], tx.to_bytes().as_slice());
+
+        let mut reader = stream.as_slice();
+        assert_eq!(RingCtTransaction::read_from(&mut reader), Ok(tx.clone()));
+        assert_eq!(RingCtTransaction::read_from(&mut reader), Ok(other_tx));
+        assert_eq!(
+            MlsagSignature::read_from(&mut reader),
+            Ok(tx.mlsags[0].clone())
+        );
+        assert_eq!(
+            OutputProof::read_from(&mut reader),
+            Ok(tx.outputs[0].clone())
+        );
+        assert_eq!(
+            RingCtTransaction::read_from(&mut reader),
+            Err(Error::UnexpectedEndOfBytes)
+        );
+    }
+
+    #[test]
+    fn test_read_from_rejects_oversized_encodings() {
+        let (tx, _) = signed_test_tx(OsRng);
+
+        // a public key followed by a range proof length of 4GiB
+        let mut bytes = tx.outputs[0].public_key.to_compressed().to_vec();
+        bytes.extend(u32::MAX.to_le_bytes());
+        assert_eq!(
+            OutputProof::read_from(&mut bytes.as_slice()),
+            Err(Error::EncodingTooLong(MAX_STREAMED_LEN))
+        );
+    }
+
+    #[test]
+    fn test_decode_limits() {
+        let (tx, _) = signed_test_tx(OsRng);
+
+        // a header without extra or inputs, declaring 2^32 - 1 outputs
+        let mut bytes = CURRENT_TX_VERSION.to_le_bytes().to_vec();
+        bytes.extend(0u64.to_le_bytes());
+        bytes.extend(0u32.to_le_bytes());
+        bytes.extend(0u32.to_le_bytes());
+        bytes.extend(u32::MAX.to_le_bytes());
+        assert_eq!(
+            RingCtTransaction::from_bytes(&bytes),
+            Err(Error::DecodeLimitExceeded {
+                field: "outputs".to_string(),
+                max: DEFAULT_MAX_OUTPUTS,
+                len: u32::MAX as usize
+            })
+        );
+        // without limits, the length is still checked against the bytes left
+        let unlimited = DecodeLimits {
+            max_outputs: usize::MAX,
+            ..Default::default()
+        };
+        assert_eq!(
+            RingCtTransaction::from_bytes_with_limits(&bytes, &unlimited),
+            Err(Error::UnexpectedEndOfBytes)
+        );
+
+        let strict = DecodeLimits {
+            max_ring_size: 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            RingCtTransaction::from_bytes_with_limits(&tx.to_bytes(), &strict),
+            Err(Error::DecodeLimitExceeded {
+                field: "ring members".to_string(),
+                max: 2,
+                len: 3
+            })
+        );
+        let strict = DecodeLimits {
+            max_encoded_len: tx.serialized_size() - 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            RingCtTransaction::read_from_with_limits(&mut tx.to_bytes().as_slice(), &strict),
+            Err(Error::EncodingTooLong(strict.max_encoded_len))
+        );
+        assert_eq!(
+            RingCtTransaction::from_bytes_with_limits(
+                &tx.to_bytes(),
+                &DecodeLimits::from_policy(&RingCtPolicy::default())
+            ),
+            Ok(tx)
+        );
+    }
+
+    #[test]
+    fn test_unknown_tx_version_is_rejected() {
+        let (mut tx, public_commitments) = signed_test_tx(OsRng);
+        tx.version = (TX_VERSION_4 + 1) | TX_FLAGS;
+
+        assert_eq!(
+            tx.verify(&public_commitments),
+            Err(Error::UnsupportedTransactionVersion(tx.version))
+        );
+        assert_eq!(
+            RingCtTransaction::from_bytes(&tx.to_bytes()),
+            Err(Error::UnsupportedTransactionVersion(tx.version))
+        );
+    }
+
+    #[test]
+    fn test_legacy_versions_are_rejected() {
+        let (mut tx, public_commitments) = signed_test_tx(OsRng);
+        assert_eq!(tx.version, CURRENT_TX_VERSION);
+        assert_eq!(tx.format(), TX_VERSION_1);
+        assert_eq!(tx.range_proof_bits(), Ok(64));
+
+        tx.version = TX_VERSION_1;
+        assert_eq!(
+            tx.verify(&public_commitments),
+            Err(Error::UnsupportedTransactionVersion(TX_VERSION_1))
+        );
+        tx.version = TX_VERSION_1 | TX_RFC9380_KEY_IMAGES;
+        assert_eq!(
+            tx.verify(&public_commitments),
+            Err(Error::UnsupportedTransactionVersion(tx.version))
+        );
+        assert_eq!(
+            RingCtTransaction::from_bytes(&tx.to_bytes()),
+            Err(Error::UnsupportedTransactionVersion(tx.version))
+        );
+
+        let key_image = crate::key_image(Scalar::from(7u64));
+        assert_eq!(
+            key_image,
+            G1Projective::hash_to_curve(
+                &crate::public_key(Scalar::from(7u64)).to_compressed(),
+                crate::HASH_TO_CURVE_DST,
+                &[]
+            ) * Scalar::from(7u64)
+        );
+    }
+
+    #[test]
+    fn test_aggregated_range_proof() {
+        let mut rng = OsRng;
+
+        // three outputs, padded to four parties in the aggregated proof
+        let (ring_ct, ledger) = test_material(&[5, 3, 2], rng);
+        let (tx, _revealed_output_commitments) = ring_ct
+            .sign_with_version(TX_VERSION_2, &mut rng)
+            .expect("Failed to sign transaction");
+
+        assert!(tx.aggregated_range_proof.is_some());
+        assert!(tx.outputs.iter().all(|o| o.range_proof().is_none()));
+        assert!(tx.verify(&ring_commitments(&tx, &ledger)).is_ok());
+
+        let parsed = RingCtTransaction::from_bytes(&tx.to_bytes()).expect("Failed to parse tx");
+        assert_eq!(parsed, tx);
+
+        // the single proof has no per-output transcripts to flag as independent
+        assert_eq!(tx.version & TX_INDEPENDENT_RANGE_PROOFS, 0);
+        let mut flagged = tx.clone();
+        flagged.version |= TX_INDEPENDENT_RANGE_PROOFS;
+        assert_eq!(
+            flagged.verify(&ring_commitments(&tx, &ledger)),
+            Err(Error::UnsupportedTransactionVersion(flagged.version))
+        );
+        assert_eq!(
+            RingCtTransaction::from_bytes(&flagged.to_bytes()),
+            Err(Error::UnsupportedTransactionVersion(flagged.version))
+        );
+
+        // dropping the aggregated proof must not be accepted
+        let mut stripped = tx;
+        stripped.aggregated_range_proof = None;
+        assert_eq!(
+            stripped.verify_range_proofs(),
+            Err(Error::InvalidRangeProofLayout)
+        );
+    }
+
+    #[test]
+    fn test_sign_rejects_unbalanced_material() {
+        let mut rng = OsRng;
+
+        let (mut ring_ct, _ledger) = test_material(&[2, 1], rng);
+        ring_ct.outputs[0].amount = Amount::new(3);
+        assert_eq!(
+            ring_ct.sign(&mut rng).err(),
+            Some(Error::InvalidBalance {
+                input_amount: 3,
+                output_amount: 4
+            })
+        );
+
+        ring_ct.outputs.clear();
+        assert_eq!(ring_ct.sign(&mut rng).err(), Some(Error::NoOutputs));
+    }
+
+    #[test]
+    fn test_sign_rejects_overflowing_output_amounts() {
+        let mut rng = OsRng;
+
+        // the outputs would wrap around to the input amount of u64::MAX
+        let (mut ring_ct, _ledger) = test_material(&[u64::MAX], rng);
+        ring_ct.outputs[0].amount = Amount::new(u64::MAX - 1);
+        ring_ct
+            .outputs
+            .push(Output::new(G1Projective::random(&mut rng), 2));
+        assert_eq!(ring_ct.sign(&mut rng).err(), Some(Error::AmountOverflow));
+
+        assert_eq!(
+            Amount::checked_sum([Amount::MAX, Amount::new(1)]),
+            Err(Error::AmountOverflow)
+        );
+        assert_eq!(
+            Amount::checked_sum([Amount::new(2), Amount::new(1)]),
+            Ok(Amount::new(3))
+        );
+    }
+
+    #[test]
+    fn test_wrapped_output_amounts_do_not_balance() {
+        let mut rng = OsRng;
+        let (ring_ct, ledger) = test_material(&[2, 1], rng);
+        let (tx, revealed_output_commitments) =
+            ring_ct.sign(&mut rng).expect("Failed to sign transaction");
+        let public_commitments = ring_commitments(&tx, &ledger);
+
+        // moving more than an output's amount to another still balances, the
+        // output committing to the amount wrapped around the group order
+        let shift =
+            RingCtMaterial::pc_gens().B * Scalar::from(revealed_output_commitments[1].value + 1);
+        let mut minted = tx;
+        minted.outputs[0].commitment =
+            (G1Projective::from(minted.outputs[0].commitment) + shift).to_affine();
+        minted.outputs[1].commitment =
+            (G1Projective::from(minted.outputs[1].commitment) - shift).to_affine();
+        assert_eq!(minted.check_balance(minted.fee_commitment()), Ok(()));
+        assert!(minted.range_proof_results()[1].is_err());
+        assert!(minted.verify(&public_commitments).is_err());
+
+        // so amounts are bounded such that no number of them wraps
+        assert_eq!(check_sum_cannot_wrap(usize::MAX, RANGE_PROOF_BITS), Ok(()));
+        assert_eq!(
+            check_sum_cannot_wrap(usize::MAX, 2 * RANGE_PROOF_BITS),
+            Ok(())
+        );
+        assert_eq!(
+            check_sum_cannot_wrap(2, 253),
+            Err(Error::BalanceMayWrap {
+                terms: 2,
+                bits: 253
+            })
+        );
+    }
+
+    #[test]
+    fn test_outputs_summing_past_u64_max_are_rejected() {
+        let mut rng = OsRng;
+        let (mut ring_ct, _ledger) = test_material(&[4], rng);
+
+        // 4 * (2^62 + 1), which a wrapping u64 sum would take for the input's 4
+        ring_ct.outputs = (0..4)
+            .map(|_| Output::new(G1Projective::random(&mut rng), u64::MAX / 4 + 2))
+            .collect();
+        assert_eq!(
+            Amount::checked_sum(ring_ct.outputs.iter().map(Output::amount)),
+            Err(Error::AmountOverflow)
+        );
+        assert_eq!(ring_ct.validate(), Err(Error::AmountOverflow));
+        assert_eq!(ring_ct.sign(&mut rng).err(), Some(Error::AmountOverflow));
+    }
+
+    #[test]
+    fn test_verify_opts_can_require_aggregated_range_proofs() {
+        let mut rng = OsRng;
+        let (ring_ct, ledger) = test_material(&[2, 1], rng);
+        let opts = VerifyOpts {
+            range_proofs: RangeProofVerification::RequireAggregated,
+            ..Default::default()
+        };
+
+        let (tx, _) = ring_ct.sign(&mut rng).expect("Failed to sign transaction");
+        let public_commitments = ring_commitments(&tx, &ledger);
+        assert_eq!(
+            tx.verify_with_opts(&public_commitments, &VerifyOpts::default()),
+            Ok(())
+        );
+        assert_eq!(
+            tx.verify_with_opts(&public_commitments, &opts),
+            Err(Error::RangeProofsNotAggregated)
+        );
+
+        let (tx, _) = ring_ct
+            .sign_with_version(TX_VERSION_2, &mut rng)
+            .expect("Failed to sign transaction");
+        assert_eq!(
+            tx.verify_with_opts(&ring_commitments(&tx, &ledger), &opts),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_range_proofs_verify_as_a_batch() {
+        let mut rng = OsRng;
+        let (ring_ct, ledger) = test_material(&[2, 1, 3], rng);
+        let pc_gens = RingCtMaterial::pc_gens();
+        let as_proven = VerifyOpts {
+            range_proofs: RangeProofVerification::AsProven,
+            ..Default::default()
+        };
+
+        for version in [
+            TX_VERSION_1,
+            version_with_range_proof_bits(TX_VERSION_1, 32).unwrap(),
+        ] {
+            let (mut tx, _) = ring_ct
+                .sign_with_version(version, &mut rng)
+                .expect("Failed to sign transaction");
+            let public_commitments = ring_commitments(&tx, &ledger);
+            assert_eq!(tx.verify_range_proof_batch(pc_gens, &CpuBackend), Ok(()));
+            assert_eq!(
+                tx.verify_with_opts(&public_commitments, &VerifyOpts::default()),
+                Ok(())
+            );
+            assert_eq!(tx.verify_with_opts(&public_commitments, &as_proven), Ok(()));
+
+            // The batch fails on any invalid proof, and verifying each proof
+            // then reports which one it was.
+            tx.outputs[1].range_proof = tx.outputs[2].range_proof.clone();
+            assert!(tx.verify_range_proof_batch(pc_gens, &CpuBackend).is_err());
+            assert!(matches!(
+                tx.range_proof_results()[..],
+                [
+                    Ok(()),
+                    Err(Error::RangeProofInvalid {
+                        output_index: 1,
+                        ..
+                    }),
+                    Ok(())
+                ]
+            ));
+        }
+    }
+
+    #[test]
+    fn test_range_proof_batches_across_formats_and_bit_lengths() {
+        let mut rng = OsRng;
+        let (ring_ct, ledger) = test_material(&[2, 1, 3, 4, 5, 6, 7, 8], rng);
+        let pc_gens = RingCtMaterial::pc_gens();
+
+        for format in [TX_VERSION_1, TX_VERSION_3, TX_VERSION_4] {
+            for bits in [8, 16, 32, 64] {
+                let version = version_with_range_proof_bits(format, bits).unwrap();
+                let (mut tx, _) = ring_ct
+                    .sign_with_version(version, &mut rng)
+                    .expect("Failed to sign transaction");
+                let public_commitments = ring_commitments(&tx, &ledger);
+                assert_eq!(tx.verify_range_proof_batch(pc_gens, &CpuBackend), Ok(()));
+                assert_eq!(
+                    tx.verify_with_opts(&public_commitments, &VerifyOpts::default()),
+                    Ok(())
+                );
+
+                // A single bad proof among the outputs fails the batch, and
+                // the fallback names its output.
+                let bad_output = 5;
+                tx.outputs[bad_output].range_proof = tx.outputs[0].range_proof.clone();
+                assert!(tx.verify_range_proof_batch(pc_gens, &CpuBackend).is_err());
+                let result = tx.verify_range_proofs_with_gens(
+                    &tx.bp_gens(),
+                    pc_gens,
+                    &CpuBackend,
+                    RangeProofVerification::Batched,
+                );
+                assert!(
+                    matches!(
+                        result,
+                        Err(Error::RangeProofInvalid { output_index, .. })
+                            if output_index == bad_output
+                    ),
+                    "{result:?} for version {version:#06x}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_fee_is_part_of_the_balance() {
+        let mut rng = OsRng;
+
+        let (mut ring_ct, ledger) = test_material(&[2, 1], rng);
+        ring_ct.outputs[0].amount = Amount::new(1);
+        ring_ct.fee = Amount::new(1);
+        let (tx, _revealed_output_commitments) =
+            ring_ct.sign(&mut rng).expect("Failed to sign transaction");
+        let public_commitments = ring_commitments(&tx, &ledger);
+        assert!(tx.verify(&public_commitments).is_ok());
+
+        let parsed = RingCtTransaction::from_bytes(&tx.to_bytes()).expect("Failed to parse tx");
+        assert_eq!(parsed.fee, 1);
+
+        // changing the fee invalidates the signatures
+        let mut tampered = tx;
+        tampered.fee = Amount::new(2);
+        assert!(tampered.verify(&public_commitments).is_err());
+    }
+
+    #[test]
+    fn test_locked_outputs() {
+        let mut rng = OsRng;
+
+        let (mut ring_ct, ledger) = test_material(&[2, 1], rng);
+        ring_ct.outputs[0] = ring_ct.outputs[0].clone().with_lock(100);
+        let (tx, _revealed_output_commitments) =
+            ring_ct.sign(&mut rng).expect("Failed to sign transaction");
+        let public_commitments = ring_commitments(&tx, &ledger);
+        assert_eq!(tx.verify(&public_commitments), Ok(()));
+
+        let parsed = RingCtTransaction::from_bytes(&tx.to_bytes()).expect("Failed to parse tx");
+        assert_eq!(parsed, tx);
+
+        let locked = &tx.outputs[0];
+        assert_eq!(locked.lock(), Some(100));
+        assert!(matches!(
+            locked.verify_spendable(99),
+            Err(Error::OutputLocked { lock: 100, .. })
+        ));
+        assert_eq!(locked.verify_spendable(100), Ok(()));
+        assert_eq!(tx.outputs[1].verify_spendable(0), Ok(()));
+
+        // a transaction spending a ring with a locked member is rejected
+        let locked_ring_member = tx.public_keys_per_ring()[0][0];
+        let lookup = |pk: &G1Affine| (*pk == locked_ring_member).then_some(10);
+        assert!(matches!(
+            tx.verify_rings_spendable(&lookup, 9),
+            Err(Error::OutputLocked { lock: 10, .. })
+        ));
+        assert_eq!(tx.verify_rings_spendable(&lookup, 10), Ok(()));
+
+        // the lock can't be removed without invalidating the signatures
+        let mut unlocked = tx;
+        unlocked.outputs[0].lock = None;
+        assert!(unlocked.verify(&public_commitments).is_err());
+    }
+
+    #[test]
+    fn test_extra_data_is_signed() {
+        let mut rng = OsRng;
+
+        let (mut ring_ct, ledger) = test_material(&[2, 1], rng);
+        ring_ct.extra = b"invoice 42".to_vec();
+        ring_ct.outputs[1] = ring_ct.outputs[1]
+            .clone()
+            .with_extra(b"payment id".to_vec());
+        let (tx, _revealed_output_commitments) =
+            ring_ct.sign(&mut rng).expect("Failed to sign transaction");
+        let public_commitments = ring_commitments(&tx, &ledger);
+        assert_eq!(tx.verify(&public_commitments), Ok(()));
+        assert_eq!(tx.outputs[0].extra(), b"");
+        assert_eq!(tx.outputs[1].extra(), b"payment id");
+
+        let parsed = RingCtTransaction::from_bytes(&tx.to_bytes()).expect("Failed to parse tx");
+        assert_eq!(parsed, tx);
+
+        // changing either invalidates the signatures
+        let mut tampered = tx.clone();
+        tampered.extra = b"invoice 43".to_vec();
+        assert!(tampered.verify(&public_commitments).is_err());
+        let mut tampered = tx;
+        tampered.outputs[1].extra = b"payment id 2".to_vec();
+        assert!(tampered.verify(&public_commitments).is_err());
+
+        ring_ct.extra = vec![0; MAX_EXTRA_LEN + 1];
+        assert_eq!(
+            ring_ct.sign(&mut rng).err(),
+            Some(Error::ExtraTooLong {
+                location: "transaction".to_string(),
+                len: MAX_EXTRA_LEN + 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_network_id_prevents_replay() {
+        let mut rng = OsRng;
+        let (mainnet, testnet) = ([1; 32], [2; 32]);
+
+        let (mut ring_ct, ledger) = test_material(&[2, 1], rng);
+        ring_ct.network_id = Some(testnet);
+        let (tx, _revealed_output_commitments) = ring_ct
+            .sign_with_version(TX_VERSION_2, &mut rng)
+            .expect("Failed to sign transaction");
+        let public_commitments = ring_commitments(&tx, &ledger);
+        assert_eq!(tx.version & TX_NETWORK_ID, TX_NETWORK_ID);
+        assert_eq!(tx.range_proof_bits(), Ok(64));
+        assert_eq!(tx.format(), TX_VERSION_2);
+        assert_eq!(tx.serialized_size(), tx.to_bytes().len());
+        assert_eq!(
+            RingCtTransaction::from_bytes(&tx.to_bytes()),
+            Ok(tx.clone())
+        );
+
+        let opts = |network_id| VerifyOpts {
+            network_id: Some(network_id),
+            ..Default::default()
+        };
+        assert_eq!(
+            tx.verify_with_opts(&public_commitments, &opts(testnet)),
+            Ok(())
+        );
+        assert_eq!(
+            tx.verify_with_opts(&public_commitments, &opts(mainnet)),
+            Err(Error::WrongNetwork {
+                expected: codec::to_hex(&mainnet),
+                actual: Some(codec::to_hex(&testnet)),
+            })
+        );
+
+        // relabelling the network invalidates the signatures, and dropping it
+        // without clearing the version flag is malformed
+        let mut relabelled = tx.clone();
+        relabelled.network_id = Some(mainnet);
+        assert!(relabelled.verify(&public_commitments).is_err());
+        let mut stripped = tx;
+        stripped.network_id = None;
+        assert_eq!(
+            stripped.verify(&public_commitments),
+            Err(Error::NetworkIdDoesNotMatchVersion(stripped.version))
+        );
+        stripped.version &= !TX_NETWORK_ID;
+        assert!(stripped.verify(&public_commitments).is_err());
+    }
+
+    #[test]
+    fn test_transactions_expire() {
+        let mut rng = OsRng;
+
+        let (mut ring_ct, ledger) = test_material(&[2, 1], rng);
+        ring_ct.network_id = Some([1; 32]);
+        ring_ct.not_valid_after = Some(100);
+        let (tx, _revealed_output_commitments) =
+            ring_ct.sign(&mut rng).expect("Failed to sign transaction");
+        let public_commitments = ring_commitments(&tx, &ledger);
+        assert_eq!(tx.version & TX_EXPIRY, TX_EXPIRY);
+        assert_eq!(tx.range_proof_bits(), Ok(64));
+        assert_eq!(
+            RingCtTransaction::from_bytes(&tx.to_bytes()),
+            Ok(tx.clone())
+        );
+
+        assert_eq!(tx.verify_at(&public_commitments, 100), Ok(()));
+        assert_eq!(
+            tx.verify_at(&public_commitments, 101),
+            Err(Error::TransactionExpired {
+                not_valid_after: 100,
+                height: 101
+            })
+        );
+
+        // extending the expiry invalidates the signatures
+        let mut extended = tx.clone();
+        extended.not_valid_after = Some(200);
+        assert!(extended.verify_at(&public_commitments, 150).is_err());
+        let mut stripped = tx;
+        stripped.not_valid_after = None;
+        assert_eq!(
+            stripped.verify(&public_commitments),
+            Err(Error::ExpiryDoesNotMatchVersion(stripped.version))
+        );
+    }
+
+    #[test]
+    fn test_verify_with_lookup() {
+        let mut rng = OsRng;
+
+        let (ring_ct, ledger) = test_material(&[2, 1], rng);
+        let (tx, _revealed_output_commitments) =
+            ring_ct.sign(&mut rng).expect("Failed to sign transaction");
+        assert!(tx.verify_with_lookup(&ledger).is_ok());
+
+        let missing_pk = tx.mlsags[0].public_keys()[0];
+        let result = tx.verify_with_lookup(&|pk: &G1Affine| {
+            if pk == &missing_pk {
+                None
+            } else {
+                ledger.lookup(*pk)
+            }
+        });
+        assert_eq!(
+            result,
+            Err(Error::CommitmentNotFound {
+                input_index: 0,
+                public_key: codec::to_hex(&missing_pk.to_compressed()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_clsag_tx() {
+        let mut rng = OsRng;
+
+        let (ring_ct, ledger) = test_material(&[2, 1], rng);
+        let (tx, _revealed_output_commitments) = ring_ct
+            .sign_with_version(TX_VERSION_3, &mut rng)
+            .expect("Failed to sign transaction");
+        assert!(tx.mlsags.is_empty());
+        assert_eq!(tx.clsags.len(), 1);
+
+        let public_commitments = ring_commitments(&tx, &ledger);
+        assert!(tx.verify(&public_commitments).is_ok());
+
+        let parsed = RingCtTransaction::from_bytes(&tx.to_bytes()).expect("Failed to parse tx");
+        assert_eq!(parsed, tx);
+
+        // a CLSAG carries one response per ring member rather than two
+        let (mlsag_tx, _) = ring_ct.sign(&mut rng).expect("Failed to sign transaction");
+        assert!(tx.clsags[0].to_bytes().len() < mlsag_tx.mlsags[0].to_bytes().len());
+
+        let mut tampered = tx;
+        tampered.clsags[0].s[0] += Scalar::one();
+        assert_eq!(
+            tampered.verify(&public_commitments),
+            Err(Error::ClsagInvalid {
+                input_index: 0,
+                key_image: tampered.clsags[0].key_image().to_string(),
+                reason: Box::new(Error::InvalidRingSignature)
+            })
+        );
+    }
+
+    #[test]
+    fn test_matrix_mlsag_tx() {
+        let mut rng = OsRng;
+        let pc_gens = crate::pedersen_gens();
+        let mut ledger = MockLedger::new();
+
+        let true_inputs: Vec<TrueInput> = [3, 4]
+            .iter()
+            .map(|amount| {
+                TrueInput::new(
+                    Scalar::random(&mut rng),
+                    RevealedCommitment::from_value(*amount, &mut rng),
+                )
+            })
+            .collect();
+        for true_input in true_inputs.iter() {
+            ledger.log(
+                true_input.public_key(),
+                true_input.revealed_commitment.commit(&pc_gens),
+            );
+        }
+        for _ in 0..4 {
+            ledger.log(
+                G1Projective::random(&mut rng),
+                G1Projective::random(&mut rng),
+            );
+        }
+
+        // each ring draws its own decoys
+        let mut exclude: Vec<G1Projective> =
+            true_inputs.iter().map(TrueInput::public_key).collect();
+        let inputs = true_inputs
+            .into_iter()
+            .map(|true_input| {
+                let decoy_inputs = ledger.fetch_decoys(2, &exclude);
+                exclude.extend(
+                    decoy_inputs
+                        .iter()
+                        .map(|d| G1Projective::from(d.public_key)),
+                );
+                MlsagMaterial::new(true_input, decoy_inputs, &mut rng).unwrap()
+            })
+            .collect();
+        let mut ring_ct = RingCtMaterial {
+            inputs,
+            outputs: vec![Output::new(G1Projective::random(&mut rng), 6)],
+            fee: Amount::new(1),
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+        ring_ct.inputs[0].pi_base = 0;
+        ring_ct.inputs[1].pi_base = 1;
+
+        assert_eq!(
+            ring_ct.sign_with_version(TX_VERSION_4, &mut rng).err(),
+            Some(Error::TrueInputsNotAligned { input_index: 1 })
+        );
+
+        ring_ct.align_rings().expect("Failed to align rings");
+        let (tx, _) = ring_ct
+            .sign_with_version(TX_VERSION_4, &mut rng)
+            .expect("Failed to sign transaction");
+        assert!(tx.mlsags.is_empty() && tx.clsags.is_empty());
+        assert_eq!(tx.key_images().len(), 2);
+
+        let public_commitments = ring_commitments(&tx, &ledger);
+        assert_eq!(tx.verify(&public_commitments), Ok(()));
+        assert!(tx.verify_detailed(&public_commitments).is_valid());
+
+        let parsed = RingCtTransaction::from_bytes(&tx.to_bytes()).expect("Failed to parse tx");
+        assert_eq!(parsed, tx);
+
+        let mut tampered = tx.clone();
+        tampered.matrix_mlsag.as_mut().unwrap().r[1][2].0 += Scalar::one();
+        assert_eq!(
+            tampered.verify(&public_commitments),
+            Err(Error::MatrixMlsagInvalid {
+                reason: Box::new(Error::InvalidRingSignature)
+            })
+        );
+
+        let mut relabelled = tx;
+        relabelled.version = TX_VERSION_1 | TX_FLAGS;
+        assert_eq!(
+            relabelled.verify(&public_commitments),
+            Err(Error::InvalidRingSignatureLayout)
+        );
+    }
+
+    #[test]
+    fn test_empty_rings_do_not_verify() {
+        let mut rng = OsRng;
+        let (ring_ct, _ledger) = test_material(&[2, 1], rng);
+
+        let (mut tx, _) = ring_ct.sign(&mut rng).expect("Failed to sign transaction");
+        tx.mlsags[0].ring.clear();
+        tx.mlsags[0].r.clear();
+        assert!(matches!(
+            tx.verify(&[vec![]]),
+            Err(Error::MlsagInvalid { reason, .. }) if *reason == Error::EmptyRing
+        ));
+
+        let (mut tx, _) = ring_ct
+            .sign_with_version(TX_VERSION_3, &mut rng)
+            .expect("Failed to sign transaction");
+        tx.clsags[0].ring.clear();
+        tx.clsags[0].s.clear();
+        assert!(matches!(
+            tx.verify(&[vec![]]),
+            Err(Error::ClsagInvalid { reason, .. }) if *reason == Error::EmptyRing
+        ));
+    }
+
+    #[test]
+    fn test_range_proofs_are_bound_to_their_output_and_version() {
+        let (tx, _public_commitments) = signed_test_tx(OsRng);
+        assert_eq!(tx.range_proof_results(), vec![Ok(()), Ok(())]);
+
+        // the proof and commitment alone can't be moved to another public key
+        let mut moved = tx.clone();
+        moved.outputs[1].public_key = G1Projective::random(OsRng).to_affine();
+        assert_eq!(moved.range_proof_results()[0], Ok(()));
+        assert!(moved.range_proof_results()[1].is_err());
+
+        let mut relabelled = tx.clone();
+        relabelled.version = TX_VERSION_3 | TX_FLAGS;
+        assert!(relabelled.range_proof_results()[0].is_err());
+
+        // nor can the outputs be moved to a transaction spending other inputs
+        let mut transplanted = tx;
+        transplanted.mlsags[0].key_image = KeyImage::from(G1Projective::random(OsRng));
+        assert!(transplanted.range_proof_results()[0].is_err());
+    }
+
+    #[test]
+    fn test_independent_range_proofs() {
+        let mut rng = OsRng;
+        let (ring_ct, ledger) = test_material(&[2, 1, 3], rng);
+        let (tx, _revealed_output_commitments) =
+            ring_ct.sign(&mut rng).expect("Failed to sign transaction");
+        assert_eq!(tx.version, CURRENT_TX_VERSION);
+        assert_eq!(
+            tx.version & TX_INDEPENDENT_RANGE_PROOFS,
+            TX_INDEPENDENT_RANGE_PROOFS
+        );
+        assert_eq!(tx.range_proof_bits(), Ok(64));
+        assert_eq!(tx.verify(&ring_commitments(&tx, &ledger)), Ok(()));
+
+        // a bad proof doesn't affect the outputs after it
+        let mut tampered = tx.clone();
+        tampered.outputs[1].commitment = tampered.outputs[0].commitment;
+        let results = tampered.range_proof_results();
+        assert_eq!(results[0], Ok(()));
+        assert!(results[1].is_err());
+        assert_eq!(results[2], Ok(()));
+
+        // the proofs aren't valid in a shared transcript
+        let mut relabelled = tx;
+        relabelled.version &= !TX_INDEPENDENT_RANGE_PROOFS;
+        assert!(relabelled.range_proof_results()[1].is_err());
+
+        // proving is deterministic in the rng, whatever the scheduling
+        let (ring_ct, _ledger) = test_material(&[2, 1, 3], rng);
+        let sign = || {
+            ring_ct
+                .sign(&mut ChaCha20Rng::from_seed([7; 32]))
+                .expect("Failed to sign transaction")
+                .0
+        };
+        assert_eq!(sign(), sign());
+    }
+
+    #[test]
+    fn test_range_proofs_of_fewer_bits() {
+        let mut rng = OsRng;
+        let (ring_ct, ledger) = test_material(&[2, 1], rng);
+        let version = version_with_range_proof_bits(TX_VERSION_2, 32).unwrap();
+        assert_eq!(tx_format(version), TX_VERSION_2);
+        assert_eq!(range_proof_bits(version), Ok(32));
+        assert_eq!(
+            version_with_range_proof_bits(TX_VERSION_1, 64),
+            Ok(TX_VERSION_1)
+        );
+        assert_eq!(
+            version_with_range_proof_bits(TX_VERSION_1, 48),
+            Err(Error::UnsupportedRangeProofBits(48))
+        );
+
+        let (tx, _) = ring_ct
+            .sign_with_version(version, &mut rng)
+            .expect("Failed to sign transaction");
+        let tx = RingCtTransaction::from_bytes(&tx.to_bytes()).unwrap();
+        assert_eq!(tx.range_proof_bits(), Ok(32));
+        assert_eq!(tx.verify(&ring_commitments(&tx, &ledger)), Ok(()));
+
+        let (tx_64, _) = ring_ct
+            .sign_with_version(TX_VERSION_2, &mut rng)
+            .expect("Failed to sign transaction");
+        assert!(tx.to_bytes().len() < tx_64.to_bytes().len());
+
+        // the bit length is part of the version the proofs are bound to
+        let mut relabelled = tx;
+        relabelled.version = TX_VERSION_2 | TX_FLAGS;
+        assert!(relabelled.range_proof_results()[0].is_err());
+
+        let (too_large, _) = test_material(&[1 << 32, 1], rng);
+        let version = version_with_range_proof_bits(TX_VERSION_1, 32).unwrap();
+        assert_eq!(
+            too_large.sign_with_version(version, &mut rng).err(),
+            Some(Error::AmountExceedsRangeProofBits {
+                output_index: 0,
+                bits: 32
+            })
+        );
+    }
+
+    #[test]
+    fn test_id_only_covers_the_signed_message() {
+        let (tx, _public_commitments) = signed_test_tx(OsRng);
+        let decoded = RingCtTransaction::from_bytes(&tx.to_bytes()).unwrap();
+        assert_eq!(decoded.id(), tx.id());
+        assert_ne!(tx.id(), tx.hash());
+
+        // unsigned parts change the hash but not the id
+        let mut resigned = tx.clone();
+        resigned.mlsags[0].r[0].0 += Scalar::one();
+        assert_eq!(resigned.id(), tx.id());
+        assert_ne!(resigned.hash(), tx.hash());
+
+        // signed parts change both
+        let mut refeed = tx.clone();
+        refeed.fee = Amount::new(1);
+        assert_ne!(refeed.id(), tx.id());
+
+        let mut redirected = tx.clone();
+        redirected.outputs.swap(0, 1);
+        assert_ne!(redirected.id(), tx.id());
+    }
+
+    #[test]
+    fn test_incremental_hashes_match_the_buffered_ones() {
+        let sha3 = |parts: &[&[u8]]| {
+            let mut sha3 = Sha3::v256();
+            for part in parts {
+                sha3.update(part);
+            }
+            let mut hash = [0; 32];
+            sha3.finalize(&mut hash);
+            hash
+        };
+
+        let (tx, _public_commitments) = signed_test_tx(OsRng);
+        assert_eq!(tx.hash(), sha3(&[&tx.to_bytes()]));
+        assert_eq!(tx.id(), sha3(&[TXID_DOMAIN, &tx.gen_message()]));
+    }
+
+    #[test]
+    fn test_verify_enforces_policy_limits() {
+        let (tx, public_commitments) = signed_test_tx(OsRng);
+        let policy = RingCtPolicy::default();
+        assert_eq!(tx.verify_with_policy(&public_commitments, &policy), Ok(()));
+
+        let policy = RingCtPolicy {
+            max_outputs: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            tx.verify_with_policy(&public_commitments, &policy),
+            Err(Error::TooManyOutputs { max: 1, actual: 2 })
+        );
+
+        let policy = RingCtPolicy {
+            max_decoys: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            tx.verify_with_policy(&public_commitments, &policy),
+            Err(Error::TooManyDecoys {
+                input_index: 0,
+                max: 1,
+                actual: 2
+            })
+        );
+
+        let policy = RingCtPolicy {
+            max_inputs: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            tx.check_policy(&policy),
+            Err(Error::TooManyInputs { max: 0, actual: 1 })
+        );
+    }
+
+    #[test]
+    fn test_check_dust() {
+        let (ring_ct, _ledger) = test_material(&[5, 0], OsRng);
+        let (tx, revealed_output_commitments) = ring_ct
+            .sign(&mut OsRng)
+            .expect("Failed to sign transaction");
+
+        let policy = RingCtPolicy::default();
+        assert_eq!(tx.check_dust(&policy, &revealed_output_commitments), Ok(()));
+
+        let policy = RingCtPolicy {
+            min_output_amount: Amount::new(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            tx.check_dust(&policy, &revealed_output_commitments),
+            Err(Error::DustOutput {
+                output_index: 1,
+                amount: 0,
+                min: 1
+            })
+        );
+        assert_eq!(
+            tx.check_dust(&policy, &revealed_output_commitments[..1]),
+            Err(Error::AmountSecretsDoNotMatchCommitment)
+        );
+    }
+
+    #[test]
+    fn test_identity_points_are_rejected() {
+        let (tx, public_commitments) = signed_test_tx(OsRng);
+
+        let mut tampered = tx.clone();
+        tampered.mlsags[0].key_image = KeyImage::from(G1Affine::identity());
+        let expected = Err(Error::PointIsIdentity("input 0 key image".to_string()));
+        assert_eq!(tampered.validate(), expected);
+        assert_eq!(tampered.verify(&public_commitments), expected);
+        assert_eq!(
+            RingCtTransaction::from_bytes(&tampered.to_bytes()).err(),
+            expected.err()
+        );
+
+        let mut tampered = tx;
+        tampered.mlsags[0].ring[1].0 = G1Affine::identity();
+        assert_eq!(
+            tampered.validate(),
+            Err(Error::PointIsIdentity(
+                "input 0 ring member 1 public key".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_verify_requires_commitments_for_every_ring() {
+        let (tx, _public_commitments) = signed_test_tx(OsRng);
+        assert_eq!(
+            tx.verify(&[]),
+            Err(Error::ExpectedAPublicCommitmentsForEachRingEntry)
+        );
+    }
+
+    #[test]
+    fn test_verify_rejects_surplus_rings() {
+        let (tx, mut public_commitments) = signed_test_tx(OsRng);
+        public_commitments.push(public_commitments[0].clone());
+
+        assert_eq!(
+            tx.verify(&public_commitments),
+            Err(Error::ExpectedAPublicCommitmentsForEachRingEntry)
+        );
+        let report = tx.verify_detailed(&public_commitments);
+        assert!(!report.is_valid());
+        assert_eq!(
+            report.ring_signatures,
+            vec![Err(Error::ExpectedAPublicCommitmentsForEachRingEntry)]
+        );
+    }
+
+    #[test]
+    fn test_verify_with_config_enforces_ring_size() {
+        let (tx, public_commitments) = signed_test_tx(OsRng);
+
+        // the test material uses 2 decoys per input
+        let config = RingCtConfig {
+            decoys_per_input: 2,
+        };
+        assert!(tx.verify_with_config(&public_commitments, &config).is_ok());
+
+        for decoys_per_input in [1, 3] {
+            let config = RingCtConfig { decoys_per_input };
+            assert_eq!(
+                tx.verify_with_config(&public_commitments, &config),
+                Err(Error::InvalidRingSize {
+                    input_index: 0,
+                    expected: decoys_per_input + 1,
+                    actual: 3
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_recipient_can_decrypt_output_amount() {
+        let mut rng = OsRng;
+        let (mut ring_ct, _ledger) = test_material(&[3], rng);
+
+        let recipient_secret_key = Scalar::random(&mut rng);
+        ring_ct.outputs = vec![Output::new(crate::public_key(recipient_secret_key), 3)];
+
+        let (tx, revealed_output_commitments) =
+            ring_ct.sign(&mut rng).expect("Failed to sign transaction");
+        let tx = RingCtTransaction::from_bytes(&tx.to_bytes()).unwrap();
+
+        let amount_secrets = tx.outputs[0]
+            .try_decrypt_amount(recipient_secret_key)
+            .unwrap();
+        assert_eq!(amount_secrets.value(), 3);
+        assert_eq!(
+            amount_secrets.revealed_commitment(),
+            revealed_output_commitments[0]
+        );
+
+        assert_eq!(
+            tx.outputs[0].try_decrypt_amount(Scalar::random(&mut rng)),
+            Err(Error::AmountDecryptionFailed)
+        );
+    }
+
+    #[test]
+    fn test_true_input_from_previous_output() {
+        let mut rng = OsRng;
+        let (mut ring_ct, _ledger) = test_material(&[2, 1], rng);
+
+        let owner_secret_key = Scalar::random(&mut rng);
+        ring_ct.outputs[0] = Output::new(crate::public_key(owner_secret_key), 2);
+
+        let (tx, revealed_output_commitments) =
+            ring_ct.sign(&mut rng).expect("Failed to sign transaction");
+        let output = &tx.outputs[0];
+
+        let amount_secrets = output.try_decrypt_amount(owner_secret_key).unwrap();
+        let true_input = TrueInput::from_output(output, owner_secret_key, amount_secrets)
+            .expect("Failed to spend output");
+        assert_eq!(true_input.public_key().to_affine(), *output.public_key());
+        assert_eq!(
+            true_input.revealed_commitment,
+            revealed_output_commitments[0]
+        );
+
+        assert_eq!(
+            TrueInput::from_output(output, Scalar::random(&mut rng), amount_secrets).err(),
+            Some(Error::SecretKeyDoesNotOwnOutput)
+        );
+        assert_eq!(
+            TrueInput::from_output(output, owner_secret_key, revealed_output_commitments[1]).err(),
+            Some(Error::AmountSecretsDoNotMatchCommitment)
+        );
+    }
+
+    #[test]
+    fn test_stealth_outputs_are_found_by_scanning() {
+        let mut rng = OsRng;
+        let (mut ring_ct, _ledger) = test_material(&[2, 1], rng);
+
+        let recipient = StealthKeys::random(&mut rng);
+        let bystander = StealthKeys::random(&mut rng);
+        ring_ct.outputs = vec![
+            Output::to_stealth_address(&recipient.address(), 2, &mut rng),
+            Output::to_stealth_address(&bystander.address(), 1, &mut rng),
+        ];
+
+        let (tx, _revealed_output_commitments) =
+            ring_ct.sign(&mut rng).expect("Failed to sign transaction");
+        let tx = RingCtTransaction::from_bytes(&tx.to_bytes()).unwrap();
+
+        let owned = recipient.scan(&tx);
+        assert_eq!(owned.len(), 1);
+        assert_eq!(owned[0].output_index, 0);
+        assert_eq!(owned[0].amount_secrets.value(), 2);
+        assert_eq!(
+            crate::public_key(owned[0].secret_key).to_affine(),
+            *tx.outputs[0].public_key()
+        );
+
+        assert!(StealthKeys::random(&mut rng).scan(&tx).is_empty());
+    }
+
+    #[test]
+    fn test_stealth_amounts_are_read_by_rewinding_the_range_proof() {
+        let mut rng = OsRng;
+        let (mut ring_ct, _ledger) = test_material(&[2, 1], rng);
+
+        let recipient = StealthKeys::random(&mut rng);
+        ring_ct.outputs = vec![
+            Output::to_stealth_address(&recipient.address(), 2, &mut rng),
+            Output::to_stealth_address(&recipient.address(), 1, &mut rng),
+        ];
+
+        let (tx, revealed_output_commitments) =
+            ring_ct.sign(&mut rng).expect("Failed to sign transaction");
+        assert!(tx.outputs.iter().all(|o| o.encrypted_amount().is_none()));
+
+        // the last output's blinding balances the inputs rather than being drawn
+        for (output_index, output) in tx.outputs.iter().enumerate() {
+            assert_eq!(
+                output
+                    .rewind_amount(
+                        recipient.view_secret_key(),
+                        &tx.range_proof_context(output_index)
+                    )
+                    .map(|a| a.revealed_commitment()),
+                Ok(revealed_output_commitments[output_index])
+            );
+        }
+        assert_eq!(
+            tx.outputs[0].rewind_amount(Scalar::random(&mut rng), &tx.range_proof_context(0)),
+            Err(Error::RangeProofRewindFailed)
+        );
+        assert_eq!(
+            tx.outputs[0].rewind_amount(recipient.view_secret_key(), &tx.range_proof_context(1)),
+            Err(Error::RangeProofRewindFailed)
+        );
+
+        let mut context = tx.range_proof_context(0);
+        context.version &= !TX_INDEPENDENT_RANGE_PROOFS;
+        assert_eq!(
+            tx.outputs[0].rewind_amount(recipient.view_secret_key(), &context),
+            Err(Error::RangeProofNotIndependent(context.version))
+        );
+
+        let viewed = recipient.view_key().scan(&tx);
+        assert_eq!(viewed.len(), 2);
+        assert_eq!(viewed[1].amount_secrets.value(), 1);
+    }
+
+    #[test]
+    fn test_double_spends_are_detected() {
+        let (tx, _public_commitments) = signed_test_tx(OsRng);
+        let mut spent = BTreeSet::new();
+
+        assert_eq!(tx.check_double_spend(&spent), Ok(()));
+        assert_eq!(tx.spend_key_images(&mut spent), Ok(()));
+        assert!(spent.contains(&tx.key_images()[0]));
+
+        let double_spend = Err(Error::DoubleSpend {
+            input_index: 0,
+            key_image: tx.key_images()[0].to_string(),
+        });
+        assert_eq!(tx.check_double_spend(&spent), double_spend);
+        assert_eq!(tx.spend_key_images(&mut spent), double_spend);
+    }
+
+    #[test]
+    fn test_verify_detailed_reports_every_failure() {
+        let (mut tx, public_commitments) = signed_test_tx(OsRng);
+
+        let report = tx.verify_detailed(&public_commitments);
+        assert!(report.is_valid());
+        assert_eq!(report.ring_signatures, vec![Ok(())]);
+        assert_eq!(report.range_proofs, vec![Ok(()), Ok(())]);
+
+        // Swapping the outputs breaks the message and the first range proof,
+        // but the balance still holds.
+        tx.outputs.swap(0, 1);
+        let report = tx.verify_detailed(&public_commitments);
+        assert!(!report.is_valid());
+        assert_eq!(
+            report.ring_signatures,
+            vec![Err(Error::MlsagInvalid {
+                input_index: 0,
+                key_image: tx.mlsags[0].key_image().to_string(),
+                reason: Box::new(Error::InvalidRingSignature)
+            })]
+        );
+        assert!(matches!(
+            report.range_proofs[0],
+            Err(Error::RangeProofInvalid {
+                output_index: 0,
+                ..
+            })
+        ));
+        assert_eq!(report.balance, Ok(()));
+        assert_eq!(
+            tx.verify(&public_commitments).err().as_ref(),
+            report.errors().first().copied()
+        );
+    }
+
+    #[test]
+    fn test_generators_are_cached() {
+        assert!(std::ptr::eq(
+            RingCtMaterial::pc_gens(),
+            RingCtMaterial::pc_gens()
+        ));
+        assert!(matches!(RingCtMaterial::bp_gens(), Cow::Borrowed(_)));
+        assert!(matches!(
+            RingCtMaterial::aggregated_bp_gens(CACHED_BP_GENS_PARTIES),
+            Cow::Borrowed(_)
+        ));
+        assert!(matches!(
+            RingCtMaterial::aggregated_bp_gens(CACHED_BP_GENS_PARTIES * 2),
+            Cow::Owned(_)
+        ));
+    }
+
+    #[test]
+    fn test_tx_policy_adds_consensus_rules() {
+        struct FeeFloor(Amount);
+
+        impl TxPolicy for FeeFloor {
+            fn check(&self, tx: &RingCtTransaction, fee_commitment: G1Projective) -> Result<()> {
+                DefaultPolicy.check(tx, fee_commitment)?;
+                if tx.fee < self.0 {
+                    return Err(Error::PolicyViolation(format!(
+                        "fee {} is below the floor of {}",
+                        tx.fee, self.0
+                    )));
+                }
+                Ok(())
+            }
+        }
+
+        let (tx, public_commitments) = signed_test_tx(OsRng);
+        assert_eq!(
+            tx.verify_with_tx_policy(&public_commitments, &FeeFloor(tx.fee)),
+            Ok(())
+        );
+
+        let floor = || FeeFloor(Amount::new(tx.fee.value() + 1));
+        assert!(matches!(
+            tx.verify_with_tx_policy(&public_commitments, &floor()),
+            Err(Error::PolicyViolation(_))
+        ));
+        let verifier = crate::RingCtVerifier::default().with_tx_policy(floor());
+        assert!(matches!(
+            verifier.verify_with_commitments(&tx, &public_commitments),
+            Err(Error::PolicyViolation(_))
+        ));
+    }
+
+    #[test]
+    fn test_output_proofs_verify_standalone() {
+        let (tx, _) = signed_test_tx(OsRng);
+        let bp_gens = RingCtMaterial::aggregated_bp_gens(1);
+        let pc_gens = RingCtMaterial::pc_gens();
+
+        for (output_index, output) in tx.outputs.iter().enumerate() {
+            let context = tx.range_proof_context(output_index);
+            assert_eq!(output.verify(&bp_gens, pc_gens, &context), Ok(()));
+        }
+
+        // the proof is bound to its output's place in its transaction
+        let mut context = tx.range_proof_context(0);
+        context.output_index = 1;
+        assert!(tx.outputs[0].verify(&bp_gens, pc_gens, &context).is_err());
+        let mut context = tx.range_proof_context(0);
+        context
+            .key_images
+            .push(KeyImage::from(G1Projective::generator()));
+        assert!(tx.outputs[0].verify(&bp_gens, pc_gens, &context).is_err());
+
+        let mut context = tx.range_proof_context(0);
+        context.version &= !TX_INDEPENDENT_RANGE_PROOFS;
+        assert_eq!(
+            tx.outputs[0].verify(&bp_gens, pc_gens, &context),
+            Err(Error::RangeProofNotIndependent(context.version))
+        );
+        // genesis transactions share one transcript between their outputs
+        context.version = TX_VERSION_GENESIS;
+        assert_eq!(
+            tx.outputs[0].verify(&bp_gens, pc_gens, &context),
+            Err(Error::RangeProofNotIndependent(TX_VERSION_GENESIS))
+        );
     }
 }
@@ -0,0 +1,149 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Serializes points as hex strings in human-readable formats such as JSON,
+//! and as compact bytes otherwise. Fields use it with
+//! `#[serde(with = "crate::serde_hex")]`, which also covers options, vectors
+//! and pairs of points.
+
+use bls_bulletproofs::blstrs::G1Affine;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::codec;
+
+pub(crate) fn serialize<T: HexForm, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    value.to_hex_form().serialize(serializer)
+}
+
+pub(crate) fn deserialize<'de, T: HexForm, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<T, D::Error> {
+    T::Form::deserialize(deserializer).map(T::from_hex_form)
+}
+
+/// A type with a hex representation in human-readable formats
+pub(crate) trait HexForm: Sized {
+    type Form: Serialize + for<'de> Deserialize<'de>;
+
+    fn to_hex_form(&self) -> Self::Form;
+    fn from_hex_form(form: Self::Form) -> Self;
+}
+
+pub(crate) struct HexPoint(G1Affine);
+
+impl Serialize for HexPoint {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&codec::to_hex(&self.0.to_compressed()))
+        } else {
+            self.0.serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for HexPoint {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if !deserializer.is_human_readable() {
+            return G1Affine::deserialize(deserializer).map(Self);
+        }
+        let hex = String::deserialize(deserializer)?;
+        let bytes: [u8; 48] = codec::from_hex(&hex)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| de::Error::custom("expected 96 hex digits"))?;
+        Option::from(G1Affine::from_compressed(&bytes))
+            .map(Self)
+            .ok_or_else(|| de::Error::custom("invalid compressed G1 point"))
+    }
+}
+
+impl HexForm for G1Affine {
+    type Form = HexPoint;
+
+    fn to_hex_form(&self) -> HexPoint {
+        HexPoint(*self)
+    }
+
+    fn from_hex_form(form: HexPoint) -> Self {
+        form.0
+    }
+}
+
+impl<T: HexForm> HexForm for Option<T> {
+    type Form = Option<T::Form>;
+
+    fn to_hex_form(&self) -> Self::Form {
+        self.as_ref().map(T::to_hex_form)
+    }
+
+    fn from_hex_form(form: Self::Form) -> Self {
+        form.map(T::from_hex_form)
+    }
+}
+
+impl<T: HexForm> HexForm for Vec<T> {
+    type Form = Vec<T::Form>;
+
+    fn to_hex_form(&self) -> Self::Form {
+        self.iter().map(T::to_hex_form).collect()
+    }
+
+    fn from_hex_form(form: Self::Form) -> Self {
+        form.into_iter().map(T::from_hex_form).collect()
+    }
+}
+
+impl<A: HexForm, B: HexForm> HexForm for (A, B) {
+    type Form = (A::Form, B::Form);
+
+    fn to_hex_form(&self) -> Self::Form {
+        (self.0.to_hex_form(), self.1.to_hex_form())
+    }
+
+    fn from_hex_form(form: Self::Form) -> Self {
+        (A::from_hex_form(form.0), B::from_hex_form(form.1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{blstrs::G1Projective, group::Curve, group::Group, rand::rngs::OsRng};
+
+    use crate::{DecoyInput, KeyImage};
+
+    #[test]
+    fn test_points_are_hex_in_json_and_bytes_in_bincode() {
+        let decoy = DecoyInput {
+            public_key: G1Projective::random(OsRng).to_affine(),
+            commitment: G1Projective::random(OsRng).to_affine(),
+        };
+
+        let json = serde_json::to_value(decoy).unwrap();
+        assert_eq!(
+            json["public_key"],
+            crate::codec::to_hex(&decoy.public_key.to_compressed())
+        );
+        let decoded: DecoyInput = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.public_key, decoy.public_key);
+        assert_eq!(decoded.commitment, decoy.commitment);
+
+        let bytes = bincode::serialize(&decoy).unwrap();
+        let decoded: DecoyInput = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(decoded.public_key, decoy.public_key);
+        assert_eq!(decoded.commitment, decoy.commitment);
+
+        let key_image = KeyImage::from(G1Projective::random(OsRng).to_affine());
+        let json = serde_json::to_string(&key_image).unwrap();
+        assert_eq!(
+            json,
+            format!("\"{}\"", crate::codec::to_hex(&key_image.to_bytes()))
+        );
+        assert_eq!(serde_json::from_str::<KeyImage>(&json).unwrap(), key_image);
+        assert!(serde_json::from_str::<KeyImage>("\"00\"").is_err());
+    }
+}
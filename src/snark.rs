@@ -0,0 +1,333 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Experimental support for wrapping a verified [`RingCtTransaction`] in a succinct proof.
+//!
+//! This crate does not ship a succinct proving system. It defines the statement such a
+//! proof attests to ("this transaction verifies under policy P against the ring
+//! commitments with root R"), the traits a proving backend implements, and
+//! [`Reexecution`], a reference backend whose proof is the transaction itself and whose
+//! verification re-runs `verify()`. It is sound but not succinct, and is what other
+//! backends are checked against.
+
+use bls_bulletproofs::blstrs::G1Affine;
+use tiny_keccak::{Hasher, Sha3};
+
+use crate::{
+    codec::Reader,
+    ringct::RingCtTransaction,
+    tx_policy::{DefaultPolicy, TxPolicy},
+    Error, Result,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const VALIDITY_STATEMENT_DOMAIN: &[u8] = b"blst-ringct-validity-statement";
+const COMMITMENT_ROOT_DOMAIN: &[u8] = b"blst-ringct-validity-commitment-root";
+const DEFAULT_POLICY_ID_DOMAIN: &[u8] = b"blst-ringct-default-policy";
+
+/// A `TxPolicy` with an identifier, so that a statement names the consensus
+/// rules its transaction was verified under. Policies with different rules
+/// must have different ids.
+pub trait IdentifiedPolicy: TxPolicy {
+    fn policy_id(&self) -> [u8; 32];
+}
+
+impl IdentifiedPolicy for DefaultPolicy {
+    fn policy_id(&self) -> [u8; 32] {
+        hash(&[DEFAULT_POLICY_ID_DOMAIN])
+    }
+}
+
+/// The public inputs of a validity proof.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ValidityStatement {
+    pub tx_hash: [u8; 32],
+    pub policy_id: [u8; 32],
+    pub commitment_root: [u8; 32],
+}
+
+impl ValidityStatement {
+    /// Fully verifies `tx` under `policy` and, only if it is valid, returns
+    /// the statement a wrapping proof would attest to, naming the policy and
+    /// the ring commitments it was verified against.
+    pub fn from_verified_tx<P: IdentifiedPolicy>(
+        tx: &RingCtTransaction,
+        public_commitments_per_ring: &[Vec<G1Affine>],
+        policy: &P,
+    ) -> Result<Self> {
+        tx.verify_with_tx_policy(public_commitments_per_ring, policy)?;
+        Ok(Self {
+            tx_hash: tx.hash(),
+            policy_id: policy.policy_id(),
+            commitment_root: Self::commitment_root(public_commitments_per_ring),
+        })
+    }
+
+    /// The root of the ring commitments a transaction is verified against,
+    /// binding their number, the size of each ring and their order
+    pub fn commitment_root(public_commitments_per_ring: &[Vec<G1Affine>]) -> [u8; 32] {
+        let mut sha3 = Sha3::v256();
+        sha3.update(COMMITMENT_ROOT_DOMAIN);
+        sha3.update(&(public_commitments_per_ring.len() as u32).to_le_bytes());
+        for public_commitments in public_commitments_per_ring {
+            sha3.update(&(public_commitments.len() as u32).to_le_bytes());
+            for commitment in public_commitments {
+                sha3.update(&commitment.to_compressed());
+            }
+        }
+
+        let mut hash = [0; 32];
+        sha3.finalize(&mut hash);
+        hash
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Default::default();
+        v.extend(self.tx_hash);
+        v.extend(self.policy_id);
+        v.extend(self.commitment_root);
+        v
+    }
+
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let statement = Self {
+            tx_hash: reader.read_array()?,
+            policy_id: reader.read_array()?,
+            commitment_root: reader.read_array()?,
+        };
+        reader.finish()?;
+        Ok(statement)
+    }
+
+    pub fn hash(&self) -> [u8; 32] {
+        hash(&[VALIDITY_STATEMENT_DOMAIN, &self.to_bytes()])
+    }
+}
+
+/// A backend able to produce a succinct proof for a [`ValidityStatement`].
+///
+/// A prover must only prove statements that `ValidityStatement::from_verified_tx()`
+/// returns for the transaction and ring commitments it is given.
+pub trait ValidityProver {
+    type Proof;
+
+    fn prove(
+        &self,
+        statement: &ValidityStatement,
+        tx: &RingCtTransaction,
+        public_commitments_per_ring: &[Vec<G1Affine>],
+    ) -> Result<Self::Proof>;
+}
+
+/// Checks a succinct proof against a [`ValidityStatement`] without access to the transaction.
+pub trait ValidityVerifier {
+    type Proof;
+
+    fn verify(&self, statement: &ValidityStatement, proof: &Self::Proof) -> Result<()>;
+}
+
+/// A [`Reexecution`] proof: the transaction and the ring commitments it was
+/// verified against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReexecutionProof {
+    pub tx: RingCtTransaction,
+    pub public_commitments_per_ring: Vec<Vec<G1Affine>>,
+}
+
+/// The reference backend, proving a statement by handing over the transaction
+/// and verifying it by running `verify()` again under the policy `P`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Reexecution<P>(pub P);
+
+impl<P: IdentifiedPolicy> ValidityProver for Reexecution<P> {
+    type Proof = ReexecutionProof;
+
+    fn prove(
+        &self,
+        statement: &ValidityStatement,
+        tx: &RingCtTransaction,
+        public_commitments_per_ring: &[Vec<G1Affine>],
+    ) -> Result<Self::Proof> {
+        if ValidityStatement::from_verified_tx(tx, public_commitments_per_ring, &self.0)?
+            != *statement
+        {
+            return Err(Error::ValidityStatementMismatch);
+        }
+        Ok(ReexecutionProof {
+            tx: tx.clone(),
+            public_commitments_per_ring: public_commitments_per_ring.to_vec(),
+        })
+    }
+}
+
+impl<P: IdentifiedPolicy> ValidityVerifier for Reexecution<P> {
+    type Proof = ReexecutionProof;
+
+    fn verify(&self, statement: &ValidityStatement, proof: &Self::Proof) -> Result<()> {
+        let proven = ValidityStatement::from_verified_tx(
+            &proof.tx,
+            &proof.public_commitments_per_ring,
+            &self.0,
+        )?;
+        if proven != *statement {
+            return Err(Error::ValidityStatementMismatch);
+        }
+        Ok(())
+    }
+}
+
+fn hash(parts: &[&[u8]]) -> [u8; 32] {
+    let mut sha3 = Sha3::v256();
+    for part in parts {
+        sha3.update(part);
+    }
+    let mut hash = [0; 32];
+    sha3.finalize(&mut hash);
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{
+        blstrs::{G1Projective, Scalar},
+        group::{ff::Field, Group},
+        rand::rngs::OsRng,
+    };
+
+    use super::*;
+    use crate::{
+        mock::MockLedger, ringct::Amount, MlsagMaterial, Output, RevealedCommitment,
+        RingCtMaterial, TrueInput,
+    };
+
+    fn signed_tx() -> (RingCtTransaction, Vec<Vec<G1Affine>>) {
+        let mut ledger = MockLedger::new();
+        ledger.fill(4, 0);
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(3, OsRng),
+        );
+        ledger.log(
+            true_input.public_key(),
+            true_input
+                .revealed_commitment
+                .commit(RingCtMaterial::pc_gens()),
+        );
+        let decoy_inputs = ledger.fetch_decoys(3, &[true_input.public_key()]);
+        let material = RingCtMaterial {
+            inputs: vec![MlsagMaterial::new(true_input, decoy_inputs, &mut OsRng).unwrap()],
+            outputs: vec![Output::new(G1Projective::random(OsRng), 3)],
+            fee: Amount::ZERO,
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+        let (tx, _revealed_output_commitments) = material
+            .sign(&mut OsRng)
+            .expect("Failed to sign transaction");
+        let public_commitments = ledger.ring_commitments(&tx).unwrap();
+        (tx, public_commitments)
+    }
+
+    /// A policy with its own id, rejecting every transaction
+    struct RejectAll;
+
+    impl TxPolicy for RejectAll {
+        fn check(&self, _tx: &RingCtTransaction, _fee_commitment: G1Projective) -> Result<()> {
+            Err(Error::PolicyViolation(
+                "rejects every transaction".to_string(),
+            ))
+        }
+    }
+
+    impl IdentifiedPolicy for RejectAll {
+        fn policy_id(&self) -> [u8; 32] {
+            [1; 32]
+        }
+    }
+
+    #[test]
+    fn test_statement_binds_the_policy_and_ring_commitments() {
+        let (tx, public_commitments) = signed_tx();
+
+        let statement =
+            ValidityStatement::from_verified_tx(&tx, &public_commitments, &DefaultPolicy).unwrap();
+        assert_eq!(statement.tx_hash, tx.hash());
+        assert_eq!(statement.policy_id, DefaultPolicy.policy_id());
+        assert_eq!(
+            statement.commitment_root,
+            ValidityStatement::commitment_root(&public_commitments)
+        );
+        assert_eq!(
+            ValidityStatement::from_bytes(&statement.to_bytes()),
+            Ok(statement)
+        );
+
+        // the statement is only made for a transaction valid under the policy
+        assert!(matches!(
+            ValidityStatement::from_verified_tx(&tx, &public_commitments, &RejectAll),
+            Err(Error::PolicyViolation(_))
+        ));
+
+        // and against the ring commitments the root names
+        let mut other_commitments = public_commitments.clone();
+        other_commitments[0][0] = G1Projective::random(OsRng).to_affine();
+        assert_ne!(
+            ValidityStatement::commitment_root(&other_commitments),
+            statement.commitment_root
+        );
+        assert!(
+            ValidityStatement::from_verified_tx(&tx, &other_commitments, &DefaultPolicy).is_err()
+        );
+    }
+
+    #[test]
+    fn test_reexecution_proves_and_verifies_statements() {
+        let (tx, public_commitments) = signed_tx();
+        let backend = Reexecution(DefaultPolicy);
+        let statement =
+            ValidityStatement::from_verified_tx(&tx, &public_commitments, &DefaultPolicy).unwrap();
+
+        let proof = backend.prove(&statement, &tx, &public_commitments).unwrap();
+        assert_eq!(backend.verify(&statement, &proof), Ok(()));
+
+        // statements the transaction doesn't verify to are neither proven nor accepted
+        for tampered in [
+            ValidityStatement {
+                policy_id: RejectAll.policy_id(),
+                ..statement
+            },
+            ValidityStatement {
+                commitment_root: [0; 32],
+                ..statement
+            },
+            ValidityStatement {
+                tx_hash: [0; 32],
+                ..statement
+            },
+        ] {
+            assert_eq!(
+                backend.prove(&tampered, &tx, &public_commitments),
+                Err(Error::ValidityStatementMismatch)
+            );
+            assert_eq!(
+                backend.verify(&tampered, &proof),
+                Err(Error::ValidityStatementMismatch)
+            );
+        }
+
+        // a verifier under another policy re-verifies under its own rules
+        assert!(matches!(
+            Reexecution(RejectAll).verify(&statement, &proof),
+            Err(Error::PolicyViolation(_))
+        ));
+    }
+}
@@ -0,0 +1,238 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Spent proofs, with which a quorum, eg. a mint or a network section, proves
+//! it recorded a key image as spent by a transaction.
+//!
+//! The quorum's secret key is split with `threshold::split_secret_key()`.
+//! Each member signs the key image and transaction hash with its share, and
+//! any `threshold` of the resulting `SpentProofShare`s aggregate into a
+//! `SpentProof`, a BLS signature in G2 under the quorum's G1 public key.
+
+use bls_bulletproofs::{
+    blstrs::{pairing, G1Affine, G2Affine, G2Projective},
+    group::{prime::PrimeCurveAffine, Curve, Group},
+};
+use std::collections::BTreeSet;
+
+use crate::{
+    codec::Reader, ringct::RingCtTransaction, threshold::lagrange_coefficient, Error, KeyImage,
+    KeyShare, Result,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const SPENT_PROOF_DOMAIN: &[u8] = b"blst-ringct-spent-proof";
+
+/// A quorum member's share of a `SpentProof`
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpentProofShare {
+    pub key_image: KeyImage,
+    pub transaction_hash: [u8; 32],
+    /// The index of the member's key share
+    pub index: u32,
+    pub signature_share: G2Affine,
+}
+
+/// Proves a quorum recorded `key_image` as spent by the transaction with
+/// hash `transaction_hash`
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpentProof {
+    pub key_image: KeyImage,
+    pub transaction_hash: [u8; 32],
+    pub signature: G2Affine,
+}
+
+impl SpentProofShare {
+    /// Signs that `key_image` was spent by the transaction `transaction_hash`
+    pub fn sign(key_share: &KeyShare, key_image: KeyImage, transaction_hash: [u8; 32]) -> Self {
+        Self {
+            key_image,
+            transaction_hash,
+            index: key_share.index,
+            signature_share: (message(&key_image, &transaction_hash) * key_share.secret_share)
+                .to_affine(),
+        }
+    }
+
+    /// Checks the share against the public key of the member's key share,
+    /// `secret_share * G`
+    pub fn verify(&self, public_key_share: &G1Affine) -> Result<()> {
+        verify_signature(
+            &self.key_image,
+            &self.transaction_hash,
+            &self.signature_share,
+            public_key_share,
+        )
+    }
+}
+
+impl SpentProof {
+    /// Combines `threshold` shares from distinct members which signed the
+    /// same key image and transaction. Invalid shares make an invalid proof,
+    /// so check them first with `SpentProofShare::verify()` if they may be.
+    pub fn aggregate(shares: &[SpentProofShare], threshold: usize) -> Result<Self> {
+        let first = shares.first().ok_or(Error::NotEnoughSigners {
+            threshold,
+            signers: 0,
+        })?;
+        if shares
+            .iter()
+            .any(|s| s.key_image != first.key_image || s.transaction_hash != first.transaction_hash)
+        {
+            return Err(Error::SpentProofSharesMismatch);
+        }
+
+        let signers: Vec<u32> = BTreeSet::from_iter(shares.iter().map(|s| s.index))
+            .into_iter()
+            .collect();
+        if signers.len() < threshold || threshold == 0 {
+            return Err(Error::NotEnoughSigners {
+                threshold,
+                signers: signers.len(),
+            });
+        }
+        let signers = &signers[..threshold];
+
+        let mut signature = G2Projective::identity();
+        for index in signers {
+            let share = shares.iter().find(|s| s.index == *index).unwrap();
+            signature += share.signature_share * lagrange_coefficient(*index, signers)?;
+        }
+
+        Ok(Self {
+            key_image: first.key_image,
+            transaction_hash: first.transaction_hash,
+            signature: signature.to_affine(),
+        })
+    }
+
+    /// Checks the proof against the quorum's public key
+    pub fn verify(&self, public_key: &G1Affine) -> Result<()> {
+        verify_signature(
+            &self.key_image,
+            &self.transaction_hash,
+            &self.signature,
+            public_key,
+        )
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Default::default();
+        v.extend(self.key_image.to_bytes());
+        v.extend(self.transaction_hash);
+        v.extend(self.signature.to_compressed());
+        v
+    }
+
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let key_image = KeyImage::from(reader.read_point()?);
+        let transaction_hash = reader.read_array()?;
+        let signature = Option::from(G2Affine::from_compressed(&reader.read_array()?))
+            .ok_or(Error::InvalidPointEncoding)?;
+        reader.finish()?;
+        Ok(Self {
+            key_image,
+            transaction_hash,
+            signature,
+        })
+    }
+}
+
+impl RingCtTransaction {
+    /// Checks that every input of the transaction has a spent proof from the
+    /// quorum with public key `public_key`
+    pub fn verify_spent_proofs(
+        &self,
+        spent_proofs: &[SpentProof],
+        public_key: &G1Affine,
+    ) -> Result<()> {
+        let hash = self.hash();
+        for (input_index, key_image) in self.key_images().iter().enumerate() {
+            spent_proofs
+                .iter()
+                .find(|p| p.key_image == *key_image && p.transaction_hash == hash)
+                .ok_or(Error::MissingSpentProof { input_index })?
+                .verify(public_key)?;
+        }
+        Ok(())
+    }
+}
+
+fn message(key_image: &KeyImage, transaction_hash: &[u8; 32]) -> G2Projective {
+    let mut msg = key_image.to_bytes().to_vec();
+    msg.extend(transaction_hash);
+    G2Projective::hash_to_curve(&msg, SPENT_PROOF_DOMAIN, &[])
+}
+
+fn verify_signature(
+    key_image: &KeyImage,
+    transaction_hash: &[u8; 32],
+    signature: &G2Affine,
+    public_key: &G1Affine,
+) -> Result<()> {
+    if pairing(&G1Affine::generator(), signature)
+        != pairing(
+            public_key,
+            &message(key_image, transaction_hash).to_affine(),
+        )
+    {
+        return Err(Error::InvalidSpentProof {
+            key_image: key_image.to_string(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{
+        blstrs::{G1Projective, Scalar},
+        group::ff::Field,
+        rand::rngs::OsRng,
+    };
+
+    use super::*;
+    use crate::threshold::split_secret_key;
+
+    #[test]
+    fn test_spent_proof_shares_aggregate() {
+        let section_key_shares = split_secret_key(Scalar::random(OsRng), 3, 5, &mut OsRng).unwrap();
+        let section_public_key = section_key_shares[0].public_key;
+        let key_image = KeyImage::from(G1Projective::random(OsRng));
+        let transaction_hash = [7; 32];
+
+        let shares: Vec<SpentProofShare> = section_key_shares
+            .iter()
+            .map(|k| SpentProofShare::sign(k, key_image, transaction_hash))
+            .collect();
+        let public_key_share = crate::public_key(section_key_shares[1].secret_share).to_affine();
+        assert_eq!(shares[1].verify(&public_key_share), Ok(()));
+
+        let proof = SpentProof::aggregate(&shares[2..], 3).unwrap();
+        assert_eq!(proof.verify(&section_public_key), Ok(()));
+        assert_eq!(SpentProof::from_bytes(&proof.to_bytes()), Ok(proof));
+
+        assert_eq!(
+            SpentProof::aggregate(&[shares[0], shares[0], shares[1]], 3),
+            Err(Error::NotEnoughSigners {
+                threshold: 3,
+                signers: 2
+            })
+        );
+
+        let other = SpentProofShare::sign(&section_key_shares[3], key_image, [8; 32]);
+        assert_eq!(
+            SpentProof::aggregate(&[shares[0], shares[1], other], 3),
+            Err(Error::SpentProofSharesMismatch)
+        );
+    }
+}
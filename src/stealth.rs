@@ -0,0 +1,408 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::{ff::Field, Curve, Group},
+    rand::{CryptoRng, RngCore},
+};
+use tiny_keccak::{Hasher, Sha3};
+
+use crate::{
+    mlsag::hash_to_scalar,
+    ringct::{OutputProof, RingCtTransaction},
+    AmountSecrets,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+const ONE_TIME_KEY_DOMAIN: &[u8] = b"blst-ringct-stealth-one-time-key";
+const VIEW_TAG_DOMAIN: &[u8] = b"blst-ringct-stealth-view-tag";
+const REWIND_KEY_DOMAIN: &[u8] = b"blst-ringct-stealth-rewind-key";
+
+/// The public half of a recipient's stealth keys, published so that senders
+/// can derive a fresh, unlinkable public key for every output they pay to it.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StealthAddress {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub view_public_key: G1Affine,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub spend_public_key: G1Affine,
+}
+
+/// A one-time output key derived from a StealthAddress
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OneTimeKey {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub public_key: G1Affine,
+    /// The ephemeral public key `rG` the recipient needs to recognise the output
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub ephemeral_public_key: G1Affine,
+    /// The first byte of a hash of the shared secret, lets scanners discard
+    /// most outputs that aren't theirs before deriving the one-time key.
+    pub view_tag: u8,
+    /// The address's view key, which the output's amount secrets are encrypted to
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub view_public_key: G1Affine,
+    /// A hash of the shared secret, the output's range proof nonces that
+    /// carry its amount are drawn with so that the view key can rewind it to
+    /// read the amount. Not part of the transaction.
+    pub rewind_key: [u8; 32],
+}
+
+impl StealthAddress {
+    /// Derives a one-time output public key `H(rV)G + S`
+    pub fn derive_one_time_key(&self, rng: &mut (impl RngCore + CryptoRng)) -> OneTimeKey {
+        self.derive_one_time_key_from_secret(Scalar::random(&mut *rng))
+    }
+
+    /// Derives the one-time key for the ephemeral secret `r`, which a sender
+    /// keeps in order to later prove the payment with a `PaymentProof`
+    pub fn derive_one_time_key_from_secret(&self, ephemeral_secret: Scalar) -> OneTimeKey {
+        let ephemeral_public_key = G1Projective::generator() * ephemeral_secret;
+        let shared_secret = (self.view_public_key * ephemeral_secret).to_affine();
+
+        let public_key = G1Projective::generator() * shared_secret_scalar(&shared_secret)
+            + self.spend_public_key;
+
+        OneTimeKey {
+            public_key: public_key.to_affine(),
+            ephemeral_public_key: ephemeral_public_key.to_affine(),
+            view_tag: view_tag(&shared_secret),
+            view_public_key: self.view_public_key,
+            rewind_key: rewind_key(&shared_secret),
+        }
+    }
+}
+
+/// A recipient's (view, spend) key pair. The view key is enough to detect
+/// owned outputs, spending them also requires the spend key.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct StealthKeys {
+    view_secret_key: Scalar,
+    spend_secret_key: Scalar,
+}
+
+impl StealthKeys {
+    pub fn new(view_secret_key: Scalar, spend_secret_key: Scalar) -> Self {
+        Self {
+            view_secret_key,
+            spend_secret_key,
+        }
+    }
+
+    pub fn random(rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        Self::new(Scalar::random(&mut *rng), Scalar::random(&mut *rng))
+    }
+
+    pub fn view_secret_key(&self) -> Scalar {
+        self.view_secret_key
+    }
+
+    pub fn spend_secret_key(&self) -> Scalar {
+        self.spend_secret_key
+    }
+
+    pub fn address(&self) -> StealthAddress {
+        StealthAddress {
+            view_public_key: crate::public_key(self.view_secret_key).to_affine(),
+            spend_public_key: crate::public_key(self.spend_secret_key).to_affine(),
+        }
+    }
+
+    /// The view key, to hand to an auditor or a watch-only wallet
+    pub fn view_key(&self) -> ViewKey {
+        ViewKey::new(
+            self.view_secret_key,
+            crate::public_key(self.spend_secret_key).to_affine(),
+        )
+    }
+
+    /// Returns the one-time secret key of `output` if it was paid to these keys.
+    /// This always derives the full one-time key, ignoring the view tag.
+    pub fn one_time_secret_key(&self, output: &OutputProof) -> Option<Scalar> {
+        let shared_secret = self.shared_secret(output)?;
+        self.derive_one_time_secret_key(output, &shared_secret)
+    }
+
+    /// Finds the outputs of `tx` paid to these keys and recovers their secrets
+    pub fn scan(&self, tx: &RingCtTransaction) -> Vec<OwnedOutput> {
+        self.find_outputs(&tx.outputs, Some(tx))
+    }
+
+    /// Finds the outputs paid to these keys and recovers their secrets. Outputs
+    /// whose view tag doesn't match are discarded after a single byte comparison,
+    /// skipping the one-time key derivation and amount decryption. Outputs whose
+    /// amount is read by rewinding their range proof need the transaction the
+    /// proof was made in, and are only found by `scan()`.
+    pub fn scan_outputs(&self, outputs: &[OutputProof]) -> Vec<OwnedOutput> {
+        self.find_outputs(outputs, None)
+    }
+
+    fn find_outputs(
+        &self,
+        outputs: &[OutputProof],
+        tx: Option<&RingCtTransaction>,
+    ) -> Vec<OwnedOutput> {
+        outputs
+            .iter()
+            .enumerate()
+            .filter_map(|(output_index, output)| {
+                let shared_secret = self.shared_secret(output)?;
+                if matches!(output.view_tag(), Some(tag) if tag != view_tag(&shared_secret)) {
+                    return None;
+                }
+                let secret_key = self.derive_one_time_secret_key(output, &shared_secret)?;
+                // outputs from before amounts were encrypted to the view key
+                // can still be read with the one-time key
+                let amount_secrets = read_amount(output, output_index, tx, &shared_secret)
+                    .or_else(|| output.try_decrypt_amount(self.view_secret_key).ok())
+                    .or_else(|| output.try_decrypt_amount(secret_key).ok())?;
+                Some(OwnedOutput {
+                    output_index,
+                    secret_key,
+                    amount_secrets,
+                })
+            })
+            .collect()
+    }
+
+    fn shared_secret(&self, output: &OutputProof) -> Option<G1Affine> {
+        let ephemeral_public_key = output.ephemeral_public_key()?;
+        Some((ephemeral_public_key * self.view_secret_key).to_affine())
+    }
+
+    fn derive_one_time_secret_key(
+        &self,
+        output: &OutputProof,
+        shared_secret: &G1Affine,
+    ) -> Option<Scalar> {
+        let one_time_secret_key = shared_secret_scalar(shared_secret) + self.spend_secret_key;
+
+        if crate::public_key(one_time_secret_key).to_affine() == *output.public_key() {
+            Some(one_time_secret_key)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for StealthKeys {
+    fn zeroize(&mut self) {
+        crate::zeroize_scalar(&mut self.view_secret_key);
+        crate::zeroize_scalar(&mut self.spend_secret_key);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for StealthKeys {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for StealthKeys {}
+
+/// A recipient's view secret key with their spend public key. It detects the
+/// recipient's outputs and reads their amounts, but can't spend them.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct ViewKey {
+    view_secret_key: Scalar,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    spend_public_key: G1Affine,
+}
+
+impl ViewKey {
+    pub fn new(view_secret_key: Scalar, spend_public_key: G1Affine) -> Self {
+        Self {
+            view_secret_key,
+            spend_public_key,
+        }
+    }
+
+    pub fn view_secret_key(&self) -> Scalar {
+        self.view_secret_key
+    }
+
+    pub fn address(&self) -> StealthAddress {
+        StealthAddress {
+            view_public_key: crate::public_key(self.view_secret_key).to_affine(),
+            spend_public_key: self.spend_public_key,
+        }
+    }
+
+    /// Finds the outputs of `tx` paid to this key's address and reads their amounts
+    pub fn scan(&self, tx: &RingCtTransaction) -> Vec<ViewedOutput> {
+        self.find_outputs(&tx.outputs, Some(tx))
+    }
+
+    /// Finds the outputs paid to this key's address and reads their amounts.
+    /// Only outputs whose amounts were encrypted to the view key are found,
+    /// those read by rewinding their range proof are only found by `scan()`.
+    pub fn scan_outputs(&self, outputs: &[OutputProof]) -> Vec<ViewedOutput> {
+        self.find_outputs(outputs, None)
+    }
+
+    fn find_outputs(
+        &self,
+        outputs: &[OutputProof],
+        tx: Option<&RingCtTransaction>,
+    ) -> Vec<ViewedOutput> {
+        outputs
+            .iter()
+            .enumerate()
+            .filter_map(|(output_index, output)| {
+                let shared_secret = self.shared_secret(output)?;
+                let amount_secrets = read_amount(output, output_index, tx, &shared_secret)
+                    .or_else(|| output.try_decrypt_amount(self.view_secret_key).ok())?;
+                Some(ViewedOutput {
+                    output_index,
+                    amount_secrets,
+                })
+            })
+            .collect()
+    }
+
+    /// The shared secret `aR` of `output`, if it was paid to this key's address
+    pub(crate) fn shared_secret(&self, output: &OutputProof) -> Option<G1Affine> {
+        let ephemeral_public_key = output.ephemeral_public_key()?;
+        let shared_secret = (ephemeral_public_key * self.view_secret_key).to_affine();
+        if matches!(output.view_tag(), Some(tag) if tag != view_tag(&shared_secret)) {
+            return None;
+        }
+        let one_time_key = G1Projective::generator() * shared_secret_scalar(&shared_secret)
+            + self.spend_public_key;
+        (one_time_key.to_affine() == *output.public_key()).then_some(shared_secret)
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for ViewKey {
+    fn zeroize(&mut self) {
+        crate::zeroize_scalar(&mut self.view_secret_key);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for ViewKey {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for ViewKey {}
+
+/// An output found by scanning with a ViewKey
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ViewedOutput {
+    pub output_index: usize,
+    pub amount_secrets: AmountSecrets,
+}
+
+/// An output found by scanning a transaction, with everything needed to spend it
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct OwnedOutput {
+    pub output_index: usize,
+    pub secret_key: Scalar,
+    pub amount_secrets: AmountSecrets,
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for OwnedOutput {
+    fn zeroize(&mut self) {
+        crate::zeroize_scalar(&mut self.secret_key);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for OwnedOutput {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for OwnedOutput {}
+
+pub(crate) fn shared_secret_scalar(shared_secret: &G1Affine) -> Scalar {
+    hash_to_scalar(&[ONE_TIME_KEY_DOMAIN, &shared_secret.to_compressed()])
+}
+
+fn view_tag(shared_secret: &G1Affine) -> u8 {
+    let mut sha3 = Sha3::v256();
+    sha3.update(VIEW_TAG_DOMAIN);
+    sha3.update(&shared_secret.to_compressed());
+    let mut hash = [0u8; 32];
+    sha3.finalize(&mut hash);
+    hash[0]
+}
+
+/// Rewinds the range proof of an output of `tx` without an encrypted amount
+fn read_amount(
+    output: &OutputProof,
+    output_index: usize,
+    tx: Option<&RingCtTransaction>,
+    shared_secret: &G1Affine,
+) -> Option<AmountSecrets> {
+    let tx = tx.filter(|_| output.encrypted_amount().is_none())?;
+    output
+        .rewind_amount_with_key(
+            &rewind_key(shared_secret),
+            &tx.range_proof_context(output_index),
+        )
+        .ok()
+}
+
+pub(crate) fn rewind_key(shared_secret: &G1Affine) -> [u8; 32] {
+    let mut sha3 = Sha3::v256();
+    sha3.update(REWIND_KEY_DOMAIN);
+    sha3.update(&shared_secret.to_compressed());
+    let mut hash = [0u8; 32];
+    sha3.finalize(&mut hash);
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::rand::rngs::OsRng;
+
+    use super::*;
+
+    #[test]
+    fn test_one_time_keys_are_unlinkable_and_recoverable() {
+        let keys = StealthKeys::random(&mut OsRng);
+        let address = keys.address();
+
+        let key_a = address.derive_one_time_key(&mut OsRng);
+        let key_b = address.derive_one_time_key(&mut OsRng);
+        let (pk_a, ephemeral_a) = (key_a.public_key, key_a.ephemeral_public_key);
+        assert_ne!(pk_a, key_b.public_key);
+        assert_ne!(pk_a, address.spend_public_key);
+
+        let shared_secret = (ephemeral_a * keys.view_secret_key()).to_affine();
+        let sk_a = shared_secret_scalar(&shared_secret) + keys.spend_secret_key();
+        assert_eq!(crate::public_key(sk_a).to_affine(), pk_a);
+        assert_eq!(view_tag(&shared_secret), key_a.view_tag);
+
+        let other_keys = StealthKeys::random(&mut OsRng);
+        let other_shared_secret = (ephemeral_a * other_keys.view_secret_key()).to_affine();
+        let other_sk = shared_secret_scalar(&other_shared_secret) + other_keys.spend_secret_key();
+        assert_ne!(crate::public_key(other_sk).to_affine(), pk_a);
+    }
+}
@@ -0,0 +1,160 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Proptest strategies for generating random but balanced transaction material,
+//! so that downstream crates can property-test their reissue logic.
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::{ff::Field, Curve, Group},
+    rand::RngCore,
+};
+use proptest::{collection::vec, prelude::*};
+use rand_chacha::{rand_core::SeedableRng, ChaChaRng};
+
+use crate::{
+    ringct::Amount, DecoyInput, MlsagMaterial, Output, RevealedCommitment, RingCtConfig,
+    RingCtMaterial, TrueInput,
+};
+
+/// The largest amount generated for a single input, small enough that the
+/// amounts of many inputs can be summed without overflowing.
+pub const MAX_INPUT_AMOUNT: u64 = u32::MAX as u64;
+
+// Curve points and scalars are derived from a generated seed, which keeps
+// shrinking meaningful without having to reject non-canonical encodings.
+fn seeded_rng() -> impl Strategy<Value = ChaChaRng> {
+    any::<u64>().prop_map(ChaChaRng::seed_from_u64)
+}
+
+pub fn scalar() -> impl Strategy<Value = Scalar> {
+    seeded_rng().prop_map(Scalar::random)
+}
+
+pub fn g1_affine() -> impl Strategy<Value = G1Affine> {
+    seeded_rng().prop_map(|rng| G1Projective::random(rng).to_affine())
+}
+
+pub fn revealed_commitment(value: u64) -> impl Strategy<Value = RevealedCommitment> {
+    scalar().prop_map(move |blinding| RevealedCommitment { value, blinding })
+}
+
+pub fn output(amount: u64) -> impl Strategy<Value = Output> {
+    g1_affine().prop_map(move |public_key| Output::new(public_key, amount))
+}
+
+pub fn decoy_input() -> impl Strategy<Value = DecoyInput> {
+    (g1_affine(), g1_affine()).prop_map(|(public_key, commitment)| DecoyInput {
+        public_key,
+        commitment,
+    })
+}
+
+/// The material for spending an input of `value` in a ring with `decoys` decoys
+pub fn mlsag_material(value: u64, decoys: usize) -> impl Strategy<Value = MlsagMaterial> {
+    (
+        scalar(),
+        revealed_commitment(value),
+        vec(decoy_input(), decoys),
+        seeded_rng(),
+    )
+        .prop_map(|(secret_key, revealed_commitment, decoy_inputs, mut rng)| {
+            MlsagMaterial::new(
+                TrueInput::new(secret_key, revealed_commitment),
+                decoy_inputs,
+                &mut rng,
+            )
+            .expect("Random decoys are valid")
+        })
+}
+
+/// Balanced material with 1 to `max_inputs` inputs and 1 to `max_outputs` outputs,
+/// where the output amounts and the fee sum to the input amounts.
+pub fn ringct_material(
+    config: RingCtConfig,
+    max_inputs: usize,
+    max_outputs: usize,
+) -> impl Strategy<Value = RingCtMaterial> {
+    (
+        vec(0..=MAX_INPUT_AMOUNT, 1..=max_inputs.max(1)),
+        1..=max_outputs.max(1),
+        seeded_rng(),
+    )
+        .prop_flat_map(move |(input_amounts, num_outputs, mut rng)| {
+            let total: u64 = input_amounts.iter().sum();
+            let fee = rng.next_u64() % (total + 1);
+            let output_amounts = split_amount(total - fee, num_outputs, &mut rng);
+
+            let inputs: Vec<_> = input_amounts
+                .into_iter()
+                .map(|amount| mlsag_material(amount, config.decoys_per_input))
+                .collect();
+            let outputs: Vec<_> = output_amounts.into_iter().map(output).collect();
+
+            (inputs, outputs, Just(fee))
+        })
+        .prop_map(|(inputs, outputs, fee)| RingCtMaterial {
+            inputs,
+            outputs,
+            fee: Amount::new(fee),
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        })
+}
+
+// Splits `amount` into `parts` amounts by cutting it at random points
+fn split_amount(amount: u64, parts: usize, rng: &mut impl RngCore) -> Vec<u64> {
+    let mut cuts: Vec<u64> = (1..parts).map(|_| rng.next_u64() % (amount + 1)).collect();
+    cuts.push(0);
+    cuts.push(amount);
+    cuts.sort_unstable();
+    cuts.windows(2).map(|w| w[1] - w[0]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::rand::rngs::OsRng;
+
+    use super::*;
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(8))]
+
+        #[test]
+        fn prop_generated_material_signs_and_verifies(
+            material in ringct_material(RingCtConfig { decoys_per_input: 2 }, 3, 3)
+        ) {
+            let output_sum: u64 = material.outputs.iter().map(|o| o.amount.value()).sum();
+            let input_sum: u64 = material
+                .inputs
+                .iter()
+                .map(|input| input.true_input.revealed_commitment.value)
+                .sum();
+            prop_assert_eq!(input_sum, output_sum + material.fee.value());
+
+            let (tx, _) = material.sign(&mut OsRng).expect("Failed to sign transaction");
+            let pc_gens = crate::pedersen_gens();
+            let public_commitments: Vec<_> = material
+                .inputs
+                .iter()
+                .map(|input| input.commitments(&pc_gens))
+                .collect();
+            prop_assert_eq!(tx.verify(&public_commitments), Ok(()));
+        }
+
+        #[test]
+        fn prop_split_amount_preserves_the_total(
+            amount in 0..=MAX_INPUT_AMOUNT,
+            parts in 1usize..8,
+            mut rng in seeded_rng(),
+        ) {
+            let split = split_amount(amount, parts, &mut rng);
+            prop_assert_eq!(split.len(), parts);
+            prop_assert_eq!(split.iter().sum::<u64>(), amount);
+        }
+    }
+}
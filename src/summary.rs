@@ -0,0 +1,165 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Read-only views of a transaction for wallets and explorers, which don't
+//! depend on how the transaction lays out its signatures.
+
+use bls_bulletproofs::blstrs::G1Affine;
+use std::fmt;
+
+use crate::{
+    codec,
+    ringct::{Amount, RingCtTransaction},
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+impl RingCtTransaction {
+    /// The number of inputs, whichever ring signature scheme signs them
+    pub fn input_count(&self) -> usize {
+        self.key_images().len()
+    }
+
+    pub fn output_count(&self) -> usize {
+        self.outputs.len()
+    }
+
+    /// The size of the inputs' rings, or `None` if the transaction has no
+    /// inputs or its rings differ in size
+    pub fn ring_size(&self) -> Option<usize> {
+        let ring_sizes: Vec<usize> = self.public_keys_per_ring().iter().map(Vec::len).collect();
+        match ring_sizes.split_first() {
+            Some((first, rest)) if rest.iter().all(|s| s == first) => Some(*first),
+            _ => None,
+        }
+    }
+
+    /// The public keys of every input's ring, ring after ring
+    pub fn public_keys(&self) -> Vec<G1Affine> {
+        self.public_keys_per_ring().into_iter().flatten().collect()
+    }
+
+    /// The commitment of each output
+    pub fn output_commitments(&self) -> Vec<G1Affine> {
+        self.outputs.iter().map(|o| o.commitment().0).collect()
+    }
+
+    pub fn summary(&self) -> TransactionSummary {
+        TransactionSummary {
+            id: self.id(),
+            version: self.version,
+            input_count: self.input_count(),
+            output_count: self.output_count(),
+            ring_size: self.ring_size(),
+            fee: self.fee,
+            extra_len: self.extra.len(),
+            size: self.serialized_size(),
+        }
+    }
+}
+
+/// The shape of a transaction, as `RingCtTransaction::summary()` gives it
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransactionSummary {
+    /// The transaction id, see `RingCtTransaction::id()`
+    pub id: [u8; 32],
+    pub version: u16,
+    pub input_count: usize,
+    pub output_count: usize,
+    /// `None` if the rings differ in size
+    pub ring_size: Option<usize>,
+    pub fee: Amount,
+    pub extra_len: usize,
+    /// The serialized size in bytes
+    pub size: usize,
+}
+
+impl fmt::Display for TransactionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "tx {} (version {:#06x}): {} inputs",
+            codec::to_hex(&self.id),
+            self.version,
+            self.input_count
+        )?;
+        if let Some(ring_size) = self.ring_size {
+            write!(f, " with rings of {}", ring_size)?;
+        }
+        write!(
+            f,
+            ", {} outputs, fee {}, {} bytes",
+            self.output_count, self.fee, self.size
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{
+        blstrs::{G1Projective, Scalar},
+        group::{ff::Field, Curve, Group},
+        rand::rngs::OsRng,
+    };
+
+    use super::*;
+    use crate::{
+        mock::random_decoys, MlsagMaterial, Output, RevealedCommitment, RingCtMaterial, TrueInput,
+    };
+
+    #[test]
+    fn test_transaction_summary() {
+        let inputs = (0..2)
+            .map(|_| {
+                let true_input = TrueInput::new(
+                    Scalar::random(OsRng),
+                    RevealedCommitment::from_value(3, OsRng),
+                );
+                let decoy_inputs = random_decoys(3);
+                MlsagMaterial::new(true_input, decoy_inputs, &mut OsRng).unwrap()
+            })
+            .collect();
+        let material = RingCtMaterial {
+            inputs,
+            outputs: vec![
+                Output::new(G1Projective::random(OsRng), 4),
+                Output::new(G1Projective::random(OsRng), 1),
+            ],
+            fee: Amount::new(1),
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+        let (tx, revealed_output_commitments) = material
+            .sign(&mut OsRng)
+            .expect("Failed to sign transaction");
+
+        assert_eq!(tx.input_count(), 2);
+        assert_eq!(tx.output_count(), 2);
+        assert_eq!(tx.ring_size(), Some(4));
+        assert_eq!(tx.public_keys(), material.public_keys());
+        assert_eq!(
+            tx.output_commitments()[1],
+            revealed_output_commitments[1]
+                .commit(RingCtMaterial::pc_gens())
+                .to_affine()
+        );
+
+        let summary = tx.summary();
+        assert_eq!(summary.size, tx.to_bytes().len());
+        assert_eq!(
+            summary.to_string(),
+            format!(
+                "tx {} (version {:#06x}): 2 inputs with rings of 4, 2 outputs, fee 1, {} bytes",
+                codec::to_hex(&tx.id()),
+                tx.version,
+                summary.size
+            )
+        );
+    }
+}
@@ -0,0 +1,762 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! m-of-n threshold signing of an MLSAG ring signature.
+//!
+//! The true input's secret key is split into Shamir shares. A coordinator,
+//! who knows the input's commitment secrets, drives a two round protocol:
+//!
+//! 1. Each co-signer sends a `SigningCommitment` to a fresh pair of nonces.
+//! 2. The coordinator closes the ring and sends back a `SigningChallenge`
+//!    with the message, the ring, the decoys' responses and every
+//!    co-signer's commitment. Each co-signer recomputes the challenge from
+//!    these, rather than trusting a challenge handed to it, and answers with
+//!    a `PartialSignature`.
+//!
+//! As in FROST, a co-signer's nonce is `d_i + rho_i e_i`, with a binding
+//! factor `rho_i` hashing the message and the whole list of commitments, so
+//! commitments chosen after seeing the others can't steer the ring's nonce
+//! across concurrent sessions. A `SignerNonce` answers a single challenge.
+//!
+//! The partial responses are combined into a regular `MlsagSignature`.
+
+use std::collections::BTreeMap;
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::{ff::Field, Curve, Group},
+    rand::{CryptoRng, RngCore},
+    PedersenGens,
+};
+
+use crate::{
+    mlsag::{c_hash, hash_to_scalar, shuffle_ring},
+    DecoyInput, Error, KeyImage, MlsagSignature, Result, RevealedCommitment,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+const BINDING_FACTOR_DOMAIN: &[u8] = b"blst-ringct-threshold-binding-factor";
+
+/// A Shamir share of a true input's secret key
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct KeyShare {
+    /// The share's non-zero evaluation point
+    pub index: u32,
+    pub secret_share: Scalar,
+    /// The public key of the shared secret key
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub public_key: G1Affine,
+}
+
+/// Splits `secret_key` into `num_shares` shares, any `threshold` of which can sign
+pub fn split_secret_key<S: Into<Scalar>>(
+    secret_key: S,
+    threshold: usize,
+    num_shares: usize,
+    rng: &mut (impl RngCore + CryptoRng),
+) -> Result<Vec<KeyShare>> {
+    if threshold == 0 || threshold > num_shares || num_shares > u32::MAX as usize {
+        return Err(Error::InvalidThreshold {
+            threshold,
+            num_shares,
+        });
+    }
+
+    let secret_key = secret_key.into();
+    let public_key = crate::public_key(secret_key).to_affine();
+
+    // f(X) = secret_key + a_1 X + ... + a_{t-1} X^{t-1}
+    let mut coefficients = vec![secret_key];
+    coefficients.extend((1..threshold).map(|_| Scalar::random(&mut *rng)));
+
+    let shares = (1..=num_shares as u32)
+        .map(|index| {
+            let x = Scalar::from(index as u64);
+            let secret_share = coefficients
+                .iter()
+                .rev()
+                .fold(Scalar::zero(), |acc, a| acc * x + a);
+            KeyShare {
+                index,
+                secret_share,
+                public_key,
+            }
+        })
+        .collect();
+
+    #[cfg(feature = "zeroize")]
+    for a in coefficients.iter_mut() {
+        crate::zeroize_scalar(a);
+    }
+
+    Ok(shares)
+}
+
+impl KeyShare {
+    /// Round 1: commits to a fresh pair of nonces, which answer a single challenge
+    pub fn commit(&self, rng: &mut (impl RngCore + CryptoRng)) -> (SignerNonce, SigningCommitment) {
+        let hiding_nonce = Scalar::random(&mut *rng);
+        let binding_nonce = Scalar::random(&mut *rng);
+        let hashed_public_key = crate::hash_to_curve(self.public_key.into());
+
+        let commitment = SigningCommitment {
+            index: self.index,
+            nonce_commitment: crate::public_key(hiding_nonce).to_affine(),
+            nonce_key_image: (hashed_public_key * hiding_nonce).to_affine(),
+            binding_nonce_commitment: crate::public_key(binding_nonce).to_affine(),
+            binding_nonce_key_image: (hashed_public_key * binding_nonce).to_affine(),
+            partial_key_image: (hashed_public_key * self.secret_share).to_affine(),
+        };
+
+        (
+            SignerNonce {
+                commitment: commitment.clone(),
+                nonces: Some((hiding_nonce, binding_nonce)),
+            },
+            commitment,
+        )
+    }
+
+    /// Round 2: recomputes the ring's challenge from the coordinator's
+    /// `challenge` and answers it, spending the nonce. Answering a second
+    /// challenge with the same nonce fails with `Error::NonceReused`.
+    pub fn sign(
+        &self,
+        nonce: &mut SignerNonce,
+        challenge: &SigningChallenge,
+    ) -> Result<PartialSignature> {
+        if nonce.commitment.index != self.index
+            || !challenge.commitments.contains(&nonce.commitment)
+        {
+            return Err(Error::UnexpectedSigner(self.index));
+        }
+        let closed = challenge.close_ring(self.public_key)?;
+        let lambda = lagrange_coefficient(self.index, &closed.signers)?;
+        let rho = closed.binding_factor(self.index)?;
+
+        let (hiding_nonce, binding_nonce) = nonce.spend()?;
+        Ok(PartialSignature {
+            index: self.index,
+            response: hiding_nonce + rho * binding_nonce
+                - closed.c[closed.pi] * lambda * self.secret_share,
+        })
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for KeyShare {
+    fn zeroize(&mut self) {
+        crate::zeroize_scalar(&mut self.secret_share);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for KeyShare {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for KeyShare {}
+
+/// A co-signer's secret nonces, kept between the two rounds. They are
+/// deliberately neither `Clone` nor serializable, so they can't be copied
+/// into a second session.
+#[derive(Debug)]
+pub struct SignerNonce {
+    commitment: SigningCommitment,
+    nonces: Option<(Scalar, Scalar)>,
+}
+
+impl SignerNonce {
+    /// Takes the nonces out, leaving the nonce spent
+    fn spend(&mut self) -> Result<(Scalar, Scalar)> {
+        let nonces = self
+            .nonces
+            .ok_or(Error::NonceReused(self.commitment.index))?;
+        #[cfg(feature = "zeroize")]
+        self.zeroize();
+        self.nonces = None;
+        Ok(nonces)
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for SignerNonce {
+    fn zeroize(&mut self) {
+        if let Some((hiding_nonce, binding_nonce)) = self.nonces.as_mut() {
+            crate::zeroize_scalar(hiding_nonce);
+            crate::zeroize_scalar(binding_nonce);
+        }
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for SignerNonce {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for SignerNonce {}
+
+/// Round 1 message from a co-signer to the coordinator
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigningCommitment {
+    pub index: u32,
+    /// `d_i G`
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub nonce_commitment: G1Affine,
+    /// `d_i Hp(P)`
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub nonce_key_image: G1Affine,
+    /// `e_i G`
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub binding_nonce_commitment: G1Affine,
+    /// `e_i Hp(P)`
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub binding_nonce_key_image: G1Affine,
+    /// `x_i Hp(P)`
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub partial_key_image: G1Affine,
+}
+
+/// Round 2 message from the coordinator to the co-signers, everything needed
+/// to recompute the challenge at the true input
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigningChallenge {
+    pub msg: Vec<u8>,
+    /// The ring's public keys, each with its commitment minus the pseudo commitment
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub ring: Vec<(G1Affine, G1Affine)>,
+    /// The responses of the decoys, in ring order
+    pub decoy_responses: Vec<(Scalar, Scalar)>,
+    /// The coordinator's nonce commitment for the commitment column
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub commitment_nonce_commitment: G1Affine,
+    /// Every co-signer's round 1 commitment, ordered by index
+    pub commitments: Vec<SigningCommitment>,
+}
+
+impl SigningChallenge {
+    /// The challenge a co-signer holding a share of `public_key` answers
+    pub fn challenge_for(&self, public_key: G1Affine) -> Result<Scalar> {
+        let closed = self.close_ring(public_key)?;
+        Ok(closed.c[closed.pi])
+    }
+
+    /// Walks the ring from the co-signers' combined nonces at `public_key`
+    fn close_ring(&self, public_key: G1Affine) -> Result<ClosedRing> {
+        #[allow(non_snake_case)]
+        let G1 = G1Projective::generator();
+
+        let signers: Vec<u32> = self.commitments.iter().map(|c| c.index).collect();
+        let pi = self
+            .ring
+            .iter()
+            .position(|(pk, _)| *pk == public_key)
+            .ok_or(Error::InvalidSigningChallenge)?;
+        if signers.is_empty()
+            || signers.windows(2).any(|w| w[0] >= w[1])
+            || self.decoy_responses.len() + 1 != self.ring.len()
+        {
+            return Err(Error::InvalidSigningChallenge);
+        }
+
+        let binding_factors = binding_factors(&self.msg, &self.commitments);
+        let mut key_image = G1Projective::identity();
+        let mut nonce_commitment = G1Projective::identity();
+        let mut nonce_key_image = G1Projective::identity();
+        for (c, rho) in self.commitments.iter().zip(binding_factors.iter()) {
+            key_image += c.partial_key_image * lagrange_coefficient(c.index, &signers)?;
+            nonce_commitment += c.nonce_commitment + c.binding_nonce_commitment * rho;
+            nonce_key_image += c.nonce_key_image + c.binding_nonce_key_image * rho;
+        }
+        let key_image = key_image.to_affine();
+
+        let ring = &self.ring;
+        let mut c: Vec<Scalar> = (0..ring.len()).map(|_| Scalar::zero()).collect();
+        c[(pi + 1) % ring.len()] = c_hash(
+            &self.msg,
+            nonce_commitment,
+            self.commitment_nonce_commitment.into(),
+            nonce_key_image,
+        );
+
+        for offset in 1..ring.len() {
+            let n = (pi + offset) % ring.len();
+            let r = self.decoy_responses[if n < pi { n } else { n - 1 }];
+            c[(n + 1) % ring.len()] = c_hash(
+                &self.msg,
+                G1 * r.0 + ring[n].0 * c[n],
+                G1 * r.1 + ring[n].1 * c[n],
+                crate::hash_to_curve(ring[n].0.into()) * r.0 + key_image * c[n],
+            );
+        }
+
+        Ok(ClosedRing {
+            pi,
+            c,
+            key_image: key_image.into(),
+            signers,
+            binding_factors,
+        })
+    }
+}
+
+/// A ring closed over a `SigningChallenge`
+struct ClosedRing {
+    pi: usize,
+    c: Vec<Scalar>,
+    key_image: KeyImage,
+    signers: Vec<u32>,
+    binding_factors: Vec<Scalar>,
+}
+
+impl ClosedRing {
+    fn binding_factor(&self, index: u32) -> Result<Scalar> {
+        self.signers
+            .iter()
+            .position(|i| *i == index)
+            .map(|i| self.binding_factors[i])
+            .ok_or(Error::UnexpectedSigner(index))
+    }
+}
+
+/// Round 2 message from a co-signer to the coordinator
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialSignature {
+    pub index: u32,
+    pub response: Scalar,
+}
+
+/// The coordinator's side of a threshold MLSAG signature over one input
+#[derive(Debug, Clone)]
+pub struct SigningSession {
+    public_key: G1Affine,
+    revealed_commitment: RevealedCommitment,
+    decoy_inputs: Vec<DecoyInput>,
+    threshold: usize,
+    pi_base: u32,
+    commitment_nonce: Scalar,
+    decoy_responses: Vec<(Scalar, Scalar)>,
+    commitments: BTreeMap<u32, SigningCommitment>,
+    challenged: Option<ChallengedRing>,
+    partial_signatures: BTreeMap<u32, Scalar>,
+}
+
+#[derive(Debug, Clone)]
+struct ChallengedRing {
+    c0: Scalar,
+    r_commitment: Scalar,
+    public_commitments: Vec<G1Affine>,
+    key_image: KeyImage,
+    pseudo_commitment: G1Affine,
+    challenge: SigningChallenge,
+}
+
+impl SigningSession {
+    pub fn new(
+        public_key: G1Affine,
+        revealed_commitment: RevealedCommitment,
+        decoy_inputs: Vec<DecoyInput>,
+        threshold: usize,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Self {
+        let mut decoy_inputs = decoy_inputs;
+        let pi_base = shuffle_ring(&mut decoy_inputs, &mut *rng);
+        let commitment_nonce = Scalar::random(&mut *rng);
+        let decoy_responses = (0..decoy_inputs.len())
+            .map(|_| (Scalar::random(&mut *rng), Scalar::random(&mut *rng)))
+            .collect();
+
+        Self {
+            public_key,
+            revealed_commitment,
+            decoy_inputs,
+            threshold,
+            pi_base,
+            commitment_nonce,
+            decoy_responses,
+            commitments: Default::default(),
+            challenged: None,
+            partial_signatures: Default::default(),
+        }
+    }
+
+    pub fn pi(&self) -> usize {
+        self.pi_base as usize % (self.decoy_inputs.len() + 1)
+    }
+
+    pub fn public_keys(&self) -> Vec<G1Affine> {
+        let mut keys = Vec::from_iter(self.decoy_inputs.iter().map(DecoyInput::public_key));
+        keys.insert(self.pi(), self.public_key);
+        keys
+    }
+
+    pub fn commitments(&self, pc_gens: &PedersenGens) -> Vec<G1Affine> {
+        let mut cs = Vec::from_iter(self.decoy_inputs.iter().map(DecoyInput::commitment));
+        cs.insert(
+            self.pi(),
+            self.revealed_commitment.commit(pc_gens).to_affine(),
+        );
+        cs
+    }
+
+    /// Records a co-signer's round 1 commitment
+    pub fn add_commitment(&mut self, commitment: SigningCommitment) -> Result<()> {
+        if self.challenged.is_some() {
+            return Err(Error::InvalidSigningSessionState);
+        }
+        if commitment.index == 0 || self.commitments.contains_key(&commitment.index) {
+            return Err(Error::UnexpectedSigner(commitment.index));
+        }
+        self.commitments.insert(commitment.index, commitment);
+        Ok(())
+    }
+
+    /// The input's key image, combined from the co-signers' partial key images.
+    /// This is needed to build the message before the challenge can be computed.
+    pub fn key_image(&self) -> Result<KeyImage> {
+        let signers = self.signers()?;
+        let mut key_image = G1Projective::identity();
+        for c in self.commitments.values() {
+            key_image += c.partial_key_image * lagrange_coefficient(c.index, &signers)?;
+        }
+        Ok(key_image.into())
+    }
+
+    /// Closes the ring over `msg` and returns the challenge for the co-signers
+    pub fn challenge(
+        &mut self,
+        msg: &[u8],
+        revealed_pseudo_commitment: &RevealedCommitment,
+        pc_gens: &PedersenGens,
+    ) -> Result<SigningChallenge> {
+        if self.challenged.is_some() {
+            return Err(Error::InvalidSigningSessionState);
+        }
+        self.signers()?;
+
+        let public_commitments = self.commitments(pc_gens);
+        let pseudo_commitment = revealed_pseudo_commitment.commit(pc_gens);
+        let ring: Vec<(G1Affine, G1Affine)> = self
+            .public_keys()
+            .into_iter()
+            .zip(public_commitments.iter())
+            .map(|(pk, commitment)| (pk, (*commitment - pseudo_commitment).to_affine()))
+            .collect();
+
+        let challenge = SigningChallenge {
+            msg: msg.to_vec(),
+            ring,
+            decoy_responses: self.decoy_responses.clone(),
+            commitment_nonce_commitment: crate::public_key(self.commitment_nonce).to_affine(),
+            commitments: self.commitments.values().cloned().collect(),
+        };
+        let closed = challenge.close_ring(self.public_key)?;
+
+        let commitment_secret_key =
+            self.revealed_commitment.blinding - revealed_pseudo_commitment.blinding;
+        let r_commitment = self.commitment_nonce - closed.c[closed.pi] * commitment_secret_key;
+
+        self.challenged = Some(ChallengedRing {
+            c0: closed.c[0],
+            r_commitment,
+            public_commitments,
+            key_image: closed.key_image,
+            pseudo_commitment: pseudo_commitment.to_affine(),
+            challenge: challenge.clone(),
+        });
+
+        Ok(challenge)
+    }
+
+    /// Records a co-signer's round 2 response
+    pub fn add_partial_signature(&mut self, partial_signature: PartialSignature) -> Result<()> {
+        let challenged = self
+            .challenged
+            .as_ref()
+            .ok_or(Error::InvalidSigningSessionState)?;
+        if !challenged
+            .challenge
+            .commitments
+            .iter()
+            .any(|c| c.index == partial_signature.index)
+        {
+            return Err(Error::UnexpectedSigner(partial_signature.index));
+        }
+        self.partial_signatures
+            .insert(partial_signature.index, partial_signature.response);
+        Ok(())
+    }
+
+    /// Combines the partial signatures into an MLSAG, which is verified before
+    /// being returned so a misbehaving co-signer is caught here.
+    pub fn finalize(mut self) -> Result<MlsagSignature> {
+        let challenged = self
+            .challenged
+            .take()
+            .ok_or(Error::InvalidSigningSessionState)?;
+        if let Some(missing) = challenged
+            .challenge
+            .commitments
+            .iter()
+            .map(|c| c.index)
+            .find(|index| !self.partial_signatures.contains_key(index))
+        {
+            return Err(Error::MissingPartialSignature(missing));
+        }
+
+        let mut r = challenged.challenge.decoy_responses;
+        // todo: replace fold() with sum() when supported in blstrs
+        r.insert(
+            self.pi(),
+            (
+                self.partial_signatures
+                    .values()
+                    .fold(Scalar::zero(), |sum, response| sum + response),
+                challenged.r_commitment,
+            ),
+        );
+
+        let mlsag = MlsagSignature {
+            c0: challenged.c0,
+            r,
+            key_image: challenged.key_image,
+            ring: challenged.challenge.ring,
+            pseudo_commitment: challenged.pseudo_commitment,
+        };
+        mlsag.verify(&challenged.challenge.msg, &challenged.public_commitments)?;
+        Ok(mlsag)
+    }
+
+    fn signers(&self) -> Result<Vec<u32>> {
+        if self.commitments.len() < self.threshold {
+            return Err(Error::NotEnoughSigners {
+                threshold: self.threshold,
+                signers: self.commitments.len(),
+            });
+        }
+        Ok(self.commitments.keys().copied().collect())
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for SigningSession {
+    fn zeroize(&mut self) {
+        self.revealed_commitment.zeroize();
+        crate::zeroize_scalar(&mut self.commitment_nonce);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for SigningSession {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for SigningSession {}
+
+/// The binding factor of each commitment, over the message and the whole list
+fn binding_factors(msg: &[u8], commitments: &[SigningCommitment]) -> Vec<Scalar> {
+    let encoded: Vec<u8> = commitments
+        .iter()
+        .flat_map(|c| {
+            let points = [
+                c.nonce_commitment,
+                c.nonce_key_image,
+                c.binding_nonce_commitment,
+                c.binding_nonce_key_image,
+                c.partial_key_image,
+            ];
+            c.index
+                .to_le_bytes()
+                .into_iter()
+                .chain(points.into_iter().flat_map(|p| p.to_compressed()))
+        })
+        .collect();
+    commitments
+        .iter()
+        .map(|c| hash_to_scalar(&[BINDING_FACTOR_DOMAIN, &c.index.to_le_bytes(), msg, &encoded]))
+        .collect()
+}
+
+/// The Lagrange coefficient at zero of `index` amongst `signers`
+pub(crate) fn lagrange_coefficient(index: u32, signers: &[u32]) -> Result<Scalar> {
+    let x_i = Scalar::from(index as u64);
+    let (numerator, denominator) = signers.iter().filter(|j| **j != index).fold(
+        (Scalar::one(), Scalar::one()),
+        |(num, den), j| {
+            let x_j = Scalar::from(*j as u64);
+            (num * x_j, den * (x_j - x_i))
+        },
+    );
+
+    Option::from(denominator.invert())
+        .map(|inv: Scalar| numerator * inv)
+        .ok_or(Error::UnexpectedSigner(index))
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::rand::rngs::OsRng;
+
+    use super::*;
+
+    fn session_for(
+        secret_key: Scalar,
+        threshold: usize,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> (SigningSession, RevealedCommitment) {
+        let revealed_commitment = RevealedCommitment::from_value(3, &mut *rng);
+        let decoy_inputs = Vec::from_iter((0..2).map(|_| DecoyInput {
+            public_key: G1Projective::random(&mut *rng).to_affine(),
+            commitment: G1Projective::random(&mut *rng).to_affine(),
+        }));
+        let session = SigningSession::new(
+            crate::public_key(secret_key).to_affine(),
+            revealed_commitment,
+            decoy_inputs,
+            threshold,
+            rng,
+        );
+        (session, RevealedCommitment::from_value(3, &mut *rng))
+    }
+
+    #[test]
+    fn test_2_of_3_threshold_mlsag() {
+        let mut rng = OsRng;
+        let pc_gens = crate::pedersen_gens();
+        let secret_key = Scalar::random(&mut rng);
+        let shares = split_secret_key(secret_key, 2, 3, &mut rng).unwrap();
+
+        let (mut session, revealed_pseudo_commitment) = session_for(secret_key, 2, &mut rng);
+        let signers = [&shares[0], &shares[2]];
+
+        let mut nonces = Vec::from_iter(signers.iter().map(|share| {
+            let (nonce, commitment) = share.commit(&mut rng);
+            session.add_commitment(commitment).unwrap();
+            nonce
+        }));
+        assert_eq!(
+            session.key_image().unwrap(),
+            KeyImage::from(crate::key_image(secret_key))
+        );
+
+        let msg = b"threshold";
+        let challenge = session
+            .challenge(msg, &revealed_pseudo_commitment, &pc_gens)
+            .unwrap();
+        for (share, nonce) in signers.into_iter().zip(nonces.iter_mut()) {
+            let partial_signature = share.sign(nonce, &challenge).unwrap();
+            session.add_partial_signature(partial_signature).unwrap();
+        }
+
+        let public_commitments = session.commitments(&pc_gens);
+        let mlsag = session.finalize().unwrap();
+        assert!(mlsag.verify(msg, &public_commitments).is_ok());
+    }
+
+    #[test]
+    fn test_threshold_requires_enough_signers() {
+        let mut rng = OsRng;
+        let secret_key = Scalar::random(&mut rng);
+        let shares = split_secret_key(secret_key, 2, 3, &mut rng).unwrap();
+
+        let (mut session, revealed_pseudo_commitment) = session_for(secret_key, 2, &mut rng);
+        let (_nonce, commitment) = shares[1].commit(&mut rng);
+        session.add_commitment(commitment).unwrap();
+
+        assert_eq!(
+            session.challenge(b"msg", &revealed_pseudo_commitment, &crate::pedersen_gens()),
+            Err(Error::NotEnoughSigners {
+                threshold: 2,
+                signers: 1
+            })
+        );
+        assert!(matches!(
+            split_secret_key(secret_key, 4, 3, &mut rng),
+            Err(Error::InvalidThreshold { .. })
+        ));
+    }
+
+    #[test]
+    fn test_co_signers_recompute_the_challenge() {
+        let mut rng = OsRng;
+        let pc_gens = crate::pedersen_gens();
+        let secret_key = Scalar::random(&mut rng);
+        let shares = split_secret_key(secret_key, 2, 3, &mut rng).unwrap();
+
+        let (mut session, revealed_pseudo_commitment) = session_for(secret_key, 2, &mut rng);
+        let (mut nonce_a, commitment_a) = shares[0].commit(&mut rng);
+        let (mut nonce_b, commitment_b) = shares[1].commit(&mut rng);
+        session.add_commitment(commitment_a).unwrap();
+        session.add_commitment(commitment_b).unwrap();
+        let challenge = session
+            .challenge(b"msg", &revealed_pseudo_commitment, &pc_gens)
+            .unwrap();
+        assert_eq!(
+            challenge.challenge_for(shares[0].public_key).unwrap(),
+            challenge.challenge_for(shares[1].public_key).unwrap()
+        );
+
+        // A challenge leaving out the co-signer's commitment is refused
+        // without spending the nonce.
+        let mut without_a = challenge.clone();
+        without_a.commitments.remove(0);
+        assert_eq!(
+            shares[0].sign(&mut nonce_a, &without_a),
+            Err(Error::UnexpectedSigner(shares[0].index))
+        );
+
+        // Responses to a challenge over another message don't combine.
+        let mut other_msg = challenge.clone();
+        other_msg.msg = b"other msg".to_vec();
+        session
+            .add_partial_signature(shares[0].sign(&mut nonce_a, &other_msg).unwrap())
+            .unwrap();
+        session
+            .add_partial_signature(shares[1].sign(&mut nonce_b, &challenge).unwrap())
+            .unwrap();
+        assert_eq!(session.finalize(), Err(Error::InvalidRingSignature));
+    }
+
+    #[test]
+    fn test_nonce_answers_a_single_challenge() {
+        let mut rng = OsRng;
+        let pc_gens = crate::pedersen_gens();
+        let secret_key = Scalar::random(&mut rng);
+        let shares = split_secret_key(secret_key, 1, 1, &mut rng).unwrap();
+        let (mut nonce, commitment) = shares[0].commit(&mut rng);
+
+        let challenges = Vec::from_iter((0..2).map(|_| {
+            let (mut session, revealed_pseudo_commitment) = session_for(secret_key, 1, &mut rng);
+            session.add_commitment(commitment.clone()).unwrap();
+            session
+                .challenge(b"msg", &revealed_pseudo_commitment, &pc_gens)
+                .unwrap()
+        }));
+
+        assert!(shares[0].sign(&mut nonce, &challenges[0]).is_ok());
+        assert_eq!(
+            shares[0].sign(&mut nonce, &challenges[1]),
+            Err(Error::NonceReused(shares[0].index))
+        );
+    }
+}
@@ -0,0 +1,551 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Experimental Triptych: a linkable ring signature whose size is logarithmic
+//! in the size of the ring, built on the one-of-many proofs of Groth and Kohlweiss
+//! and of Bootle et al. Rings of 128 or more members are practical, where the
+//! size of an MLSAG or CLSAG grows linearly.
+//!
+//! As with MLSAG, the signer proves knowledge of both the secret key of a ring
+//! member and the blinding factor of its hidden commitment, for the same member.
+//! The ring size must be a power of two.
+//!
+//! The key image is `J = x⁻¹ U` for secret key `x` and a fixed generator `U`,
+//! so it does not link with the key images of MLSAG or CLSAG. A ledger must
+//! not accept both for the same outputs.
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::{ff::Field, Curve, Group},
+    rand::{CryptoRng, RngCore},
+    PedersenGens,
+};
+
+use crate::{
+    codec::{self, Reader},
+    mlsag::{append_ring, hash_to_scalar, nonce_transcript},
+    Error, KeyImage, MlsagMaterial, Result, RevealedCommitment,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const GENERATORS_DOMAIN: &[u8] = b"blst-ringct-triptych-generators";
+const KEY_IMAGE_DOMAIN: &[u8] = b"blst-ringct-triptych-key-image";
+const CHALLENGE_DOMAIN: &[u8] = b"blst-ringct-triptych-challenge";
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct TriptychSignature {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub ring: Vec<(G1Affine, G1Affine)>,
+    /// `J = x⁻¹ U`, unlike the key images of MLSAG and CLSAG
+    pub key_image: KeyImage,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub pseudo_commitment: G1Affine,
+    /// Commitments to the bits of the signer's index and their masks
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub a: G1Affine,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub b: G1Affine,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub c: G1Affine,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub d: G1Affine,
+    /// One of each per bit of the ring index
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub x: Vec<G1Affine>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub y: Vec<G1Affine>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub w: Vec<G1Affine>,
+    pub f: Vec<Scalar>,
+    pub z_a: Scalar,
+    pub z_c: Scalar,
+    pub z: Scalar,
+    pub z_commitment: Scalar,
+}
+
+impl MlsagMaterial {
+    /// Signs this input with a Triptych instead of an MLSAG. The ring size must
+    /// be a power of two. The nonces are drawn as `sign()` draws them, from a
+    /// transcript of `msg` and the ring rekeyed with the true input's secrets.
+    pub fn sign_triptych(
+        &self,
+        msg: &[u8],
+        revealed_pseudo_commitment: &RevealedCommitment,
+        pc_gens: &PedersenGens,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<TriptychSignature> {
+        #[allow(non_snake_case)]
+        let G = G1Projective::generator();
+
+        let ring_len = self.count_inputs();
+        let bits = index_bits(ring_len)?;
+        let pi = self.pi();
+
+        let pseudo_commitment = revealed_pseudo_commitment.commit(pc_gens);
+        let ring: Vec<(G1Affine, G1Affine)> = self
+            .public_keys()
+            .into_iter()
+            .zip(self.commitments(pc_gens))
+            .map(|(pk, commitment)| (pk, (commitment - pseudo_commitment).to_affine()))
+            .collect();
+        let pseudo_commitment = pseudo_commitment.to_affine();
+
+        let mut transcript = nonce_transcript(b"triptych", msg);
+        append_ring(&mut transcript, &ring, &pseudo_commitment);
+        let mut nonce_rng = self.nonce_rng(&transcript, rng);
+
+        let secret_key = self.true_input.secret_key;
+        let blinding =
+            self.true_input.revealed_commitment.blinding - revealed_pseudo_commitment.blinding;
+        let inverse: Option<Scalar> = secret_key.invert().into();
+        let key_image = key_image_generator() * inverse.ok_or(Error::SecretKeyIsZero)?;
+
+        // sigma[j] = [1 - b, b] for bit b of the signer's index, and a[j] masks it
+        let sigma: Vec<[Scalar; 2]> = (0..bits)
+            .map(|j| {
+                let bit = Scalar::from(((pi >> j) & 1) as u64);
+                [Scalar::one() - bit, bit]
+            })
+            .collect();
+        let a: Vec<[Scalar; 2]> = (0..bits)
+            .map(|_| {
+                let a1 = Scalar::random(&mut nonce_rng);
+                [-a1, a1]
+            })
+            .collect();
+        let [r_a, r_b, r_c, r_d] = [(); 4].map(|_| Scalar::random(&mut nonce_rng));
+
+        let generators = matrix_generators(bits);
+        let commit_a = matrix_commit(&generators, &a, r_a);
+        let commit_b = matrix_commit(&generators, &sigma, r_b);
+        let commit_c = matrix_commit(
+            &generators,
+            &Vec::from_iter(
+                a.iter()
+                    .zip(&sigma)
+                    .map(|(a, s)| [0, 1].map(|i| a[i] * (Scalar::one() - s[i].double()))),
+            ),
+            r_c,
+        );
+        let commit_d = matrix_commit(
+            &generators,
+            &Vec::from_iter(a.iter().map(|a| [0, 1].map(|i| -a[i].square()))),
+            r_d,
+        );
+
+        // coefficients[k][j]: the coefficient of x^j in prod_j (sigma[j][k_j] x + a[j][k_j])
+        let coefficients: Vec<Vec<Scalar>> = (0..ring_len)
+            .map(|k| {
+                (0..bits).fold(vec![Scalar::one()], |poly, j| {
+                    let bit = (k >> j) & 1;
+                    let mut product = vec![Scalar::zero(); poly.len() + 1];
+                    for (t, coefficient) in poly.iter().enumerate() {
+                        product[t] += *coefficient * a[j][bit];
+                        product[t + 1] += *coefficient * sigma[j][bit];
+                    }
+                    product
+                })
+            })
+            .collect();
+
+        let rho: Vec<Scalar> = (0..bits).map(|_| Scalar::random(&mut nonce_rng)).collect();
+        let tau: Vec<Scalar> = (0..bits).map(|_| Scalar::random(&mut nonce_rng)).collect();
+        let public_keys: Vec<G1Projective> = ring.iter().map(|(pk, _)| pk.into()).collect();
+        let hidden_commitments: Vec<G1Projective> = ring.iter().map(|(_, c)| c.into()).collect();
+
+        let mut x = Vec::with_capacity(bits);
+        let mut y = Vec::with_capacity(bits);
+        let mut w = Vec::with_capacity(bits);
+        for j in 0..bits {
+            let p_j = Vec::from_iter(coefficients.iter().map(|p| p[j]));
+            x.push((G1Projective::multi_exp(&public_keys, &p_j) + G * rho[j]).to_affine());
+            y.push((key_image * rho[j]).to_affine());
+            w.push((G1Projective::multi_exp(&hidden_commitments, &p_j) + G * tau[j]).to_affine());
+        }
+
+        let mut sig = TriptychSignature {
+            ring,
+            key_image: key_image.into(),
+            pseudo_commitment,
+            a: commit_a.to_affine(),
+            b: commit_b.to_affine(),
+            c: commit_c.to_affine(),
+            d: commit_d.to_affine(),
+            x,
+            y,
+            w,
+            f: vec![],
+            z_a: Scalar::zero(),
+            z_c: Scalar::zero(),
+            z: Scalar::zero(),
+            z_commitment: Scalar::zero(),
+        };
+
+        let xi = sig.challenge(msg);
+        let xi_powers = powers(xi, bits);
+
+        sig.f = a
+            .iter()
+            .zip(&sigma)
+            .map(|(a, s)| s[1] * xi + a[1])
+            .collect();
+        sig.z_a = r_a + xi * r_b;
+        sig.z_c = xi * r_c + r_d;
+        sig.z = secret_key * xi_powers[bits] - inner_product(&rho, &xi_powers);
+        sig.z_commitment = blinding * xi_powers[bits] - inner_product(&tau, &xi_powers);
+
+        Ok(sig)
+    }
+}
+
+impl TriptychSignature {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Default::default();
+        codec::write_len(&mut v, self.ring.len());
+        for (x, y) in self.ring.iter() {
+            v.extend(x.to_compressed());
+            v.extend(y.to_compressed());
+        }
+        v.extend(self.key_image.to_bytes());
+        for point in [&self.pseudo_commitment, &self.a, &self.b, &self.c, &self.d] {
+            v.extend(point.to_compressed());
+        }
+        for points in [&self.x, &self.y, &self.w] {
+            codec::write_len(&mut v, points.len());
+            for point in points.iter() {
+                v.extend(point.to_compressed());
+            }
+        }
+        codec::write_len(&mut v, self.f.len());
+        for f in self.f.iter() {
+            v.extend(f.to_bytes_le());
+        }
+        for scalar in [&self.z_a, &self.z_c, &self.z, &self.z_commitment] {
+            v.extend(scalar.to_bytes_le());
+        }
+        v
+    }
+
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let sig = Self::decode(&mut reader)?;
+        reader.finish()?;
+        Ok(sig)
+    }
+
+    pub(crate) fn decode(reader: &mut Reader) -> Result<Self> {
+        let ring = reader.read_vec(|r| Ok((r.read_point()?, r.read_point()?)))?;
+        let key_image = KeyImage::from(reader.read_point()?);
+        let pseudo_commitment = reader.read_point()?;
+        let [a, b, c, d] = [
+            reader.read_point()?,
+            reader.read_point()?,
+            reader.read_point()?,
+            reader.read_point()?,
+        ];
+        let x = reader.read_vec(Reader::read_point)?;
+        let y = reader.read_vec(Reader::read_point)?;
+        let w = reader.read_vec(Reader::read_point)?;
+        let f = reader.read_vec(Reader::read_scalar)?;
+
+        Ok(Self {
+            ring,
+            key_image,
+            pseudo_commitment,
+            a,
+            b,
+            c,
+            d,
+            x,
+            y,
+            w,
+            f,
+            z_a: reader.read_scalar()?,
+            z_c: reader.read_scalar()?,
+            z: reader.read_scalar()?,
+            z_commitment: reader.read_scalar()?,
+        })
+    }
+
+    pub fn pseudo_commitment(&self) -> G1Affine {
+        self.pseudo_commitment
+    }
+
+    pub fn key_image(&self) -> KeyImage {
+        self.key_image
+    }
+
+    pub fn public_keys(&self) -> Vec<G1Affine> {
+        self.ring.iter().map(|(pk, _)| *pk).collect()
+    }
+
+    pub fn verify(&self, msg: &[u8], public_commitments: &[G1Affine]) -> Result<()> {
+        if self.ring.is_empty() {
+            return Err(Error::EmptyRing);
+        }
+        let bits = index_bits(self.ring.len())?;
+        if self.ring.len() != public_commitments.len() {
+            return Err(Error::ExpectedAPublicCommitmentsForEachRingEntry);
+        }
+        if [self.x.len(), self.y.len(), self.w.len(), self.f.len()]
+            .iter()
+            .any(|len| *len != bits)
+        {
+            return Err(Error::InvalidRingSignature);
+        }
+        // Check that hidden commitments in the ring where computed with: C - C'
+        for ((_, hidden_commitment), public_commitment) in self.ring.iter().zip(public_commitments)
+        {
+            if G1Projective::from(hidden_commitment)
+                != public_commitment - G1Projective::from(self.pseudo_commitment)
+            {
+                return Err(Error::InvalidHiddenCommitmentInRing);
+            }
+        }
+
+        #[allow(non_snake_case)]
+        let G = G1Projective::generator();
+        let xi = self.challenge(msg);
+        let xi_powers = powers(xi, bits);
+        let f: Vec<[Scalar; 2]> = self.f.iter().map(|f1| [xi - f1, *f1]).collect();
+
+        // the bits committed to are each 0 or 1, and f opens them
+        let generators = matrix_generators(bits);
+        if G1Projective::from(self.a) + self.b * xi != matrix_commit(&generators, &f, self.z_a) {
+            return Err(Error::InvalidRingSignature);
+        }
+        let f_squares = Vec::from_iter(f.iter().map(|f| [0, 1].map(|i| f[i] * (xi - f[i]))));
+        if self.c * xi + self.d != matrix_commit(&generators, &f_squares, self.z_c) {
+            return Err(Error::InvalidRingSignature);
+        }
+
+        // t[k] evaluates at xi the polynomial that is of degree m only for the signer
+        let t: Vec<Scalar> = (0..self.ring.len())
+            .map(|k| {
+                f.iter()
+                    .enumerate()
+                    .fold(Scalar::one(), |t, (j, f)| t * f[(k >> j) & 1])
+            })
+            .collect();
+        let neg_xi_powers = Vec::from_iter(xi_powers[..bits].iter().map(|p| -*p));
+
+        let sum = |points: Vec<G1Projective>, offsets: &[G1Affine], scalars: &[Scalar]| {
+            let offsets = offsets.iter().map(G1Projective::from);
+            let points = Vec::from_iter(points.into_iter().chain(offsets));
+            G1Projective::multi_exp(&points, scalars)
+        };
+        let public_keys: Vec<G1Projective> = self.ring.iter().map(|(pk, _)| pk.into()).collect();
+        let hidden_commitments: Vec<G1Projective> =
+            self.ring.iter().map(|(_, c)| c.into()).collect();
+        let t_then_neg_xi = Vec::from_iter(t.iter().chain(&neg_xi_powers).copied());
+        let u_then_neg_xi =
+            Vec::from_iter(std::iter::once(xi_powers[bits]).chain(neg_xi_powers.iter().copied()));
+
+        let key_image = G1Projective::from(self.key_image.to_affine());
+        let valid = sum(public_keys, &self.x, &t_then_neg_xi) == G * self.z
+            && sum(hidden_commitments, &self.w, &t_then_neg_xi) == G * self.z_commitment
+            && sum(vec![key_image_generator()], &self.y, &u_then_neg_xi) == key_image * self.z;
+
+        if valid {
+            Ok(())
+        } else {
+            Err(Error::InvalidRingSignature)
+        }
+    }
+
+    // The Fiat-Shamir challenge over the message and everything committed to
+    // before the responses
+    fn challenge(&self, msg: &[u8]) -> Scalar {
+        let ring_points = self.ring.iter().flat_map(|(pk, c)| [pk, c]);
+        let points: Vec<[u8; 48]> = ring_points
+            .chain([
+                &self.key_image.to_affine(),
+                &self.pseudo_commitment,
+                &self.a,
+                &self.b,
+                &self.c,
+                &self.d,
+            ])
+            .chain(self.x.iter().chain(&self.y).chain(&self.w))
+            .map(G1Affine::to_compressed)
+            .collect();
+        let material: Vec<&[u8]> = [CHALLENGE_DOMAIN, msg]
+            .into_iter()
+            .chain(points.iter().map(|p| p.as_slice()))
+            .collect();
+        hash_to_scalar(&material)
+    }
+}
+
+/// The number of bits in an index of a ring of `ring_len` members
+fn index_bits(ring_len: usize) -> Result<usize> {
+    if ring_len < 2 || !ring_len.is_power_of_two() {
+        return Err(Error::RingSizeNotPowerOfTwo(ring_len));
+    }
+    Ok(ring_len.trailing_zeros() as usize)
+}
+
+fn key_image_generator() -> G1Projective {
+    G1Projective::hash_to_curve(b"U", KEY_IMAGE_DOMAIN, &[])
+}
+
+// Independent generators for committing to a matrix of `bits` rows of 2 scalars
+fn matrix_generators(bits: usize) -> Vec<[G1Projective; 2]> {
+    (0..bits as u32)
+        .map(|j| {
+            [0u8, 1].map(|i| {
+                let msg = [&j.to_le_bytes()[..], &[i]].concat();
+                G1Projective::hash_to_curve(&msg, GENERATORS_DOMAIN, &[])
+            })
+        })
+        .collect()
+}
+
+fn matrix_commit(
+    generators: &[[G1Projective; 2]],
+    matrix: &[[Scalar; 2]],
+    blinding: Scalar,
+) -> G1Projective {
+    let points = Vec::from_iter(
+        std::iter::once(G1Projective::generator()).chain(generators.iter().flatten().copied()),
+    );
+    let scalars = Vec::from_iter(std::iter::once(blinding).chain(matrix.iter().flatten().copied()));
+    G1Projective::multi_exp(&points, &scalars)
+}
+
+// [1, x, x^2, .., x^n]
+fn powers(x: Scalar, n: usize) -> Vec<Scalar> {
+    std::iter::successors(Some(Scalar::one()), |p| Some(*p * x))
+        .take(n + 1)
+        .collect()
+}
+
+fn inner_product(a: &[Scalar], b: &[Scalar]) -> Scalar {
+    a.iter()
+        .zip(b)
+        .fold(Scalar::zero(), |sum, (a, b)| sum + *a * *b)
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::rand::rngs::OsRng;
+    use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+
+    use super::*;
+    use crate::{mock::random_decoys, TrueInput};
+
+    fn ring_material(ring_len: usize) -> (MlsagMaterial, RevealedCommitment, Vec<G1Affine>) {
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(5, OsRng),
+        );
+        let revealed_pseudo_commitment = true_input.random_pseudo_commitment(OsRng);
+        let decoys = random_decoys(ring_len - 1);
+        let material = MlsagMaterial::new(true_input, decoys, &mut OsRng).unwrap();
+        let public_commitments = material.commitments(&crate::pedersen_gens());
+        (material, revealed_pseudo_commitment, public_commitments)
+    }
+
+    #[test]
+    fn test_triptych_signs_and_verifies() {
+        let pc_gens = crate::pedersen_gens();
+        for ring_len in [2, 8, 128] {
+            let (material, pseudo, public_commitments) = ring_material(ring_len);
+            let sig = material
+                .sign_triptych(b"msg", &pseudo, &pc_gens, &mut OsRng)
+                .unwrap();
+            assert_eq!(sig.verify(b"msg", &public_commitments), Ok(()));
+            assert_eq!(
+                sig.verify(b"other msg", &public_commitments),
+                Err(Error::InvalidRingSignature)
+            );
+
+            let decoded = TriptychSignature::from_bytes(&sig.to_bytes()).unwrap();
+            assert_eq!(decoded, sig);
+
+            // the same key always has the same key image
+            let again = material
+                .sign_triptych(b"other msg", &pseudo, &pc_gens, &mut OsRng)
+                .unwrap();
+            assert_eq!(again.key_image, sig.key_image);
+        }
+    }
+
+    #[test]
+    fn test_triptych_nonces_are_bound_to_the_message() {
+        let pc_gens = crate::pedersen_gens();
+        let (material, pseudo, public_commitments) = ring_material(8);
+        let sign = |msg: &[u8]| {
+            material
+                .sign_triptych(msg, &pseudo, &pc_gens, &mut ChaCha20Rng::from_seed([3; 32]))
+                .unwrap()
+        };
+
+        // the same rng doesn't repeat the nonces for another message
+        let (sig_a, sig_b) = (sign(b"msg a"), sign(b"msg b"));
+        assert_eq!(sig_a.verify(b"msg a", &public_commitments), Ok(()));
+        assert_eq!(sig_b.verify(b"msg b", &public_commitments), Ok(()));
+        assert_ne!(sig_a.a, sig_b.a);
+        assert_ne!(sig_a.x, sig_b.x);
+        assert_eq!(sign(b"msg a"), sig_a);
+    }
+
+    #[test]
+    fn test_triptych_size_is_logarithmic() {
+        let pc_gens = crate::pedersen_gens();
+        let (material, pseudo, _) = ring_material(128);
+        let triptych = material
+            .sign_triptych(b"msg", &pseudo, &pc_gens, &mut OsRng)
+            .unwrap();
+        let mlsag = material.sign(b"msg", &pseudo, &pc_gens, &mut OsRng);
+
+        // without the ring itself, which both carry
+        let ring_bytes = 128 * 2 * 48;
+        assert_eq!(triptych.x.len(), 7);
+        assert!(triptych.to_bytes().len() - ring_bytes < (mlsag.to_bytes().len() - ring_bytes) / 4);
+    }
+
+    #[test]
+    fn test_tampered_triptych_is_rejected() {
+        let pc_gens = crate::pedersen_gens();
+        let (material, pseudo, public_commitments) = ring_material(8);
+        let sig = material
+            .sign_triptych(b"msg", &pseudo, &pc_gens, &mut OsRng)
+            .unwrap();
+
+        let mut tampered = sig.clone();
+        tampered.f[0] += Scalar::one();
+        assert_eq!(
+            tampered.verify(b"msg", &public_commitments),
+            Err(Error::InvalidRingSignature)
+        );
+
+        // a different key image can't be substituted to double spend
+        let mut tampered = sig.clone();
+        tampered.key_image = KeyImage::from(G1Projective::random(OsRng));
+        assert_eq!(
+            tampered.verify(b"msg", &public_commitments),
+            Err(Error::InvalidRingSignature)
+        );
+
+        let mut tampered = sig;
+        tampered.z_commitment += Scalar::one();
+        assert_eq!(
+            tampered.verify(b"msg", &public_commitments),
+            Err(Error::InvalidRingSignature)
+        );
+
+        let (material, pseudo, _) = ring_material(6);
+        assert_eq!(
+            material.sign_triptych(b"msg", &pseudo, &pc_gens, &mut OsRng),
+            Err(Error::RingSizeNotPowerOfTwo(6))
+        );
+    }
+}
@@ -0,0 +1,42 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use bls_bulletproofs::blstrs::G1Projective;
+
+use crate::{instrument::Phase, ringct::RingCtTransaction, Error, Result};
+
+/// The consensus rules a transaction must follow once its ring signatures and
+/// range proofs are verified.
+///
+/// Networks add rules, eg. a fee floor or a whitelist of denominations, by
+/// implementing this and checking `DefaultPolicy` first, reporting their own
+/// failures as `Error::PolicyViolation`.
+pub trait TxPolicy: Send + Sync {
+    /// Checks `tx`, where `fee_commitment` commits to its fee with a zero
+    /// blinding factor
+    fn check(&self, tx: &RingCtTransaction, fee_commitment: G1Projective) -> Result<()>;
+}
+
+/// The rules of `RingCtTransaction::verify()`: at least one input, key images
+/// and ring members unique across inputs, and inputs balancing the outputs
+/// and fee.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultPolicy;
+
+impl TxPolicy for DefaultPolicy {
+    fn check(&self, tx: &RingCtTransaction, fee_commitment: G1Projective) -> Result<()> {
+        // Verify that the tx has at least one input
+        if tx.key_images().is_empty() {
+            return Err(Error::TransactionMustHaveAnInput);
+        }
+
+        Phase::new("uniqueness").in_scope(|| {
+            tx.verify_key_images_unique()?;
+            tx.verify_public_keys_unique()
+        })?;
+        Phase::new("balance").in_scope(|| tx.check_balance(fee_commitment))
+    }
+}
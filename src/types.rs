@@ -0,0 +1,161 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Newtypes telling apart the points and scalars which play different roles,
+//! so that eg. a commitment can't be passed where a public key is expected.
+//!
+//! Raw `G1Affine`, `G1Projective` and `Scalar` values convert into them with
+//! `From`, and back with `From` or the inner field.
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::Curve,
+};
+use std::fmt;
+
+use crate::{codec, Error, Result};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The public key owning an output, `P = xG`
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicKey(#[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))] pub G1Affine);
+
+/// The secret key of a `PublicKey`
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SecretKey(pub Scalar);
+
+/// The blinding factor of a Pedersen commitment
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlindingFactor(pub Scalar);
+
+/// A Pedersen commitment to an amount, `C = vB + bB'`
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Commitment(
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))] pub G1Affine,
+);
+
+// Implements the conversions and encoding shared by the point newtypes
+macro_rules! point_newtype {
+    ($name:ident) => {
+        impl $name {
+            pub fn to_bytes(&self) -> [u8; 48] {
+                self.0.to_compressed()
+            }
+
+            pub fn from_bytes(bytes: &[u8; 48]) -> Result<Self> {
+                Option::from(G1Affine::from_compressed(bytes))
+                    .map(Self)
+                    .ok_or(Error::InvalidPointEncoding)
+            }
+        }
+
+        impl From<G1Affine> for $name {
+            fn from(point: G1Affine) -> Self {
+                Self(point)
+            }
+        }
+
+        impl From<G1Projective> for $name {
+            fn from(point: G1Projective) -> Self {
+                Self(point.to_affine())
+            }
+        }
+
+        impl From<$name> for G1Affine {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl From<$name> for G1Projective {
+            fn from(value: $name) -> Self {
+                value.0.into()
+            }
+        }
+
+        impl PartialEq<G1Affine> for $name {
+            fn eq(&self, other: &G1Affine) -> bool {
+                self.0 == *other
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&codec::to_hex(&self.to_bytes()))
+            }
+        }
+    };
+}
+
+// Secrets are displayed redacted, so they don't end up in logs
+macro_rules! scalar_newtype {
+    ($name:ident) => {
+        impl From<Scalar> for $name {
+            fn from(scalar: Scalar) -> Self {
+                Self(scalar)
+            }
+        }
+
+        impl From<$name> for Scalar {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}(..)", stringify!($name))
+            }
+        }
+    };
+}
+
+point_newtype!(PublicKey);
+point_newtype!(Commitment);
+scalar_newtype!(SecretKey);
+scalar_newtype!(BlindingFactor);
+
+impl SecretKey {
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey::from(crate::public_key(self.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{group::ff::Field, rand::rngs::OsRng};
+
+    use super::*;
+
+    #[test]
+    fn test_newtype_conversions_and_display() {
+        let secret_key = SecretKey::from(Scalar::random(OsRng));
+        let public_key = secret_key.public_key();
+        assert_eq!(public_key, crate::public_key(secret_key).to_affine());
+        assert_eq!(
+            PublicKey::from_bytes(&public_key.to_bytes()),
+            Ok(public_key)
+        );
+        assert_eq!(
+            public_key.to_string(),
+            codec::to_hex(&public_key.0.to_compressed())
+        );
+        assert_eq!(secret_key.to_string(), "SecretKey(..)");
+
+        let commitment = Commitment::from(G1Projective::from(public_key));
+        assert_eq!(G1Affine::from(commitment), public_key.0);
+        assert_eq!(
+            Commitment::from_bytes(&[0; 48]),
+            Err(Error::InvalidPointEncoding)
+        );
+    }
+}
@@ -0,0 +1,286 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::{ff::PrimeField, Group},
+    BulletproofGens, PedersenGens,
+};
+
+use std::sync::Arc;
+
+use crate::{
+    ringct::{RangeProofVerification, RingCtTransaction, RANGE_PROOF_BITS},
+    tx_policy::{DefaultPolicy, TxPolicy},
+    CommitmentLookup, CpuBackend, Result, RingCtPolicy, VerificationBackend,
+};
+
+const WINDOW_BITS: usize = 4;
+const WINDOW_SIZE: usize = 1 << WINDOW_BITS;
+const NUM_WINDOWS: usize = (Scalar::NUM_BITS as usize).div_ceil(WINDOW_BITS);
+
+/// The multiples of a fixed base in 4 bit windows, so that multiplying the base
+/// takes one addition per window and no doublings.
+///
+/// Lookups depend on the scalar, so this must only be used with public scalars.
+#[derive(Clone)]
+struct FixedBaseTable {
+    // windows[i][j] = j * 16^i * base
+    windows: Vec<[G1Projective; WINDOW_SIZE]>,
+}
+
+impl FixedBaseTable {
+    fn new(base: G1Projective) -> Self {
+        let mut windows = Vec::with_capacity(NUM_WINDOWS);
+        let mut window_base = base;
+        for _ in 0..NUM_WINDOWS {
+            let mut multiples = [G1Projective::identity(); WINDOW_SIZE];
+            let mut multiple = G1Projective::identity();
+            for m in multiples.iter_mut().skip(1) {
+                multiple += window_base;
+                *m = multiple;
+            }
+            window_base = multiple + window_base;
+            windows.push(multiples);
+        }
+        Self { windows }
+    }
+
+    fn mul(&self, scalar: &Scalar) -> G1Projective {
+        let bytes = scalar.to_bytes_le();
+        self.windows
+            .iter()
+            .enumerate()
+            .fold(G1Projective::identity(), |acc, (i, multiples)| {
+                let digit = (bytes[i / 2] >> ((i % 2) * WINDOW_BITS)) & (WINDOW_SIZE as u8 - 1);
+                acc + multiples[digit as usize]
+            })
+    }
+}
+
+/// Verifies transactions with generators and tables that are set up once,
+/// for nodes verifying many transactions over their lifetime.
+#[derive(Clone)]
+pub struct RingCtVerifier {
+    policy: RingCtPolicy,
+    bp_gens: BulletproofGens,
+    pc_gens: PedersenGens,
+    // tables for the value and blinding bases of the Pedersen commitments
+    value_table: FixedBaseTable,
+    blinding_table: FixedBaseTable,
+    backend: Arc<dyn VerificationBackend>,
+    tx_policy: Arc<dyn TxPolicy>,
+}
+
+impl Default for RingCtVerifier {
+    fn default() -> Self {
+        Self::new(RingCtPolicy::default())
+    }
+}
+
+impl RingCtVerifier {
+    /// Sets up a verifier for transactions within the limits of `policy`
+    pub fn new(policy: RingCtPolicy) -> Self {
+        let pc_gens = crate::pedersen_gens();
+        Self {
+            policy,
+            // enough parties for an aggregated proof over the most outputs allowed
+            bp_gens: BulletproofGens::new(
+                RANGE_PROOF_BITS,
+                policy.max_outputs.max(1).next_power_of_two(),
+            ),
+            value_table: FixedBaseTable::new(pc_gens.B),
+            blinding_table: FixedBaseTable::new(pc_gens.B_blinding),
+            pc_gens,
+            backend: Arc::new(CpuBackend),
+            tx_policy: Arc::new(DefaultPolicy),
+        }
+    }
+
+    /// Does the multi-exponentiations of ring signature and batched range
+    /// proof verification with `backend` rather than on the CPU
+    pub fn with_backend(mut self, backend: impl VerificationBackend + 'static) -> Self {
+        self.backend = Arc::new(backend);
+        self
+    }
+
+    /// Checks verified transactions against the consensus rules of
+    /// `tx_policy` rather than `DefaultPolicy`'s
+    pub fn with_tx_policy(mut self, tx_policy: impl TxPolicy + 'static) -> Self {
+        self.tx_policy = Arc::new(tx_policy);
+        self
+    }
+
+    pub fn policy(&self) -> &RingCtPolicy {
+        &self.policy
+    }
+
+    /// Computes the Pedersen commitment `value * B + blinding * B_blinding` with
+    /// the precomputed tables. Not constant time, so only for public values.
+    pub fn commit(&self, value: Scalar, blinding: Scalar) -> G1Projective {
+        self.value_table.mul(&value) + self.blinding_table.mul(&blinding)
+    }
+
+    /// Verifies `tx`, resolving the commitment of each ring member through `lookup`
+    pub fn verify(&self, tx: &RingCtTransaction, lookup: &impl CommitmentLookup) -> Result<()> {
+        let public_commitments_per_ring = tx.lookup_public_commitments(lookup)?;
+        self.verify_with_commitments(tx, &public_commitments_per_ring)
+    }
+
+    /// Verifies `tx` against the commitments of each ring's members, as with
+    /// `RingCtTransaction::verify_with_policy()`
+    pub fn verify_with_commitments(
+        &self,
+        tx: &RingCtTransaction,
+        public_commitments_per_ring: &[Vec<G1Affine>],
+    ) -> Result<()> {
+        tx.verify_with_gens(
+            public_commitments_per_ring,
+            &self.policy,
+            &self.bp_gens,
+            &self.pc_gens,
+            self.value_table.mul(&Scalar::from(tx.fee.value())),
+            self.backend.as_ref(),
+            self.tx_policy.as_ref(),
+            RangeProofVerification::Batched,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use bls_bulletproofs::{
+        group::{ff::Field, Curve},
+        rand::rngs::OsRng,
+    };
+
+    use super::*;
+    use crate::{
+        ringct::Amount, DecoyInput, Error, MlsagMaterial, Output, RingCtMaterial, TrueInput,
+    };
+
+    #[test]
+    fn test_table_multiplication_matches_scalar_multiplication() {
+        let base = G1Projective::random(OsRng);
+        let table = FixedBaseTable::new(base);
+        for scalar in [
+            Scalar::zero(),
+            Scalar::one(),
+            -Scalar::one(),
+            Scalar::from(u64::MAX),
+            Scalar::random(OsRng),
+        ] {
+            assert_eq!(table.mul(&scalar), base * scalar);
+        }
+
+        let verifier = RingCtVerifier::default();
+        let (value, blinding) = (Scalar::from(42), Scalar::random(OsRng));
+        assert_eq!(
+            verifier.commit(value, blinding),
+            crate::pedersen_gens().commit(value, blinding)
+        );
+    }
+
+    fn signed_tx() -> (RingCtTransaction, BTreeMap<[u8; 48], G1Affine>) {
+        let mut ring = BTreeMap::new();
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            crate::RevealedCommitment::from_value(3, OsRng),
+        );
+        let pc_gens = crate::pedersen_gens();
+        ring.insert(
+            true_input.public_key().to_affine().to_compressed(),
+            true_input.revealed_commitment.commit(&pc_gens).to_affine(),
+        );
+        let decoys: Vec<_> = (0..4)
+            .map(|_| {
+                let decoy = DecoyInput {
+                    public_key: G1Projective::random(OsRng).to_affine(),
+                    commitment: G1Projective::random(OsRng).to_affine(),
+                };
+                ring.insert(decoy.public_key.to_compressed(), decoy.commitment);
+                decoy
+            })
+            .collect();
+
+        let material = RingCtMaterial {
+            inputs: vec![MlsagMaterial::new(true_input, decoys, &mut OsRng).unwrap()],
+            outputs: vec![Output::new(G1Projective::random(OsRng).to_affine(), 2)],
+            fee: Amount::new(1),
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+        let (tx, _) = material
+            .sign(&mut OsRng)
+            .expect("Failed to sign transaction");
+        (tx, ring)
+    }
+
+    #[test]
+    fn test_verifier_agrees_with_transaction_verify() {
+        let (tx, ring) = signed_tx();
+        let lookup = |pk: &G1Affine| ring.get(&pk.to_compressed()).copied();
+
+        let verifier = RingCtVerifier::default();
+        assert_eq!(tx.verify_with_lookup(&lookup), Ok(()));
+        assert_eq!(verifier.verify(&tx, &lookup), Ok(()));
+
+        let mut overpaying = tx.clone();
+        overpaying.fee = Amount::new(2);
+        assert_eq!(
+            verifier.verify(&overpaying, &lookup).err(),
+            overpaying.verify_with_lookup(&lookup).err()
+        );
+
+        let strict = RingCtVerifier::new(RingCtPolicy {
+            max_decoys: 3,
+            ..Default::default()
+        });
+        assert_eq!(
+            strict.verify(&tx, &lookup),
+            Err(Error::TooManyDecoys {
+                input_index: 0,
+                max: 3,
+                actual: 4
+            })
+        );
+    }
+
+    #[test]
+    fn test_verifier_uses_its_backend() {
+        struct CountingBackend(Arc<AtomicUsize>);
+
+        impl VerificationBackend for CountingBackend {
+            fn multi_exp(&self, points: &[G1Projective], scalars: &[Scalar]) -> G1Projective {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                CpuBackend.multi_exp(points, scalars)
+            }
+        }
+
+        let (tx, ring) = signed_tx();
+        let lookup = |pk: &G1Affine| ring.get(&pk.to_compressed()).copied();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let verifier = RingCtVerifier::default().with_backend(CountingBackend(calls.clone()));
+
+        assert_eq!(verifier.verify(&tx, &lookup), Ok(()));
+        // three multi-exponentiations for each of the five ring members, and
+        // one for the batched range proofs
+        assert_eq!(calls.load(Ordering::SeqCst), 16);
+
+        let mut tampered = tx.clone();
+        tampered.fee = Amount::new(2);
+        assert!(matches!(
+            verifier.verify(&tampered, &lookup),
+            Err(Error::MlsagInvalid { input_index: 0, .. })
+        ));
+    }
+}
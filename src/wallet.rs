@@ -0,0 +1,393 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! The scanning loop of a wallet: finding the outputs paid to its stealth
+//! keys among a ledger's transactions, with what's needed to spend them.
+//!
+//! A `WatchOnlyWallet` only holds a `ViewKey`, so a server tracking incoming
+//! payments and balances never holds anything able to spend them: it has no
+//! way to derive one-time secret keys, key images or signatures. To see
+//! which of its outputs are spent, it imports the `SignedKeyImage`s the
+//! offline `Wallet` exports for them.
+
+use bls_bulletproofs::rand::{CryptoRng, RngCore};
+use std::collections::BTreeMap;
+
+use crate::{
+    codec,
+    ringct::{Amount, OutputProof, RingCtTransaction},
+    AmountSecrets, Error, KeyImage, KeyImageStore, OwnedOutput, Result, SignedKeyImage,
+    StealthAddress, StealthKeys, TrueInput, ViewKey,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A wallet holding a recipient's stealth keys
+#[derive(Debug, Clone)]
+pub struct Wallet {
+    keys: StealthKeys,
+}
+
+impl Wallet {
+    pub fn new(keys: StealthKeys) -> Self {
+        Self { keys }
+    }
+
+    pub fn keys(&self) -> &StealthKeys {
+        &self.keys
+    }
+
+    /// The address to publish to be paid
+    pub fn address(&self) -> StealthAddress {
+        self.keys.address()
+    }
+
+    pub fn view_key(&self) -> ViewKey {
+        self.keys.view_key()
+    }
+
+    /// A wallet with only the view key, to hand to a watch-only server
+    pub fn watch_only(&self) -> WatchOnlyWallet {
+        WatchOnlyWallet::new(self.view_key())
+    }
+
+    /// The key images of `outputs`, proven, for a watch-only wallet to import
+    pub fn export_key_images(
+        &self,
+        outputs: &[ScannedOutput],
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Vec<SignedKeyImage> {
+        outputs
+            .iter()
+            .map(|o| SignedKeyImage::sign(o.owned.secret_key, &mut *rng))
+            .collect()
+    }
+
+    /// Finds the outputs of `txs` paid to the wallet. Each output is checked
+    /// against the view key first, then its one-time secret key is derived
+    /// and its amount decrypted.
+    pub fn scan<'a>(
+        &self,
+        txs: impl IntoIterator<Item = &'a RingCtTransaction>,
+    ) -> Vec<ScannedOutput> {
+        txs.into_iter()
+            .flat_map(|tx| {
+                let tx_hash = tx.hash();
+                self.keys
+                    .scan(tx)
+                    .into_iter()
+                    .map(move |owned| ScannedOutput {
+                        tx_hash,
+                        output: tx.outputs[owned.output_index].clone(),
+                        owned,
+                    })
+            })
+            .collect()
+    }
+}
+
+/// An output found by `Wallet::scan()`
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct ScannedOutput {
+    /// The hash of the transaction which created the output
+    pub tx_hash: [u8; 32],
+    pub output: OutputProof,
+    pub owned: OwnedOutput,
+}
+
+impl ScannedOutput {
+    pub fn amount(&self) -> Amount {
+        self.owned.amount_secrets.value()
+    }
+
+    /// The key image spending the output will reveal
+    pub fn key_image(&self) -> KeyImage {
+        crate::key_image(self.owned.secret_key).into()
+    }
+
+    /// The true input spending the output, to build `MlsagMaterial` with
+    pub fn true_input(&self) -> TrueInput {
+        TrueInput::new(
+            self.owned.secret_key,
+            self.owned.amount_secrets.revealed_commitment(),
+        )
+    }
+}
+
+/// A wallet holding only a view key, which finds incoming outputs and
+/// tracks their balance but can't spend them
+#[derive(Debug, Clone)]
+pub struct WatchOnlyWallet {
+    view_key: ViewKey,
+    outputs: BTreeMap<([u8; 32], usize), WatchedOutput>,
+    // the imported key images, by output public key
+    key_images: BTreeMap<[u8; 48], KeyImage>,
+}
+
+impl WatchOnlyWallet {
+    pub fn new(view_key: ViewKey) -> Self {
+        Self {
+            view_key,
+            outputs: BTreeMap::new(),
+            key_images: BTreeMap::new(),
+        }
+    }
+
+    pub fn view_key(&self) -> &ViewKey {
+        &self.view_key
+    }
+
+    pub fn address(&self) -> StealthAddress {
+        self.view_key.address()
+    }
+
+    /// Finds the outputs of `txs` paid to the wallet and records them,
+    /// returning those it hadn't recorded yet. Only outputs whose amounts
+    /// were encrypted to the view key are found.
+    pub fn scan<'a>(
+        &mut self,
+        txs: impl IntoIterator<Item = &'a RingCtTransaction>,
+    ) -> Vec<WatchedOutput> {
+        let mut found = vec![];
+        for tx in txs {
+            let tx_hash = tx.hash();
+            for viewed in self.view_key.scan(tx) {
+                let key = (tx_hash, viewed.output_index);
+                if self.outputs.contains_key(&key) {
+                    continue;
+                }
+                let watched = WatchedOutput {
+                    tx_hash,
+                    output_index: viewed.output_index,
+                    output: tx.outputs[viewed.output_index].clone(),
+                    amount_secrets: viewed.amount_secrets,
+                };
+                self.outputs.insert(key, watched.clone());
+                found.push(watched);
+            }
+        }
+        found
+    }
+
+    /// The outputs found so far, ordered by transaction hash and index
+    pub fn outputs(&self) -> impl Iterator<Item = &WatchedOutput> {
+        self.outputs.values()
+    }
+
+    /// The total amount of the outputs found so far
+    pub fn balance(&self) -> Result<Amount> {
+        Amount::checked_sum(self.outputs().map(WatchedOutput::amount))
+    }
+
+    /// Records the key images exported by the offline wallet, after checking
+    /// each proof and that it's for an output found so far. Nothing is
+    /// recorded if any of them fails.
+    pub fn import_key_images(&mut self, signed_key_images: &[SignedKeyImage]) -> Result<()> {
+        for signed in signed_key_images {
+            signed.verify()?;
+            if !self
+                .outputs()
+                .any(|o| *o.output.public_key() == signed.public_key)
+            {
+                return Err(Error::UnknownOutput {
+                    public_key: codec::to_hex(&signed.public_key.to_compressed()),
+                });
+            }
+        }
+        for signed in signed_key_images {
+            self.key_images
+                .insert(signed.public_key.to_compressed(), signed.key_image);
+        }
+        Ok(())
+    }
+
+    /// The imported key image of `output`, if any
+    pub fn key_image(&self, output: &WatchedOutput) -> Option<KeyImage> {
+        self.key_images
+            .get(&output.output.public_key().to_compressed())
+            .copied()
+    }
+
+    /// The outputs found so far which aren't known to be spent, given the
+    /// ledger's spent key images. Outputs without an imported key image are
+    /// counted as unspent.
+    pub fn unspent<'a>(
+        &'a self,
+        spent: &'a impl KeyImageStore,
+    ) -> impl Iterator<Item = &'a WatchedOutput> {
+        self.outputs()
+            .filter(move |o| !matches!(self.key_image(o), Some(k) if spent.contains(&k)))
+    }
+
+    /// The total amount of the `unspent()` outputs
+    pub fn unspent_balance(&self, spent: &impl KeyImageStore) -> Result<Amount> {
+        Amount::checked_sum(self.unspent(spent).map(WatchedOutput::amount))
+    }
+}
+
+/// An output found by `WatchOnlyWallet::scan()`, without its secret key
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchedOutput {
+    /// The hash of the transaction which created the output
+    pub tx_hash: [u8; 32],
+    pub output_index: usize,
+    pub output: OutputProof,
+    pub amount_secrets: AmountSecrets,
+}
+
+impl WatchedOutput {
+    pub fn amount(&self) -> Amount {
+        self.amount_secrets.value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::{
+        blstrs::{G1Projective, Scalar},
+        group::{ff::Field, Group},
+        rand::rngs::OsRng,
+    };
+
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::{mock::random_decoys, MlsagMaterial, Output, RevealedCommitment, RingCtMaterial};
+
+    // A transaction spending an input of 10 to `outputs`
+    fn pay(outputs: Vec<Output>) -> RingCtTransaction {
+        let true_input = TrueInput::new(
+            Scalar::random(OsRng),
+            RevealedCommitment::from_value(10, OsRng),
+        );
+        let decoy_inputs = random_decoys(2);
+        let material = RingCtMaterial {
+            inputs: vec![MlsagMaterial::new(true_input, decoy_inputs, &mut OsRng).unwrap()],
+            outputs,
+            fee: Amount::ZERO,
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+        let (tx, _revealed_output_commitments) = material
+            .sign(&mut OsRng)
+            .expect("Failed to sign transaction");
+        tx
+    }
+
+    #[test]
+    fn test_wallet_scan_finds_spendable_outputs() {
+        let wallet = Wallet::new(StealthKeys::random(&mut OsRng));
+        let other = StealthKeys::random(&mut OsRng);
+
+        let tx_a = pay(vec![
+            Output::to_stealth_address(&other.address(), 4, &mut OsRng),
+            Output::to_stealth_address(&wallet.address(), 6, &mut OsRng),
+        ]);
+        let tx_b = pay(vec![Output::to_stealth_address(
+            &wallet.address(),
+            10,
+            &mut OsRng,
+        )]);
+        let tx_c = pay(vec![Output::new(G1Projective::random(OsRng), 10)]);
+
+        let found = wallet.scan([&tx_a, &tx_b, &tx_c]);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].tx_hash, tx_a.hash());
+        assert_eq!(found[0].owned.output_index, 1);
+        assert_eq!(found[0].amount(), Amount::new(6));
+        assert_eq!(found[1].tx_hash, tx_b.hash());
+        assert_eq!(found[1].amount(), Amount::new(10));
+
+        for scanned in found.iter() {
+            let true_input = TrueInput::from_output(
+                &scanned.output,
+                scanned.owned.secret_key,
+                scanned.owned.amount_secrets,
+            )
+            .expect("Scanned output should be spendable");
+            assert_eq!(KeyImage::from(true_input.key_image()), scanned.key_image());
+            assert_eq!(
+                scanned.true_input().revealed_commitment,
+                true_input.revealed_commitment
+            );
+        }
+    }
+
+    #[test]
+    fn test_watch_only_wallet_tracks_balance() {
+        let wallet = Wallet::new(StealthKeys::random(&mut OsRng));
+        let mut watch_only = wallet.watch_only();
+        assert_eq!(watch_only.address(), wallet.address());
+
+        let txs = [
+            pay(vec![
+                Output::to_stealth_address(
+                    &StealthKeys::random(&mut OsRng).address(),
+                    4,
+                    &mut OsRng,
+                ),
+                Output::to_stealth_address(&wallet.address(), 6, &mut OsRng),
+            ]),
+            pay(vec![Output::to_stealth_address(
+                &wallet.address(),
+                10,
+                &mut OsRng,
+            )]),
+        ];
+
+        let found = watch_only.scan(&txs);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].output_index, 1);
+        assert_eq!(found[0].output, txs[0].outputs[1]);
+        assert_eq!(watch_only.balance(), Ok(Amount::new(16)));
+
+        // rescanning doesn't count outputs twice
+        assert!(watch_only.scan(&txs).is_empty());
+        assert_eq!(watch_only.outputs().count(), 2);
+        assert_eq!(watch_only.balance(), Ok(Amount::new(16)));
+
+        let spendable = wallet.scan(&txs);
+        assert_eq!(spendable.len(), 2);
+        assert_eq!(spendable[1].owned.amount_secrets, found[1].amount_secrets);
+    }
+
+    #[test]
+    fn test_imported_key_images_mark_outputs_spent() {
+        let wallet = Wallet::new(StealthKeys::random(&mut OsRng));
+        let mut watch_only = wallet.watch_only();
+        let txs = [
+            pay(vec![Output::to_stealth_address(
+                &wallet.address(),
+                10,
+                &mut OsRng,
+            )]),
+            pay(vec![Output::to_stealth_address(
+                &wallet.address(),
+                10,
+                &mut OsRng,
+            )]),
+        ];
+        watch_only.scan(&txs);
+
+        let spendable = wallet.scan(&txs);
+        let signed_key_images = wallet.export_key_images(&spendable[..1], &mut OsRng);
+        assert_eq!(watch_only.import_key_images(&signed_key_images), Ok(()));
+
+        let spent = BTreeSet::from([spendable[0].key_image()]);
+        assert_eq!(watch_only.unspent_balance(&spent), Ok(Amount::new(10)));
+        assert_eq!(watch_only.balance(), Ok(Amount::new(20)));
+
+        let stranger = SignedKeyImage::sign(Scalar::random(OsRng), &mut OsRng);
+        assert!(matches!(
+            watch_only.import_key_images(&[stranger]),
+            Err(Error::UnknownOutput { .. })
+        ));
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! wasm-bindgen bindings for building and verifying transactions in the
+//! browser. Points and scalars are passed as their 48 byte compressed and
+//! 32 byte little-endian encodings, transactions as `RingCtTransaction::to_bytes()`.
+
+use bls_bulletproofs::blstrs::{G1Affine, Scalar};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    codec::Reader, ringct::RingCtTransaction, DecoyInput, Error, Output, Result,
+    RevealedCommitment, RingCtTransactionBuilder, TrueInput,
+};
+
+impl From<Error> for JsValue {
+    fn from(error: Error) -> Self {
+        JsValue::from_str(&error.to_string())
+    }
+}
+
+#[wasm_bindgen(js_name = Output)]
+pub struct WasmOutput(Output);
+
+#[wasm_bindgen(js_class = Output)]
+impl WasmOutput {
+    #[wasm_bindgen(constructor)]
+    pub fn new(public_key: &[u8], amount: u64) -> std::result::Result<WasmOutput, JsValue> {
+        Ok(Self(Output::new(decode_point(public_key)?, amount)))
+    }
+
+    #[wasm_bindgen(getter, js_name = publicKey)]
+    pub fn public_key(&self) -> Vec<u8> {
+        self.0.public_key.to_compressed().to_vec()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn amount(&self) -> u64 {
+        self.0.amount.value()
+    }
+}
+
+/// Collects the inputs and outputs of a transaction, then signs it
+#[wasm_bindgen(js_name = RingCtMaterial)]
+#[derive(Default)]
+pub struct WasmRingCtMaterial(RingCtTransactionBuilder);
+
+#[wasm_bindgen(js_class = RingCtMaterial)]
+impl WasmRingCtMaterial {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds an input to spend, decoys added afterwards go into its ring
+    #[wasm_bindgen(js_name = addTrueInput)]
+    pub fn add_true_input(
+        &mut self,
+        secret_key: &[u8],
+        value: u64,
+        blinding: &[u8],
+    ) -> std::result::Result<(), JsValue> {
+        let true_input = TrueInput::new(
+            decode_scalar(secret_key)?,
+            RevealedCommitment {
+                value,
+                blinding: decode_scalar(blinding)?,
+            },
+        );
+        self.update(|builder| builder.add_true_input(true_input));
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = addDecoy)]
+    pub fn add_decoy(
+        &mut self,
+        public_key: &[u8],
+        commitment: &[u8],
+    ) -> std::result::Result<(), JsValue> {
+        let decoy = DecoyInput {
+            public_key: decode_point(public_key)?,
+            commitment: decode_point(commitment)?,
+        };
+        self.update(|builder| builder.add_decoys([decoy]));
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = addOutput)]
+    pub fn add_output(&mut self, output: &WasmOutput) {
+        let output = output.0.clone();
+        self.update(|builder| builder.add_output(output));
+    }
+
+    #[wasm_bindgen(js_name = setFee)]
+    pub fn set_fee(&mut self, fee: u64) {
+        self.update(|builder| builder.set_fee(fee));
+    }
+
+    #[wasm_bindgen(js_name = setExtra)]
+    pub fn set_extra(&mut self, extra: &[u8]) {
+        self.update(|builder| builder.set_extra(extra));
+    }
+
+    /// Signs the transaction and returns its bytes
+    pub fn sign(&self) -> std::result::Result<Vec<u8>, JsValue> {
+        let (tx, _revealed_output_commitments) = self.0.clone().sign()?;
+        Ok(tx.to_bytes())
+    }
+
+    fn update(&mut self, f: impl FnOnce(RingCtTransactionBuilder) -> RingCtTransactionBuilder) {
+        self.0 = f(std::mem::take(&mut self.0));
+    }
+}
+
+/// Verifies a transaction given the concatenated commitments of every ring
+/// member, in the order of `RingCtTransaction::public_keys_per_ring()`.
+#[wasm_bindgen(js_name = verifyTransaction)]
+pub fn verify_transaction(
+    tx_bytes: &[u8],
+    public_commitments: &[u8],
+) -> std::result::Result<(), JsValue> {
+    let tx = RingCtTransaction::from_bytes(tx_bytes)?;
+
+    let mut reader = Reader::new(public_commitments);
+    let public_commitments_per_ring = tx
+        .public_keys_per_ring()
+        .iter()
+        .map(|ring| ring.iter().map(|_| reader.read_point()).collect())
+        .collect::<Result<Vec<Vec<G1Affine>>>>()?;
+    reader.finish()?;
+
+    tx.verify(&public_commitments_per_ring)?;
+    Ok(())
+}
+
+fn decode_point(bytes: &[u8]) -> Result<G1Affine> {
+    let mut reader = Reader::new(bytes);
+    let point = reader.read_point()?;
+    reader.finish()?;
+    Ok(point)
+}
+
+fn decode_scalar(bytes: &[u8]) -> Result<Scalar> {
+    let mut reader = Reader::new(bytes);
+    let scalar = reader.read_scalar()?;
+    reader.finish()?;
+    Ok(scalar)
+}
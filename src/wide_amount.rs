@@ -0,0 +1,240 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! 128-bit amounts, for tokens whose precision doesn't fit a 64-bit Amount.
+//!
+//! Range proofs are limited to 64 bits, so a 128-bit amount `v = lo + 2^64 hi`
+//! is committed to as two limb commitments `C_lo = lo B + b_lo B'` and
+//! `C_hi = hi B + b_hi B'`, proven in range with one aggregated range proof.
+//! The limbs are linked to the amount's commitment by `C = C_lo + 2^64 C_hi`,
+//! so balance checks over the amount commitments work as they do for 64-bit
+//! amounts.
+//!
+//! The transaction formats still carry 64-bit amounts, this module provides
+//! the commitments, proofs and balance check for a format with wide amounts.
+
+use bls_bulletproofs::{
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::{ff::Field, Curve, Group},
+    merlin::Transcript,
+    rand::{CryptoRng, RngCore},
+    PedersenGens, RangeProof,
+};
+
+use crate::{
+    codec::{self, Reader},
+    ringct::{check_sum_cannot_wrap, MERLIN_TRANSCRIPT_LABEL, RANGE_PROOF_BITS},
+    Error, Result, RingCtMaterial,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A 128-bit amount
+pub type WideAmount = u128;
+
+const LIMBS: usize = 2;
+
+/// The value and blinding factor of a commitment to a 128-bit amount
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WideRevealedCommitment {
+    pub value: WideAmount,
+    pub blinding: Scalar,
+}
+
+impl WideRevealedCommitment {
+    /// Construct a revealed commitment from a value, generating a blinding randomly
+    pub fn from_value(value: WideAmount, mut rng: impl RngCore) -> Self {
+        Self {
+            value,
+            blinding: Scalar::random(&mut rng),
+        }
+    }
+
+    pub fn commit(&self, pc_gens: &PedersenGens) -> G1Projective {
+        pc_gens.commit(wide_amount_scalar(self.value), self.blinding)
+    }
+}
+
+/// Proves that a commitment is to a 128-bit amount
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct WideRangeProof {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub low_commitment: G1Affine,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub high_commitment: G1Affine,
+    /// An aggregated range proof over both limb commitments
+    pub range_proof: RangeProof,
+}
+
+impl WideRangeProof {
+    /// Proves that the commitment of `revealed_commitment` is to a 128-bit amount
+    pub fn prove(
+        revealed_commitment: &WideRevealedCommitment,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Self> {
+        let (low, high) = limbs(revealed_commitment.value);
+        // split the blinding so that the limb commitments combine to the commitment
+        let high_blinding = Scalar::random(&mut *rng);
+        let low_blinding = revealed_commitment.blinding - high_blinding * limb_base();
+
+        let (range_proof, commitments) = RangeProof::prove_multiple_with_rng(
+            &RingCtMaterial::aggregated_bp_gens(LIMBS),
+            RingCtMaterial::pc_gens(),
+            &mut wide_range_proof_transcript(),
+            &[low, high],
+            &[low_blinding, high_blinding],
+            RANGE_PROOF_BITS,
+            &mut *rng,
+        )?;
+
+        Ok(Self {
+            low_commitment: commitments[0],
+            high_commitment: commitments[1],
+            range_proof,
+        })
+    }
+
+    /// The commitment the limb commitments combine to
+    pub fn commitment(&self) -> G1Affine {
+        (G1Projective::from(self.low_commitment) + self.high_commitment * limb_base()).to_affine()
+    }
+
+    /// Verifies that `commitment` is to a 128-bit amount
+    pub fn verify(&self, commitment: &G1Affine) -> Result<()> {
+        if self.commitment() != *commitment {
+            return Err(Error::InvalidWideRangeProof);
+        }
+        self.range_proof
+            .verify_multiple(
+                &RingCtMaterial::aggregated_bp_gens(LIMBS),
+                RingCtMaterial::pc_gens(),
+                &mut wide_range_proof_transcript(),
+                &[self.low_commitment, self.high_commitment],
+                RANGE_PROOF_BITS,
+            )
+            .map_err(|_| Error::InvalidWideRangeProof)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Default::default();
+        v.extend(self.low_commitment.to_compressed());
+        v.extend(self.high_commitment.to_compressed());
+        let range_proof = self.range_proof.to_bytes();
+        codec::write_len(&mut v, range_proof.len());
+        v.extend(range_proof);
+        v
+    }
+
+    /// Parses the encoding produced by `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let low_commitment = reader.read_point()?;
+        let high_commitment = reader.read_point()?;
+        let range_proof_len = reader.read_len()?;
+        let range_proof = RangeProof::from_bytes(&reader.take(range_proof_len)?)?;
+        reader.finish()?;
+
+        Ok(Self {
+            low_commitment,
+            high_commitment,
+            range_proof,
+        })
+    }
+}
+
+/// Checks that the input commitments sum to the output commitments plus a
+/// transparent 128-bit fee, committed to with a zero blinding factor
+pub fn check_wide_balance(
+    input_commitments: &[G1Affine],
+    output_commitments: &[G1Affine],
+    fee: WideAmount,
+) -> Result<()> {
+    let total = |commitments: &[G1Affine]| {
+        commitments
+            .iter()
+            .fold(G1Projective::identity(), |sum, c| sum + *c)
+    };
+    let fee_commitment = RingCtMaterial::pc_gens().B * wide_amount_scalar(fee);
+    let bits = LIMBS * RANGE_PROOF_BITS;
+    check_sum_cannot_wrap(input_commitments.len(), bits)?;
+    check_sum_cannot_wrap(output_commitments.len() + 1, bits)?; // + 1 for the fee
+
+    if total(input_commitments) != total(output_commitments) + fee_commitment {
+        return Err(Error::InputPseudoCommitmentsDoNotSumToOutputCommitments);
+    }
+    Ok(())
+}
+
+/// The low and high 64-bit limbs of `value`
+fn limbs(value: WideAmount) -> (u64, u64) {
+    (value as u64, (value >> 64) as u64)
+}
+
+/// 2^64, the weight of the high limb
+fn limb_base() -> Scalar {
+    Scalar::from(u64::MAX) + Scalar::one()
+}
+
+fn wide_amount_scalar(value: WideAmount) -> Scalar {
+    let (low, high) = limbs(value);
+    Scalar::from(low) + Scalar::from(high) * limb_base()
+}
+
+fn wide_range_proof_transcript() -> Transcript {
+    let mut transcript = Transcript::new(MERLIN_TRANSCRIPT_LABEL);
+    transcript.append_message(b"dom-sep", b"wide_amount");
+    transcript
+}
+
+#[cfg(test)]
+mod tests {
+    use bls_bulletproofs::rand::rngs::OsRng;
+
+    use super::*;
+
+    #[test]
+    fn test_wide_range_proof() {
+        let pc_gens = RingCtMaterial::pc_gens();
+        for value in [0, 1, u64::MAX as u128 + 1, u128::MAX] {
+            let revealed = WideRevealedCommitment::from_value(value, OsRng);
+            let commitment = revealed.commit(pc_gens).to_affine();
+
+            let proof = WideRangeProof::prove(&revealed, &mut OsRng).expect("Failed to prove");
+            assert_eq!(proof.commitment(), commitment);
+            assert_eq!(proof.verify(&commitment), Ok(()));
+
+            let decoded = WideRangeProof::from_bytes(&proof.to_bytes()).unwrap();
+            assert_eq!(decoded.verify(&commitment), Ok(()));
+
+            let other = WideRevealedCommitment::from_value(value, OsRng).commit(pc_gens);
+            assert_eq!(
+                proof.verify(&other.to_affine()),
+                Err(Error::InvalidWideRangeProof)
+            );
+        }
+    }
+
+    #[test]
+    fn test_wide_balance() {
+        let pc_gens = RingCtMaterial::pc_gens();
+        let input = WideRevealedCommitment::from_value(u128::MAX, OsRng);
+        let output = WideRevealedCommitment {
+            value: u128::MAX - 7,
+            blinding: input.blinding,
+        };
+        let inputs = [input.commit(pc_gens).to_affine()];
+        let outputs = [output.commit(pc_gens).to_affine()];
+
+        assert_eq!(check_wide_balance(&inputs, &outputs, 7), Ok(()));
+        assert_eq!(
+            check_wide_balance(&inputs, &outputs, 6),
+            Err(Error::InputPseudoCommitmentsDoNotSumToOutputCommitments)
+        );
+    }
+}
@@ -0,0 +1,108 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Golden encodings of values whose bytes are known, pinning the wire format
+//! described in `docs/wire-format.md`.
+
+mod common;
+
+use bls_ringct::{
+    blstrs::{G1Affine, Scalar},
+    group::prime::PrimeCurveAffine,
+    ringct::{OutputProof, RingCtTransaction, CURRENT_TX_VERSION, TX_VERSION_1},
+    EncryptedAmount, Error, KeyImage, OwnershipProof, RevealedCommitment,
+};
+use common::vectors::{from_hex, to_hex, Vector};
+
+// The compressed G1 generator
+const GENERATOR: &str = "97f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb";
+
+fn scalar_hex(n: u8) -> String {
+    format!("{:02x}{}", n, "00".repeat(31))
+}
+
+#[test]
+fn test_golden_encodings() {
+    let revealed_commitment = RevealedCommitment {
+        value: 5,
+        blinding: Scalar::from(7u64),
+    };
+    let golden = format!("0500000000000000{}", scalar_hex(7));
+    assert_eq!(to_hex(&revealed_commitment.to_bytes()), golden);
+    assert_eq!(
+        RevealedCommitment::from_bytes(&from_hex(&golden)),
+        Ok(revealed_commitment)
+    );
+
+    let key_image = KeyImage::from(G1Affine::generator());
+    assert_eq!(to_hex(&key_image.to_bytes()), GENERATOR);
+
+    let encrypted_amount = EncryptedAmount {
+        ephemeral_public_key: G1Affine::generator(),
+        ciphertext: vec![1, 2, 3],
+        tag: [9; 32],
+    };
+    let golden = format!("{}03000000010203{}", GENERATOR, "09".repeat(32));
+    assert_eq!(to_hex(&encrypted_amount.to_bytes()), golden);
+    assert_eq!(
+        EncryptedAmount::from_bytes(&from_hex(&golden)),
+        Ok(encrypted_amount)
+    );
+
+    let proof = OwnershipProof {
+        challenge: Scalar::from(1u64),
+        response: Scalar::from(2u64),
+    };
+    let golden = format!("{}{}", scalar_hex(1), scalar_hex(2));
+    assert_eq!(to_hex(&proof.to_bytes()), golden);
+    assert_eq!(OwnershipProof::from_bytes(&from_hex(&golden)), Ok(proof));
+}
+
+#[test]
+fn test_transaction_layout() {
+    let vector = Vector::generate(0, TX_VERSION_1);
+    let bytes = vector.tx.to_bytes();
+    assert_eq!(vector.tx.version, CURRENT_TX_VERSION);
+
+    // version, fee, empty extra, then two signatures
+    assert_eq!(
+        to_hex(&bytes[..2]),
+        to_hex(&CURRENT_TX_VERSION.to_le_bytes())
+    );
+    assert_eq!(to_hex(&bytes[2..10]), "0100000000000000");
+    assert_eq!(to_hex(&bytes[10..14]), "00000000");
+    assert_eq!(to_hex(&bytes[14..18]), "02000000");
+    assert_eq!(bytes.len(), RingCtTransaction::estimate_size(2, 3, 2));
+
+    // a plain output ends with an empty extra and an absent lock
+    let output = vector.tx.outputs[0].to_bytes();
+    assert_eq!(to_hex(&output[output.len() - 5..]), "0000000000");
+}
+
+#[test]
+fn test_malformed_encodings_are_rejected() {
+    let mut output = Vector::generate(0, TX_VERSION_1).tx.outputs[0].to_bytes();
+    let last = output.len() - 1;
+    output[last] = 2;
+    assert_eq!(
+        OutputProof::from_bytes(&output),
+        Err(Error::InvalidPresenceFlag(2))
+    );
+
+    let golden = from_hex(&format!("0500000000000000{}", scalar_hex(7)));
+    assert_eq!(
+        RevealedCommitment::from_bytes(&golden[..39]),
+        Err(Error::UnexpectedEndOfBytes)
+    );
+    assert_eq!(
+        RevealedCommitment::from_bytes(&[golden.as_slice(), &[0]].concat()),
+        Err(Error::TrailingBytes)
+    );
+    assert_eq!(
+        RevealedCommitment::from_bytes(&[0xff; 40]),
+        Err(Error::InvalidScalarEncoding)
+    );
+}
@@ -0,0 +1,128 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Deterministic construction of the known-answer test vectors, shared by the
+//! `gen_vectors` example and the `vectors` integration test.
+
+// each user of this module only needs some of it
+#![allow(dead_code)]
+
+use bls_ringct::{
+    blstrs::{G1Affine, G1Projective, Scalar},
+    group::{ff::Field, Curve, Group},
+    pedersen_gens,
+    ringct::{Amount, RingCtTransaction, TX_VERSION_1, TX_VERSION_2, TX_VERSION_3, TX_VERSION_4},
+    DecoyInput, MlsagMaterial, Output, RevealedCommitment, RingCtMaterial, TrueInput,
+};
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+
+pub const SEEDS: [u64; 3] = [0, 1, 2];
+pub const VERSIONS: [u16; 4] = [TX_VERSION_1, TX_VERSION_2, TX_VERSION_3, TX_VERSION_4];
+
+const DECOYS_PER_INPUT: usize = 2;
+const INPUT_AMOUNTS: [u64; 2] = [3, 4];
+const OUTPUT_AMOUNTS: [u64; 2] = [5, 1];
+const FEE: Amount = Amount::new(1);
+
+/// A transaction signed with every random draw taken from a seeded ChaCha20 RNG
+pub struct Vector {
+    pub seed: u64,
+    pub version: u16,
+    pub tx: RingCtTransaction,
+    pub public_commitments: Vec<Vec<G1Affine>>,
+}
+
+impl Vector {
+    pub fn generate(seed: u64, version: u16) -> Self {
+        let mut rng = ChaCha20Rng::seed_from_u64(seed);
+        let pc_gens = pedersen_gens();
+
+        let inputs: Vec<MlsagMaterial> = INPUT_AMOUNTS
+            .iter()
+            .map(|amount| {
+                let true_input = TrueInput::new(
+                    Scalar::random(&mut rng),
+                    RevealedCommitment::from_value(*amount, &mut rng),
+                );
+                let decoy_inputs = (0..DECOYS_PER_INPUT)
+                    .map(|_| DecoyInput {
+                        public_key: G1Projective::random(&mut rng).to_affine(),
+                        commitment: G1Projective::random(&mut rng).to_affine(),
+                    })
+                    .collect();
+                MlsagMaterial::new(true_input, decoy_inputs, &mut rng).unwrap()
+            })
+            .collect();
+        let outputs = OUTPUT_AMOUNTS
+            .iter()
+            .map(|amount| Output::new(G1Projective::random(&mut rng), *amount))
+            .collect();
+        let mut material = RingCtMaterial {
+            inputs,
+            outputs,
+            fee: FEE,
+            extra: vec![],
+            network_id: None,
+            not_valid_after: None,
+        };
+        if version == TX_VERSION_4 {
+            material
+                .align_rings()
+                .expect("Failed to align test vector rings");
+        }
+        let public_commitments = material
+            .inputs
+            .iter()
+            .map(|i| i.commitments(&pc_gens))
+            .collect();
+        let (tx, _revealed_output_commitments) = material
+            .sign_with_version(version, &mut rng)
+            .expect("Failed to sign test vector");
+
+        Self {
+            seed,
+            version,
+            tx,
+            public_commitments,
+        }
+    }
+
+    pub fn file_name(&self) -> String {
+        format!("v{}_seed{}.txt", self.version, self.seed)
+    }
+
+    /// One `key = value` line per field, byte strings in lowercase hex
+    pub fn to_text(&self) -> String {
+        let key_images: Vec<String> = self.tx.key_images().iter().map(|k| k.to_string()).collect();
+        format!(
+            "seed = {}\nversion = {}\ntx = {}\nhash = {}\nkey_images = {}\n",
+            self.seed,
+            self.version,
+            to_hex(&self.tx.to_bytes()),
+            to_hex(&self.tx.hash()),
+            key_images.join(","),
+        )
+    }
+}
+
+/// Reads the value of `key` from a vector file
+pub fn field<'a>(text: &'a str, key: &str) -> Option<&'a str> {
+    text.lines().find_map(|line| {
+        let (k, v) = line.split_once('=')?;
+        (k.trim() == key).then(|| v.trim())
+    })
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn from_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("Invalid hex"))
+        .collect()
+}
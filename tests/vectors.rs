@@ -0,0 +1,73 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+mod common;
+
+use std::{collections::BTreeSet, fs, path::Path};
+
+use bls_ringct::ringct::RingCtTransaction;
+use common::vectors::{field, from_hex, to_hex, Vector, SEEDS, VERSIONS};
+
+fn vector_files() -> Vec<(String, String)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/vectors");
+    let mut files: Vec<_> = fs::read_dir(dir)
+        .expect("Failed to read tests/vectors")
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? == "txt" {
+                Some(path)
+            } else {
+                None
+            }
+        })
+        .map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            let text = fs::read_to_string(&path).expect("Failed to read vector");
+            (name, text)
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn test_vectors_match_committed_bytes() {
+    let mut versions = BTreeSet::new();
+    for (name, text) in vector_files() {
+        let seed = field(&text, "seed").unwrap().parse().unwrap();
+        let version = field(&text, "version").unwrap().parse().unwrap();
+        versions.insert(version);
+        let vector = Vector::generate(seed, version);
+
+        assert_eq!(vector.file_name(), name);
+        assert_eq!(vector.to_text(), text, "{} no longer matches", name);
+
+        let tx = RingCtTransaction::from_bytes(&from_hex(field(&text, "tx").unwrap()))
+            .expect("Failed to decode vector");
+        assert_eq!(to_hex(&tx.hash()), field(&text, "hash").unwrap());
+        assert_eq!(tx.verify(&vector.public_commitments), Ok(()));
+    }
+
+    for version in VERSIONS {
+        assert!(
+            versions.contains(&version),
+            "No committed vector for version {}",
+            version
+        );
+    }
+}
+
+#[test]
+fn test_vector_generation_is_deterministic() {
+    for version in VERSIONS {
+        for seed in SEEDS {
+            let a = Vector::generate(seed, version);
+            let b = Vector::generate(seed, version);
+            assert_eq!(a.to_text(), b.to_text());
+            assert_eq!(a.tx.verify(&a.public_commitments), Ok(()));
+        }
+    }
+}